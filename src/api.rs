@@ -1,17 +1,25 @@
-use std::collections::HashSet;
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use thiserror::Error;
 use tracing::{debug, warn};
 
+#[cfg(feature = "async")]
+pub mod r#async;
+
 use crate::{
-    bootloader, bridge_control, halfkay, hex,
+    board::Board,
+    bootloader, bridge_control, error_code, halfkay, hex,
     operation::OperationEvent,
-    selector, serial_reboot, targets,
+    readback, selector, serial_reboot, targets,
     targets::{Target, TargetKind},
 };
 
+pub use crate::reboot_api::ensure_bootloader;
+
 #[derive(Debug, Clone)]
 pub enum FlashSelection {
     /// Auto-select a target using safe heuristics.
@@ -20,6 +28,44 @@ pub enum FlashSelection {
     All,
     /// Select a single target using a parsed selector.
     Device(selector::TargetSelector),
+    /// Select every target matched by a selector (e.g. a HalfKay path glob).
+    AllMatching(selector::TargetSelector),
+    /// Select one target per selector (each must match exactly one), deduplicated by id.
+    Devices(Vec<selector::TargetSelector>),
+}
+
+/// Backoff between block write retries: attempt `n` waits `min(base * factor^(n-1), max)`
+/// before the device is reopened.
+///
+/// The default is a constant 150 ms (`factor` 1.0).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub base: Duration,
+    pub factor: f64,
+    pub max: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            base: Duration::from_millis(150),
+            factor: 1.0,
+            max: Duration::from_millis(150),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry `attempt` (1-based).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exp = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let secs = self.base.as_secs_f64() * self.factor.max(0.0).powi(exp);
+        if secs.is_finite() && secs < self.max.as_secs_f64() {
+            Duration::from_secs_f64(secs)
+        } else {
+            self.max
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -37,6 +83,8 @@ pub struct FlashOptions {
 
     /// Retries per block on write failure.
     pub retries: u32,
+    /// Wait before each of those retries.
+    pub retry_policy: RetryPolicy,
 
     /// Prefer a specific serial port name when selecting among multiple Serial targets.
     ///
@@ -45,9 +93,110 @@ pub struct FlashOptions {
 
     pub bridge: bridge_control::BridgeControlOptions,
 
+    /// Firmware parsing options.
+    pub load: hex::LoadOptions,
+
+    /// Write every block, including blank ones (0xFF), instead of skipping blank regions.
+    ///
+    /// Full "nuke and pave" reflash for recovery; much slower than the default.
+    pub full_reflash: bool,
+
     pub reopen_timeout: Duration,
+    /// Settle time after reopening HalfKay for a retry (the wait before it is `retry_policy`).
     pub reopen_delay: Duration,
     pub soft_reboot_delay: Duration,
+    /// Baud rate that triggers the soft reboot of serial targets (see
+    /// [`serial_reboot::soft_reboot_port_with_baud`]).
+    pub reboot_baud: u32,
+    /// How serial targets are rebooted into HalfKay.
+    pub reboot_strategy: serial_reboot::RebootStrategy,
+
+    /// Only report a target as done once its USB serial port is back after boot.
+    ///
+    /// Makes `TargetDone { ok: true }` mean "verified running". Ignored with `no_reboot`.
+    pub atomic: bool,
+    /// Max time to wait for the serial port to return in `atomic` mode.
+    pub serial_reappear_timeout: Duration,
+    /// After boot, wait up to this long for the USB serial port to come back (emitting
+    /// `SerialReappeared`) before reporting the target done, so callers can open it right away.
+    ///
+    /// Unlike `atomic`, a port that does not return in time does not fail the target.
+    /// Ignored with `no_reboot`.
+    pub wait_for_serial_after_boot: Option<Duration>,
+
+    /// After the boot command, check that HalfKay detaches; fail with `BootFailed` if it
+    /// is still attached after `boot_confirm_timeout`. Ignored with `no_reboot`.
+    pub confirm_boot: bool,
+    pub boot_confirm_timeout: Duration,
+
+    /// After boot, read the written blocks back over USB serial and compare them with the
+    /// image (see [`crate::readback`]).
+    ///
+    /// Only serial-origin targets whose firmware completes the readback handshake are
+    /// verified; others (including firmware that never answers) report `VerifySkipped`.
+    pub verify: bool,
+    /// Per-read timeout of the readback exchange.
+    pub verify_timeout: Duration,
+
+    /// Pause between consecutive targets (e.g. to let a USB hub's power settle).
+    pub between_targets_delay: Duration,
+
+    /// Flash up to this many HalfKay targets at once (`0`/`1` = one at a time).
+    ///
+    /// Serial targets are still flashed one at a time, after the HalfKay ones; oc-bridge is
+    /// paused once around the whole run. Events of concurrent targets interleave (each
+    /// carries its `target_id`). With `atomic`, the reported serial port may belong to a
+    /// sibling board that booted at the same time.
+    pub parallel: usize,
+
+    /// Pause after each successfully written block except the last (paces writes through
+    /// flaky hubs). Independent of `reopen_delay`, which only applies to retries.
+    pub inter_block_delay: Duration,
+
+    /// Emit `Block` for every `progress_stride`-th block only, plus the first and last one
+    /// (`0`/`1` = every block). `Progress`, `Retry` and per-target events are unaffected.
+    pub progress_stride: usize,
+
+    /// Deadline for each of the first few blocks, which wait for the flash erase (default 45s).
+    pub erase_timeout: Duration,
+    /// Deadline for each later block (default 500ms).
+    ///
+    /// Bounds the HID write and its in-place retries; a block that misses it goes through
+    /// the `retries` policy (reopen, then write again).
+    pub write_timeout: Duration,
+
+    /// HalfKay HID open options (e.g. exclusive access on Windows).
+    pub hid_open: halfkay::OpenOptions,
+
+    /// Interval between HID enumerations while waiting for HalfKay to appear.
+    pub halfkay_poll_interval: Duration,
+
+    /// After soft-rebooting a serial target, refuse to flash a HalfKay device whose serial
+    /// number does not match that target (e.g. another board put into the bootloader).
+    pub require_serial_match: bool,
+
+    /// Refuse to soft-reboot serial targets: they fail with `SerialRebootForbidden` while
+    /// HalfKay targets are still flashed (fixtures whose serial line must not be toggled).
+    pub no_serial_reboot: bool,
+
+    /// Planning only: return a plan with no selected targets instead of `NoTargets`.
+    ///
+    /// Lets callers validate firmware without hardware. Flashing still requires a target.
+    pub allow_no_targets: bool,
+
+    /// Cancellation flag; set it to `true` to stop before the next block, retry or poll.
+    ///
+    /// Cancelling returns `FlashError::Cancelled` and still resumes oc-bridge. A board stopped
+    /// mid-write stays in HalfKay with partial firmware; flash it again.
+    pub cancel: Option<Arc<AtomicBool>>,
+
+    /// Max time for discovery, and for each target from soft reboot to boot (writes and
+    /// retries included); `None` = no limit.
+    ///
+    /// Checked between steps (blocks, retries, polls), so a single blocking write is not
+    /// interrupted. Exceeding it returns `FlashError::OperationTimeout` and still resumes
+    /// oc-bridge.
+    pub operation_timeout: Option<Duration>,
 }
 
 impl Default for FlashOptions {
@@ -57,25 +206,98 @@ impl Default for FlashOptions {
             wait_timeout: None,
             no_reboot: false,
             retries: 3,
+            retry_policy: RetryPolicy::default(),
             serial_port: None,
             bridge: bridge_control::BridgeControlOptions::default(),
+            load: hex::LoadOptions::default(),
+            full_reflash: false,
             reopen_timeout: Duration::from_secs(10),
             reopen_delay: Duration::from_millis(150),
             soft_reboot_delay: Duration::from_millis(250),
+            reboot_baud: serial_reboot::TEENSY_REBOOT_BAUD,
+            reboot_strategy: serial_reboot::RebootStrategy::default(),
+            atomic: false,
+            serial_reappear_timeout: Duration::from_secs(10),
+            wait_for_serial_after_boot: None,
+            confirm_boot: false,
+            boot_confirm_timeout: Duration::from_millis(500),
+            verify: false,
+            verify_timeout: Duration::from_millis(1000),
+            between_targets_delay: Duration::ZERO,
+            parallel: 1,
+            inter_block_delay: Duration::ZERO,
+            progress_stride: 1,
+            erase_timeout: halfkay::WriteTimeouts::default().erase,
+            write_timeout: halfkay::WriteTimeouts::default().write,
+            hid_open: halfkay::OpenOptions::default(),
+            halfkay_poll_interval: Duration::from_millis(50),
+            require_serial_match: false,
+            no_serial_reboot: false,
+            allow_no_targets: false,
+            cancel: None,
+            operation_timeout: None,
         }
     }
 }
 
+impl FlashOptions {
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|c| c.load(Ordering::SeqCst))
+    }
+
+    /// `Cancelled` or `OperationTimeout` if a step that began at `started` must stop now.
+    fn check_abort(&self, started: Instant) -> Result<(), FlashError> {
+        if self.is_cancelled() {
+            return Err(FlashError::Cancelled);
+        }
+        let elapsed = started.elapsed();
+        match self.operation_timeout {
+            Some(limit) if elapsed >= limit => Err(FlashError::OperationTimeout { elapsed }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Per-target firmware choice for fixtures where each bay gets a different variant.
+#[derive(Debug, Clone, Default)]
+pub struct FirmwareMap {
+    /// USB serial number -> HEX path.
+    pub by_serial: BTreeMap<String, PathBuf>,
+    /// Firmware for targets without a mapping (including HalfKay targets, which expose no serial).
+    ///
+    /// When `None`, unmapped targets are skipped.
+    pub fallback: Option<PathBuf>,
+}
+
+impl FirmwareMap {
+    pub fn path_for(&self, target: &Target) -> Option<&Path> {
+        let mapped = match target {
+            Target::Serial(s) => s
+                .serial_number
+                .as_ref()
+                .and_then(|sn| self.by_serial.get(sn)),
+            Target::HalfKay(_) => None,
+        };
+        mapped.or(self.fallback.as_ref()).map(PathBuf::as_path)
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum FlashErrorKind {
     NoDevice,
     AmbiguousTarget,
     InvalidHex,
     WriteFailed,
+    /// Flashed, but the flash could not be read back to verify it.
+    ReadbackFailed,
+    Cancelled,
     Unexpected,
 }
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum FlashError {
     #[error("no target device found")]
     NoTargets,
@@ -92,10 +314,10 @@ pub enum FlashError {
         source: targets::DiscoverError,
     },
 
-    #[error("invalid HEX: {source}")]
+    #[error("invalid firmware file: {source}")]
     InvalidHex {
         #[source]
-        source: hex::HexError,
+        source: hex::FirmwareError,
     },
 
     #[error("unable to pause oc-bridge: {message}")]
@@ -131,8 +353,53 @@ pub enum FlashError {
         source: halfkay::HalfKayError,
     },
 
+    #[error("device did not come back as USB serial within {timeout_ms}ms after boot")]
+    BootNotConfirmed { timeout_ms: u64 },
+
+    #[error("HalfKay still attached {timeout_ms}ms after the boot command (board did not start)")]
+    BootFailed { timeout_ms: u64 },
+
+    #[error("no firmware mapped for any selected target ({skipped} skipped)")]
+    NoFirmwareMapped { skipped: usize },
+
+    #[error(
+        "HalfKay device at {path} is not the rebooted board (expected serial {}, found {})",
+        .expected.as_deref().unwrap_or("unknown"),
+        .found.as_deref().unwrap_or("none")
+    )]
+    SerialMismatch {
+        path: String,
+        expected: Option<String>,
+        found: Option<String>,
+    },
+
+    #[error("HalfKay device at {path} is a {found}, but the firmware is built for {expected}")]
+    BoardMismatch {
+        path: String,
+        expected: Board,
+        found: Board,
+    },
+
+    #[error("{target_id} is a serial target and serial reboots are disabled")]
+    SerialRebootForbidden { target_id: String },
+
+    #[error("verify failed: flash differs from the image at addr=0x{addr:06X}")]
+    VerifyFailed { addr: usize },
+
+    #[error("unable to read flash back: {source}")]
+    ReadbackFailed {
+        #[source]
+        source: readback::ReadbackError,
+    },
+
     #[error("flash failed for {failed}/{total} targets")]
     MultiTargetFailed { failed: usize, total: usize },
+
+    #[error("flash cancelled")]
+    Cancelled,
+
+    #[error("operation timed out after {}ms", .elapsed.as_millis())]
+    OperationTimeout { elapsed: Duration },
 }
 
 impl FlashError {
@@ -149,7 +416,44 @@ impl FlashError {
             FlashError::WriteFailed { .. } | FlashError::ReopenFailed { .. } => {
                 FlashErrorKind::WriteFailed
             }
+            FlashError::BootNotConfirmed { .. } => FlashErrorKind::WriteFailed,
+            FlashError::BootFailed { .. } => FlashErrorKind::WriteFailed,
+            FlashError::NoFirmwareMapped { .. } => FlashErrorKind::NoDevice,
+            FlashError::SerialMismatch { .. } => FlashErrorKind::AmbiguousTarget,
+            FlashError::BoardMismatch { .. } => FlashErrorKind::InvalidHex,
+            FlashError::SerialRebootForbidden { .. } => FlashErrorKind::AmbiguousTarget,
+            FlashError::VerifyFailed { .. } => FlashErrorKind::WriteFailed,
+            FlashError::ReadbackFailed { .. } => FlashErrorKind::ReadbackFailed,
             FlashError::MultiTargetFailed { .. } => FlashErrorKind::WriteFailed,
+            FlashError::Cancelled => FlashErrorKind::Cancelled,
+            FlashError::OperationTimeout { .. } => FlashErrorKind::WriteFailed,
+        }
+    }
+
+    /// Stable numeric code for this error (see [`crate::error_code`]).
+    pub fn code(&self) -> u32 {
+        match self {
+            FlashError::NoTargets => error_code::NO_TARGETS,
+            FlashError::TargetNotFound { .. } => error_code::TARGET_NOT_FOUND,
+            FlashError::AmbiguousTarget { .. } => error_code::AMBIGUOUS_TARGET,
+            FlashError::DiscoveryFailed { .. } => error_code::DISCOVERY_FAILED,
+            FlashError::InvalidHex { .. } => error_code::INVALID_HEX,
+            FlashError::BridgePauseFailed { .. } => error_code::BRIDGE_PAUSE_FAILED,
+            FlashError::SoftRebootFailed { .. } => error_code::SOFT_REBOOT_FAILED,
+            FlashError::OpenHalfKay { .. } => error_code::OPEN_HALFKAY_FAILED,
+            FlashError::WriteFailed { .. } => error_code::WRITE_FAILED,
+            FlashError::ReopenFailed { .. } => error_code::REOPEN_FAILED,
+            FlashError::BootNotConfirmed { .. } => error_code::BOOT_NOT_CONFIRMED,
+            FlashError::BootFailed { .. } => error_code::BOOT_FAILED,
+            FlashError::NoFirmwareMapped { .. } => error_code::NO_FIRMWARE_MAPPED,
+            FlashError::SerialMismatch { .. } => error_code::SERIAL_MISMATCH,
+            FlashError::BoardMismatch { .. } => error_code::BOARD_MISMATCH,
+            FlashError::SerialRebootForbidden { .. } => error_code::SERIAL_REBOOT_FORBIDDEN,
+            FlashError::VerifyFailed { .. } => error_code::VERIFY_FAILED,
+            FlashError::ReadbackFailed { .. } => error_code::READBACK_FAILED,
+            FlashError::MultiTargetFailed { .. } => error_code::MULTI_TARGET_FAILED,
+            FlashError::Cancelled => error_code::CANCELLED,
+            FlashError::OperationTimeout { .. } => error_code::OPERATION_TIMEOUT,
         }
     }
 }
@@ -181,12 +485,23 @@ where
     F: FnMut(OperationEvent),
 {
     debug!(hex_path = %hex_path.display(), "load hex and plan flash");
-    let fw = hex::FirmwareImage::load_teensy41(hex_path)
+    on_event(OperationEvent::HexLoadStart {
+        path: hex_path.display().to_string(),
+    });
+    let mut fw =
+        hex::FirmwareImage::load_teensy41_auto_with_progress(hex_path, &opts.load, |line_no| {
+            on_event(OperationEvent::HexLoading { line_no })
+        })
         .map_err(|e| FlashError::InvalidHex { source: e })?;
+    if opts.full_reflash {
+        fw.write_every_block();
+    }
 
     on_event(OperationEvent::HexLoaded {
         bytes: fw.byte_count,
         blocks: fw.num_blocks,
+        crc32: fw.crc32(),
+        digest: fw.content_digest(),
     });
 
     debug!(
@@ -196,15 +511,11 @@ where
         "hex loaded"
     );
 
-    let targets = discover_targets_for_flash(opts, &mut on_event)?;
-    let selected = select_targets(
-        selection,
-        opts.serial_port.as_deref(),
-        &targets,
-        true,
-        &mut on_event,
-    )?;
-    let needs_serial = selected.iter().any(|t| t.kind() == TargetKind::Serial);
+    let selected = discover_and_select(opts, selection, &mut on_event)?;
+    // With `no_serial_reboot` serial targets fail before any port is opened, so the bridge
+    // has nothing to give up.
+    let needs_serial =
+        !opts.no_serial_reboot && selected.iter().any(|t| t.kind() == TargetKind::Serial);
 
     Ok(FlashPlan {
         firmware: fw,
@@ -223,28 +534,270 @@ where
     F: FnMut(OperationEvent),
 {
     let plan = plan_teensy41_with_selection(hex_path, opts, selection, &mut on_event)?;
+    run_plan(plan, opts, &mut on_event)
+}
+
+/// Like [`flash_teensy41_with_selection`], also reporting how many blocks differ from
+/// `baseline` (`BaselineDiff`) before any device is touched.
+///
+/// Every block is still written: writing block 0 makes HalfKay erase the whole flash, so
+/// skipping the unchanged blocks would leave them blank.
+pub fn flash_teensy41_with_baseline<F>(
+    hex_path: &Path,
+    opts: &FlashOptions,
+    selection: FlashSelection,
+    baseline: Option<&hex::FirmwareImage>,
+    mut on_event: F,
+) -> Result<(), FlashError>
+where
+    F: FnMut(OperationEvent),
+{
+    let plan = plan_teensy41_with_selection(hex_path, opts, selection, &mut on_event)?;
+    if let Some(baseline) = baseline {
+        on_event(baseline_diff(&plan.firmware, baseline));
+    }
+    run_plan(plan, opts, &mut on_event)
+}
+
+fn baseline_diff(fw: &hex::FirmwareImage, baseline: &hex::FirmwareImage) -> OperationEvent {
+    let total_blocks = fw.blocks_to_write.len();
+    let changed_blocks = fw.diff_blocks(baseline).len();
+    debug!(total_blocks, changed_blocks, "baseline diff");
+    OperationEvent::BaselineDiff {
+        total_blocks,
+        changed_blocks,
+    }
+}
+
+fn run_plan<F>(plan: FlashPlan, opts: &FlashOptions, on_event: &mut F) -> Result<(), FlashError>
+where
+    F: FnMut(OperationEvent),
+{
+    if plan.selected_targets.is_empty() {
+        return Err(FlashError::NoTargets);
+    }
     let fw = plan.firmware;
     let selected = plan.selected_targets;
 
+    if opts.parallel > 1 && selected.len() > 1 {
+        return crate::operation_runner::run_targets_with_bridge_parallel(
+            selected,
+            &opts.bridge,
+            crate::operation_runner::ParallelPacing {
+                workers: opts.parallel,
+                between_targets_delay: opts.between_targets_delay,
+            },
+            bridge_control::pause_oc_bridge,
+            |target, target_id, mut on_event| {
+                flash_one_target(target, target_id, &fw, opts, &mut on_event)
+            },
+            flash_run_errors(),
+            on_event,
+        )
+        .map_err(|e| cancelled_or(opts, e));
+    }
+
     crate::operation_runner::run_targets_with_bridge(
         selected,
         &opts.bridge,
+        opts.between_targets_delay,
         bridge_control::pause_oc_bridge,
         |target, target_id, on_event| flash_one_target(target, target_id, &fw, opts, on_event),
-        crate::operation_runner::RunTargetsErrors {
-            is_ambiguous: |e: &FlashError| matches!(e.kind(), FlashErrorKind::AmbiguousTarget),
-            make_ambiguous: |message| FlashError::AmbiguousTarget { message },
-            make_multi_failed: |failed, total| FlashError::MultiTargetFailed { failed, total },
-            make_bridge_pause_failed: |err: bridge_control::BridgeControlErrorInfo| {
-                let mut msg = err.message;
-                if let Some(hint) = err.hint {
-                    msg = format!("{msg} ({hint})");
-                }
-                FlashError::BridgePauseFailed { message: msg }
-            },
+        flash_run_errors(),
+        on_event,
+    )
+    .map_err(|e| cancelled_or(opts, e))
+}
+
+type FlashRunErrors = crate::operation_runner::RunTargetsErrors<
+    fn(&FlashError) -> bool,
+    fn(String) -> FlashError,
+    fn(usize, usize) -> FlashError,
+    fn(bridge_control::BridgeControlErrorInfo) -> FlashError,
+>;
+
+/// How a multi-target flash run reports its failures.
+fn flash_run_errors() -> FlashRunErrors {
+    crate::operation_runner::RunTargetsErrors {
+        is_ambiguous: |e: &FlashError| matches!(e.kind(), FlashErrorKind::AmbiguousTarget),
+        make_ambiguous: |message| FlashError::AmbiguousTarget { message },
+        make_multi_failed: |failed, total| FlashError::MultiTargetFailed { failed, total },
+        make_bridge_pause_failed: bridge_pause_failed,
+    }
+}
+
+/// Later targets fail fast once cancelled; report the cancellation, not a multi-target failure.
+fn cancelled_or(opts: &FlashOptions, e: FlashError) -> FlashError {
+    if opts.is_cancelled() {
+        FlashError::Cancelled
+    } else {
+        e
+    }
+}
+
+fn bridge_pause_failed(err: bridge_control::BridgeControlErrorInfo) -> FlashError {
+    let mut msg = err.message;
+    if let Some(hint) = err.hint {
+        msg = format!("{msg} ({hint})");
+    }
+    FlashError::BridgePauseFailed { message: msg }
+}
+
+/// Runs `body` with oc-bridge paused, for callers driving their own target loop.
+///
+/// When `needs_serial` is false the bridge is left alone. Otherwise it is paused first (a
+/// failed pause returns `BridgePauseFailed` without running `body`) and resumed afterwards
+/// per `opts.resume_policy`, judged on `body`'s result. Pause/resume progress is reported
+/// through `on_event`, which `body` also receives. If `body` panics, the guard still resumes
+/// the bridge on unwind.
+pub fn with_bridge_paused<F, R, B>(
+    opts: &bridge_control::BridgeControlOptions,
+    needs_serial: bool,
+    on_event: &mut F,
+    body: B,
+) -> Result<R, FlashError>
+where
+    F: FnMut(OperationEvent),
+    B: FnOnce(&mut F) -> Result<R, FlashError>,
+{
+    let guard = if needs_serial {
+        crate::operation_runner::pause_bridge_with_events(
+            opts,
+            bridge_control::pause_oc_bridge,
+            on_event,
+        )
+        .map_err(bridge_pause_failed)?
+    } else {
+        None
+    };
+    let result = body(on_event);
+    crate::operation_runner::resume_bridge_with_events(guard, opts, result.is_ok(), on_event);
+    result
+}
+
+/// Flash each selected target with the firmware chosen by `map`.
+///
+/// Every referenced HEX file is loaded and validated before any device is touched.
+/// Targets without firmware are reported with `TargetSkipped` and do not count as failures.
+pub fn flash_teensy41_with_firmware_map<F>(
+    map: &FirmwareMap,
+    opts: &FlashOptions,
+    selection: FlashSelection,
+    mut on_event: F,
+) -> Result<(), FlashError>
+where
+    F: FnMut(OperationEvent),
+{
+    let mut paths: Vec<&Path> = map.by_serial.values().map(PathBuf::as_path).collect();
+    paths.extend(map.fallback.as_deref());
+    paths.sort();
+    paths.dedup();
+
+    let mut images: HashMap<&Path, hex::FirmwareImage> = HashMap::new();
+    for path in paths {
+        debug!(hex_path = %path.display(), "load mapped hex");
+        on_event(OperationEvent::HexLoadStart {
+            path: path.display().to_string(),
+        });
+        let mut fw =
+            hex::FirmwareImage::load_teensy41_auto_with_progress(path, &opts.load, |line_no| {
+                on_event(OperationEvent::HexLoading { line_no })
+            })
+            .map_err(|e| FlashError::InvalidHex { source: e })?;
+        if opts.full_reflash {
+            fw.write_every_block();
+        }
+        on_event(OperationEvent::HexLoaded {
+            bytes: fw.byte_count,
+            blocks: fw.num_blocks,
+            crc32: fw.crc32(),
+            digest: fw.content_digest(),
+        });
+        images.insert(path, fw);
+    }
+
+    let selected = discover_and_select(opts, selection, &mut on_event)?;
+    if selected.is_empty() {
+        return Err(FlashError::NoTargets);
+    }
+
+    let mut assigned: HashMap<String, &hex::FirmwareImage> = HashMap::new();
+    let mut to_flash: Vec<Target> = Vec::new();
+    let mut skipped = 0usize;
+    for target in selected {
+        match map.path_for(&target) {
+            Some(path) => {
+                let fw = &images[path];
+                on_event(OperationEvent::FirmwareAssigned {
+                    target_id: target.id(),
+                    path: path.display().to_string(),
+                    crc32: fw.crc32(),
+                });
+                assigned.insert(target.id(), fw);
+                to_flash.push(target);
+            }
+            None => {
+                skipped += 1;
+                on_event(OperationEvent::TargetSkipped {
+                    target_id: target.id(),
+                    reason: "no firmware mapped for this target".to_string(),
+                });
+            }
+        }
+    }
+    if to_flash.is_empty() {
+        return Err(FlashError::NoFirmwareMapped { skipped });
+    }
+
+    crate::operation_runner::run_targets_with_bridge(
+        to_flash,
+        &opts.bridge,
+        opts.between_targets_delay,
+        bridge_control::pause_oc_bridge,
+        |target, target_id, on_event| {
+            flash_one_target(target, target_id, assigned[target_id], opts, on_event)
         },
+        flash_run_errors(),
         &mut on_event,
     )
+    .map_err(|e| cancelled_or(opts, e))
+}
+
+/// Run discovery and target selection only (no HEX load, no device access).
+pub fn select_flash_targets<F>(
+    opts: &FlashOptions,
+    selection: FlashSelection,
+    mut on_event: F,
+) -> Result<Vec<Target>, FlashError>
+where
+    F: FnMut(OperationEvent),
+{
+    discover_and_select(opts, selection, &mut on_event)
+}
+
+fn discover_and_select<F>(
+    opts: &FlashOptions,
+    selection: FlashSelection,
+    on_event: &mut F,
+) -> Result<Vec<Target>, FlashError>
+where
+    F: FnMut(OperationEvent),
+{
+    let targets = match discover_targets_for_flash(opts, on_event) {
+        Ok(t) => t,
+        Err(FlashError::NoTargets) if opts.allow_no_targets => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    if targets.is_empty() {
+        return Ok(Vec::new());
+    }
+    select_targets(
+        selection,
+        opts.serial_port.as_deref(),
+        &targets,
+        true,
+        on_event,
+    )
 }
 
 fn discover_targets_for_flash<F>(
@@ -289,6 +842,7 @@ where
                 return Err(FlashError::NoTargets);
             }
         }
+        opts.check_abort(start)?;
         std::thread::sleep(Duration::from_millis(250));
     }
 }
@@ -322,34 +876,33 @@ where
     let selected: Vec<Target> = match selection {
         FlashSelection::All => targets.to_vec(),
 
-        FlashSelection::Device(sel) => {
-            let idx = match selector::resolve_one(&sel, targets) {
-                Ok(idx) => idx,
-                Err(selector::SelectorError::NoMatch { selector }) => {
-                    let halfkay_count = halfkay.len();
-
-                    let hint = if selector.starts_with("serial:") && halfkay_count > 0 {
-                        if halfkay_count == 1 {
-                            ". Hint: HalfKay bootloader detected; try --device index:0, or run `midi-studio-loader list`."
-                        } else {
-                            ". Hint: HalfKay bootloaders detected; try --device index:<n>, or run `midi-studio-loader list`."
-                        }
-                    } else {
-                        ". Hint: run `midi-studio-loader list`."
-                    };
-
-                    return Err(FlashError::TargetNotFound {
-                        selector,
-                        hint: hint.to_string(),
-                    });
-                }
-                Err(e) => {
-                    return Err(FlashError::AmbiguousTarget {
-                        message: format!("{}. Hint: run `midi-studio-loader list`.", e),
-                    })
+        FlashSelection::AllMatching(sel) => match selector::resolve(&sel, targets) {
+            Ok(indexes) if indexes.is_empty() => {
+                return Err(FlashError::TargetNotFound {
+                    selector: sel.to_string(),
+                    hint: ". Hint: run `midi-studio-loader list`.".to_string(),
+                });
+            }
+            Ok(indexes) => indexes.into_iter().map(|i| targets[i].clone()).collect(),
+            Err(e) => {
+                return Err(FlashError::AmbiguousTarget {
+                    message: format!("{}. Hint: run `midi-studio-loader list`.", e),
+                })
+            }
+        },
+
+        FlashSelection::Device(sel) => vec![targets[resolve_device(&sel, targets)?].clone()],
+
+        FlashSelection::Devices(sels) => {
+            // Resolve every selector before returning any target: one bad selector fails all.
+            let mut selected: Vec<Target> = Vec::new();
+            for sel in &sels {
+                let target = &targets[resolve_device(sel, targets)?];
+                if !selected.iter().any(|t| t.id() == target.id()) {
+                    selected.push(target.clone());
                 }
-            };
-            vec![targets[idx].clone()]
+            }
+            selected
         }
 
         FlashSelection::Auto => {
@@ -365,12 +918,11 @@ where
                     ),
                 });
             } else if let Some(port) = serial_port {
+                let preferred = selector::TargetSelector::Id(format!("serial:{port}"));
                 let matches: Vec<Target> = serial
                     .iter()
-                    .filter_map(|t| match t {
-                        Target::Serial(s) if s.port_name == port => Some(t.clone()),
-                        _ => None,
-                    })
+                    .filter(|t| t.matches_selector(&preferred))
+                    .cloned()
                     .collect();
                 if matches.len() == 1 {
                     vec![matches[0].clone()]
@@ -412,6 +964,52 @@ where
     Ok(selected)
 }
 
+/// Resolve a `--device` selector to exactly one target, mapping selector errors.
+fn resolve_device(sel: &selector::TargetSelector, targets: &[Target]) -> Result<usize, FlashError> {
+    match selector::resolve_one(sel, targets) {
+        Ok(idx) => Ok(idx),
+        Err(selector::SelectorError::NoMatch { selector }) => {
+            let halfkay_count = targets
+                .iter()
+                .filter(|t| t.kind() == TargetKind::HalfKay)
+                .count();
+
+            let hint = if selector.starts_with("serial:") && halfkay_count > 0 {
+                if halfkay_count == 1 {
+                    ". Hint: HalfKay bootloader detected; try --device index:0, or run `midi-studio-loader list`."
+                } else {
+                    ". Hint: HalfKay bootloaders detected; try --device index:<n>, or run `midi-studio-loader list`."
+                }
+            } else {
+                ". Hint: run `midi-studio-loader list`."
+            };
+
+            Err(FlashError::TargetNotFound {
+                selector,
+                hint: hint.to_string(),
+            })
+        }
+        Err(e) => Err(FlashError::AmbiguousTarget {
+            message: format!("{}. Hint: run `midi-studio-loader list`.", e),
+        }),
+    }
+}
+
+/// Flash one target (soft-rebooting it first if it is a serial target).
+///
+/// Does not touch oc-bridge: wrap calls in [`with_bridge_paused`] when flashing serial targets.
+pub fn flash_target<F>(
+    target: &Target,
+    fw: &hex::FirmwareImage,
+    opts: &FlashOptions,
+    on_event: &mut F,
+) -> Result<(), FlashError>
+where
+    F: FnMut(OperationEvent),
+{
+    flash_one_target(target, &target.id(), fw, opts, on_event)
+}
+
 fn flash_one_target<F>(
     target: &Target,
     target_id: &str,
@@ -423,59 +1021,136 @@ where
     F: FnMut(OperationEvent),
 {
     debug!(target_id = target_id, kind = ?target.kind(), "flash target");
+    let run = TargetRun {
+        id: target_id,
+        started: Instant::now(),
+    };
+    opts.check_abort(run.started)?;
     match target {
-        Target::HalfKay(t) => flash_halfkay_path(&t.path, target_id, fw, opts, on_event),
+        Target::HalfKay(t) => flash_halfkay_path(&t.path, run, false, fw, opts, on_event),
+        Target::Serial(_) if opts.no_serial_reboot => Err(FlashError::SerialRebootForbidden {
+            target_id: target_id.to_string(),
+        }),
         Target::Serial(t) => {
-            // 1) snapshot existing HalfKay devices
-            let before = halfkay::list_paths().map_err(|e| FlashError::DiscoveryFailed {
-                source: targets::DiscoverError::Hid(e),
-            })?;
-            let before: HashSet<String> = before.into_iter().collect();
-
-            // 2) reboot selected serial port
-            match serial_reboot::soft_reboot_port(&t.port_name) {
-                Ok(()) => {
-                    on_event(OperationEvent::SoftReboot {
-                        target_id: target_id.to_string(),
-                        port: t.port_name.clone(),
-                    });
-                    std::thread::sleep(opts.soft_reboot_delay);
-                }
-                Err(e) => {
+            // 1) reboot the selected serial port, 2) wait for a new HalfKay path to appear
+            let reboot = bootloader::SoftRebootOptions {
+                strategy: opts.reboot_strategy,
+                baud: opts.reboot_baud,
+                settle_delay: opts.soft_reboot_delay,
+                dtr_grace: bootloader::AUTO_DTR_GRACE,
+                timeout: Some(opts.wait_timeout.unwrap_or_else(|| Duration::from_secs(60))),
+                poll_interval: opts.halfkay_poll_interval,
+                ambiguity_settle: bootloader::AMBIGUITY_SETTLE,
+            };
+            let hk_path = bootloader::soft_reboot_and_wait(
+                &t.port_name,
+                t.serial_number.as_deref(),
+                &reboot,
+                |step| match step {
+                    bootloader::SoftRebootStep::Sent(strategy) => {
+                        on_event(OperationEvent::SoftReboot {
+                            target_id: target_id.to_string(),
+                            port: t.port_name.clone(),
+                            strategy,
+                            baud: opts.reboot_baud,
+                        });
+                        true
+                    }
+                    bootloader::SoftRebootStep::Poll(_) => opts.check_abort(run.started).is_ok(),
+                },
+            )
+            .map_err(|e| match e {
+                bootloader::SoftRebootError::Reboot(e) => {
                     warn!(target_id = target_id, port = %t.port_name, err = %e, "soft reboot failed");
                     on_event(OperationEvent::SoftRebootSkipped {
                         target_id: target_id.to_string(),
                         error: e.to_string(),
                     });
-                    return Err(FlashError::SoftRebootFailed {
+                    FlashError::SoftRebootFailed {
                         port: t.port_name.clone(),
                         source: e,
-                    });
+                    }
                 }
-            }
-
-            // 3) wait for a new HalfKay path to appear
-            let timeout = opts.wait_timeout.unwrap_or_else(|| Duration::from_secs(60));
-            let hk_path =
-                bootloader::wait_for_new_halfkay(&before, timeout, Duration::from_millis(50))
-                    .map_err(|e| FlashError::AmbiguousTarget {
-                        message: e.to_string(),
-                    })?;
+                bootloader::SoftRebootError::Wait(bootloader::WaitHalfKayError::ListFailed(e)) => {
+                    FlashError::DiscoveryFailed {
+                        source: targets::DiscoverError::Hid(e),
+                    }
+                }
+                bootloader::SoftRebootError::Wait(bootloader::WaitHalfKayError::Cancelled) => {
+                    opts.check_abort(run.started)
+                        .err()
+                        .unwrap_or(FlashError::Cancelled)
+                }
+                bootloader::SoftRebootError::Wait(e) => FlashError::AmbiguousTarget {
+                    message: e.to_string(),
+                },
+            })?;
 
             on_event(OperationEvent::HalfKayAppeared {
                 target_id: target_id.to_string(),
                 path: hk_path.clone(),
             });
 
+            if opts.require_serial_match {
+                check_halfkay_serial(&hk_path, t.serial_number.as_deref())?;
+            }
+
             // 4) flash by that path
-            flash_halfkay_path(&hk_path, target_id, fw, opts, on_event)
+            flash_halfkay_path(&hk_path, run, true, fw, opts, on_event)
         }
     }
 }
 
+fn check_halfkay_serial(path: &str, expected: Option<&str>) -> Result<(), FlashError> {
+    let found = halfkay::serial_number_for_path(path).map_err(|e| FlashError::OpenHalfKay {
+        path: path.to_string(),
+        source: e,
+    })?;
+    let matches = match (expected, found.as_deref()) {
+        (Some(usb), Some(hk)) => halfkay::serial_matches(usb, hk),
+        _ => false,
+    };
+    if matches {
+        return Ok(());
+    }
+    Err(FlashError::SerialMismatch {
+        path: path.to_string(),
+        expected: expected.map(str::to_string),
+        found,
+    })
+}
+
+/// Refuse to flash `fw` onto a HalfKay device whose `bcdDevice` names another board.
+///
+/// Best-effort: a device that cannot be listed or reports an unknown code is let through.
+fn check_halfkay_board(path: &str, fw: &hex::FirmwareImage) -> Result<(), FlashError> {
+    let found = halfkay::list_devices()
+        .ok()
+        .and_then(|devices| devices.into_iter().find(|d| d.path == path))
+        .and_then(|d| Board::from_bcd_device(d.release_number));
+    match found {
+        Some(found) if found != fw.board => Err(FlashError::BoardMismatch {
+            path: path.to_string(),
+            expected: fw.board,
+            found,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// The target being flashed, and when its `operation_timeout` clock started.
+#[derive(Debug, Clone, Copy)]
+struct TargetRun<'a> {
+    id: &'a str,
+    started: Instant,
+}
+
+/// `serial_origin`: the board was soft-rebooted from USB serial, so its firmware may be able
+/// to read flash back (`verify`).
 fn flash_halfkay_path<F>(
     path: &str,
-    target_id: &str,
+    run: TargetRun,
+    serial_origin: bool,
     fw: &hex::FirmwareImage,
     opts: &FlashOptions,
     on_event: &mut F,
@@ -485,31 +1160,180 @@ where
 {
     // On Linux, udev permissions/ACLs may be applied slightly after the device node appears.
     // Retrying open improves robustness during rapid re-enumeration.
-    let mut dev =
-        reopen_halfkay_by_path(path, opts.reopen_timeout).map_err(|e| FlashError::OpenHalfKay {
+    check_halfkay_board(path, fw)?;
+    let dev = reopen_halfkay_by_path(path, &opts.hid_open, opts.reopen_timeout).map_err(|e| {
+        FlashError::OpenHalfKay {
             path: path.to_string(),
             source: e,
-        })?;
+        }
+    })?;
 
     on_event(OperationEvent::HalfKayOpen {
-        target_id: target_id.to_string(),
+        target_id: run.id.to_string(),
         path: dev.path.clone(),
     });
 
+    let write_timeouts = halfkay::WriteTimeouts {
+        erase: opts.erase_timeout,
+        write: opts.write_timeout,
+    };
+    let mut dev = write_blocks(
+        dev,
+        run,
+        fw,
+        opts,
+        |dev, addr, i| halfkay::write_block_teensy41_with(dev, fw, addr, i, &write_timeouts),
+        |addr| {
+            reopen_halfkay_by_path(path, &opts.hid_open, opts.reopen_timeout).map_err(|e| {
+                FlashError::ReopenFailed {
+                    path: path.to_string(),
+                    addr,
+                    source: e,
+                }
+            })
+        },
+        on_event,
+    )?;
+
+    let verify_serial = opts.verify && serial_origin;
+    if opts.verify && !verify_serial {
+        on_event(OperationEvent::VerifySkipped {
+            target_id: run.id.to_string(),
+            reason: "halfkay_target",
+        });
+    } else if verify_serial && opts.no_reboot {
+        on_event(OperationEvent::VerifySkipped {
+            target_id: run.id.to_string(),
+            reason: "no_reboot",
+        });
+    }
+
+    if !opts.no_reboot {
+        let serial_required = opts.atomic || verify_serial;
+        let serial_wait = if serial_required {
+            Some(opts.serial_reappear_timeout)
+        } else {
+            opts.wait_for_serial_after_boot
+        };
+        // The device is in HalfKay mode, so its serial port is absent from this snapshot.
+        let serial_before = match serial_wait {
+            Some(_) => Some(
+                bootloader::serial_port_names()
+                    .map_err(|source| FlashError::DiscoveryFailed { source })?,
+            ),
+            None => None,
+        };
+        // Tells the board's port apart from others appearing meanwhile. Best-effort, except
+        // when the port is required: `atomic` must not be confirmed by another device.
+        let halfkay_serial = match serial_wait {
+            Some(_) if serial_required => {
+                halfkay::serial_number_for_path(path).map_err(|e| FlashError::OpenHalfKay {
+                    path: path.to_string(),
+                    source: e,
+                })?
+            }
+            Some(_) => halfkay::serial_number_for_path(path).ok().flatten(),
+            None => None,
+        };
+
+        on_event(OperationEvent::Boot {
+            target_id: run.id.to_string(),
+        });
+        if opts.confirm_boot {
+            match halfkay::boot_teensy41_confirmed(&mut dev, opts.boot_confirm_timeout) {
+                Ok(halfkay::BootOutcome::Confirmed) => on_event(OperationEvent::BootConfirmed {
+                    target_id: run.id.to_string(),
+                }),
+                Ok(halfkay::BootOutcome::NotConfirmed) | Err(_) => {
+                    let timeout_ms = opts.boot_confirm_timeout.as_millis() as u64;
+                    on_event(OperationEvent::BootFailed {
+                        target_id: run.id.to_string(),
+                        timeout_ms,
+                    });
+                    return Err(FlashError::BootFailed { timeout_ms });
+                }
+            }
+        } else {
+            let _ = halfkay::boot_teensy41(&mut dev);
+        }
+
+        if let (Some(before), Some(timeout)) = (serial_before, serial_wait) {
+            match wait_for_new_serial(
+                &before,
+                halfkay_serial.as_deref(),
+                timeout,
+                opts,
+                run.started,
+            ) {
+                Ok(port) => {
+                    on_event(OperationEvent::SerialReappeared {
+                        target_id: run.id.to_string(),
+                        port: port.clone(),
+                    });
+                    if verify_serial {
+                        verify_readback(&port, run.id, fw, opts, on_event)?;
+                    }
+                }
+                // Best-effort wait: the firmware was written and booted.
+                Err(FlashError::BootNotConfirmed { .. }) if !serial_required => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    on_event(OperationEvent::Done {
+        target_id: run.id.to_string(),
+    });
+    Ok(())
+}
+
+/// Write every block of `fw` through `write`, reopening the device with `reopen` before a retry.
+///
+/// Returns the device in use after the last block (a retry may have replaced it).
+fn write_blocks<D, F, W, R>(
+    mut dev: D,
+    run: TargetRun,
+    fw: &hex::FirmwareImage,
+    opts: &FlashOptions,
+    mut write: W,
+    mut reopen: R,
+    on_event: &mut F,
+) -> Result<D, FlashError>
+where
+    F: FnMut(OperationEvent),
+    W: FnMut(&mut D, usize, usize) -> Result<(), halfkay::HalfKayError>,
+    R: FnMut(usize) -> Result<D, FlashError>,
+{
     let total_to_write = fw.blocks_to_write.len();
+    let block_size = fw.board.block_size() as u64;
+    let bytes_total = total_to_write as u64 * block_size;
+    let stride = opts.progress_stride.max(1);
     for (i, block_addr) in fw.blocks_to_write.iter().copied().enumerate() {
-        on_event(OperationEvent::Block {
-            target_id: target_id.to_string(),
-            index: i,
-            total: total_to_write,
-            addr: block_addr,
-        });
+        opts.check_abort(run.started)?;
+        if i % stride == 0 || i + 1 == total_to_write {
+            on_event(OperationEvent::Block {
+                target_id: run.id.to_string(),
+                index: i,
+                total: total_to_write,
+                addr: block_addr,
+            });
+        }
 
         let mut attempt: u32 = 0;
         loop {
             attempt = attempt.saturating_add(1);
-            match halfkay::write_block_teensy41(&mut dev, fw, block_addr, i) {
-                Ok(()) => break,
+            match write(&mut dev, block_addr, i) {
+                Ok(()) => {
+                    on_event(OperationEvent::Progress {
+                        target_id: run.id.to_string(),
+                        bytes_written: (i as u64 + 1) * block_size,
+                        bytes_total,
+                    });
+                    if !opts.inter_block_delay.is_zero() && i + 1 < total_to_write {
+                        std::thread::sleep(opts.inter_block_delay);
+                    }
+                    break;
+                }
                 Err(e) => {
                     if attempt > opts.retries {
                         return Err(FlashError::WriteFailed {
@@ -518,50 +1342,98 @@ where
                             source: e,
                         });
                     }
+                    opts.check_abort(run.started)?;
 
+                    let delay = opts.retry_policy.delay(attempt);
                     on_event(OperationEvent::Retry {
-                        target_id: target_id.to_string(),
+                        target_id: run.id.to_string(),
                         addr: block_addr,
                         attempt,
                         retries: opts.retries,
+                        delay_ms: delay.as_millis() as u64,
                         error: e.to_string(),
                     });
 
-                    std::thread::sleep(opts.reopen_delay);
-                    dev = reopen_halfkay_by_path(path, opts.reopen_timeout).map_err(|e2| {
-                        FlashError::ReopenFailed {
-                            path: path.to_string(),
-                            addr: block_addr,
-                            source: e2,
-                        }
-                    })?;
+                    std::thread::sleep(delay);
+                    dev = reopen(block_addr)?;
                     std::thread::sleep(opts.reopen_delay);
                 }
             }
         }
     }
+    Ok(dev)
+}
 
-    if !opts.no_reboot {
-        on_event(OperationEvent::Boot {
-            target_id: target_id.to_string(),
-        });
-        let _ = halfkay::boot_teensy41(&mut dev);
+fn verify_readback<F>(
+    port: &str,
+    target_id: &str,
+    fw: &hex::FirmwareImage,
+    opts: &FlashOptions,
+    on_event: &mut F,
+) -> Result<(), FlashError>
+where
+    F: FnMut(OperationEvent),
+{
+    match readback::verify_port(port, fw, opts.verify_timeout) {
+        Ok(readback::ReadbackOutcome::Match) => {
+            on_event(OperationEvent::Verified {
+                target_id: target_id.to_string(),
+                port: port.to_string(),
+            });
+            Ok(())
+        }
+        Ok(readback::ReadbackOutcome::Unsupported) => {
+            on_event(OperationEvent::VerifySkipped {
+                target_id: target_id.to_string(),
+                reason: "readback_unsupported",
+            });
+            Ok(())
+        }
+        Ok(readback::ReadbackOutcome::Mismatch { addr }) => Err(FlashError::VerifyFailed { addr }),
+        Err(e) => Err(FlashError::ReadbackFailed { source: e }),
     }
+}
 
-    on_event(OperationEvent::Done {
-        target_id: target_id.to_string(),
-    });
-    Ok(())
+fn wait_for_new_serial(
+    before: &HashSet<String>,
+    halfkay_serial: Option<&str>,
+    timeout: Duration,
+    opts: &FlashOptions,
+    target_started: Instant,
+) -> Result<String, FlashError> {
+    bootloader::wait_for_new_serial(
+        before,
+        halfkay_serial,
+        timeout,
+        opts.halfkay_poll_interval,
+        || opts.check_abort(target_started).is_ok(),
+    )
+    .map_err(|e| match e {
+        bootloader::WaitSerialError::ListFailed(source) => FlashError::DiscoveryFailed { source },
+        bootloader::WaitSerialError::Timeout => FlashError::BootNotConfirmed {
+            timeout_ms: timeout.as_millis() as u64,
+        },
+        e @ bootloader::WaitSerialError::Ambiguous { .. } => FlashError::AmbiguousTarget {
+            message: e.to_string(),
+        },
+        bootloader::WaitSerialError::Cancelled => opts
+            .check_abort(target_started)
+            .err()
+            .unwrap_or(FlashError::Cancelled),
+    })
 }
 
 fn reopen_halfkay_by_path(
     path: &str,
+    open: &halfkay::OpenOptions,
     timeout: Duration,
 ) -> Result<halfkay::HalfKayDevice, halfkay::HalfKayError> {
     let start = Instant::now();
     loop {
-        match halfkay::open_by_path(path) {
+        match halfkay::open_by_path_with(path, open) {
             Ok(d) => return Ok(d),
+            // Another process holds the device; retrying will not help.
+            Err(e @ halfkay::HalfKayError::Busy) => return Err(e),
             Err(e) => {
                 if start.elapsed() >= timeout {
                     return Err(e);
@@ -593,9 +1465,50 @@ mod tests {
             vid: 0x16C0,
             pid: 0x0478,
             path: path.to_string(),
+            bcd_device: None,
         })
     }
 
+    #[test]
+    fn retry_policy_backs_off_up_to_max() {
+        let flat = RetryPolicy::default();
+        assert_eq!(flat.delay(1), Duration::from_millis(150));
+        assert_eq!(flat.delay(4), Duration::from_millis(150));
+
+        let policy = RetryPolicy {
+            base: Duration::from_millis(100),
+            factor: 2.0,
+            max: Duration::from_millis(500),
+        };
+        let delays: Vec<u128> = (1..=5).map(|a| policy.delay(a).as_millis()).collect();
+        assert_eq!(delays, vec![100, 200, 400, 500, 500]);
+        assert_eq!(policy.delay(u32::MAX), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn firmware_map_prefers_serial_mapping_then_fallback() {
+        let mut map = FirmwareMap::default();
+        map.by_serial
+            .insert("1234".to_string(), PathBuf::from("bay1.hex"));
+
+        let mut mapped = serial("COM5");
+        if let Target::Serial(s) = &mut mapped {
+            s.serial_number = Some("1234".to_string());
+        }
+        let unmapped = serial("COM6");
+
+        assert_eq!(map.path_for(&mapped), Some(Path::new("bay1.hex")));
+        assert_eq!(map.path_for(&unmapped), None);
+        assert_eq!(map.path_for(&halfkay("HK1")), None);
+
+        map.fallback = Some(PathBuf::from("default.hex"));
+        assert_eq!(map.path_for(&unmapped), Some(Path::new("default.hex")));
+        assert_eq!(
+            map.path_for(&halfkay("HK1")),
+            Some(Path::new("default.hex"))
+        );
+    }
+
     #[test]
     fn select_targets_auto_prefers_single_halfkay() {
         let targets = vec![serial("COM5"), halfkay("HK1"), serial("COM6")];
@@ -613,6 +1526,69 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn select_targets_by_product_maps_selector_errors() {
+        let with_product = |port: &str| {
+            let mut t = serial(port);
+            if let Target::Serial(s) = &mut t {
+                s.product = Some("MIDI Studio Mk2".to_string());
+            }
+            t
+        };
+        let product = |name: &str| {
+            FlashSelection::Device(selector::TargetSelector::Product(name.to_string()))
+        };
+        let mut ignore = |_| {};
+
+        let one = vec![with_product("COM5"), serial("COM6")];
+        let selected = select_targets(product("midi studio mk2"), None, &one, true, &mut ignore);
+        assert_eq!(selected.unwrap()[0].id(), "serial:COM5");
+
+        let twins = vec![with_product("COM5"), with_product("COM6")];
+        let err = select_targets(product("MIDI Studio Mk2"), None, &twins, true, &mut ignore);
+        assert!(matches!(err, Err(FlashError::AmbiguousTarget { .. })));
+
+        let err = select_targets(product("Other"), None, &one, true, &mut ignore);
+        assert!(matches!(err, Err(FlashError::TargetNotFound { .. })));
+    }
+
+    #[test]
+    fn select_targets_devices_resolves_each_selector() {
+        let targets = vec![
+            serial("COM5"),
+            serial("COM6"),
+            halfkay("HK1"),
+            serial("COM7"),
+        ];
+        let devices = |sels: &[&str]| {
+            FlashSelection::Devices(
+                sels.iter()
+                    .map(|s| selector::parse_selector(s).unwrap())
+                    .collect(),
+            )
+        };
+        let mut events: Vec<OperationEvent> = Vec::new();
+
+        let selected = select_targets(
+            devices(&["COM7", "index:2", "serial:COM7"]),
+            None,
+            &targets,
+            true,
+            &mut |e| events.push(e),
+        )
+        .unwrap();
+        let ids: Vec<String> = selected.iter().map(Target::id).collect();
+        assert_eq!(ids, vec!["serial:COM7", "halfkay:HK1"]);
+        assert!(events.is_empty());
+
+        let err = select_targets(devices(&["COM5", "COM9"]), None, &targets, true, &mut |e| {
+            events.push(e)
+        });
+        assert!(
+            matches!(err, Err(FlashError::TargetNotFound { selector, .. }) if selector == "serial:COM9")
+        );
+    }
+
     #[test]
     fn select_targets_auto_prefers_named_serial_port() {
         let targets = vec![serial("COM5"), serial("COM6")];
@@ -649,4 +1625,206 @@ mod tests {
             .iter()
             .any(|e| matches!(e, OperationEvent::TargetSelected { .. })));
     }
+
+    #[test]
+    fn flash_error_codes_are_stable() {
+        assert_eq!(FlashError::NoTargets.code(), 1);
+        assert_eq!(FlashError::BootFailed { timeout_ms: 500 }.code(), 17);
+        assert_eq!(
+            FlashError::SerialRebootForbidden {
+                target_id: "serial:COM3".to_string()
+            }
+            .code(),
+            18
+        );
+        assert_eq!(FlashError::VerifyFailed { addr: 0x400 }.code(), 19);
+        assert_eq!(
+            FlashError::MultiTargetFailed {
+                failed: 1,
+                total: 2
+            }
+            .code(),
+            11
+        );
+        assert_eq!(
+            FlashError::AmbiguousTarget {
+                message: String::new()
+            }
+            .code(),
+            crate::reboot_api::RebootError::AmbiguousTarget {
+                message: String::new()
+            }
+            .code()
+        );
+    }
+
+    fn four_block_image() -> hex::FirmwareImage {
+        let board = crate::board::Board::Teensy41;
+        hex::FirmwareImage {
+            data: vec![0xFF; board.code_size()],
+            byte_count: 0,
+            num_blocks: board.code_size() / board.block_size(),
+            blocks_to_write: (0..4).map(|i| i * board.block_size()).collect(),
+            min_addr: None,
+            max_addr: None,
+            start_address: None,
+            fill: 0xFF,
+            board,
+        }
+    }
+
+    fn test_run() -> TargetRun<'static> {
+        TargetRun {
+            id: "halfkay:HK",
+            started: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn write_blocks_stops_when_cancelled() {
+        let fw = four_block_image();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let opts = FlashOptions {
+            cancel: Some(cancel.clone()),
+            ..Default::default()
+        };
+
+        let mut written = Vec::new();
+        let res = write_blocks(
+            (),
+            test_run(),
+            &fw,
+            &opts,
+            |_, addr, _| {
+                written.push(addr);
+                cancel.store(true, Ordering::SeqCst);
+                Ok(())
+            },
+            |_| Ok(()),
+            &mut |_| {},
+        );
+        assert!(matches!(res, Err(FlashError::Cancelled)));
+        assert_eq!(written, [0]);
+        assert_eq!(FlashError::Cancelled.code(), error_code::CANCELLED);
+    }
+
+    #[test]
+    fn baseline_diff_still_writes_every_block() {
+        let fw = four_block_image();
+        let mut baseline = four_block_image();
+        baseline.data[fw.board.block_size()] = 0x00;
+
+        match baseline_diff(&fw, &baseline) {
+            OperationEvent::BaselineDiff {
+                total_blocks,
+                changed_blocks,
+            } => assert_eq!((total_blocks, changed_blocks), (4, 1)),
+            other => panic!("expected BaselineDiff, got {other:?}"),
+        }
+
+        let mut written = Vec::new();
+        write_blocks(
+            (),
+            test_run(),
+            &fw,
+            &FlashOptions::default(),
+            |_, addr, _| {
+                written.push(addr);
+                Ok(())
+            },
+            |_| Ok(()),
+            &mut |_| {},
+        )
+        .unwrap();
+        assert_eq!(written, fw.blocks_to_write);
+    }
+
+    #[test]
+    fn write_blocks_emits_block_events_every_stride() {
+        let fw = four_block_image();
+        let block_indexes = |stride| {
+            let opts = FlashOptions {
+                progress_stride: stride,
+                ..Default::default()
+            };
+            let mut indexes = Vec::new();
+            let mut progress = 0;
+            write_blocks(
+                (),
+                test_run(),
+                &fw,
+                &opts,
+                |_, _, _| Ok(()),
+                |_| Ok(()),
+                &mut |ev| match ev {
+                    OperationEvent::Block { index, .. } => indexes.push(index),
+                    OperationEvent::Progress { .. } => progress += 1,
+                    _ => {}
+                },
+            )
+            .unwrap();
+            assert_eq!(progress, 4);
+            indexes
+        };
+
+        assert_eq!(block_indexes(1), [0, 1, 2, 3]);
+        assert_eq!(block_indexes(0), [0, 1, 2, 3]);
+        // First and last block are always reported.
+        assert_eq!(block_indexes(2), [0, 2, 3]);
+        assert_eq!(block_indexes(10), [0, 3]);
+    }
+
+    #[test]
+    fn write_blocks_retry_loop_honors_operation_timeout() {
+        let fw = four_block_image();
+        let opts = FlashOptions {
+            retries: u32::MAX,
+            retry_policy: RetryPolicy {
+                base: Duration::from_millis(5),
+                factor: 1.0,
+                max: Duration::from_millis(5),
+            },
+            reopen_delay: Duration::ZERO,
+            operation_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+
+        let res = write_blocks(
+            (),
+            test_run(),
+            &fw,
+            &opts,
+            |_, _, _| {
+                Err(halfkay::HalfKayError::ShortWrite {
+                    got: 0,
+                    expected: 1,
+                })
+            },
+            |_| Ok(()),
+            &mut |_| {},
+        );
+        match res {
+            Err(FlashError::OperationTimeout { elapsed }) => {
+                assert!(elapsed >= Duration::from_millis(50));
+            }
+            other => panic!("expected OperationTimeout, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn with_bridge_paused_skips_bridge_without_serial() {
+        let mut events: Vec<OperationEvent> = Vec::new();
+        let mut on_event = |e| events.push(e);
+        let r = with_bridge_paused(
+            &bridge_control::BridgeControlOptions::default(),
+            false,
+            &mut on_event,
+            |on_event| {
+                on_event(OperationEvent::DiscoverStart);
+                Err::<(), _>(FlashError::NoTargets)
+            },
+        );
+        assert!(matches!(r, Err(FlashError::NoTargets)));
+        assert!(matches!(events.as_slice(), [OperationEvent::DiscoverStart]));
+    }
 }
@@ -0,0 +1,146 @@
+//! Async wrapper over the blocking flash API (`async` feature).
+//!
+//! The flash runs on tokio's blocking pool; events are forwarded over a channel so an async
+//! caller can await them without tying up a runtime thread.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use super::{flash_teensy41_with_selection, FlashError, FlashOptions, FlashSelection};
+use crate::operation::OperationEvent;
+
+/// A flash running on the blocking pool.
+///
+/// Dropping the task (or a pending [`FlashTask::join`]) requests cancellation through
+/// `FlashOptions::cancel`: the flash stops at its next check and still resumes oc-bridge.
+pub struct FlashTask {
+    /// Operation events, in order. Yields `None` once the flash has finished.
+    pub events: mpsc::UnboundedReceiver<OperationEvent>,
+    handle: JoinHandle<Result<(), FlashError>>,
+    cancel: CancelOnDrop,
+}
+
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+impl FlashTask {
+    /// Request cancellation without waiting for the flash to stop.
+    pub fn cancel(&self) {
+        self.cancel.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Wait for the flash to finish. Events not yet received are discarded.
+    ///
+    /// A panic in the flash routine is resumed on the caller.
+    pub async fn join(self) -> Result<(), FlashError> {
+        let FlashTask { handle, cancel, .. } = self;
+        let result = match handle.await {
+            Ok(result) => result,
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Err(_) => Err(FlashError::Cancelled),
+        };
+        // Held until here so that dropping this future mid-flash cancels it.
+        drop(cancel);
+        result
+    }
+}
+
+/// Async [`flash_teensy41_with_selection`].
+///
+/// Uses `opts.cancel` when set (so an existing flag still works), otherwise installs one.
+///
+/// # Panics
+///
+/// When called outside a tokio runtime.
+pub fn flash_teensy41_async(
+    hex_path: PathBuf,
+    mut opts: FlashOptions,
+    selection: FlashSelection,
+) -> FlashTask {
+    let cancel = opts
+        .cancel
+        .get_or_insert_with(|| Arc::new(AtomicBool::new(false)))
+        .clone();
+    spawn_flash(cancel, move |on_event| {
+        flash_teensy41_with_selection(&hex_path, &opts, selection, on_event)
+    })
+}
+
+/// Run `flash` on the blocking pool, forwarding its events to the returned task.
+fn spawn_flash<R>(cancel: Arc<AtomicBool>, flash: R) -> FlashTask
+where
+    R: FnOnce(&mut dyn FnMut(OperationEvent)) -> Result<(), FlashError> + Send + 'static,
+{
+    let (tx, events) = mpsc::unbounded_channel();
+    let handle = tokio::task::spawn_blocking(move || {
+        flash(&mut |ev| {
+            // A caller that stopped listening still gets the result from `join`.
+            let _ = tx.send(ev);
+        })
+    });
+    FlashTask {
+        events,
+        handle,
+        cancel: CancelOnDrop(cancel),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn flash_async_forwards_events_and_result() {
+        runtime().block_on(async {
+            // A stand-in flash: the test must never discover (and flash) a real board.
+            let cancel = Arc::new(AtomicBool::new(false));
+            let mut task = spawn_flash(cancel, |on_event| {
+                on_event(OperationEvent::DiscoverStart);
+                on_event(OperationEvent::DiscoverDone { count: 0 });
+                Err(FlashError::NoTargets)
+            });
+            let mut events = Vec::new();
+            while let Some(ev) = task.events.recv().await {
+                events.push(ev);
+            }
+            assert!(matches!(
+                events[..],
+                [
+                    OperationEvent::DiscoverStart,
+                    OperationEvent::DiscoverDone { count: 0 }
+                ]
+            ));
+            assert!(matches!(task.join().await, Err(FlashError::NoTargets)));
+        });
+    }
+
+    #[test]
+    fn dropping_the_task_sets_the_cancel_flag() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let opts = FlashOptions {
+            cancel: Some(flag.clone()),
+            ..Default::default()
+        };
+        runtime().block_on(async {
+            let task =
+                flash_teensy41_async(PathBuf::from("missing.hex"), opts, FlashSelection::Auto);
+            drop(task);
+        });
+        assert!(flag.load(Ordering::SeqCst));
+    }
+}
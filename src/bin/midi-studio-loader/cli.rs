@@ -11,16 +11,88 @@ pub enum BridgeMethodArg {
     None,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum BridgeResumePolicyArg {
+    /// Resume after every operation.
+    Always,
+    /// Resume only when every target succeeded.
+    OnSuccess,
+    /// Leave the bridge paused (resume it yourself).
+    Never,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum ListSortArg {
+    /// Discovery order (matches `--device index:<n>`).
+    #[default]
+    Index,
+    /// USB serial number (targets without one last).
+    Serial,
+    /// Serial port name or HID path.
+    Port,
+    /// Target kind (HalfKay first), then discovery order.
+    Kind,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum BoardArg {
+    /// Teensy 4.0 (2 MB flash).
+    Teensy40,
+    /// Teensy 4.1 (8 MB flash).
+    #[default]
+    Teensy41,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum BridgeActionArg {
+    /// Pause oc-bridge and leave it paused (prints how to resume it).
+    Pause,
+    /// Resume oc-bridge over its control IPC.
+    Resume,
+    /// Query oc-bridge control status.
+    Status,
+}
+
 #[derive(ValueEnum, Clone, Copy, Debug)]
 pub enum JsonProgressArg {
     /// Emit a JSON event for every written block.
     Blocks,
     /// Emit fewer JSON events by throttling block output to percent changes.
     Percent,
+    /// Emit a byte-count `progress` event after every written block instead of `block` events.
+    Bytes,
     /// Do not emit per-block progress events.
     None,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListFormatArg {
+    /// One line per target (default).
+    Human,
+    /// JSON lines.
+    Json,
+    /// `index,id,kind,vid,pid,port,serial,product` header, then one row per target.
+    Csv,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum RebootStrategyArg {
+    /// Set the soft-reboot baud rate (`--reboot-baud`).
+    MagicBaud,
+    /// Pulse DTR, for USB-serial adapters that ignore the baud rate.
+    DtrPulse,
+    /// Magic baud first, then a DTR pulse if HalfKay does not appear.
+    Auto,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum JsonStreamArg {
+    /// JSON lines on stdout (default).
+    Stdout,
+    /// JSON lines on stderr, leaving stdout empty.
+    Stderr,
+}
+
 #[derive(Parser)]
 #[command(name = "midi-studio-loader")]
 #[command(about = "Teensy 4.1 flasher CLI (HalfKay)")]
@@ -32,8 +104,9 @@ pub struct Cli {
 
 #[derive(Subcommand)]
 pub enum Command {
-    /// Flash an Intel HEX to a Teensy 4.1 in HalfKay bootloader mode.
-    Flash(FlashArgs),
+    /// Flash an Intel HEX or S-record firmware to a Teensy 4.1 in HalfKay bootloader mode.
+    // Boxed: flash takes far more options than the other subcommands.
+    Flash(Box<FlashArgs>),
 
     /// Try to enter HalfKay bootloader without the button.
     Reboot(RebootArgs),
@@ -43,6 +116,22 @@ pub enum Command {
 
     /// Diagnose target detection and bridge coordination.
     Doctor(DoctorArgs),
+
+    /// Check that a flash would be ready to run (targets, firmware, oc-bridge) without flashing.
+    Preflight(PreflightArgs),
+
+    /// Pause, resume or query oc-bridge without flashing.
+    Bridge(BridgeArgs),
+
+    /// Flash every board plugged in while running, until Ctrl-C (burn-in stations).
+    Watch(WatchArgs),
+
+    /// Flash a test image onto the attached board and check that it boots (factory QA).
+    Selftest(SelftestArgs),
+
+    /// Print the process exit codes and their meanings.
+    #[command(hide = true)]
+    ExitCodes(ExitCodesArgs),
 }
 
 #[derive(Parser, Clone)]
@@ -75,23 +164,121 @@ pub struct BridgeControlArgs {
     #[arg(long, default_value_t = 7999)]
     pub bridge_control_port: u16,
 
+    /// oc-bridge control Unix socket; replaces the control port on Linux/macOS (ignored on
+    /// Windows).
+    #[arg(long, value_name = "PATH")]
+    pub bridge_control_socket: Option<PathBuf>,
+
     /// Max time to wait for oc-bridge IPC.
     #[arg(long, default_value_t = 2500)]
     pub bridge_control_timeout_ms: u64,
+
+    /// Extra oc-bridge IPC pause attempts before falling back to service/process control.
+    ///
+    /// All attempts share `--bridge-control-timeout-ms`.
+    #[arg(long, default_value_t = 2)]
+    pub bridge_control_retries: u32,
+
+    /// Argument to relaunch oc-bridge with when the process fallback cannot read its command
+    /// line (repeatable; replaces the default `--daemon --no-relaunch`).
+    #[arg(
+        long = "bridge-relaunch-arg",
+        value_name = "ARG",
+        allow_hyphen_values = true
+    )]
+    pub bridge_relaunch_args: Vec<String>,
+
+    /// When to resume oc-bridge after the operation.
+    ///
+    /// The bridge is always resumed if the process aborts mid-operation.
+    #[arg(long, value_enum, default_value_t = BridgeResumePolicyArg::Always)]
+    pub bridge_resume_policy: BridgeResumePolicyArg,
+
+    /// Leave oc-bridge paused after the operation (same as `--bridge-resume-policy never`).
+    ///
+    /// For quick flash iterations: later runs find the bridge already paused. The resume
+    /// command is printed.
+    #[arg(long, conflicts_with = "bridge_resume_policy")]
+    pub hold_bridge: bool,
+
+    /// Extra attempts when restarting the bridge service/processes fails.
+    #[arg(long, default_value_t = 2)]
+    pub bridge_resume_retries: u32,
+
+    /// Delay between bridge resume attempts.
+    #[arg(long, default_value_t = 1000)]
+    pub bridge_resume_retry_interval_ms: u64,
+
+    /// Do not warn when pausing would interrupt an active oc-bridge session.
+    #[arg(long)]
+    pub assume_yes: bool,
 }
 
 #[derive(Parser)]
 pub struct FlashArgs {
-    /// Path to Intel HEX firmware.
-    pub hex: PathBuf,
+    /// Path to Intel HEX or S-record firmware (fallback for unmapped targets with --firmware-map).
+    #[arg(required_unless_present_any = ["firmware_map", "print_selected"])]
+    pub hex: Option<PathBuf>,
 
-    /// Flash every detected target sequentially.
-    #[arg(long, conflicts_with = "device")]
+    /// Per-target firmware by USB serial number: `serial=path[,serial=path...]`.
+    ///
+    /// Unmapped targets use the positional HEX, or are skipped when it is omitted.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_parser = parse_firmware_map_entry,
+        conflicts_with_all = ["dry_run", "dry_run_no_device"]
+    )]
+    pub firmware_map: Vec<(String, PathBuf)>,
+
+    /// Label for the firmware, echoed in `hex_loaded`, `target_done` and the summary.
+    ///
+    /// Opaque: never interpreted, only reported.
+    #[arg(long, value_name = "LABEL")]
+    pub firmware_name: Option<String>,
+
+    /// Also write each target's events to `<DIR>/<serial-or-id>.ndjson`.
+    #[arg(long, value_name = "DIR")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Firmware last flashed to the target(s): report how many blocks differ from it
+    /// (every block is still written, since HalfKay erases the whole flash).
+    #[arg(
+        long,
+        value_name = "HEX",
+        conflicts_with_all = ["firmware_map", "dry_run", "dry_run_no_device"]
+    )]
+    pub baseline: Option<PathBuf>,
+
+    /// Flash every detected target sequentially (with --device: every target it matches).
+    #[arg(long)]
     pub all: bool,
 
-    /// Select a specific target (e.g. serial:COM6, halfkay:<path>, index:0).
-    #[arg(long, conflicts_with = "all")]
-    pub device: Option<String>,
+    /// Delay between consecutive targets with --all (milliseconds).
+    #[arg(long, default_value_t = 0)]
+    pub between_targets_delay_ms: u64,
+
+    /// Flash up to N HalfKay targets at once with --all (serial targets still go one at a time).
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with = "firmware_map"
+    )]
+    pub parallel: u64,
+
+    /// Delay after each written block except the last (milliseconds); paces writes through
+    /// flaky USB hubs.
+    #[arg(long, default_value_t = 0)]
+    pub inter_block_delay_ms: u64,
+
+    /// Select a specific target (e.g. serial:COM6, serial:/dev/ttyACM*, sn:<usb-serial>,
+    /// product:<name>, halfkay:<path>, halfkay:*<glob>*, index:0). `index:<n>` counts in
+    /// `list` order: HalfKay targets by path, then serial targets by port. Repeat to flash
+    /// several targets.
+    #[arg(long)]
+    pub device: Vec<String>,
 
     /// Wait for a target to appear (HalfKay or PJRC USB serial).
     #[arg(long)]
@@ -101,14 +288,116 @@ pub struct FlashArgs {
     #[arg(long, default_value_t = 0)]
     pub wait_timeout_ms: u64,
 
+    /// Max time for discovery and for each target, soft reboot to boot including write
+    /// retries (0 = no limit). Checked between blocks, retries and polls.
+    #[arg(long, default_value_t = 0)]
+    pub operation_timeout_ms: u64,
+
+    /// Interval between HID enumerations while waiting for HalfKay (milliseconds).
+    #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u64).range(1..))]
+    pub halfkay_poll_ms: u64,
+
     /// Do not reboot after programming.
     #[arg(long)]
     pub no_reboot: bool,
 
+    /// Only count a target as OK once it boots and its USB serial port is back.
+    #[arg(long, conflicts_with = "no_reboot")]
+    pub atomic: bool,
+
+    /// After boot, wait up to this long for the USB serial port to come back before reporting
+    /// the target done (milliseconds, 0 = don't wait). Not fatal if it does not return.
+    #[arg(long, default_value_t = 0, conflicts_with = "no_reboot")]
+    pub wait_serial_ms: u64,
+
+    /// Fail the target if HalfKay is still attached shortly after the boot command.
+    #[arg(long, conflicts_with = "no_reboot")]
+    pub confirm_boot: bool,
+
+    /// After boot, read the firmware back over USB serial and compare it with the file.
+    ///
+    /// Only serial targets whose firmware answers the readback handshake are verified; others
+    /// are skipped. A firmware that does not answer at all fails with exit code 14.
+    #[arg(long, conflicts_with = "no_reboot")]
+    pub verify: bool,
+
+    /// Abort if the HalfKay device that appears after a soft reboot has a different serial
+    /// number than the serial target (safe setting for shared benches).
+    #[arg(long)]
+    pub require_serial_match: bool,
+
+    /// Never soft-reboot a serial target: fail it instead, and flash only boards already in
+    /// HalfKay (for fixtures whose serial line must not be toggled).
+    #[arg(long, conflicts_with = "require_serial_match")]
+    pub no_serial_reboot: bool,
+
+    /// Baud rate that triggers the soft reboot: 134 for Teensyduino, 1200 for boards using
+    /// the 1200bps touch convention.
+    #[arg(long, default_value_t = 134)]
+    pub reboot_baud: u32,
+
+    /// How serial targets are rebooted: `auto` falls back to a DTR pulse when the magic baud
+    /// rate gets no HalfKay within 2 s.
+    #[arg(long, value_enum, default_value_t = RebootStrategyArg::MagicBaud)]
+    pub reboot_strategy: RebootStrategyArg,
+
+    /// Reject HEX files that end without an EOF record (truncated downloads).
+    #[arg(long)]
+    pub strict_eof: bool,
+
+    /// Reject HEX files whose records overwrite a byte with a different value.
+    #[arg(long)]
+    pub strict_overlaps: bool,
+
+    /// Write every block, filling blank regions with 0xFF (full recovery reflash; slow).
+    #[arg(long)]
+    pub full_reflash: bool,
+
+    /// Reject firmware whose populated image exceeds this many bytes.
+    #[arg(long)]
+    pub max_firmware_bytes: Option<usize>,
+
+    /// Load address of a raw `.bin` firmware (ignored for HEX and S-record input).
+    #[arg(long, value_name = "ADDR", default_value = "0x60000000", value_parser = parse_u32)]
+    pub bin_base: u32,
+
+    /// Board the firmware is built for; data beyond its flash is rejected, and so is a HalfKay
+    /// device that identifies as the other board.
+    #[arg(long, value_enum, default_value_t = BoardArg::Teensy41)]
+    pub board: BoardArg,
+
+    /// Erased-flash byte used to pad the image; blocks made only of it are skipped.
+    #[arg(long, value_name = "BYTE", default_value = "0xFF", value_parser = parse_u8)]
+    pub fill_byte: u8,
+
     /// Retries per block on write failure.
     #[arg(long, default_value_t = 3)]
     pub retries: u32,
 
+    /// Wait before the first retry of a block (milliseconds).
+    #[arg(long, default_value_t = 150)]
+    pub retry_delay_ms: u64,
+
+    /// Multiply the retry wait by this factor on each further attempt (1 = constant).
+    #[arg(long, default_value_t = 1.0)]
+    pub retry_backoff: f64,
+
+    /// Upper bound for the retry wait (milliseconds).
+    #[arg(long, default_value_t = 5000)]
+    pub retry_max_delay_ms: u64,
+
+    /// Deadline for each of the first blocks, which wait for the flash erase (milliseconds).
+    #[arg(long, default_value_t = 45_000)]
+    pub erase_timeout_ms: u64,
+
+    /// Deadline for each later block write (milliseconds).
+    #[arg(long, default_value_t = 500)]
+    pub write_timeout_ms: u64,
+
+    /// Open HalfKay without sharing so other processes cannot touch it mid-flash (Windows only).
+    #[arg(long)]
+    pub hid_open_exclusive: bool,
+
     /// Prefer a specific serial port name (e.g. COM6) when selecting among multiple devices.
     #[arg(long)]
     pub serial_port: Option<String>,
@@ -128,32 +417,222 @@ pub struct FlashArgs {
     ///
     /// - blocks: emit every block (most verbose)
     /// - percent: emit fewer progress events
+    /// - bytes: emit byte counts (`progress`) instead of blocks
     /// - none: no per-block progress events
     #[arg(long, value_enum, default_value_t = JsonProgressArg::Percent, requires = "json")]
     pub json_progress: JsonProgressArg,
 
+    /// Only emit the final JSON verdict (`operation_summary`, `dry_run`, `error`).
+    #[arg(long, requires = "json")]
+    pub json_compact_summary: bool,
+
+    /// Stream for JSON event lines (stdout keeps the default contract).
+    #[arg(long, value_enum, default_value_t = JsonStreamArg::Stdout, requires = "json")]
+    pub json_stream: JsonStreamArg,
+
+    /// Write the JSON lines to this file (truncated) instead of stdout.
+    #[arg(
+        long,
+        value_name = "PATH",
+        requires = "json",
+        conflicts_with = "json_stream"
+    )]
+    pub json_output: Option<PathBuf>,
+
+    /// Flush stdout and stderr after every event (keeps JSON and human lines in order).
+    #[arg(long)]
+    pub flush_events: bool,
+
     /// Validate inputs and selection without flashing.
     #[arg(long)]
     pub dry_run: bool,
 
+    /// Like --dry-run, but succeed with zero targets when no device is connected.
+    ///
+    /// Useful to validate a HEX file in CI without hardware.
+    #[arg(long)]
+    pub dry_run_no_device: bool,
+
+    /// Print the target id(s) that would be flashed and exit (no HEX load).
+    #[arg(long, conflicts_with_all = ["dry_run", "dry_run_no_device"])]
+    pub print_selected: bool,
+
     /// Reduce output (only errors).
     #[arg(long, conflicts_with = "verbose")]
     pub quiet: bool,
 
+    /// Print nothing at all on success; on failure, print the errors (for CI logs).
+    #[arg(long, conflicts_with = "verbose")]
+    pub quiet_success: bool,
+
     /// More logs to stderr.
     #[arg(long, short)]
     pub verbose: bool,
+
+    /// Never color human output (also disabled by `NO_COLOR` and when stderr is not a terminal).
+    #[arg(long)]
+    pub no_color: bool,
 }
 
 #[derive(Parser)]
 pub struct ListArgs {
+    /// Output format.
+    #[arg(long, value_enum, default_value_t = ListFormatArg::Human)]
+    pub format: ListFormatArg,
+
+    /// Emit JSON line output (same as `--format json`).
+    #[arg(long, conflicts_with = "format")]
+    pub json: bool,
+
+    /// Write the JSON lines or CSV rows to this file (truncated) instead of stdout.
+    ///
+    /// Not available with `--format human`.
+    #[arg(long, short = 'o', value_name = "PATH", alias = "json-output")]
+    pub output: Option<PathBuf>,
+
+    /// Order targets for display. Indexes always refer to discovery order.
+    #[arg(long, value_enum, default_value_t = ListSortArg::Index)]
+    pub sort: ListSortArg,
+
+    /// Keep running and report targets as they appear/disappear (Ctrl-C to stop).
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Poll interval for `--watch`.
+    #[arg(long, default_value_t = 500, requires = "watch")]
+    pub watch_interval_ms: u64,
+}
+
+impl ListArgs {
+    /// `--format`, with `--json` as an alias for `--format json`.
+    pub fn output_format(&self) -> ListFormatArg {
+        if self.json {
+            ListFormatArg::Json
+        } else {
+            self.format
+        }
+    }
+
+    /// Reject flag combinations clap cannot express.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.output.is_some() && self.output_format() == ListFormatArg::Human {
+            return Err("--output needs --format json or --format csv");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+pub struct PreflightArgs {
+    /// Path to the Intel HEX or S-record firmware that would be flashed.
+    pub hex: PathBuf,
+
+    /// Check every detected target (with --device: every target it matches).
+    #[arg(long)]
+    pub all: bool,
+
+    /// Select a specific target (e.g. serial:COM6, serial:/dev/ttyACM*, sn:<usb-serial>,
+    /// product:<name>, halfkay:<path>, halfkay:*<glob>*, index:0). `index:<n>` counts in
+    /// `list` order: HalfKay targets by path, then serial targets by port.
+    #[arg(long)]
+    pub device: Option<String>,
+
+    /// Reject HEX files that end without an EOF record (truncated downloads).
+    #[arg(long)]
+    pub strict_eof: bool,
+
+    #[command(flatten)]
+    pub bridge: BridgeControlArgs,
+
+    /// Emit JSON line output.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Parser)]
+pub struct BridgeArgs {
+    #[arg(value_enum)]
+    pub action: BridgeActionArg,
+
+    #[command(flatten)]
+    pub bridge: BridgeControlArgs,
+
+    /// Emit JSON line output.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Parser)]
+pub struct WatchArgs {
+    /// Path to the Intel HEX or S-record firmware flashed onto each new board.
+    pub hex: PathBuf,
+
+    /// Poll interval for new boards.
+    #[arg(long, default_value_t = 500)]
+    pub interval_ms: u64,
+
+    /// A new board must stay attached this long before it is flashed.
+    #[arg(long, default_value_t = 1000)]
+    pub debounce_ms: u64,
+
+    /// After boot, wait up to this long for the board's USB serial port, so the rebooted board
+    /// is not taken for a new one (0 = don't wait).
+    #[arg(long, default_value_t = 5000)]
+    pub wait_serial_ms: u64,
+
+    #[command(flatten)]
+    pub bridge: BridgeControlArgs,
+
+    /// Emit JSON line output.
+    #[arg(long)]
+    pub json: bool,
+
+    /// More logs to stderr.
+    #[arg(long, short)]
+    pub verbose: bool,
+
+    /// Never color human output (also disabled by `NO_COLOR` and when stderr is not a terminal).
+    #[arg(long)]
+    pub no_color: bool,
+}
+
+#[derive(Parser)]
+pub struct SelftestArgs {
+    /// Flash this firmware instead of the test image embedded in the binary (e.g. a blink
+    /// sketch that enumerates as USB serial).
+    #[arg(long)]
+    pub hex: Option<PathBuf>,
+
+    /// Max time to wait for HalfKay to appear (0 = forever).
+    #[arg(long, default_value_t = 60000)]
+    pub wait_timeout_ms: u64,
+
+    /// The test passes only if the board is back as USB serial this long after boot.
+    #[arg(long, default_value_t = 10000)]
+    pub wait_serial_ms: u64,
+
+    #[command(flatten)]
+    pub bridge: BridgeControlArgs,
+
     /// Emit JSON line output.
     #[arg(long)]
     pub json: bool,
+
+    /// More logs to stderr.
+    #[arg(long, short)]
+    pub verbose: bool,
+
+    /// Never color human output (also disabled by `NO_COLOR` and when stderr is not a terminal).
+    #[arg(long)]
+    pub no_color: bool,
 }
 
 #[derive(Parser)]
 pub struct DoctorArgs {
+    /// Dump HID usage, report sizes and raw report descriptor of each HalfKay device.
+    #[arg(long)]
+    pub dump_hid_descriptor: bool,
+
     /// Skip probing oc-bridge IPC.
     #[arg(long)]
     pub no_bridge_control: bool,
@@ -170,13 +649,33 @@ pub struct DoctorArgs {
     #[arg(long, default_value_t = 7999)]
     pub bridge_control_port: u16,
 
+    /// oc-bridge control Unix socket; replaces the control port on Linux/macOS (ignored on
+    /// Windows).
+    #[arg(long, value_name = "PATH")]
+    pub bridge_control_socket: Option<PathBuf>,
+
     /// Max time to wait for oc-bridge IPC.
     #[arg(long, default_value_t = 2500)]
     pub bridge_control_timeout_ms: u64,
 
+    /// Try to remediate what doctor finds, asking before each change.
+    ///
+    /// - Linux: install the PJRC udev rules and reload udev (needs root)
+    /// - Windows: check the HalfKay driver and print install guidance
+    #[arg(long)]
+    pub fix: bool,
+
+    /// Apply `--fix` changes without asking.
+    #[arg(long, requires = "fix")]
+    pub yes: bool,
+
     /// Emit JSON output.
     #[arg(long)]
     pub json: bool,
+
+    /// Write the JSON lines to this file (truncated) instead of stdout.
+    #[arg(long, value_name = "PATH", requires = "json")]
+    pub json_output: Option<PathBuf>,
 }
 
 #[derive(Parser)]
@@ -185,14 +684,57 @@ pub struct RebootArgs {
     #[arg(long, default_value_t = 60000)]
     pub wait_timeout_ms: u64,
 
-    /// Reboot every detected target sequentially.
-    #[arg(long, conflicts_with = "device")]
+    /// Interval between HID enumerations while waiting for HalfKay (milliseconds).
+    #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u64).range(1..))]
+    pub halfkay_poll_ms: u64,
+
+    /// Only report what would happen (already in HalfKay / soft reboot); touch nothing.
+    #[arg(long)]
+    pub probe_only: bool,
+
+    /// Print the target id(s) that would be rebooted and exit.
+    #[arg(long, conflicts_with = "probe_only")]
+    pub print_selected: bool,
+
+    /// Run discovery and selection, report the target(s) and whether oc-bridge would be
+    /// paused (`dry_run` event), and exit without rebooting anything.
+    #[arg(long, conflicts_with_all = ["probe_only", "print_selected"])]
+    pub dry_run: bool,
+
+    /// Send the boot command to HalfKay target(s) instead, restarting the firmware already in
+    /// flash (e.g. after an interrupted flash). Serial targets are left alone.
+    #[arg(long, conflicts_with_all = ["probe_only", "print_selected", "dry_run"])]
+    pub to_serial: bool,
+
+    /// With --to-serial: wait up to this long for the USB serial port to come back after boot
+    /// (milliseconds, 0 = don't wait). Fails if it does not return.
+    #[arg(long, default_value_t = 0, requires = "to_serial")]
+    pub wait_serial_ms: u64,
+
+    /// Reboot every detected target sequentially (with --device: every target it matches).
+    #[arg(long)]
     pub all: bool,
 
-    /// Select a specific target (e.g. serial:COM6, halfkay:<path>, index:0).
-    #[arg(long, conflicts_with = "all")]
+    /// Delay between consecutive targets with --all (milliseconds).
+    #[arg(long, default_value_t = 0)]
+    pub between_targets_delay_ms: u64,
+
+    /// Select a specific target (e.g. serial:COM6, serial:/dev/ttyACM*, sn:<usb-serial>,
+    /// product:<name>, halfkay:<path>, halfkay:*<glob>*, index:0). `index:<n>` counts in
+    /// `list` order: HalfKay targets by path, then serial targets by port.
+    #[arg(long)]
     pub device: Option<String>,
 
+    /// Baud rate that triggers the soft reboot: 134 for Teensyduino, 1200 for boards using
+    /// the 1200bps touch convention.
+    #[arg(long, default_value_t = 134)]
+    pub reboot_baud: u32,
+
+    /// How serial targets are rebooted: `auto` falls back to a DTR pulse when the magic baud
+    /// rate gets no HalfKay within 2 s.
+    #[arg(long, value_enum, default_value_t = RebootStrategyArg::MagicBaud)]
+    pub reboot_strategy: RebootStrategyArg,
+
     /// Prefer a specific serial port name (e.g. COM6).
     #[arg(long)]
     pub serial_port: Option<String>,
@@ -208,7 +750,62 @@ pub struct RebootArgs {
     #[arg(long, requires = "json")]
     pub json_timestamps: bool,
 
+    /// Only emit the final JSON verdict (`operation_summary`, `error`).
+    #[arg(long, requires = "json")]
+    pub json_compact_summary: bool,
+
+    /// Stream for JSON event lines (stdout keeps the default contract).
+    #[arg(long, value_enum, default_value_t = JsonStreamArg::Stdout, requires = "json")]
+    pub json_stream: JsonStreamArg,
+
+    /// Write the JSON lines to this file (truncated) instead of stdout.
+    #[arg(
+        long,
+        value_name = "PATH",
+        requires = "json",
+        conflicts_with = "json_stream"
+    )]
+    pub json_output: Option<PathBuf>,
+
+    /// Flush stdout and stderr after every event (keeps JSON and human lines in order).
+    #[arg(long)]
+    pub flush_events: bool,
+
     /// More logs to stderr.
     #[arg(long, short)]
     pub verbose: bool,
+
+    /// Never color human output (also disabled by `NO_COLOR` and when stderr is not a terminal).
+    #[arg(long)]
+    pub no_color: bool,
+}
+
+fn parse_firmware_map_entry(s: &str) -> Result<(String, PathBuf), String> {
+    match s.split_once('=') {
+        Some((serial, path)) if !serial.is_empty() && !path.is_empty() => {
+            Ok((serial.to_string(), PathBuf::from(path)))
+        }
+        _ => Err(format!("expected serial=path, got '{s}'")),
+    }
+}
+
+/// Decimal or `0x`-prefixed hexadecimal `u32`.
+fn parse_u32(s: &str) -> Result<u32, String> {
+    let r = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    r.map_err(|e| format!("invalid address '{s}': {e}"))
+}
+
+fn parse_u8(s: &str) -> Result<u8, String> {
+    let v = parse_u32(s).map_err(|_| format!("invalid byte '{s}'"))?;
+    u8::try_from(v).map_err(|_| format!("invalid byte '{s}': must be 0..=0xFF"))
+}
+
+#[derive(Parser, Clone)]
+pub struct ExitCodesArgs {
+    /// Emit JSON output.
+    #[arg(long)]
+    pub json: bool,
 }
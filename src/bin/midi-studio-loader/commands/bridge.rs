@@ -0,0 +1,80 @@
+use midi_studio_loader::bridge_control;
+use midi_studio_loader::operation::OperationEvent;
+
+use crate::cli;
+use crate::context;
+use crate::exit_codes;
+use crate::output::{Event, Reporter};
+
+pub fn run(args: cli::BridgeArgs, out: &mut dyn Reporter) -> i32 {
+    let opts = context::bridge_opts(&args.bridge);
+    match args.action {
+        cli::BridgeActionArg::Pause => pause(&opts, out),
+        cli::BridgeActionArg::Resume => resume(&opts, out),
+        cli::BridgeActionArg::Status => status(&opts, out),
+    }
+}
+
+fn pause(opts: &bridge_control::BridgeControlOptions, out: &mut dyn Reporter) -> i32 {
+    out.emit(Event::Operation(OperationEvent::BridgePauseStart));
+    let paused = bridge_control::pause_oc_bridge(opts);
+    match paused.outcome {
+        bridge_control::BridgePauseOutcome::Paused(info) => {
+            out.emit(Event::Operation(OperationEvent::BridgePaused { info }));
+        }
+        bridge_control::BridgePauseOutcome::Skipped(reason) => {
+            out.emit(Event::Operation(OperationEvent::BridgePauseSkipped {
+                reason,
+            }));
+        }
+        bridge_control::BridgePauseOutcome::Failed(error) => {
+            out.emit(Event::Operation(OperationEvent::BridgePauseFailed {
+                error,
+            }));
+            return exit_codes::EXIT_UNEXPECTED;
+        }
+    }
+    // The guard would resume on drop; disarm it so the bridge stays paused after we exit.
+    if let Some(guard) = paused.guard {
+        out.emit(Event::Operation(OperationEvent::BridgeLeftPaused {
+            hint: guard.keep_paused(),
+        }));
+    }
+    exit_codes::EXIT_OK
+}
+
+fn resume(opts: &bridge_control::BridgeControlOptions, out: &mut dyn Reporter) -> i32 {
+    let endpoint = opts.control_endpoint();
+    out.emit(Event::Operation(OperationEvent::BridgeResumeStart));
+    match bridge_control::control_resume(&endpoint, opts.control_timeout) {
+        Ok(()) => {
+            out.emit(Event::Operation(OperationEvent::BridgeResumed));
+            exit_codes::EXIT_OK
+        }
+        Err(e) => {
+            out.emit(Event::Operation(OperationEvent::BridgeResumeFailed {
+                error: bridge_control::BridgeControlErrorInfo {
+                    message: format!("bridge resume failed: {e}"),
+                    hint: None,
+                },
+            }));
+            exit_codes::EXIT_UNEXPECTED
+        }
+    }
+}
+
+fn status(opts: &bridge_control::BridgeControlOptions, out: &mut dyn Reporter) -> i32 {
+    let endpoint = opts.control_endpoint();
+    let status = bridge_control::control_status_at(&endpoint, opts.control_timeout)
+        .map_err(|e| e.to_string());
+    let code = if status.is_ok() {
+        exit_codes::EXIT_OK
+    } else {
+        exit_codes::EXIT_UNEXPECTED
+    };
+    out.emit(Event::BridgeStatus {
+        endpoint: endpoint.to_string(),
+        status,
+    });
+    code
+}
@@ -1,10 +1,12 @@
+use std::io::{BufRead, IsTerminal, Write};
 use std::time::Duration;
 
-use midi_studio_loader::{bridge_control, targets};
+use midi_studio_loader::operation::OperationEvent;
+use midi_studio_loader::{bridge_control, halfkay, targets};
 
 use crate::cli;
 use crate::exit_codes;
-use crate::output::{DoctorReport, Event, Reporter};
+use crate::output::{DoctorFix, DoctorReport, Event, FixStatus, PermissionHint, Reporter};
 
 pub fn run(args: cli::DoctorArgs, out: &mut dyn Reporter) -> i32 {
     let service_id = args
@@ -12,55 +14,453 @@ pub fn run(args: cli::DoctorArgs, out: &mut dyn Reporter) -> i32 {
         .clone()
         .unwrap_or_else(bridge_control::default_service_id_for_platform);
 
-    let targets = match targets::discover_targets() {
-        Ok(t) => t,
-        Err(e) => {
-            out.emit(Event::Error {
-                code: exit_codes::EXIT_UNEXPECTED,
-                message: format!("target discovery failed: {e}"),
-            });
-            return exit_codes::EXIT_UNEXPECTED;
+    // Enumerate each subsystem on its own so a broken one does not hide the other.
+    let mut targets = Vec::new();
+    let mut discovery_errors = Vec::new();
+    for r in [
+        targets::discover_halfkay_targets(),
+        targets::discover_serial_targets(),
+    ] {
+        match r {
+            Ok(t) => targets.extend(t),
+            Err(e) => discovery_errors.push((e.subsystem(), e.to_string())),
         }
+    }
+    targets::dedupe_targets(&mut targets);
+    targets::sort_targets(&mut targets);
+
+    let endpoint = bridge_control::BridgeControlOptions {
+        control_port: args.bridge_control_port,
+        control_socket: args.bridge_control_socket.clone(),
+        ..Default::default()
+    }
+    .control_endpoint();
+    let control_socket = match &endpoint {
+        bridge_control::ControlEndpoint::Socket(path) => Some((path.clone(), path.exists())),
+        bridge_control::ControlEndpoint::Port(_) => None,
     };
 
-    let svc_status = bridge_control::service_status(&service_id);
-    let procs = bridge_control::list_oc_bridge_processes();
+    let bridge = check_bridge(
+        &service_id,
+        &endpoint,
+        Duration::from_millis(args.bridge_control_timeout_ms),
+        !args.no_bridge_control,
+    );
 
-    let control_timeout = Duration::from_millis(args.bridge_control_timeout_ms);
-    let control = if args.no_bridge_control {
-        None
+    let (hid_devices, hid_error) = if args.dump_hid_descriptor {
+        match halfkay::describe_devices() {
+            Ok(d) => (Some(d), None),
+            Err(e) => (None, Some(e.to_string())),
+        }
     } else {
-        Some(bridge_control::control_status(
-            args.bridge_control_port,
-            control_timeout,
-        ))
+        (None, None)
     };
 
-    let (control_checked, control_status, control_error) = match control {
-        None => (false, None, None),
-        Some(Ok(st)) => (true, Some(st), None),
-        Some(Err(e)) => (true, None, Some(e.to_string())),
-    };
+    let permission_hint = check_hid_permissions(&targets);
+    let fixes = args.fix.then(|| {
+        let mut confirm = |question: &str| args.yes || ask(question);
+        platform_fixes(&targets, &mut confirm)
+    });
 
-    let (service_status, service_error) = match svc_status {
-        Ok(s) => (Some(s), None),
-        Err(e) => (None, Some(e.to_string())),
+    if let Some(pids) = competing_bridge_pids(&bridge.processes, bridge.control.as_ref()) {
+        let pids: Vec<String> = pids.iter().map(|p| p.to_string()).collect();
+        out.emit(Event::Operation(OperationEvent::Warning {
+            code: "multiple_bridge_instances",
+            message: format!(
+                "multiple oc-bridge processes share control {} (pids {}); pause/resume may hit the wrong one",
+                endpoint,
+                pids.join(", ")
+            ),
+        }));
+    }
+
+    let fix_failed = fixes
+        .iter()
+        .flatten()
+        .any(|f| f.status == FixStatus::Failed);
+    let code = if discovery_errors.is_empty() && !fix_failed {
+        exit_codes::EXIT_OK
+    } else {
+        exit_codes::EXIT_UNEXPECTED
     };
 
     let report = DoctorReport {
         service_id,
         targets,
+        discovery_errors,
         control_port: args.bridge_control_port,
+        control_socket,
         control_timeout_ms: args.bridge_control_timeout_ms,
+        control_checked: bridge.control_checked,
+        control: bridge.control,
+        control_error: bridge.control_error,
+        service_status: bridge.service_status,
+        service_error: bridge.service_error,
+        processes: bridge.processes,
+        hid_devices,
+        hid_error,
+        permission_hint,
+        fixes,
+    };
+
+    out.emit(Event::Doctor(report));
+
+    code
+}
+
+/// What oc-bridge looks like from here: IPC endpoint, service and processes.
+pub(crate) struct BridgeChecks {
+    pub control_checked: bool,
+    pub control: Option<bridge_control::BridgeControlStatus>,
+    pub control_error: Option<String>,
+    pub service_status: Option<bridge_control::ServiceStatus>,
+    pub service_error: Option<String>,
+    pub processes: Vec<bridge_control::OcBridgeProcessInfo>,
+}
+
+/// Probe oc-bridge the way `doctor` reports it (IPC only when `probe_control`).
+pub(crate) fn check_bridge(
+    service_id: &str,
+    endpoint: &bridge_control::ControlEndpoint,
+    control_timeout: Duration,
+    probe_control: bool,
+) -> BridgeChecks {
+    let (service_status, service_error) = match bridge_control::service_status(service_id) {
+        Ok(s) => (Some(s), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    let processes = bridge_control::list_oc_bridge_processes();
+
+    let (control_checked, control, control_error) = if !probe_control {
+        (false, None, None)
+    } else {
+        match bridge_control::control_status_at(endpoint, control_timeout) {
+            Ok(st) => (true, Some(st), None),
+            Err(e) => (true, None, Some(e.to_string())),
+        }
+    };
+
+    BridgeChecks {
         control_checked,
-        control: control_status,
+        control,
         control_error,
         service_status,
         service_error,
-        processes: procs,
+        processes,
+    }
+}
+
+/// First listed HalfKay device that the current user is not allowed to open.
+///
+/// Without PJRC's udev rules, hidraw nodes are root-only: the device enumerates but every
+/// flash fails with "no HalfKay device found".
+#[cfg(target_os = "linux")]
+fn check_hid_permissions(targets: &[targets::Target]) -> Option<PermissionHint> {
+    targets.iter().find_map(|t| match t {
+        targets::Target::HalfKay(hk) => match halfkay::open_by_path(&hk.path) {
+            Err(e) if e.is_permission_denied() => Some(PermissionHint {
+                path: hk.path.clone(),
+                error: e.to_string(),
+            }),
+            _ => None,
+        },
+        targets::Target::Serial(_) => None,
+    })
+}
+
+/// Device access needs no extra setup outside Linux.
+#[cfg(not(target_os = "linux"))]
+fn check_hid_permissions(_targets: &[targets::Target]) -> Option<PermissionHint> {
+    None
+}
+
+/// Ask a yes/no question on the terminal; anything but "y"/"yes" (or no terminal) is a no.
+fn ask(question: &str) -> bool {
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        return false;
+    }
+    eprint!("{question} [y/N] ");
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    if stdin.lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(target_os = "linux")]
+fn platform_fixes(
+    _targets: &[targets::Target],
+    confirm: &mut dyn FnMut(&str) -> bool,
+) -> Vec<DoctorFix> {
+    let install = install_udev_rules(
+        std::path::Path::new(crate::output::TEENSY_UDEV_RULES_PATH),
+        confirm,
+    );
+    let installed = install.status == FixStatus::Applied;
+    let mut fixes = vec![install];
+    if installed {
+        fixes.push(reload_udev_rules());
+    }
+    fixes
+}
+
+#[cfg(windows)]
+fn platform_fixes(
+    targets: &[targets::Target],
+    _confirm: &mut dyn FnMut(&str) -> bool,
+) -> Vec<DoctorFix> {
+    vec![check_hid_driver(targets)]
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn platform_fixes(
+    _targets: &[targets::Target],
+    _confirm: &mut dyn FnMut(&str) -> bool,
+) -> Vec<DoctorFix> {
+    Vec::new()
+}
+
+/// Write PJRC's udev rules to `path` unless they are already there.
+///
+/// A file that only differs in comments or whitespace counts as installed. Any other existing
+/// file is replaced only once confirmed as such, after a copy is saved next to it as `.bak`.
+#[cfg(target_os = "linux")]
+fn install_udev_rules(path: &std::path::Path, confirm: &mut dyn FnMut(&str) -> bool) -> DoctorFix {
+    use crate::output::TEENSY_UDEV_RULES;
+
+    let fix = |status, message| DoctorFix {
+        action: "install_udev_rules",
+        status,
+        message,
     };
+    let existing = std::fs::read_to_string(path).ok();
+    if existing
+        .as_deref()
+        .is_some_and(|s| udev_rule_lines(s) == udev_rule_lines(TEENSY_UDEV_RULES))
+    {
+        return fix(
+            FixStatus::Skipped,
+            format!("{} is already installed", path.display()),
+        );
+    }
+    let backup = path.with_extension("rules.bak");
+    let question = if existing.is_some() {
+        format!(
+            "Replace existing {} with the Teensy udev rules (backup: {})?",
+            path.display(),
+            backup.display()
+        )
+    } else {
+        format!("Write the Teensy udev rules to {}?", path.display())
+    };
+    if !confirm(&question) {
+        return fix(
+            FixStatus::Skipped,
+            "not confirmed (pass --yes to apply without asking)".to_string(),
+        );
+    }
+    let written = match existing {
+        Some(_) => {
+            std::fs::copy(path, &backup).and_then(|_| std::fs::write(path, TEENSY_UDEV_RULES))
+        }
+        None => std::fs::write(path, TEENSY_UDEV_RULES),
+    };
+    match written {
+        Ok(()) => fix(FixStatus::Applied, format!("wrote {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => fix(
+            FixStatus::Failed,
+            format!(
+                "{}: permission denied; re-run as root (sudo midi-studio-loader doctor --fix)",
+                path.display()
+            ),
+        ),
+        Err(e) => fix(FixStatus::Failed, format!("{}: {e}", path.display())),
+    }
+}
 
-    out.emit(Event::Doctor(report));
+/// The rules of a udev rules file: comments and blank lines dropped, whitespace trimmed.
+#[cfg(target_os = "linux")]
+fn udev_rule_lines(text: &str) -> Vec<&str> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn reload_udev_rules() -> DoctorFix {
+    let run = |args: &[&str]| -> Result<(), String> {
+        let out = std::process::Command::new("udevadm")
+            .args(args)
+            .output()
+            .map_err(|e| format!("udevadm: {e}"))?;
+        if out.status.success() {
+            Ok(())
+        } else {
+            Err(format!(
+                "udevadm {}: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&out.stderr).trim()
+            ))
+        }
+    };
+    let (status, message) =
+        match run(&["control", "--reload-rules"]).and_then(|()| run(&["trigger"])) {
+            Ok(()) => (
+                FixStatus::Applied,
+                "udev rules reloaded; replug the board".to_string(),
+            ),
+            Err(e) => (FixStatus::Failed, e),
+        };
+    DoctorFix {
+        action: "reload_udev_rules",
+        status,
+        message,
+    }
+}
+
+/// HalfKay is a plain HID device: listed means HidUsb is bound. Nothing is changed here.
+#[cfg(windows)]
+fn check_hid_driver(targets: &[targets::Target]) -> DoctorFix {
+    let bound = targets
+        .iter()
+        .any(|t| matches!(t, targets::Target::HalfKay(_)));
+    let (status, message) = if bound {
+        (
+            FixStatus::Skipped,
+            "HalfKay uses the built-in HidUsb driver; nothing to install".to_string(),
+        )
+    } else {
+        (
+            FixStatus::Manual,
+            "no HalfKay device visible: press PROGRAM on the Teensy. If Device Manager then lists \
+             16C0:0478 under \"Universal Serial Bus devices\" (a WinUSB driver, e.g. from Zadig), \
+             uninstall that driver so Windows falls back to HidUsb; no other driver is needed."
+                .to_string(),
+        )
+    };
+    DoctorFix {
+        action: "check_hid_driver",
+        status,
+        message,
+    }
+}
+
+/// PIDs of restartable oc-bridge processes when more than one runs behind a responding control port.
+fn competing_bridge_pids(
+    procs: &[bridge_control::OcBridgeProcessInfo],
+    control: Option<&bridge_control::BridgeControlStatus>,
+) -> Option<Vec<u32>> {
+    if !control.is_some_and(|st| st.ok) {
+        return None;
+    }
+    let pids: Vec<u32> = procs
+        .iter()
+        .filter(|p| p.restartable)
+        .map(|p| p.pid)
+        .collect();
+    (pids.len() > 1).then_some(pids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proc_info(pid: u32, restartable: bool) -> bridge_control::OcBridgeProcessInfo {
+        bridge_control::OcBridgeProcessInfo {
+            pid,
+            exe: None,
+            cmd: None,
+            restartable,
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn install_udev_rules_asks_once_and_is_idempotent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("00-teensy.rules");
+
+        let declined = install_udev_rules(&path, &mut |_| false);
+        assert_eq!(declined.status, FixStatus::Skipped);
+        assert!(!path.exists());
+
+        let installed = install_udev_rules(&path, &mut |_| true);
+        assert_eq!(installed.status, FixStatus::Applied);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            crate::output::TEENSY_UDEV_RULES
+        );
+
+        let again = install_udev_rules(&path, &mut |_| panic!("already installed"));
+        assert_eq!(again.status, FixStatus::Skipped);
+
+        let commented = format!("# local copy\n\n{}", crate::output::TEENSY_UDEV_RULES);
+        std::fs::write(&path, commented).unwrap();
+        let same_rules = install_udev_rules(&path, &mut |_| panic!("same rules"));
+        assert_eq!(same_rules.status, FixStatus::Skipped);
+
+        let missing_dir = dir.path().join("nope").join("00-teensy.rules");
+        let failed = install_udev_rules(&missing_dir, &mut |_| true);
+        assert_eq!(failed.status, FixStatus::Failed);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn install_udev_rules_backs_up_a_different_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("00-teensy.rules");
+        let custom = "SUBSYSTEMS==\"usb\", ATTRS{idVendor}==\"16c0\", MODE:=\"0660\"\n";
+        std::fs::write(&path, custom).unwrap();
+
+        let mut questions = Vec::new();
+        let declined = install_udev_rules(&path, &mut |q| {
+            questions.push(q.to_string());
+            false
+        });
+        assert_eq!(declined.status, FixStatus::Skipped);
+        assert!(
+            questions[0].starts_with("Replace existing"),
+            "{questions:?}"
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), custom);
+
+        let replaced = install_udev_rules(&path, &mut |_| true);
+        assert_eq!(replaced.status, FixStatus::Applied);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            crate::output::TEENSY_UDEV_RULES
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("00-teensy.rules.bak")).unwrap(),
+            custom
+        );
+    }
+
+    #[test]
+    fn competing_bridge_pids_requires_responding_control_port() {
+        let procs = vec![
+            proc_info(10, true),
+            proc_info(11, false),
+            proc_info(12, true),
+        ];
+        let status = bridge_control::BridgeControlStatus {
+            ok: true,
+            paused: false,
+            serial_open: None,
+            message: None,
+            client_count: None,
+            uptime_ms: None,
+            serial_port: None,
+        };
 
-    exit_codes::EXIT_OK
+        assert_eq!(
+            competing_bridge_pids(&procs, Some(&status)),
+            Some(vec![10, 12])
+        );
+        assert_eq!(competing_bridge_pids(&procs, None), None);
+        assert_eq!(competing_bridge_pids(&procs[..2], Some(&status)), None);
+    }
 }
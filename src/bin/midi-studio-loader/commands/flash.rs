@@ -1,7 +1,12 @@
 use std::path::Path;
+use std::time::Duration;
 
 use midi_studio_loader::api;
+use midi_studio_loader::board::Board;
+use midi_studio_loader::halfkay;
+use midi_studio_loader::hex;
 use midi_studio_loader::selector;
+use midi_studio_loader::teensy41;
 
 use crate::cli;
 use crate::context;
@@ -17,39 +22,147 @@ pub fn run(args: cli::FlashArgs, out: &mut dyn Reporter) -> i32 {
         wait: args.wait,
         wait_timeout,
         no_reboot: args.no_reboot,
+        atomic: args.atomic,
+        wait_for_serial_after_boot: context::wait_timeout(args.wait_serial_ms),
+        confirm_boot: args.confirm_boot,
+        verify: args.verify,
+        require_serial_match: args.require_serial_match,
+        no_serial_reboot: args.no_serial_reboot,
+        reboot_baud: args.reboot_baud,
+        reboot_strategy: context::reboot_strategy(args.reboot_strategy),
         retries: args.retries,
+        retry_policy: api::RetryPolicy {
+            base: Duration::from_millis(args.retry_delay_ms),
+            factor: args.retry_backoff,
+            max: Duration::from_millis(args.retry_max_delay_ms),
+        },
         serial_port: args.serial_port.clone(),
         bridge,
+        load: hex::LoadOptions {
+            strict_eof: args.strict_eof,
+            strict_overlaps: args.strict_overlaps,
+            max_firmware_bytes: args.max_firmware_bytes,
+            bin_base: Some(args.bin_base),
+            fill: Some(args.fill_byte),
+            board: match args.board {
+                cli::BoardArg::Teensy40 => Board::Teensy40,
+                cli::BoardArg::Teensy41 => Board::Teensy41,
+            },
+        },
+        full_reflash: args.full_reflash,
+        between_targets_delay: Duration::from_millis(args.between_targets_delay_ms),
+        parallel: args.parallel as usize,
+        inter_block_delay: Duration::from_millis(args.inter_block_delay_ms),
+        erase_timeout: Duration::from_millis(args.erase_timeout_ms),
+        write_timeout: Duration::from_millis(args.write_timeout_ms),
+        hid_open: halfkay::OpenOptions {
+            exclusive: args.hid_open_exclusive,
+        },
+        halfkay_poll_interval: Duration::from_millis(args.halfkay_poll_ms),
+        allow_no_targets: args.dry_run_no_device,
+        cancel: Some(context::cancel_on_ctrl_c()),
+        operation_timeout: context::wait_timeout(args.operation_timeout_ms),
         ..Default::default()
     };
 
-    let selection = if args.all {
-        api::FlashSelection::All
-    } else if let Some(sel) = args.device.clone() {
-        match selector::parse_selector(&sel) {
-            Ok(s) => api::FlashSelection::Device(s),
-            Err(e) => {
-                out.emit(Event::Error {
-                    code: exit_codes::EXIT_AMBIGUOUS,
-                    message: e.to_string(),
-                });
-                out.emit(Event::HintAmbiguousTargets);
-                return exit_codes::EXIT_AMBIGUOUS;
+    let selectors: Result<Vec<_>, _> = args
+        .device
+        .iter()
+        .map(|s| selector::parse_selector(s))
+        .collect();
+    let selection = match selectors {
+        Ok(mut sels) if sels.len() == 1 => {
+            let sel = sels.remove(0);
+            if args.all {
+                api::FlashSelection::AllMatching(sel)
+            } else {
+                api::FlashSelection::Device(sel)
             }
         }
-    } else {
-        api::FlashSelection::Auto
+        Ok(sels) if sels.is_empty() && args.all => api::FlashSelection::All,
+        Ok(sels) if sels.is_empty() => api::FlashSelection::Auto,
+        Ok(_) if args.all => {
+            out.emit(Event::Error {
+                code: exit_codes::EXIT_AMBIGUOUS,
+                message: "--all accepts a single --device".to_string(),
+            });
+            return exit_codes::EXIT_AMBIGUOUS;
+        }
+        Ok(sels) => api::FlashSelection::Devices(sels),
+        Err(e) => {
+            out.emit(Event::Error {
+                code: exit_codes::EXIT_AMBIGUOUS,
+                message: e.to_string(),
+            });
+            out.emit(Event::HintAmbiguousTargets);
+            return exit_codes::EXIT_AMBIGUOUS;
+        }
     };
 
-    if args.dry_run {
-        return dry_run(&args.hex, &opts, selection, out);
+    if args.print_selected {
+        return print_selected(&opts, selection, out);
+    }
+
+    if let Some(dir) = &args.output_dir {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            out.emit(Event::Error {
+                code: exit_codes::EXIT_UNEXPECTED,
+                message: format!("cannot create output dir {}: {e}", dir.display()),
+            });
+            return exit_codes::EXIT_UNEXPECTED;
+        }
     }
 
-    let mut rec = OperationRecorder::new("flash");
-    let r = api::flash_teensy41_with_selection(&args.hex, &opts, selection, |ev| {
+    let mut rec = OperationRecorder::new("flash").with_firmware_name(args.firmware_name.clone());
+    let mut on_event = |ev| {
         rec.observe(&ev);
         out.emit(Event::Operation(ev))
-    });
+    };
+    let r = if !args.firmware_map.is_empty() {
+        let map = api::FirmwareMap {
+            by_serial: args.firmware_map.iter().cloned().collect(),
+            fallback: args.hex.clone(),
+        };
+        api::flash_teensy41_with_firmware_map(&map, &opts, selection, &mut on_event)
+    } else {
+        let Some(hex) = args.hex.as_deref() else {
+            out.emit(Event::Error {
+                code: exit_codes::EXIT_UNEXPECTED,
+                message: "a HEX file is required unless --firmware-map is given".to_string(),
+            });
+            return exit_codes::EXIT_UNEXPECTED;
+        };
+        if args.dry_run || args.dry_run_no_device {
+            return dry_run(hex, &opts, selection, out);
+        }
+        match &args.baseline {
+            Some(path) => {
+                let baseline = match hex::FirmwareImage::load_teensy41_auto_with_progress(
+                    path,
+                    &opts.load,
+                    |_| {},
+                ) {
+                    Ok(fw) => fw,
+                    Err(e) => {
+                        out.emit(Event::ErrorWithDetails {
+                            code: exit_codes::EXIT_INVALID_HEX,
+                            message: format!("invalid baseline {}: {e}", path.display()),
+                            details: e.to_json_value(),
+                        });
+                        return exit_codes::EXIT_INVALID_HEX;
+                    }
+                };
+                api::flash_teensy41_with_baseline(
+                    hex,
+                    &opts,
+                    selection,
+                    Some(&baseline),
+                    &mut on_event,
+                )
+            }
+            None => api::flash_teensy41_with_selection(hex, &opts, selection, &mut on_event),
+        }
+    };
 
     match r {
         Ok(()) => {
@@ -60,10 +173,7 @@ pub fn run(args: cli::FlashArgs, out: &mut dyn Reporter) -> i32 {
         Err(e) => {
             let code = map_flash_error(&e);
             let msg = e.to_string();
-            out.emit(Event::Error {
-                code,
-                message: msg.clone(),
-            });
+            out.emit(flash_error_event(code, &e));
             if matches!(
                 e,
                 api::FlashError::NoTargets
@@ -72,12 +182,47 @@ pub fn run(args: cli::FlashArgs, out: &mut dyn Reporter) -> i32 {
             ) {
                 out.emit(Event::HintAmbiguousTargets);
             }
+            if matches!(e, api::FlashError::NoTargets) {
+                out.emit(Event::HintNoDevice);
+            }
             out.emit(Event::OperationSummary(rec.finish(code, Some(msg))));
             code
         }
     }
 }
 
+fn print_selected(
+    opts: &api::FlashOptions,
+    selection: api::FlashSelection,
+    out: &mut dyn Reporter,
+) -> i32 {
+    let r = api::select_flash_targets(opts, selection, |ev| out.emit(Event::Operation(ev)));
+    match r {
+        Ok(selected) => {
+            out.emit(Event::SelectedTargets(
+                selected.iter().map(|t| t.id()).collect(),
+            ));
+            exit_codes::EXIT_OK
+        }
+        Err(e) => {
+            let code = map_flash_error(&e);
+            out.emit(flash_error_event(code, &e));
+            if matches!(
+                e,
+                api::FlashError::NoTargets
+                    | api::FlashError::TargetNotFound { .. }
+                    | api::FlashError::AmbiguousTarget { .. }
+            ) {
+                out.emit(Event::HintAmbiguousTargets);
+            }
+            if matches!(e, api::FlashError::NoTargets) {
+                out.emit(Event::HintNoDevice);
+            }
+            code
+        }
+    }
+}
+
 fn dry_run(
     hex: &Path,
     opts: &api::FlashOptions,
@@ -91,8 +236,12 @@ fn dry_run(
         Ok(plan) => {
             let summary = DryRunSummary {
                 bytes: plan.firmware.byte_count,
+                digest: plan.firmware.content_digest(),
                 blocks: plan.firmware.num_blocks,
                 blocks_to_write: plan.firmware.blocks_to_write.len(),
+                min_addr: plan.firmware.min_addr.map(flash_addr),
+                max_addr: plan.firmware.max_addr.map(flash_addr),
+                start_address: plan.firmware.start_address,
                 target_ids: plan.selected_targets.iter().map(|t| t.id()).collect(),
                 needs_serial: plan.needs_serial,
                 bridge_enabled: opts.bridge.enabled,
@@ -103,10 +252,7 @@ fn dry_run(
         }
         Err(e) => {
             let code = map_flash_error(&e);
-            out.emit(Event::Error {
-                code,
-                message: e.to_string(),
-            });
+            out.emit(flash_error_event(code, &e));
             if matches!(
                 e,
                 api::FlashError::NoTargets
@@ -115,17 +261,42 @@ fn dry_run(
             ) {
                 out.emit(Event::HintAmbiguousTargets);
             }
+            if matches!(e, api::FlashError::NoTargets) {
+                out.emit(Event::HintNoDevice);
+            }
             code
         }
     }
 }
 
-fn map_flash_error(e: &api::FlashError) -> i32 {
+/// Absolute flash address of an image offset.
+fn flash_addr(offset: usize) -> u32 {
+    teensy41::FLEXSPI_BASE + offset as u32
+}
+
+/// The `error` event for a failed flash, with the HEX parse location when there is one.
+pub(crate) fn flash_error_event(code: i32, e: &api::FlashError) -> Event {
+    match e {
+        api::FlashError::InvalidHex { source } => Event::ErrorWithDetails {
+            code,
+            message: e.to_string(),
+            details: source.to_json_value(),
+        },
+        _ => Event::Error {
+            code,
+            message: e.to_string(),
+        },
+    }
+}
+
+pub(crate) fn map_flash_error(e: &api::FlashError) -> i32 {
     match e.kind() {
         api::FlashErrorKind::NoDevice => exit_codes::EXIT_NO_DEVICE,
         api::FlashErrorKind::AmbiguousTarget => exit_codes::EXIT_AMBIGUOUS,
         api::FlashErrorKind::InvalidHex => exit_codes::EXIT_INVALID_HEX,
         api::FlashErrorKind::WriteFailed => exit_codes::EXIT_WRITE_FAILED,
+        api::FlashErrorKind::ReadbackFailed => exit_codes::EXIT_READBACK_FAILED,
+        api::FlashErrorKind::Cancelled => exit_codes::EXIT_CANCELLED,
         api::FlashErrorKind::Unexpected => exit_codes::EXIT_UNEXPECTED,
     }
 }
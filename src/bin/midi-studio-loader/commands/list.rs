@@ -1,13 +1,20 @@
-use midi_studio_loader::targets;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use midi_studio_loader::targets::{self, Target};
 
 use crate::cli;
+use crate::context;
 use crate::exit_codes;
 use crate::output::{Event, Reporter};
 
-pub fn run(_args: cli::ListArgs, out: &mut dyn Reporter) -> i32 {
+pub fn run(args: cli::ListArgs, out: &mut dyn Reporter) -> i32 {
     match targets::discover_targets() {
         Ok(ts) => {
-            out.emit(Event::ListTargets(ts));
+            out.emit(Event::ListTargets(sort_targets(ts.clone(), args.sort)));
+            if args.watch {
+                watch(ts, Duration::from_millis(args.watch_interval_ms), out);
+            }
             exit_codes::EXIT_OK
         }
         Err(e) => {
@@ -19,3 +26,134 @@ pub fn run(_args: cli::ListArgs, out: &mut dyn Reporter) -> i32 {
         }
     }
 }
+
+/// Poll discovery until Ctrl-C, reporting each change against the previous snapshot.
+fn watch(mut before: Vec<Target>, interval: Duration, out: &mut dyn Reporter) {
+    let cancel = context::cancel_on_ctrl_c();
+    while !cancel.load(Ordering::SeqCst) {
+        std::thread::sleep(interval);
+        let now = match targets::discover_targets() {
+            Ok(ts) => ts,
+            Err(e) => {
+                // Transient (device mid-enumeration): keep the last snapshot and retry.
+                tracing::debug!(err = %e, "watch discovery failed");
+                continue;
+            }
+        };
+        let (added, removed) = diff_targets(&before, &now);
+        for (i, t) in removed {
+            out.emit(Event::TargetRemoved(i, t));
+        }
+        for (i, t) in added {
+            out.emit(Event::TargetAdded(i, t));
+        }
+        before = now;
+    }
+}
+
+/// Targets paired with their index in a discovery snapshot.
+type IndexedTargets = Vec<(usize, Target)>;
+
+/// Targets only in `now` (added) and only in `before` (removed), by id, each with its index
+/// in the snapshot it belongs to.
+fn diff_targets(before: &[Target], now: &[Target]) -> (IndexedTargets, IndexedTargets) {
+    let added = now
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| !before.iter().any(|b| b.id() == t.id()))
+        .map(|(i, t)| (i, t.clone()))
+        .collect();
+    let removed = before
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| !now.iter().any(|n| n.id() == t.id()))
+        .map(|(i, t)| (i, t.clone()))
+        .collect();
+    (added, removed)
+}
+
+/// Pair each target with its discovery index, then order for display (stable).
+fn sort_targets(targets: Vec<Target>, key: cli::ListSortArg) -> Vec<(usize, Target)> {
+    let mut indexed: Vec<(usize, Target)> = targets.into_iter().enumerate().collect();
+    match key {
+        cli::ListSortArg::Index => {}
+        cli::ListSortArg::Serial => indexed.sort_by(|(_, a), (_, b)| {
+            let a = serial_number(a);
+            let b = serial_number(b);
+            (a.is_none(), a).cmp(&(b.is_none(), b))
+        }),
+        cli::ListSortArg::Port => indexed.sort_by(|(_, a), (_, b)| port(a).cmp(port(b))),
+        cli::ListSortArg::Kind => indexed.sort_by_key(|(_, t)| match t {
+            Target::HalfKay(_) => 0,
+            Target::Serial(_) => 1,
+        }),
+    }
+    indexed
+}
+
+fn serial_number(t: &Target) -> Option<&str> {
+    match t {
+        Target::HalfKay(_) => None,
+        Target::Serial(s) => s.serial_number.as_deref(),
+    }
+}
+
+fn port(t: &Target) -> &str {
+    match t {
+        Target::HalfKay(hk) => &hk.path,
+        Target::Serial(s) => &s.port_name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use midi_studio_loader::targets::{HalfKayTarget, SerialTarget};
+
+    fn serial(port: &str, sn: Option<&str>) -> Target {
+        Target::Serial(SerialTarget {
+            port_name: port.to_string(),
+            vid: 0x16C0,
+            pid: 0x0483,
+            serial_number: sn.map(str::to_string),
+            manufacturer: None,
+            product: None,
+        })
+    }
+
+    #[test]
+    fn sort_keeps_discovery_indexes() {
+        let ts = vec![
+            Target::HalfKay(HalfKayTarget {
+                vid: 0x16C0,
+                pid: 0x0478,
+                path: "HK".to_string(),
+                bcd_device: None,
+            }),
+            serial("COM9", Some("200")),
+            serial("COM3", Some("100")),
+        ];
+
+        let by_serial = sort_targets(ts.clone(), cli::ListSortArg::Serial);
+        let order: Vec<usize> = by_serial.iter().map(|(i, _)| *i).collect();
+        assert_eq!(order, vec![2, 1, 0]);
+
+        let by_port = sort_targets(ts, cli::ListSortArg::Port);
+        let order: Vec<usize> = by_port.iter().map(|(i, _)| *i).collect();
+        assert_eq!(order, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn diff_targets_reports_changes_by_id() {
+        let before = vec![serial("COM3", Some("100")), serial("COM9", Some("200"))];
+        let now = vec![serial("COM4", None), serial("COM9", Some("200"))];
+
+        let (added, removed) = diff_targets(&before, &now);
+        assert_eq!(added, vec![(0, serial("COM4", None))]);
+        assert_eq!(removed, vec![(0, serial("COM3", Some("100")))]);
+
+        let (added, removed) = diff_targets(&now, &now);
+        assert!(added.is_empty() && removed.is_empty());
+    }
+}
@@ -1,4 +1,8 @@
+pub mod bridge;
 pub mod doctor;
 pub mod flash;
 pub mod list;
+pub mod preflight;
 pub mod reboot;
+pub mod selftest;
+pub mod watch;
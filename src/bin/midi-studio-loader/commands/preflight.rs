@@ -0,0 +1,169 @@
+use midi_studio_loader::operation::OperationEvent;
+use midi_studio_loader::{api, bridge_control, hex, selector};
+
+use crate::cli;
+use crate::commands::{doctor, flash};
+use crate::context;
+use crate::exit_codes;
+use crate::output::{Event, PreflightIssue, PreflightReport, Reporter};
+
+pub fn run(args: cli::PreflightArgs, out: &mut dyn Reporter) -> i32 {
+    let bridge = context::bridge_opts(&args.bridge);
+    let opts = api::FlashOptions {
+        bridge: bridge.clone(),
+        load: hex::LoadOptions {
+            strict_eof: args.strict_eof,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let mut report = PreflightReport {
+        firmware_bytes: None,
+        firmware_crc32: None,
+        target_ids: Vec::new(),
+        needs_serial: false,
+        bridge_checked: false,
+        blocking: Vec::new(),
+    };
+
+    let selection = match args.device.as_deref().map(selector::parse_selector) {
+        Some(Ok(s)) if args.all => Some(api::FlashSelection::AllMatching(s)),
+        Some(Ok(s)) => Some(api::FlashSelection::Device(s)),
+        Some(Err(e)) => {
+            report.blocking.push(PreflightIssue {
+                check: "targets",
+                code: exit_codes::EXIT_AMBIGUOUS,
+                message: e.to_string(),
+            });
+            None
+        }
+        None if args.all => Some(api::FlashSelection::All),
+        None => Some(api::FlashSelection::Auto),
+    };
+
+    // Until the plan says otherwise, assume the flash will need the serial port.
+    let mut needs_serial = true;
+    if let Some(selection) = selection {
+        let plan = api::plan_teensy41_with_selection(&args.hex, &opts, selection, |ev| {
+            // Keep the firmware fingerprint even if target selection fails afterwards.
+            if let OperationEvent::HexLoaded { bytes, crc32, .. } = ev {
+                report.firmware_bytes = Some(bytes);
+                report.firmware_crc32 = Some(crc32);
+            }
+            out.emit(Event::Operation(ev))
+        });
+        match plan {
+            Ok(plan) => {
+                report.target_ids = plan.selected_targets.iter().map(|t| t.id()).collect();
+                report.needs_serial = plan.needs_serial;
+                needs_serial = plan.needs_serial;
+            }
+            Err(e) => report.blocking.push(PreflightIssue {
+                check: match e {
+                    api::FlashError::InvalidHex { .. } => "firmware",
+                    _ => "targets",
+                },
+                code: flash::map_flash_error(&e),
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    if bridge.enabled && needs_serial {
+        let service_id = bridge
+            .service_id
+            .clone()
+            .unwrap_or_else(bridge_control::default_service_id_for_platform);
+        let checks = doctor::check_bridge(
+            &service_id,
+            &bridge.control_endpoint(),
+            bridge.control_timeout,
+            true,
+        );
+        report.bridge_checked = true;
+        if let Some(message) = bridge_blocker(&checks, bridge.allow_process_fallback) {
+            report.blocking.push(PreflightIssue {
+                check: "bridge",
+                code: exit_codes::EXIT_UNEXPECTED,
+                message,
+            });
+        }
+    }
+
+    let code = report
+        .blocking
+        .first()
+        .map_or(exit_codes::EXIT_OK, |issue| issue.code);
+    out.emit(Event::Preflight(report));
+    code
+}
+
+/// Why `--bridge-method auto` would fail to pause oc-bridge, if it would.
+///
+/// Mirrors the pause order: IPC, then the service, then restartable processes.
+fn bridge_blocker(checks: &doctor::BridgeChecks, allow_process_fallback: bool) -> Option<String> {
+    if checks.control.as_ref().is_some_and(|st| st.ok) {
+        return None;
+    }
+    if let Some(e) = &checks.service_error {
+        return Some(format!("unable to query the oc-bridge service: {e}"));
+    }
+    if matches!(
+        checks.service_status,
+        Some(bridge_control::ServiceStatus::Running | bridge_control::ServiceStatus::Stopped)
+    ) || checks.processes.is_empty()
+    {
+        return None;
+    }
+    if allow_process_fallback && checks.processes.iter().any(|p| p.restartable) {
+        return None;
+    }
+    let pids: Vec<String> = checks.processes.iter().map(|p| p.pid.to_string()).collect();
+    Some(format!(
+        "oc-bridge is running (pids {}) but cannot be paused: control port not responding, no service, {}",
+        pids.join(", "),
+        if allow_process_fallback {
+            "and the process cannot be restarted"
+        } else {
+            "and process fallback is disabled"
+        }
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checks(processes: Vec<bridge_control::OcBridgeProcessInfo>) -> doctor::BridgeChecks {
+        doctor::BridgeChecks {
+            control_checked: true,
+            control: None,
+            control_error: Some("connection refused".to_string()),
+            service_status: Some(bridge_control::ServiceStatus::NotInstalled),
+            service_error: None,
+            processes,
+        }
+    }
+
+    #[test]
+    fn bridge_blocker_follows_pause_fallbacks() {
+        let proc_info = |restartable| bridge_control::OcBridgeProcessInfo {
+            pid: 42,
+            exe: None,
+            cmd: None,
+            restartable,
+        };
+
+        assert_eq!(bridge_blocker(&checks(Vec::new()), true), None);
+        assert_eq!(bridge_blocker(&checks(vec![proc_info(true)]), true), None);
+        assert!(bridge_blocker(&checks(vec![proc_info(true)]), false)
+            .is_some_and(|m| m.contains("pids 42")));
+        assert!(bridge_blocker(&checks(vec![proc_info(false)]), true).is_some());
+
+        let mut svc_down = checks(vec![proc_info(false)]);
+        svc_down.service_status = None;
+        svc_down.service_error = Some("access denied".to_string());
+        assert!(bridge_blocker(&svc_down, true).is_some());
+    }
+}
@@ -1,16 +1,18 @@
+use std::time::Duration;
+
 use midi_studio_loader::selector;
+use midi_studio_loader::targets::Target;
 use midi_studio_loader::{api, reboot_api};
 
 use crate::cli;
 use crate::context;
 use crate::exit_codes;
-use crate::output::{Event, OperationRecorder, Reporter};
+use crate::output::{Event, OperationRecorder, RebootProbeSummary, Reporter};
 
 pub fn run(args: cli::RebootArgs, out: &mut dyn Reporter) -> i32 {
-    let selection = if args.all {
-        api::FlashSelection::All
-    } else if let Some(sel) = args.device.clone() {
+    let selection = if let Some(sel) = args.device.clone() {
         match selector::parse_selector(&sel) {
+            Ok(s) if args.all => api::FlashSelection::AllMatching(s),
             Ok(s) => api::FlashSelection::Device(s),
             Err(e) => {
                 out.emit(Event::Error {
@@ -21,6 +23,8 @@ pub fn run(args: cli::RebootArgs, out: &mut dyn Reporter) -> i32 {
                 return exit_codes::EXIT_AMBIGUOUS;
             }
         }
+    } else if args.all {
+        api::FlashSelection::All
     } else {
         api::FlashSelection::Auto
     };
@@ -28,15 +32,37 @@ pub fn run(args: cli::RebootArgs, out: &mut dyn Reporter) -> i32 {
     let opts = reboot_api::RebootOptions {
         serial_port: args.serial_port.clone(),
         wait_timeout: context::wait_timeout(args.wait_timeout_ms),
+        poll_interval: Duration::from_millis(args.halfkay_poll_ms),
+        between_targets_delay: Duration::from_millis(args.between_targets_delay_ms),
+        reboot_baud: args.reboot_baud,
+        reboot_strategy: context::reboot_strategy(args.reboot_strategy),
         bridge: context::bridge_opts(&args.bridge),
+        wait_for_serial_after_boot: context::wait_timeout(args.wait_serial_ms),
         ..Default::default()
     };
 
+    if args.probe_only || args.dry_run {
+        return probe(&opts, selection, args.dry_run, out);
+    }
+    if args.print_selected {
+        return print_selected(&opts, selection, out);
+    }
+    // Only the real reboot installs the Ctrl-C handler (probe never blocks).
+    let opts = reboot_api::RebootOptions {
+        cancel: Some(context::cancel_on_ctrl_c()),
+        ..opts
+    };
+
     let mut rec = OperationRecorder::new("reboot");
-    let r = reboot_api::reboot_teensy41_with_selection(&opts, selection, |ev| {
+    let on_event = |ev| {
         rec.observe(&ev);
         out.emit(Event::Operation(ev))
-    });
+    };
+    let r = if args.to_serial {
+        reboot_api::boot_teensy41_with_selection(&opts, selection, on_event)
+    } else {
+        reboot_api::reboot_teensy41_with_selection(&opts, selection, on_event)
+    };
     match r {
         Ok(()) => {
             let code = exit_codes::EXIT_OK;
@@ -44,26 +70,102 @@ pub fn run(args: cli::RebootArgs, out: &mut dyn Reporter) -> i32 {
             code
         }
         Err(e) => {
-            let code = match e.kind() {
-                reboot_api::RebootErrorKind::NoDevice => exit_codes::EXIT_NO_DEVICE,
-                reboot_api::RebootErrorKind::AmbiguousTarget => exit_codes::EXIT_AMBIGUOUS,
-                reboot_api::RebootErrorKind::Unexpected => exit_codes::EXIT_UNEXPECTED,
-            };
+            let code = map_reboot_error(&e);
             let msg = e.to_string();
-            out.emit(Event::Error {
-                code,
-                message: msg.clone(),
-            });
-            if matches!(
-                e,
-                reboot_api::RebootError::NoTargets
-                    | reboot_api::RebootError::TargetNotFound { .. }
-                    | reboot_api::RebootError::AmbiguousTarget { .. }
-            ) {
-                out.emit(Event::HintAmbiguousTargets);
-            }
+            emit_reboot_error(&e, code, out);
             out.emit(Event::OperationSummary(rec.finish(code, Some(msg))));
             code
         }
     }
 }
+
+/// `--probe-only` (`reboot_probe`) or `--dry-run` (`dry_run`): same plan, different event.
+fn probe(
+    opts: &reboot_api::RebootOptions,
+    selection: api::FlashSelection,
+    dry_run: bool,
+    out: &mut dyn Reporter,
+) -> i32 {
+    let r = reboot_api::plan_reboot(opts, selection, |ev| out.emit(Event::Operation(ev)));
+    match r {
+        Ok(plan) => {
+            let actions = plan
+                .selected_targets
+                .iter()
+                .map(|t| {
+                    let action = match t {
+                        Target::HalfKay(_) => "already_in_bootloader",
+                        Target::Serial(_) => "soft_reboot",
+                    };
+                    (t.id(), action)
+                })
+                .collect();
+            let summary = RebootProbeSummary {
+                actions,
+                needs_serial: plan.needs_serial,
+                bridge_enabled: opts.bridge.enabled,
+                bridge_control_port: opts.bridge.control_port,
+            };
+            out.emit(if dry_run {
+                Event::RebootDryRun(summary)
+            } else {
+                Event::RebootProbe(summary)
+            });
+            exit_codes::EXIT_OK
+        }
+        Err(e) => {
+            let code = map_reboot_error(&e);
+            emit_reboot_error(&e, code, out);
+            code
+        }
+    }
+}
+
+fn print_selected(
+    opts: &reboot_api::RebootOptions,
+    selection: api::FlashSelection,
+    out: &mut dyn Reporter,
+) -> i32 {
+    let r = reboot_api::plan_reboot(opts, selection, |ev| out.emit(Event::Operation(ev)));
+    match r {
+        Ok(plan) => {
+            out.emit(Event::SelectedTargets(
+                plan.selected_targets.iter().map(|t| t.id()).collect(),
+            ));
+            exit_codes::EXIT_OK
+        }
+        Err(e) => {
+            let code = map_reboot_error(&e);
+            emit_reboot_error(&e, code, out);
+            code
+        }
+    }
+}
+
+fn emit_reboot_error(e: &reboot_api::RebootError, code: i32, out: &mut dyn Reporter) {
+    out.emit(Event::Error {
+        code,
+        message: e.to_string(),
+    });
+    if matches!(
+        e,
+        reboot_api::RebootError::NoTargets
+            | reboot_api::RebootError::TargetNotFound { .. }
+            | reboot_api::RebootError::AmbiguousTarget { .. }
+    ) {
+        out.emit(Event::HintAmbiguousTargets);
+    }
+    if matches!(e, reboot_api::RebootError::NoTargets) {
+        out.emit(Event::HintNoDevice);
+    }
+}
+
+fn map_reboot_error(e: &reboot_api::RebootError) -> i32 {
+    match e.kind() {
+        reboot_api::RebootErrorKind::NoDevice => exit_codes::EXIT_NO_DEVICE,
+        reboot_api::RebootErrorKind::AmbiguousTarget => exit_codes::EXIT_AMBIGUOUS,
+        reboot_api::RebootErrorKind::BootNotConfirmed => exit_codes::EXIT_BOOT_NOT_CONFIRMED,
+        reboot_api::RebootErrorKind::Cancelled => exit_codes::EXIT_CANCELLED,
+        reboot_api::RebootErrorKind::Unexpected => exit_codes::EXIT_UNEXPECTED,
+    }
+}
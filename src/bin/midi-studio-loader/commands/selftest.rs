@@ -0,0 +1,184 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use midi_studio_loader::api;
+use midi_studio_loader::operation::OperationEvent;
+
+use crate::cli;
+use crate::commands::flash;
+use crate::context;
+use crate::exit_codes;
+use crate::output::{Event, Reporter, SelftestReport};
+
+/// Test firmware bundled into the binary: a Teensy 4.1 blink that enumerates as USB serial,
+/// built from `assets/selftest.S`.
+const TEST_IMAGE: &[u8] =
+    include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/selftest.hex"));
+
+/// Flash the test image onto the one attached board and check that it boots back to USB serial.
+pub fn run(args: cli::SelftestArgs, out: &mut dyn Reporter) -> i32 {
+    let hex = match &args.hex {
+        Some(path) => path.clone(),
+        None => match embedded_image() {
+            Ok(path) => path,
+            Err(message) => {
+                out.emit(Event::Error {
+                    code: exit_codes::EXIT_UNEXPECTED,
+                    message,
+                });
+                return exit_codes::EXIT_UNEXPECTED;
+            }
+        },
+    };
+    let code = run_with_image(&hex, &args, out);
+    if args.hex.is_none() {
+        let _ = std::fs::remove_file(&hex);
+    }
+    code
+}
+
+/// Write [`TEST_IMAGE`] to a temporary file for the flash API.
+fn embedded_image() -> Result<PathBuf, String> {
+    let path = std::env::temp_dir().join(format!(
+        "midi-studio-loader-selftest-{}.hex",
+        std::process::id()
+    ));
+    std::fs::write(&path, TEST_IMAGE)
+        .map_err(|e| format!("unable to stage the test image at {}: {e}", path.display()))?;
+    Ok(path)
+}
+
+fn run_with_image(hex: &Path, args: &cli::SelftestArgs, out: &mut dyn Reporter) -> i32 {
+    let wait_serial = Duration::from_millis(args.wait_serial_ms);
+    let opts = api::FlashOptions {
+        bridge: context::bridge_opts(&args.bridge),
+        wait_timeout: context::wait_timeout(args.wait_timeout_ms),
+        confirm_boot: true,
+        wait_for_serial_after_boot: Some(wait_serial),
+        cancel: Some(context::cancel_on_ctrl_c()),
+        ..Default::default()
+    };
+
+    let mut trip = RoundTrip::new(Instant::now());
+    let r = api::flash_teensy41_with_selection(hex, &opts, api::FlashSelection::Auto, |ev| {
+        trip.observe(&ev, Instant::now());
+        out.emit(Event::Operation(ev))
+    });
+
+    let (code, reason) = match r {
+        Ok(()) if trip.port.is_some() => (exit_codes::EXIT_OK, None),
+        Ok(()) => (
+            exit_codes::EXIT_WRITE_FAILED,
+            Some(format!(
+                "board did not re-enumerate as USB serial within {}ms of boot",
+                wait_serial.as_millis()
+            )),
+        ),
+        Err(e) => (flash::map_flash_error(&e), Some(e.to_string())),
+    };
+    out.emit(Event::Selftest(trip.report(reason, Instant::now())));
+    code
+}
+
+/// Timestamps of one flash + boot round trip, taken from its operation events.
+struct RoundTrip {
+    started: Instant,
+    target_id: Option<String>,
+    boot: Option<Instant>,
+    serial: Option<Instant>,
+    port: Option<String>,
+}
+
+impl RoundTrip {
+    fn new(started: Instant) -> Self {
+        Self {
+            started,
+            target_id: None,
+            boot: None,
+            serial: None,
+            port: None,
+        }
+    }
+
+    fn observe(&mut self, ev: &OperationEvent, now: Instant) {
+        match ev {
+            OperationEvent::TargetStart { target_id, .. } => {
+                self.target_id = Some(target_id.clone())
+            }
+            OperationEvent::Boot { .. } => self.boot = Some(now),
+            OperationEvent::SerialReappeared { port, .. } => {
+                self.serial = Some(now);
+                self.port = Some(port.clone());
+            }
+            _ => {}
+        }
+    }
+
+    fn report(self, reason: Option<String>, now: Instant) -> SelftestReport {
+        let ms = |d: Duration| d.as_millis() as u64;
+        SelftestReport {
+            passed: reason.is_none(),
+            target_id: self.target_id,
+            port: self.port,
+            reason,
+            flash_ms: self.boot.map(|b| ms(b - self.started)),
+            reenumerate_ms: self.boot.zip(self.serial).map(|(b, s)| ms(s - b)),
+            total_ms: ms(now - self.started),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use midi_studio_loader::hex::FirmwareImage;
+
+    use super::*;
+
+    #[test]
+    fn embedded_test_image_is_a_teensy41_image() {
+        let fw = FirmwareImage::load_teensy41_from_reader(TEST_IMAGE).unwrap();
+        assert!(fw.byte_count > 0);
+        // FlexSPI configuration block, then the image vector table at 0x1000.
+        assert_eq!(&fw.data[..4], b"FCFB");
+        assert_eq!(fw.data[0x1000..0x1004], 0x4320_00D1u32.to_le_bytes());
+        let entry = u32::from_le_bytes(fw.data[0x1004..0x1008].try_into().unwrap());
+        assert_eq!(fw.start_address, Some(entry));
+    }
+
+    #[test]
+    fn round_trip_times_flash_and_reenumeration() {
+        let t0 = Instant::now();
+        let ms = Duration::from_millis;
+        let id = || "serial:COM6".to_string();
+
+        let mut trip = RoundTrip::new(t0);
+        trip.observe(
+            &OperationEvent::TargetStart {
+                target_id: id(),
+                kind: midi_studio_loader::targets::TargetKind::Serial,
+            },
+            t0 + ms(10),
+        );
+        trip.observe(&OperationEvent::Boot { target_id: id() }, t0 + ms(3000));
+        trip.observe(
+            &OperationEvent::SerialReappeared {
+                target_id: id(),
+                port: "COM7".to_string(),
+            },
+            t0 + ms(4200),
+        );
+
+        let report = trip.report(None, t0 + ms(4300));
+        assert!(report.passed);
+        assert_eq!(report.target_id.as_deref(), Some("serial:COM6"));
+        assert_eq!(report.port.as_deref(), Some("COM7"));
+        assert_eq!(report.flash_ms, Some(3000));
+        assert_eq!(report.reenumerate_ms, Some(1200));
+        assert_eq!(report.total_ms, 4300);
+
+        // Flash failed before boot: no partial timings.
+        let report = RoundTrip::new(t0).report(Some("no device".to_string()), t0 + ms(50));
+        assert!(!report.passed);
+        assert_eq!((report.flash_ms, report.reenumerate_ms), (None, None));
+    }
+}
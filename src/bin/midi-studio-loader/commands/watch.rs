@@ -0,0 +1,354 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use midi_studio_loader::selector::TargetSelector;
+use midi_studio_loader::targets::{self, Target};
+use midi_studio_loader::{api, halfkay};
+
+use crate::cli;
+use crate::commands::flash;
+use crate::context;
+use crate::exit_codes;
+use crate::output::{Event, OperationRecorder, Reporter};
+
+/// How long a flashed board is not flashed again, so its re-enumeration (HalfKay, then USB
+/// serial) is not taken for a new board when it outlasts `--wait-serial-ms`.
+const REFLASH_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Flash each board that is plugged in while running, until Ctrl-C.
+///
+/// Boards attached at startup are left alone. Each new board goes through the normal flash
+/// flow (HEX reloaded, oc-bridge paused for serial targets) with its own `operation_summary`.
+pub fn run(args: cli::WatchArgs, out: &mut dyn Reporter) -> i32 {
+    let cancel = context::cancel_on_ctrl_c();
+    let opts = api::FlashOptions {
+        bridge: context::bridge_opts(&args.bridge),
+        wait_for_serial_after_boot: context::wait_timeout(args.wait_serial_ms),
+        cancel: Some(cancel.clone()),
+        ..Default::default()
+    };
+
+    let present = match targets::discover_targets() {
+        Ok(ts) => attached(&ts),
+        Err(e) => {
+            out.emit(Event::Error {
+                code: exit_codes::EXIT_UNEXPECTED,
+                message: e.to_string(),
+            });
+            return exit_codes::EXIT_UNEXPECTED;
+        }
+    };
+    out.emit(Event::WatchStarted {
+        ignored: present.iter().map(|a| a.id.clone()).collect(),
+    });
+
+    let mut tracker = HotplugTracker::new(
+        &present,
+        Duration::from_millis(args.debounce_ms),
+        REFLASH_COOLDOWN,
+    );
+    let interval = Duration::from_millis(args.interval_ms);
+    let (mut flashed, mut failed) = (0, 0);
+    'watch: while !cancel.load(Ordering::SeqCst) {
+        std::thread::sleep(interval);
+        let now = match targets::discover_targets() {
+            Ok(ts) => ts,
+            Err(e) => {
+                // Transient (device mid-enumeration): retry on the next poll.
+                tracing::debug!(err = %e, "watch discovery failed");
+                continue;
+            }
+        };
+
+        let present = attached(&now);
+        let ready = tracker.poll(&present, Instant::now());
+        for id in &ready {
+            let Some((i, target)) = now.iter().enumerate().find(|(_, t)| t.id() == *id) else {
+                continue;
+            };
+            let serial = present
+                .iter()
+                .find(|a| a.id == *id)
+                .and_then(|a| a.serial.clone());
+            out.emit(Event::TargetAdded(i, target.clone()));
+
+            let mut rec = OperationRecorder::new("watch");
+            let r = api::flash_teensy41_with_selection(
+                &args.hex,
+                &opts,
+                api::FlashSelection::Device(TargetSelector::Id(id.clone())),
+                |ev| {
+                    rec.observe(&ev);
+                    out.emit(Event::Operation(ev))
+                },
+            );
+            if let Some(serial) = serial {
+                tracker.flashed(serial, Instant::now());
+            }
+            match r {
+                Ok(()) => {
+                    flashed += 1;
+                    out.emit(Event::OperationSummary(
+                        rec.finish(exit_codes::EXIT_OK, None),
+                    ));
+                }
+                Err(e) => {
+                    let code = flash::map_flash_error(&e);
+                    let msg = e.to_string();
+                    out.emit(flash::flash_error_event(code, &e));
+                    out.emit(Event::OperationSummary(rec.finish(code, Some(msg))));
+                    if matches!(e, api::FlashError::Cancelled) {
+                        break 'watch;
+                    }
+                    failed += 1;
+                }
+            }
+        }
+
+        if !ready.is_empty() {
+            // Flashed boards re-enumerate (HalfKay, then serial): none of that is a new board.
+            if let Ok(ts) = targets::discover_targets() {
+                tracker.settle(&attached(&ts));
+            }
+        }
+    }
+
+    out.emit(Event::WatchStopped { flashed, failed });
+    if failed == 0 {
+        exit_codes::EXIT_OK
+    } else {
+        exit_codes::EXIT_WRITE_FAILED
+    }
+}
+
+/// An attached target as seen by [`HotplugTracker`].
+#[derive(Debug, Clone)]
+struct Attached {
+    id: String,
+    /// The board's USB serial number, when it reports one.
+    serial: Option<BoardSerial>,
+}
+
+/// A board's serial number, as reported in its current mode.
+#[derive(Debug, Clone)]
+enum BoardSerial {
+    /// Decimal string of the running firmware.
+    Usb(String),
+    /// Hex string of HalfKay.
+    HalfKay(String),
+}
+
+impl BoardSerial {
+    fn same_board(&self, other: &BoardSerial) -> bool {
+        match (self, other) {
+            (Self::Usb(a), Self::Usb(b)) => a == b,
+            (Self::HalfKay(a), Self::HalfKay(b)) => a.eq_ignore_ascii_case(b),
+            (Self::Usb(usb), Self::HalfKay(hk)) | (Self::HalfKay(hk), Self::Usb(usb)) => {
+                halfkay::serial_matches(usb, hk)
+            }
+        }
+    }
+}
+
+fn attached(targets: &[Target]) -> Vec<Attached> {
+    // HalfKay serials need a HID lookup; skip it when no HalfKay device is attached.
+    let halfkay_serials = if targets.iter().any(|t| matches!(t, Target::HalfKay(_))) {
+        halfkay::list_serial_numbers().unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    targets
+        .iter()
+        .map(|t| Attached {
+            id: t.id(),
+            serial: match t {
+                Target::Serial(s) => s.serial_number.clone().map(BoardSerial::Usb),
+                Target::HalfKay(h) => halfkay_serials
+                    .iter()
+                    .find(|(path, _)| *path == h.path)
+                    .and_then(|(_, serial)| serial.clone())
+                    .map(BoardSerial::HalfKay),
+            },
+        })
+        .collect()
+}
+
+/// Decides which newly attached targets to flash, by id.
+///
+/// A target is flashed once per insertion: only after it has been seen for `debounce`, and
+/// not again until it disappears from a poll. A board flashed less than `cooldown` ago is
+/// not flashed again under a new id (matched by serial number).
+struct HotplugTracker {
+    /// Attached targets that were flashed or must be left alone.
+    known: HashSet<String>,
+    /// New targets waiting out the debounce, with when they were first seen.
+    pending: HashMap<String, Instant>,
+    /// Serial numbers of the boards flashed recently, with when.
+    flashed: Vec<(BoardSerial, Instant)>,
+    debounce: Duration,
+    cooldown: Duration,
+}
+
+impl HotplugTracker {
+    fn new(present: &[Attached], debounce: Duration, cooldown: Duration) -> Self {
+        Self {
+            known: present.iter().map(|a| a.id.clone()).collect(),
+            pending: HashMap::new(),
+            flashed: Vec::new(),
+            debounce,
+            cooldown,
+        }
+    }
+
+    /// Record a poll of the `present` targets; returns the ids to flash now.
+    fn poll(&mut self, present: &[Attached], now: Instant) -> Vec<String> {
+        self.known.retain(|id| present.iter().any(|a| a.id == *id));
+        self.pending
+            .retain(|id, _| present.iter().any(|a| a.id == *id));
+        let cooldown = self.cooldown;
+        self.flashed
+            .retain(|(_, at)| now.duration_since(*at) < cooldown);
+
+        let mut ready = Vec::new();
+        for Attached { id, serial } in present {
+            if self.known.contains(id) {
+                continue;
+            }
+            if serial.as_ref().is_some_and(|s| self.recently_flashed(s)) {
+                // The board just flashed, back under a new id.
+                self.pending.remove(id);
+                self.known.insert(id.clone());
+                continue;
+            }
+            let first_seen = *self.pending.entry(id.clone()).or_insert(now);
+            if now.duration_since(first_seen) >= self.debounce {
+                ready.push(id.clone());
+            }
+        }
+        for id in &ready {
+            self.pending.remove(id);
+            self.known.insert(id.clone());
+        }
+        ready
+    }
+
+    /// Remember that the board with `serial` was flashed at `now`.
+    fn flashed(&mut self, serial: BoardSerial, now: Instant) {
+        self.flashed.push((serial, now));
+    }
+
+    fn recently_flashed(&self, serial: &BoardSerial) -> bool {
+        self.flashed.iter().any(|(s, _)| s.same_board(serial))
+    }
+
+    /// Treat everything attached right now as known (after a flash).
+    fn settle(&mut self, present: &[Attached]) {
+        self.known.extend(present.iter().map(|a| a.id.clone()));
+        self.pending.retain(|id, _| !self.known.contains(id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn attached(list: &[&str]) -> Vec<Attached> {
+        list.iter()
+            .map(|s| Attached {
+                id: s.to_string(),
+                serial: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tracker_flashes_each_insertion_once_after_debounce() {
+        let t0 = Instant::now();
+        let ms = Duration::from_millis;
+        let mut tracker = HotplugTracker::new(&attached(&["serial:COM3"]), ms(500), Duration::ZERO);
+
+        // Attached at startup: never flashed.
+        assert!(tracker.poll(&attached(&["serial:COM3"]), t0).is_empty());
+
+        // New board: waits out the debounce, then is flashed exactly once.
+        let both = attached(&["serial:COM3", "halfkay:HK1"]);
+        assert!(tracker.poll(&both, t0 + ms(100)).is_empty());
+        assert_eq!(tracker.poll(&both, t0 + ms(600)), ids(&["halfkay:HK1"]));
+        assert!(tracker.poll(&both, t0 + ms(700)).is_empty());
+
+        // Its re-enumeration after the flash is not a new board.
+        tracker.settle(&attached(&["serial:COM3", "serial:COM4"]));
+        assert!(tracker
+            .poll(&attached(&["serial:COM3", "serial:COM4"]), t0 + ms(2000))
+            .is_empty());
+
+        // Unplugged and plugged again: flashed again.
+        assert!(tracker
+            .poll(&attached(&["serial:COM3"]), t0 + ms(3000))
+            .is_empty());
+        let replugged = attached(&["serial:COM3", "serial:COM4"]);
+        assert!(tracker.poll(&replugged, t0 + ms(3100)).is_empty());
+        assert_eq!(
+            tracker.poll(&replugged, t0 + ms(3600)),
+            ids(&["serial:COM4"])
+        );
+    }
+
+    #[test]
+    fn tracker_drops_a_board_removed_during_debounce() {
+        let t0 = Instant::now();
+        let ms = Duration::from_millis;
+        let mut tracker = HotplugTracker::new(&[], ms(500), Duration::ZERO);
+
+        assert!(tracker.poll(&attached(&["halfkay:HK1"]), t0).is_empty());
+        assert!(tracker.poll(&[], t0 + ms(200)).is_empty());
+        // Back again: the debounce starts over.
+        assert!(tracker
+            .poll(&attached(&["halfkay:HK1"]), t0 + ms(600))
+            .is_empty());
+        assert_eq!(
+            tracker.poll(&attached(&["halfkay:HK1"]), t0 + ms(1100)),
+            ids(&["halfkay:HK1"])
+        );
+    }
+
+    #[test]
+    fn tracker_skips_a_flashed_board_reappearing_under_a_new_id() {
+        let t0 = Instant::now();
+        let ms = Duration::from_millis;
+        let mut tracker = HotplugTracker::new(&[], ms(500), ms(30_000));
+        let board = |id: &str, serial: BoardSerial| Attached {
+            id: id.to_string(),
+            serial: Some(serial),
+        };
+
+        let hk = vec![board("halfkay:HK1", BoardSerial::HalfKay("12D687".into()))];
+        assert!(tracker.poll(&hk, t0).is_empty());
+        assert_eq!(tracker.poll(&hk, t0 + ms(600)), ids(&["halfkay:HK1"]));
+        tracker.flashed(BoardSerial::HalfKay("12D687".into()), t0 + ms(700));
+
+        // settle() ran before the board came back as USB serial (--wait-serial-ms 0).
+        tracker.settle(&[]);
+        let back = vec![board("serial:COM4", BoardSerial::Usb("12345670".into()))];
+        assert!(tracker.poll(&back, t0 + ms(2000)).is_empty());
+        assert!(tracker.poll(&back, t0 + ms(3000)).is_empty());
+
+        // Another board is still flashed.
+        let other = vec![
+            back[0].clone(),
+            board("serial:COM5", BoardSerial::Usb("555".into())),
+        ];
+        assert!(tracker.poll(&other, t0 + ms(3100)).is_empty());
+        assert_eq!(tracker.poll(&other, t0 + ms(3600)), ids(&["serial:COM5"]));
+
+        // Replugged after the cooldown: flashed again.
+        assert!(tracker.poll(&[], t0 + ms(40_000)).is_empty());
+        assert!(tracker.poll(&back, t0 + ms(40_100)).is_empty());
+        assert_eq!(tracker.poll(&back, t0 + ms(40_600)), ids(&["serial:COM4"]));
+    }
+}
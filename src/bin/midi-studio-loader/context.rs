@@ -1,8 +1,14 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
-use midi_studio_loader::bridge_control::{BridgeControlMethod, BridgeControlOptions};
+use midi_studio_loader::bridge_control::{
+    BridgeControlMethod, BridgeControlOptions, BridgeResumePolicy,
+};
+use midi_studio_loader::serial_reboot::RebootStrategy;
 
 use crate::cli;
+use crate::exit_codes;
 
 pub fn wait_timeout(ms: u64) -> Option<Duration> {
     if ms == 0 {
@@ -12,6 +18,14 @@ pub fn wait_timeout(ms: u64) -> Option<Duration> {
     }
 }
 
+pub fn reboot_strategy(arg: cli::RebootStrategyArg) -> RebootStrategy {
+    match arg {
+        cli::RebootStrategyArg::MagicBaud => RebootStrategy::MagicBaud,
+        cli::RebootStrategyArg::DtrPulse => RebootStrategy::DtrPulse,
+        cli::RebootStrategyArg::Auto => RebootStrategy::Auto,
+    }
+}
+
 pub fn bridge_opts(args: &cli::BridgeControlArgs) -> BridgeControlOptions {
     let method = if args.no_bridge_control {
         BridgeControlMethod::None
@@ -32,6 +46,36 @@ pub fn bridge_opts(args: &cli::BridgeControlArgs) -> BridgeControlOptions {
         service_id: args.bridge_service_id.clone(),
         timeout: Duration::from_millis(args.bridge_timeout_ms),
         control_port: args.bridge_control_port,
+        control_socket: args.bridge_control_socket.clone(),
         control_timeout: Duration::from_millis(args.bridge_control_timeout_ms),
+        resume_policy: match args.bridge_resume_policy {
+            _ if args.hold_bridge => BridgeResumePolicy::Never,
+            cli::BridgeResumePolicyArg::Always => BridgeResumePolicy::Always,
+            cli::BridgeResumePolicyArg::OnSuccess => BridgeResumePolicy::OnSuccess,
+            cli::BridgeResumePolicyArg::Never => BridgeResumePolicy::Never,
+        },
+        warn_active_session: !args.assume_yes,
+        resume_retries: args.bridge_resume_retries,
+        resume_retry_interval: Duration::from_millis(args.bridge_resume_retry_interval_ms),
+        control_retries: args.bridge_control_retries,
+        relaunch_args: if args.bridge_relaunch_args.is_empty() {
+            None
+        } else {
+            Some(args.bridge_relaunch_args.clone())
+        },
     }
 }
+
+/// Route Ctrl-C to a cancellation flag so the operation can stop cleanly (resuming oc-bridge).
+///
+/// A second Ctrl-C exits immediately.
+pub fn cancel_on_ctrl_c() -> Arc<AtomicBool> {
+    let flag = Arc::new(AtomicBool::new(false));
+    let handler_flag = flag.clone();
+    let _ = ctrlc::set_handler(move || {
+        if handler_flag.swap(true, Ordering::SeqCst) {
+            std::process::exit(exit_codes::EXIT_CANCELLED);
+        }
+    });
+    flag
+}
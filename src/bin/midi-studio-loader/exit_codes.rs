@@ -3,4 +3,63 @@ pub const EXIT_NO_DEVICE: i32 = 10;
 pub const EXIT_INVALID_HEX: i32 = 11;
 pub const EXIT_WRITE_FAILED: i32 = 12;
 pub const EXIT_AMBIGUOUS: i32 = 13;
+pub const EXIT_READBACK_FAILED: i32 = 14;
+pub const EXIT_BOOT_NOT_CONFIRMED: i32 = 15;
 pub const EXIT_UNEXPECTED: i32 = 20;
+pub const EXIT_CANCELLED: i32 = 130;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ExitCodeInfo {
+    pub code: i32,
+    pub name: &'static str,
+    pub meaning: &'static str,
+}
+
+/// Every process exit code, in ascending order (`exit-codes` prints this table).
+pub const ALL: &[ExitCodeInfo] = &[
+    ExitCodeInfo {
+        code: EXIT_OK,
+        name: "ok",
+        meaning: "success",
+    },
+    ExitCodeInfo {
+        code: EXIT_NO_DEVICE,
+        name: "no_device",
+        meaning: "no device / no targets (or no firmware mapped for any target)",
+    },
+    ExitCodeInfo {
+        code: EXIT_INVALID_HEX,
+        name: "invalid_hex",
+        meaning: "invalid firmware file (Intel HEX or S-record)",
+    },
+    ExitCodeInfo {
+        code: EXIT_WRITE_FAILED,
+        name: "write_failed",
+        meaning: "write/flash/reboot failed",
+    },
+    ExitCodeInfo {
+        code: EXIT_AMBIGUOUS,
+        name: "ambiguous",
+        meaning: "ambiguous or mismatched target selection",
+    },
+    ExitCodeInfo {
+        code: EXIT_READBACK_FAILED,
+        name: "readback_failed",
+        meaning: "flashed, but flash could not be read back to verify it (--verify)",
+    },
+    ExitCodeInfo {
+        code: EXIT_BOOT_NOT_CONFIRMED,
+        name: "boot_not_confirmed",
+        meaning: "booted, but the USB serial port did not come back (reboot --to-serial)",
+    },
+    ExitCodeInfo {
+        code: EXIT_UNEXPECTED,
+        name: "unexpected",
+        meaning: "unexpected/internal error",
+    },
+    ExitCodeInfo {
+        code: EXIT_CANCELLED,
+        name: "cancelled",
+        meaning: "cancelled (Ctrl-C)",
+    },
+];
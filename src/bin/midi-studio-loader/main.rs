@@ -1,6 +1,6 @@
 use std::process;
 
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 
 mod cli;
 mod commands;
@@ -17,7 +17,7 @@ fn main() {
     let exit_code = match cli.command {
         cli::Command::Flash(args) => {
             let mut out = output::make_for_flash(&args);
-            let code = commands::flash::run(args, &mut *out);
+            let code = commands::flash::run(*args, &mut *out);
             out.finish();
             code
         }
@@ -28,6 +28,11 @@ fn main() {
             code
         }
         cli::Command::List(args) => {
+            if let Err(message) = args.validate() {
+                cli::Cli::command()
+                    .error(clap::error::ErrorKind::ArgumentConflict, message)
+                    .exit();
+            }
             let mut out = output::make_for_list(&args);
             let code = commands::list::run(args, &mut *out);
             out.finish();
@@ -39,6 +44,36 @@ fn main() {
             out.finish();
             code
         }
+        cli::Command::Preflight(args) => {
+            let mut out = output::make_for_preflight(&args);
+            let code = commands::preflight::run(args, &mut *out);
+            out.finish();
+            code
+        }
+        cli::Command::Bridge(args) => {
+            let mut out = output::make_for_bridge(&args);
+            let code = commands::bridge::run(args, &mut *out);
+            out.finish();
+            code
+        }
+        cli::Command::Watch(args) => {
+            let mut out = output::make_for_watch(&args);
+            let code = commands::watch::run(args, &mut *out);
+            out.finish();
+            code
+        }
+        cli::Command::Selftest(args) => {
+            let mut out = output::make_for_selftest(&args);
+            let code = commands::selftest::run(args, &mut *out);
+            out.finish();
+            code
+        }
+        cli::Command::ExitCodes(args) => {
+            let mut out = output::make_for_exit_codes(&args);
+            out.emit(output::Event::ExitCodes);
+            out.finish();
+            exit_codes::EXIT_OK
+        }
     };
 
     process::exit(exit_code);
@@ -0,0 +1,98 @@
+use std::io::Write;
+
+use midi_studio_loader::targets::Target;
+
+use crate::output::{Event, Reporter};
+
+/// Header of `list --format csv`; [`target_row`] fills the columns in this order.
+pub const LIST_HEADER: &str = "index,id,kind,vid,pid,port,serial,product";
+
+/// Reporter for `list --format csv`: a header, then one row per target.
+///
+/// `list --watch` appends a row for each target that appears. Errors go to stderr.
+pub struct CsvOutput {
+    out: Box<dyn Write>,
+    header_written: bool,
+}
+
+impl CsvOutput {
+    pub fn new(out: Box<dyn Write>) -> Self {
+        Self {
+            out,
+            header_written: false,
+        }
+    }
+
+    fn write_header(&mut self) {
+        if !self.header_written {
+            let _ = writeln!(self.out, "{LIST_HEADER}");
+            self.header_written = true;
+        }
+    }
+
+    fn write_row(&mut self, index: usize, t: &Target) {
+        self.write_header();
+        let _ = writeln!(self.out, "{}", target_row(index, t));
+    }
+}
+
+impl Reporter for CsvOutput {
+    fn emit(&mut self, event: Event) {
+        match event {
+            Event::ListTargets(targets) => {
+                // An empty list is still a header.
+                self.write_header();
+                for (i, t) in &targets {
+                    self.write_row(*i, t);
+                }
+            }
+            Event::TargetAdded(i, t) => self.write_row(i, &t),
+            Event::Error { message, .. } | Event::ErrorWithDetails { message, .. } => {
+                eprintln!("error: {message}")
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(&mut self) {
+        let _ = self.out.flush();
+    }
+}
+
+/// One CSV row for `t`; columns that do not apply to its kind are empty.
+pub fn target_row(index: usize, t: &Target) -> String {
+    let (kind, vid, pid, port, serial, product) = match t {
+        Target::HalfKay(hk) => ("halfkay", hk.vid, hk.pid, "", "", ""),
+        Target::Serial(s) => (
+            "serial",
+            s.vid,
+            s.pid,
+            s.port_name.as_str(),
+            s.serial_number.as_deref().unwrap_or(""),
+            s.product.as_deref().unwrap_or(""),
+        ),
+    };
+    [
+        index.to_string(),
+        t.id(),
+        kind.to_string(),
+        format!("{vid:04X}"),
+        format!("{pid:04X}"),
+        port.to_string(),
+        serial.to_string(),
+        product.to_string(),
+    ]
+    .iter()
+    .map(|f| field(f))
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+/// Quote `s` (doubling inner quotes) when it holds a comma, quote or line break.
+fn field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
@@ -1,11 +1,16 @@
+use std::collections::HashMap;
 use std::io::{IsTerminal, Write};
 
-use midi_studio_loader::{operation::OperationEvent, targets};
+use midi_studio_loader::{bridge_control, operation::OperationEvent, targets};
 
+use midi_studio_loader::serial_reboot::RebootStrategy;
 use midi_studio_loader::teensy41;
 
+use crate::exit_codes;
 use crate::output::{
-    format_target_line, DoctorReport, DryRunSummary, Event, OutputOptions, Reporter,
+    format_target_line, no_device_steps, DoctorReport, DryRunSummary, Event, FixStatus,
+    OperationSummary, OutputOptions, PreflightReport, RebootProbeSummary, Reporter,
+    PERMISSION_HINT, TEENSY_UDEV_RULES, TEENSY_UDEV_RULES_PATH, UDEV_RELOAD_COMMAND,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -21,23 +26,80 @@ pub struct HumanOutput {
     wait_enabled: bool,
     waiting_printed: bool,
     progress_active: bool,
-    last_percent: Option<u64>,
+    /// Progress of the targets being programmed, by target id.
+    progress: HashMap<String, TargetProgress>,
     detected: Vec<Option<targets::Target>>,
+    firmware_name: Option<String>,
+    /// `summary_recap` of the last `OperationSummary`, printed by `finish`.
+    recap: Option<String>,
+    /// ANSI colors: only on a terminal, and not with `NO_COLOR` or `--no-color`.
+    color: bool,
+}
+
+/// Progress of one target; parallel HalfKay targets interleave their events.
+#[derive(Debug, Default)]
+struct TargetProgress {
+    last_percent: Option<u64>,
+    /// Block count and address of the last `Block` event, drawn on the next `Progress`.
+    block: Option<(usize, usize)>,
+}
+
+/// Color of a status word (see [`style`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Ok,
+    Error,
+    Warn,
+}
+
+/// `text` wrapped in the ANSI color for `style`, or unchanged when `enabled` is false.
+pub fn style(enabled: bool, style: Style, text: &str) -> String {
+    if !enabled {
+        return text.to_string();
+    }
+    let code = match style {
+        Style::Ok => "32",
+        Style::Error => "31",
+        Style::Warn => "33",
+    };
+    format!("\x1b[{code}m{text}\x1b[0m")
 }
 
 impl HumanOutput {
     pub fn new(opts: OutputOptions) -> Self {
+        let is_tty = std::io::stderr().is_terminal();
+        // https://no-color.org: set and non-empty disables colors.
+        let no_color_env = matches!(std::env::var_os("NO_COLOR"), Some(v) if !v.is_empty());
         Self {
             opts,
-            is_tty: std::io::stderr().is_terminal(),
+            is_tty,
             wait_enabled: false,
             waiting_printed: false,
             progress_active: false,
-            last_percent: None,
+            progress: HashMap::new(),
             detected: Vec::new(),
+            firmware_name: None,
+            recap: None,
+            color: is_tty && !no_color_env,
         }
     }
 
+    /// Show the `--firmware-name` label when the firmware is loaded.
+    pub fn with_firmware_name(mut self, name: Option<String>) -> Self {
+        self.firmware_name = name;
+        self
+    }
+
+    /// `--no-color`: never emit ANSI colors.
+    pub fn with_color(mut self, enabled: bool) -> Self {
+        self.color &= enabled;
+        self
+    }
+
+    fn styled(&self, s: Style, text: &str) -> String {
+        style(self.color, s, text)
+    }
+
     pub fn with_wait(mut self, wait: bool) -> Self {
         self.wait_enabled = wait;
         self
@@ -75,24 +137,72 @@ impl HumanOutput {
         eprintln!("{msg}");
     }
 
-    fn progress_update(&mut self, percent: u64, i: usize, n: usize, addr: usize) {
+    fn progress_update(&mut self, target_id: &str, bytes_written: u64, bytes_total: u64) {
         if self.mode() != Mode::Progress {
             return;
         }
-
+        let Some(text) = self.progress_text(target_id, bytes_written, bytes_total, self.is_tty)
+        else {
+            return;
+        };
         if self.is_tty {
-            eprint!("\r  programming {percent:3}% ({i}/{n}) @ 0x{addr:06X}");
+            eprint!("\r  {text}");
             let _ = std::io::stderr().flush();
             self.progress_active = true;
-            self.last_percent = Some(percent);
-            return;
+        } else {
+            self.println(&format!("  {text}"));
         }
+    }
+
+    /// Record a `Progress` event of `target_id` and return the text to draw, if any.
+    ///
+    /// On a terminal this is the redraw line, which shows every target being programmed;
+    /// otherwise it is a line per 10% of each target, prefixed with the target id when
+    /// several targets are in flight.
+    pub(crate) fn progress_text(
+        &mut self,
+        target_id: &str,
+        bytes_written: u64,
+        bytes_total: u64,
+        tty: bool,
+    ) -> Option<String> {
+        let (n, addr) = self.progress.get(target_id)?.block?;
+        let several = self.progress.len() > 1;
+        let p = self.progress.get_mut(target_id)?;
+        let bytes_total = bytes_total.max(1);
+        let percent = (bytes_written * 100) / bytes_total;
+        let i = (bytes_written * n as u64 / bytes_total) as usize;
 
-        let last = self.last_percent.unwrap_or(0);
+        if tty {
+            p.last_percent = Some(percent);
+            if !several {
+                return Some(format!(
+                    "programming {percent:3}% ({i}/{n}, {bytes_written}/{bytes_total} bytes) @ 0x{addr:06X}"
+                ));
+            }
+            let mut ids: Vec<&String> = self.progress.keys().collect();
+            ids.sort();
+            let parts: Vec<String> = ids
+                .into_iter()
+                .filter_map(|id| {
+                    let p = self.progress[id].last_percent?;
+                    Some(format!("{id} {p:3}%"))
+                })
+                .collect();
+            return Some(format!("programming {}", parts.join(", ")));
+        }
+
+        let last = p.last_percent.unwrap_or(0);
         if percent == 0 || percent == 100 || percent >= last + 10 {
-            self.last_percent = Some(percent);
-            self.println(&format!("  programming {percent:3}% ({i}/{n})"));
+            p.last_percent = Some(percent);
+            let label = if several {
+                format!("{target_id}: ")
+            } else {
+                String::new()
+            };
+            return Some(format!("{label}programming {percent:3}% ({i}/{n})"));
         }
+        None
     }
 
     pub(crate) fn ambiguous_help_lines(detected: &[Option<targets::Target>]) -> Vec<String> {
@@ -137,6 +247,12 @@ impl HumanOutput {
                     self.println(&format!("target[{index}]: {id}"));
                 }
             }
+            OperationEvent::Warning { message, .. } => {
+                if self.mode() != Mode::Quiet {
+                    let line = self.styled(Style::Warn, &format!("warning: {message}"));
+                    self.println(&line);
+                }
+            }
             OperationEvent::DiscoverDone { count } => {
                 if self.mode() == Mode::Progress {
                     if count == 0 && self.wait_enabled && !self.waiting_printed {
@@ -170,7 +286,8 @@ impl HumanOutput {
             }
             OperationEvent::BridgePauseFailed { error } => {
                 if self.mode() != Mode::Quiet {
-                    self.println(&format!("oc-bridge pause failed: {}", error.message));
+                    let line = format!("oc-bridge pause failed: {}", error.message);
+                    self.println(&self.styled(Style::Warn, &line));
                 }
             }
             OperationEvent::BridgeResumeStart => {
@@ -178,6 +295,18 @@ impl HumanOutput {
                     self.println("resuming oc-bridge...");
                 }
             }
+            OperationEvent::BridgeResumeRetry {
+                attempt,
+                retries,
+                error,
+            } => {
+                if self.mode() == Mode::Verbose {
+                    let line = format!(
+                        "oc-bridge resume failed ({error}); retrying ({attempt}/{retries})"
+                    );
+                    self.println(&self.styled(Style::Warn, &line));
+                }
+            }
             OperationEvent::BridgeResumed => {
                 if self.mode() == Mode::Verbose {
                     self.println("oc-bridge resumed");
@@ -185,16 +314,80 @@ impl HumanOutput {
             }
             OperationEvent::BridgeResumeFailed { error } => {
                 if self.mode() == Mode::Verbose {
-                    self.println(&format!("oc-bridge resume failed: {}", error.message));
+                    let line = format!("oc-bridge resume failed: {}", error.message);
+                    self.println(&self.styled(Style::Warn, &line));
+                }
+            }
+            OperationEvent::BridgeLeftPaused { hint } => {
+                if self.mode() != Mode::Quiet {
+                    self.println(&self.styled(Style::Warn, "oc-bridge left paused"));
+                    if let Some(hint) = hint {
+                        self.println(&format!("  {hint}"));
+                    }
                 }
             }
-            OperationEvent::HexLoaded { bytes, blocks } => {
+            OperationEvent::HexLoadStart { path } => {
+                if self.mode() == Mode::Verbose {
+                    self.println(&format!("loading firmware {path}"));
+                }
+            }
+            OperationEvent::HexLoading { line_no } => {
+                if self.mode() == Mode::Verbose {
+                    self.println(&format!("parsing firmware (line {line_no})"));
+                } else if self.mode() == Mode::Progress && self.is_tty {
+                    eprint!("\r  parsing firmware... line {line_no}");
+                    let _ = std::io::stderr().flush();
+                    self.progress_active = true;
+                }
+            }
+            OperationEvent::HexLoaded {
+                bytes,
+                blocks,
+                crc32,
+                digest,
+            } => {
+                let label = match &self.firmware_name {
+                    Some(name) => format!("{name}: "),
+                    None => String::new(),
+                };
                 if self.mode() == Mode::Verbose {
                     self.println(&format!(
-                        "Loaded {bytes} bytes ({blocks} blocks) for Teensy 4.1"
+                        "Loaded {label}{bytes} bytes ({blocks} blocks, crc32 {crc32:08x})"
                     ));
+                    self.println(&format!("  sha256 {}", digest.sha256_hex()));
                 } else if self.mode() == Mode::Progress {
-                    self.println(&format!("firmware loaded: {bytes} bytes ({blocks} blocks)"));
+                    self.println(&format!(
+                        "firmware loaded: {label}{bytes} bytes ({blocks} blocks)"
+                    ));
+                }
+            }
+            OperationEvent::BaselineDiff {
+                total_blocks,
+                changed_blocks,
+            } => {
+                if matches!(self.mode(), Mode::Verbose | Mode::Progress) {
+                    self.println(&format!(
+                        "baseline: {changed_blocks}/{total_blocks} blocks changed (writing all)"
+                    ));
+                }
+            }
+            OperationEvent::FirmwareAssigned {
+                target_id,
+                path,
+                crc32,
+            } => {
+                if self.mode() != Mode::Quiet {
+                    self.println(&format!("{target_id}: firmware {path} (crc32 {crc32:08x})"));
+                }
+            }
+            OperationEvent::TargetSkipped { target_id, reason } => {
+                if self.mode() != Mode::Quiet {
+                    self.println(&format!("skipped {target_id}: {reason}"));
+                }
+            }
+            OperationEvent::BetweenTargetsDelay { delay_ms } => {
+                if self.mode() == Mode::Verbose {
+                    self.println(&format!("waiting {delay_ms}ms before next target"));
                 }
             }
             OperationEvent::TargetStart { target_id, .. } => {
@@ -202,7 +395,7 @@ impl HumanOutput {
                     self.println(&format!("target start: {target_id}"));
                 } else if self.mode() == Mode::Progress {
                     self.println(&format!("target: {target_id}"));
-                    self.last_percent = None;
+                    self.progress.remove(&target_id);
                 }
             }
             OperationEvent::TargetDone {
@@ -215,25 +408,39 @@ impl HumanOutput {
                         self.println(&format!("target done: {target_id}"));
                     } else {
                         self.println(&format!(
-                            "target failed: {target_id}: {}",
+                            "{} {target_id}: {}",
+                            self.styled(Style::Error, "target failed:"),
                             message.unwrap_or_default()
                         ));
                     }
                 } else if self.mode() == Mode::Progress {
                     self.finish_line();
+                    self.progress.remove(&target_id);
                     if ok {
-                        self.println(&format!("ok: {target_id}"));
+                        self.println(&format!("{} {target_id}", self.styled(Style::Ok, "ok:")));
                     } else {
                         self.println(&format!(
-                            "failed: {target_id}: {}",
+                            "{} {target_id}: {}",
+                            self.styled(Style::Error, "failed:"),
                             message.unwrap_or_default()
                         ));
                     }
                 }
             }
-            OperationEvent::SoftReboot { port, .. } => {
+            OperationEvent::SoftReboot {
+                port,
+                strategy,
+                baud,
+                ..
+            } => {
                 if self.mode() == Mode::Verbose {
-                    self.println(&format!("Soft reboot via serial: {port} (baud=134)"));
+                    let via = match strategy {
+                        RebootStrategy::DtrPulse => " (DTR pulse)".to_string(),
+                        RebootStrategy::MagicBaud | RebootStrategy::Auto => {
+                            format!(" (baud={baud})")
+                        }
+                    };
+                    self.println(&format!("Soft reboot via serial: {port}{via}"));
                 } else if self.mode() == Mode::Progress {
                     self.println(&format!("soft reboot: {port}"));
                 }
@@ -243,6 +450,16 @@ impl HumanOutput {
                     self.println(&format!("soft reboot skipped: {error}"));
                 }
             }
+            OperationEvent::WaitingForHalfKay { elapsed_ms, .. } => {
+                let secs = elapsed_ms / 1000;
+                if self.mode() == Mode::Verbose {
+                    self.println(&format!("waiting for HalfKay ({secs}s)"));
+                } else if self.mode() == Mode::Progress && self.is_tty {
+                    eprint!("\r  waiting for HalfKay... {secs}s (Ctrl-C to cancel)");
+                    let _ = std::io::stderr().flush();
+                    self.progress_active = true;
+                }
+            }
             OperationEvent::HalfKayAppeared { .. } => {
                 if self.mode() != Mode::Quiet {
                     self.println("halfkay appeared");
@@ -256,7 +473,11 @@ impl HumanOutput {
                 }
             }
             OperationEvent::Block {
-                index, total, addr, ..
+                target_id,
+                index,
+                total,
+                addr,
+                ..
             } => {
                 if self.mode() == Mode::Verbose {
                     self.println(&format!(
@@ -265,10 +486,16 @@ impl HumanOutput {
                         total
                     ));
                 } else if self.mode() == Mode::Progress {
-                    let percent = ((index + 1) as u64 * 100).saturating_div(total.max(1) as u64);
-                    self.progress_update(percent, index + 1, total, addr);
+                    self.progress.entry(target_id).or_default().block = Some((total, addr));
                 }
             }
+            OperationEvent::Progress {
+                target_id,
+                bytes_written,
+                bytes_total,
+            } => {
+                self.progress_update(&target_id, bytes_written, bytes_total);
+            }
             OperationEvent::Retry {
                 addr,
                 attempt,
@@ -278,9 +505,9 @@ impl HumanOutput {
             } => {
                 if self.mode() != Mode::Quiet {
                     self.finish_line();
-                    self.println(&format!(
-                        "retry write at 0x{addr:06X} ({attempt}/{retries}): {error}"
-                    ));
+                    let line =
+                        format!("retry write at 0x{addr:06X} ({attempt}/{retries}): {error}");
+                    self.println(&self.styled(Style::Warn, &line));
                 }
             }
             OperationEvent::Boot { .. } => {
@@ -289,6 +516,36 @@ impl HumanOutput {
                     self.println("booting device...");
                 }
             }
+            OperationEvent::BootConfirmed { .. } => {
+                if self.mode() == Mode::Verbose {
+                    self.println("boot confirmed (HalfKay detached)");
+                }
+            }
+            OperationEvent::BootFailed { timeout_ms, .. } => {
+                if self.mode() != Mode::Quiet {
+                    self.finish_line();
+                    let line = format!("boot failed: HalfKay still attached after {timeout_ms}ms");
+                    self.println(&self.styled(Style::Error, &line));
+                }
+            }
+            OperationEvent::SerialReappeared { port, .. } => {
+                if self.mode() != Mode::Quiet {
+                    self.finish_line();
+                    self.println(&format!("device back on {port}"));
+                }
+            }
+            OperationEvent::VerifySkipped { reason, .. } => {
+                if self.mode() != Mode::Quiet {
+                    self.finish_line();
+                    self.println(&format!("verify skipped ({reason})"));
+                }
+            }
+            OperationEvent::Verified { port, .. } => {
+                if self.mode() != Mode::Quiet {
+                    self.finish_line();
+                    self.println(&format!("verified (read back on {port})"));
+                }
+            }
             OperationEvent::Done { .. } => {
                 if self.mode() == Mode::Progress {
                     self.finish_line();
@@ -302,24 +559,173 @@ impl Reporter for HumanOutput {
     fn emit(&mut self, event: Event) {
         match event {
             Event::Operation(ev) => self.on_operation_event(ev),
-            Event::OperationSummary(_) => {}
+            Event::OperationSummary(summary) => {
+                if let Some(footer) = summary_footer(&summary) {
+                    self.println(&footer);
+                }
+                self.recap = Some(summary_recap(&summary));
+            }
             Event::DryRun(summary) => emit_dry_run(summary, self),
+            Event::RebootProbe(summary) => {
+                emit_reboot_probe("Probe OK (nothing was rebooted)", summary, self)
+            }
+            Event::RebootDryRun(summary) => {
+                emit_reboot_probe("Dry run OK (nothing was rebooted)", summary, self)
+            }
+            Event::SelectedTargets(ids) => {
+                // Machine-consumable answer: ids on stdout, one per line.
+                self.finish_line();
+                for id in ids {
+                    println!("{id}");
+                }
+            }
             Event::ListTargets(targets) => emit_list_targets(&targets, self),
+            Event::TargetAdded(i, t) => self.println(&format!("+ {}", format_target_line(i, &t))),
+            Event::TargetRemoved(i, t) => self.println(&format!("- {}", format_target_line(i, &t))),
             Event::Doctor(report) => emit_doctor(report, self),
-            Event::Error { code: _, message } => {
+            Event::Preflight(report) => emit_preflight(report, self),
+            Event::BridgeStatus { endpoint, status } => {
+                self.println(&format!("oc-bridge control: {endpoint}"));
+                match status {
+                    Ok(st) => self.println(&format!("  {}", format_control_status(&st))),
+                    Err(e) => self.println(&format!("  unreachable: {e}")),
+                }
+            }
+            Event::WatchStarted { ignored } => {
+                let mut line = "watching for new boards (Ctrl-C to stop)".to_string();
+                if !ignored.is_empty() {
+                    line.push_str(&format!("; ignoring {} attached", ignored.len()));
+                }
+                self.println(&line);
+            }
+            Event::Selftest(report) => {
+                let secs = |ms: u64| format!("{:.1}s", ms as f64 / 1000.0);
+                let mut timing = Vec::new();
+                if let Some(ms) = report.flash_ms {
+                    timing.push(format!("flash {}", secs(ms)));
+                }
+                if let Some(ms) = report.reenumerate_ms {
+                    timing.push(format!("serial back after {}", secs(ms)));
+                }
+                timing.push(format!("total {}", secs(report.total_ms)));
+                let line = match (&report.reason, report.port.as_deref()) {
+                    (None, Some(port)) => format!(
+                        "{} {port} ({})",
+                        self.styled(Style::Ok, "selftest: PASS"),
+                        timing.join(", ")
+                    ),
+                    (None, None) => format!(
+                        "{} ({})",
+                        self.styled(Style::Ok, "selftest: PASS"),
+                        timing.join(", ")
+                    ),
+                    (Some(reason), _) => format!(
+                        "{} {reason} ({})",
+                        self.styled(Style::Error, "selftest: FAIL"),
+                        timing.join(", ")
+                    ),
+                };
+                self.println(&line);
+            }
+            Event::WatchStopped { flashed, failed } => {
+                // Already the recap of the whole run, not of the last board.
+                self.recap = None;
+                self.println(&format!(
+                    "watch stopped: {flashed} flashed, {failed} failed"
+                ));
+            }
+            Event::ExitCodes => {
+                for info in exit_codes::ALL {
+                    println!("{:>3}  {:<12}  {}", info.code, info.name, info.meaning);
+                }
+            }
+            Event::Error { code: _, message } | Event::ErrorWithDetails { message, .. } => {
                 self.finish_line();
-                eprintln!("error: {message}");
+                eprintln!("{} {message}", self.styled(Style::Error, "error:"));
             }
             Event::HintAmbiguousTargets => self.print_ambiguous_help(),
+            Event::HintNoDevice => {
+                if self.mode() != Mode::Quiet {
+                    self.println("");
+                    for step in no_device_steps() {
+                        self.println(&format!("  - {step}"));
+                    }
+                }
+            }
         }
     }
 
     fn finish(&mut self) {
         self.finish_line();
+        if let Some(recap) = self.recap.take() {
+            self.println(&recap);
+        }
+    }
+}
+
+/// One-line recap of a run, e.g. `done: 3 ok, 1 failed, 124 blocks, 2 retries, bridge: control`.
+///
+/// Blocks and retries are left out when nothing was written; the bridge part only appears
+/// when oc-bridge was paused.
+pub fn summary_recap(summary: &OperationSummary) -> String {
+    let mut line = format!(
+        "done: {} ok, {} failed",
+        summary.targets_ok.len(),
+        summary.targets_failed.len()
+    );
+    if summary.blocks > 0 || summary.retries > 0 {
+        line.push_str(&format!(
+            ", {} blocks, {} retries",
+            summary.blocks, summary.retries
+        ));
+    }
+    if let Some(method) = &summary.bridge_method {
+        line.push_str(&format!(", bridge: {method}"));
     }
+    line
 }
 
-fn emit_list_targets(targets: &[targets::Target], out: &mut HumanOutput) {
+/// Final verdict line for multi-target runs, e.g.
+/// `Flashed 7/8 targets; failed: serial:COM9 (write failed)`.
+///
+/// `None` for single-target runs: their last progress line already is the verdict.
+pub fn summary_footer(summary: &OperationSummary) -> Option<String> {
+    let done = summary.targets_ok.len();
+    let total = done + summary.targets_failed.len();
+    if total <= 1 && summary.targets_skipped.is_empty() {
+        return None;
+    }
+
+    let verb = match summary.operation {
+        "flash" => "Flashed",
+        "reboot" => "Rebooted",
+        other => other,
+    };
+    let mut line = format!("{verb} {done}/{total} targets");
+    if let Some(name) = &summary.firmware_name {
+        line.push_str(&format!(" with {name}"));
+    }
+    if !summary.targets_failed.is_empty() {
+        let failed: Vec<String> = summary
+            .targets_failed
+            .iter()
+            .map(|id| match summary.target_errors.get(id) {
+                Some(m) => format!("{id} ({m})"),
+                None => id.clone(),
+            })
+            .collect();
+        line.push_str(&format!("; failed: {}", failed.join(", ")));
+    }
+    if !summary.targets_skipped.is_empty() {
+        line.push_str(&format!(
+            "; skipped: {}",
+            summary.targets_skipped.join(", ")
+        ));
+    }
+    Some(line)
+}
+
+fn emit_list_targets(targets: &[(usize, targets::Target)], out: &mut HumanOutput) {
     if targets.is_empty() {
         out.println(&format!(
             "No targets found (HalfKay {:04X}:{:04X} or PJRC USB serial)",
@@ -329,8 +735,8 @@ fn emit_list_targets(targets: &[targets::Target], out: &mut HumanOutput) {
         return;
     }
 
-    for (i, t) in targets.iter().enumerate() {
-        out.println(&format_target_line(i, t));
+    for (i, t) in targets {
+        out.println(&format_target_line(*i, t));
     }
 }
 
@@ -340,10 +746,31 @@ fn emit_doctor(report: DoctorReport, out: &mut HumanOutput) {
     for (i, t) in report.targets.iter().enumerate() {
         out.println(&format_target_line(i, t));
     }
+    for (_, e) in &report.discovery_errors {
+        out.println(&format!("  error: {e}"));
+    }
+    if let Some(hint) = report.permission_hint {
+        let line = format!("{}: {} ({})", hint.path, PERMISSION_HINT, hint.error);
+        out.println(&out.styled(Style::Warn, &line));
+        out.println(&format!("  {TEENSY_UDEV_RULES_PATH}:"));
+        for rule in TEENSY_UDEV_RULES.lines() {
+            out.println(&format!("    {rule}"));
+        }
+        out.println(&format!(
+            "  then: {UDEV_RELOAD_COMMAND} (and replug the board)"
+        ));
+    }
 
+    let endpoint = match &report.control_socket {
+        Some((path, exists)) => format!(
+            "{} ({})",
+            path.display(),
+            if *exists { "exists" } else { "missing" }
+        ),
+        None => format!("127.0.0.1:{}", report.control_port),
+    };
     out.println(&format!(
-        "oc-bridge control: 127.0.0.1:{} (timeout {}ms){}",
-        report.control_port,
+        "oc-bridge control: {endpoint} (timeout {}ms){}",
         report.control_timeout_ms,
         if report.control_checked {
             ""
@@ -354,10 +781,7 @@ fn emit_doctor(report: DoctorReport, out: &mut HumanOutput) {
 
     if report.control_checked {
         if let Some(st) = report.control {
-            out.println(&format!(
-                "  ok={} paused={} serial_open={:?}",
-                st.ok, st.paused, st.serial_open
-            ));
+            out.println(&format!("  {}", format_control_status(&st)));
             if let Some(m) = st.message {
                 out.println(&format!("  message: {m}"));
             }
@@ -382,6 +806,115 @@ fn emit_doctor(report: DoctorReport, out: &mut HumanOutput) {
             p.exe.as_deref().unwrap_or("")
         ));
     }
+
+    if let Some(e) = report.hid_error {
+        out.println(&format!("HalfKay HID descriptors: error: {e}"));
+    }
+    if let Some(devices) = report.hid_devices {
+        out.println(&format!("HalfKay HID descriptors: {}", devices.len()));
+        for d in devices {
+            out.println(&format!("  {} {:04X}:{:04X}", d.path, d.vid, d.pid));
+            out.println(&format!(
+                "    usage_page=0x{:04X} usage=0x{:04X} interface={} release=0x{:04X}",
+                d.usage_page, d.usage, d.interface_number, d.release_number
+            ));
+            if let Some(e) = d.report_descriptor_error {
+                out.println(&format!("    report descriptor: error: {e}"));
+                continue;
+            }
+            out.println(&format!(
+                "    reports: input={}B output={}B feature={}B (expected output {}B)",
+                d.report_sizes.input_bits / 8,
+                d.report_sizes.output_bits / 8,
+                d.report_sizes.feature_bits / 8,
+                teensy41::PACKET_SIZE
+            ));
+            out.println(&format!(
+                "    report descriptor ({} bytes):",
+                d.report_descriptor.len()
+            ));
+            for chunk in d.report_descriptor.chunks(16) {
+                out.println(&format!("      {}", hex_bytes(chunk)));
+            }
+        }
+    }
+
+    if let Some(fixes) = report.fixes {
+        out.println(&format!("fixes: {}", fixes.len()));
+        for f in fixes {
+            let status = match f.status {
+                FixStatus::Applied => out.styled(Style::Ok, f.status.as_str()),
+                FixStatus::Failed => out.styled(Style::Error, f.status.as_str()),
+                FixStatus::Manual => out.styled(Style::Warn, f.status.as_str()),
+                FixStatus::Skipped => f.status.as_str().to_string(),
+            };
+            out.println(&format!("  {}: {status}: {}", f.action, f.message));
+        }
+    }
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{b:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn emit_reboot_probe(heading: &str, summary: RebootProbeSummary, out: &mut HumanOutput) {
+    if out.mode() == Mode::Quiet {
+        return;
+    }
+
+    out.println(heading);
+    out.println(&format!("Targets: {}", summary.actions.len()));
+    for (id, action) in &summary.actions {
+        out.println(&format!("- {id}: {}", action.replace('_', " ")));
+    }
+    if summary.needs_serial && summary.bridge_enabled {
+        out.println(&format!(
+            "Bridge: would pause/resume oc-bridge (control port {})",
+            summary.bridge_control_port
+        ));
+    }
+}
+
+/// `ok=.. paused=.. serial_open=..`, plus `clients`/`uptime`/`port` when the bridge reports them.
+fn format_control_status(st: &bridge_control::BridgeControlStatus) -> String {
+    let mut line = format!(
+        "ok={} paused={} serial_open={:?}",
+        st.ok, st.paused, st.serial_open
+    );
+    if let Some(n) = st.client_count {
+        line.push_str(&format!(" clients={n}"));
+    }
+    if let Some(ms) = st.uptime_ms {
+        line.push_str(&format!(" uptime={}s", ms / 1000));
+    }
+    if let Some(port) = &st.serial_port {
+        line.push_str(&format!(" port={port}"));
+    }
+    line
+}
+
+fn emit_preflight(report: PreflightReport, out: &mut HumanOutput) {
+    if report.blocking.is_empty() {
+        out.println("Preflight OK: ready to flash");
+    } else {
+        out.println("Preflight FAILED: not ready to flash");
+        for issue in &report.blocking {
+            out.println(&format!("- {}: {}", issue.check, issue.message));
+        }
+    }
+    if let Some(bytes) = report.firmware_bytes {
+        out.println(&format!("Firmware: {bytes} bytes"));
+    }
+    if !report.target_ids.is_empty() {
+        out.println(&format!("Targets: {}", report.target_ids.join(", ")));
+    }
+    if report.bridge_checked {
+        out.println("Bridge: checked");
+    }
 }
 
 fn emit_dry_run(summary: DryRunSummary, out: &mut HumanOutput) {
@@ -394,6 +927,13 @@ fn emit_dry_run(summary: DryRunSummary, out: &mut HumanOutput) {
         "Firmware: {} bytes, blocks_to_write={}/{},",
         summary.bytes, summary.blocks_to_write, summary.blocks
     ));
+    if let (Some(min), Some(max)) = (summary.min_addr, summary.max_addr) {
+        out.println(&format!("Address range: 0x{min:08X}..=0x{max:08X}"));
+    }
+    if let Some(entry) = summary.start_address {
+        out.println(&format!("Entry point: 0x{entry:08X}"));
+    }
+    out.println(&format!("SHA-256: {}", summary.digest.sha256_hex()));
     out.println(&format!("Targets: {}", summary.target_ids.len()));
     for id in &summary.target_ids {
         out.println(&format!("- {id}"));
@@ -1,11 +1,16 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
 use std::time::Instant;
 
-use midi_studio_loader::{operation::OperationEvent, targets};
+use midi_studio_loader::serial_reboot::RebootStrategy;
+use midi_studio_loader::{bridge_control, halfkay, operation::OperationEvent, targets, teensy41};
 
+use crate::exit_codes;
 use crate::output::{
-    target_to_value, DoctorReport, DryRunSummary, Event, JsonProgressMode, OperationSummary,
-    OutputOptions, Reporter,
+    no_device_steps, target_to_value, DoctorReport, DryRunSummary, Event, JsonProgressMode,
+    JsonStream, OperationSummary, OutputOptions, PhaseTimings, PreflightReport, RebootProbeSummary,
+    Reporter, SelftestReport, PERMISSION_HINT, TEENSY_UDEV_RULES, TEENSY_UDEV_RULES_PATH,
+    UDEV_RELOAD_COMMAND,
 };
 
 #[derive(serde::Serialize)]
@@ -14,6 +19,9 @@ pub struct JsonEvent {
     event: &'static str,
     #[serde(flatten)]
     fields: BTreeMap<&'static str, serde_json::Value>,
+    /// Fields with runtime keys (see `with_fields`).
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
 }
 
 impl JsonEvent {
@@ -22,6 +30,7 @@ impl JsonEvent {
             schema: 1,
             event,
             fields: BTreeMap::new(),
+            extra: serde_json::Map::new(),
         }
     }
 
@@ -39,22 +48,62 @@ impl JsonEvent {
         self.fields.insert(k, v);
         self
     }
+
+    /// Merge the members of a JSON object (anything else is ignored).
+    pub fn with_fields(mut self, v: serde_json::Value) -> Self {
+        if let serde_json::Value::Object(m) = v {
+            self.extra.extend(m);
+        }
+        self
+    }
 }
 
 pub struct JsonOutput {
     opts: OutputOptions,
     start: Instant,
-    last_percent: Option<u64>,
+    /// Last `--json-progress percent` value emitted, by target id.
+    last_percent: HashMap<String, u64>,
+    firmware_name: Option<String>,
+    /// Destination of the JSON lines (`opts.json_stream` unless replaced with `with_writer`).
+    out: Box<dyn Write>,
+    /// Whether `out` is stderr, which then must carry nothing but JSON lines.
+    out_is_stderr: bool,
 }
 
 impl JsonOutput {
     pub fn new(opts: OutputOptions) -> Self {
+        let out: Box<dyn Write> = match opts.json_stream {
+            JsonStream::Stdout => Box::new(std::io::stdout()),
+            JsonStream::Stderr => Box::new(std::io::stderr()),
+        };
+        let out_is_stderr = opts.json_stream == JsonStream::Stderr;
         Self {
             opts,
             start: Instant::now(),
-            last_percent: None,
+            last_percent: HashMap::new(),
+            firmware_name: None,
+            out,
+            out_is_stderr,
         }
     }
+
+    /// Write the JSON lines to `out` instead (`--json-output <path>`).
+    pub fn with_writer(mut self, out: Box<dyn Write>) -> Self {
+        self.out = out;
+        self.out_is_stderr = false;
+        self
+    }
+
+    /// Whether `--verbose` errors are also echoed as plain text on stderr.
+    pub(crate) fn echoes_errors(&self) -> bool {
+        self.opts.verbose && !self.out_is_stderr
+    }
+
+    /// Tag `hex_loaded` and `target_done` with `firmware_name` (`--firmware-name`).
+    pub fn with_firmware_name(mut self, name: Option<String>) -> Self {
+        self.firmware_name = name;
+        self
+    }
 }
 
 impl JsonOutput {
@@ -70,41 +119,119 @@ impl JsonOutput {
     }
 
     fn json_event(&mut self, ev: JsonEvent) {
-        println!("{}", self.render_event_json(ev));
+        let line = self.render_event_json(ev);
+        self.print_line(&line);
     }
 
-    fn error_event(&mut self, code: i32, msg: &str) {
-        self.json_event(
-            JsonEvent::status("error")
-                .with_u64("code", code as u64)
-                .with_str("message", msg),
-        );
+    fn print_line(&mut self, line: &str) {
+        // Like `println!`, but a closed pipe or full disk must not abort the operation.
+        let _ = writeln!(self.out, "{line}");
+    }
+
+    fn error_event(&mut self, code: i32, msg: &str, details: Option<serde_json::Value>) {
+        let mut ev = error_to_json(code, msg);
+        if let Some(details) = details {
+            ev = ev.with_fields(details);
+        }
+        self.json_event(ev);
 
-        if self.opts.verbose {
+        if self.echoes_errors() {
             eprintln!("error: {msg}");
         }
     }
 }
 
+impl JsonOutput {
+    /// Whether `event` is part of the final verdict (kept with `--json-compact-summary`).
+    pub(crate) fn is_verdict(event: &Event) -> bool {
+        matches!(
+            event,
+            Event::OperationSummary(_)
+                | Event::DryRun(_)
+                | Event::RebootProbe(_)
+                | Event::RebootDryRun(_)
+                | Event::SelectedTargets(_)
+                | Event::Preflight(_)
+                | Event::BridgeStatus { .. }
+                | Event::WatchStopped { .. }
+                | Event::Selftest(_)
+                | Event::Error { .. }
+                | Event::ErrorWithDetails { .. }
+        )
+    }
+}
+
 impl Reporter for JsonOutput {
     fn emit(&mut self, event: Event) {
+        if self.opts.json_compact_summary && !Self::is_verdict(&event) {
+            return;
+        }
         match event {
             Event::Operation(ev) => self.emit_operation(ev),
             Event::OperationSummary(summary) => {
                 self.json_event(operation_summary_to_json(summary));
             }
             Event::DryRun(summary) => self.json_event(dry_run_to_json(summary)),
+            Event::RebootProbe(summary) => self.json_event(reboot_probe_to_json(summary)),
+            Event::RebootDryRun(summary) => self.json_event(reboot_dry_run_to_json(summary)),
+            Event::SelectedTargets(ids) => self.json_event(selected_targets_to_json(&ids)),
             Event::ListTargets(targets) => self.json_event(list_to_json(&targets)),
+            Event::TargetAdded(i, t) => {
+                self.json_event(target_change_to_json("target_added", i, &t))
+            }
+            Event::TargetRemoved(i, t) => {
+                self.json_event(target_change_to_json("target_removed", i, &t))
+            }
             Event::Doctor(report) => self.json_event(doctor_to_json(report)),
-            Event::Error { code, message } => self.error_event(code, &message),
+            Event::Preflight(report) => self.json_event(preflight_to_json(report)),
+            Event::BridgeStatus { endpoint, status } => {
+                self.json_event(bridge_status_to_json(&endpoint, &status))
+            }
+            Event::WatchStarted { ignored } => self.json_event(watch_started_to_json(&ignored)),
+            Event::WatchStopped { flashed, failed } => {
+                self.json_event(watch_stopped_to_json(flashed, failed))
+            }
+            Event::Selftest(report) => self.json_event(selftest_to_json(report)),
+            Event::ExitCodes => self.json_event(exit_codes_to_json()),
+            Event::Error { code, message } => self.error_event(code, &message, None),
+            Event::ErrorWithDetails {
+                code,
+                message,
+                details,
+            } => self.error_event(code, &message, Some(details)),
             Event::HintAmbiguousTargets => {}
+            Event::HintNoDevice => self.json_event(no_device_hint_to_json()),
         }
     }
 
-    fn finish(&mut self) {}
+    fn finish(&mut self) {
+        let _ = self.out.flush();
+    }
+}
+
+pub fn no_device_hint_to_json() -> JsonEvent {
+    JsonEvent::status("no_device_hint")
+        .with_u64("vid", teensy41::VID as u64)
+        .with_u64("pid_halfkay", teensy41::PID_HALFKAY as u64)
+        .with_str("platform", std::env::consts::OS)
+        .with_value(
+            "steps",
+            serde_json::Value::Array(no_device_steps().into_iter().map(Into::into).collect()),
+        )
+}
+
+/// `list --watch` change event; `target` has the same shape as `list` entries.
+pub fn target_change_to_json(event: &'static str, index: usize, t: &targets::Target) -> JsonEvent {
+    let ts_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    JsonEvent::status(event)
+        .with_u64("ts_ms", ts_ms)
+        .with_value("target", target_to_value(index, t))
 }
 
-pub fn list_to_json(targets: &[targets::Target]) -> JsonEvent {
+pub fn list_to_json(targets: &[(usize, targets::Target)]) -> JsonEvent {
     JsonEvent::status("list")
         .with_u64("count", targets.len() as u64)
         .with_value(
@@ -112,8 +239,7 @@ pub fn list_to_json(targets: &[targets::Target]) -> JsonEvent {
             serde_json::Value::Array(
                 targets
                     .iter()
-                    .enumerate()
-                    .map(|(i, t)| target_to_value(i, t))
+                    .map(|(i, t)| target_to_value(*i, t))
                     .collect(),
             ),
         )
@@ -122,34 +248,63 @@ pub fn list_to_json(targets: &[targets::Target]) -> JsonEvent {
 impl JsonOutput {
     fn emit_operation(&mut self, ev: OperationEvent) {
         match &ev {
-            OperationEvent::TargetStart { .. } => {
-                self.last_percent = None;
+            OperationEvent::TargetStart { target_id, .. } => {
+                self.last_percent.remove(target_id);
+            }
+            OperationEvent::Progress { .. }
+                if self.opts.json_progress != JsonProgressMode::Bytes =>
+            {
+                return;
             }
-            OperationEvent::Block { index, total, .. } => match self.opts.json_progress {
+            OperationEvent::Block {
+                target_id,
+                index,
+                total,
+                ..
+            } => match self.opts.json_progress {
                 JsonProgressMode::Blocks => {}
-                JsonProgressMode::None => return,
+                JsonProgressMode::Bytes | JsonProgressMode::None => return,
                 JsonProgressMode::Percent => {
                     let total_u64 = (*total).max(1) as u64;
                     let percent = ((*index + 1) as u64).saturating_mul(100) / total_u64;
                     let should_emit = *index == 0
                         || *index + 1 == *total
-                        || self.last_percent.map(|p| p != percent).unwrap_or(true);
+                        || self.last_percent.get(target_id) != Some(&percent);
                     if !should_emit {
                         return;
                     }
-                    self.last_percent = Some(percent);
+                    self.last_percent.insert(target_id.clone(), percent);
                 }
             },
             _ => {}
         }
 
-        self.json_event(operation_event_to_json(ev));
+        let line = self.operation_event_line(ev);
+        self.print_line(&line);
+    }
+
+    /// Render one operation event as a JSON line, ignoring progress filtering.
+    pub(crate) fn operation_event_line(&mut self, ev: OperationEvent) -> String {
+        let tagged = matches!(
+            ev,
+            OperationEvent::HexLoaded { .. } | OperationEvent::TargetDone { .. }
+        );
+        let mut json = operation_event_to_json(ev);
+        if let (true, Some(name)) = (tagged, &self.firmware_name) {
+            json = json.with_str("firmware_name", name);
+        }
+        self.render_event_json(json)
     }
 }
 
 pub fn dry_run_to_json(summary: DryRunSummary) -> JsonEvent {
-    JsonEvent::status("dry_run")
+    let mut ev = JsonEvent::status("dry_run")
         .with_u64("bytes", summary.bytes as u64)
+        .with_str(
+            "blocks_crc32",
+            &format!("{:08x}", summary.digest.blocks_crc32),
+        )
+        .with_str("sha256", &summary.digest.sha256_hex())
         .with_u64("blocks", summary.blocks as u64)
         .with_u64("blocks_to_write", summary.blocks_to_write as u64)
         .with_u64("targets", summary.target_ids.len() as u64)
@@ -165,9 +320,175 @@ pub fn dry_run_to_json(summary: DryRunSummary) -> JsonEvent {
                     .map(|t| serde_json::Value::from(t.clone()))
                     .collect(),
             ),
+        );
+    if let (Some(min), Some(max)) = (summary.min_addr, summary.max_addr) {
+        ev = ev
+            .with_u64("min_addr", min as u64)
+            .with_u64("max_addr", max as u64);
+    }
+    if let Some(entry) = summary.start_address {
+        ev = ev.with_u64("start_address", entry as u64);
+    }
+    ev
+}
+
+/// `bridge status`; `reachable` is 0 when the IPC failed (`error` is then set).
+pub fn bridge_status_to_json(
+    endpoint: &str,
+    status: &Result<bridge_control::BridgeControlStatus, String>,
+) -> JsonEvent {
+    let ev = JsonEvent::status("bridge_status").with_str("endpoint", endpoint);
+    match status {
+        Ok(st) => ev.with_u64("reachable", 1).with_value(
+            "control",
+            serde_json::to_value(st)
+                .unwrap_or_else(|_| serde_json::Value::Object(serde_json::Map::new())),
+        ),
+        Err(e) => ev.with_u64("reachable", 0).with_str("error", e),
+    }
+}
+
+pub fn watch_started_to_json(ignored: &[String]) -> JsonEvent {
+    JsonEvent::status("watch_started").with_value(
+        "ignored",
+        serde_json::Value::Array(ignored.iter().map(|id| id.as_str().into()).collect()),
+    )
+}
+
+pub fn watch_stopped_to_json(flashed: usize, failed: usize) -> JsonEvent {
+    JsonEvent::status("watch_stopped")
+        .with_u64("flashed", flashed as u64)
+        .with_u64("failed", failed as u64)
+}
+
+pub fn error_to_json(code: i32, message: &str) -> JsonEvent {
+    JsonEvent::status("error")
+        .with_u64("code", code as u64)
+        .with_str("message", message)
+}
+
+pub fn selftest_to_json(report: SelftestReport) -> JsonEvent {
+    let mut ev = JsonEvent::status("selftest")
+        .with_u64("passed", if report.passed { 1 } else { 0 })
+        .with_u64("total_ms", report.total_ms);
+    if let Some(id) = &report.target_id {
+        ev = ev.with_str("target_id", id);
+    }
+    if let Some(port) = &report.port {
+        ev = ev.with_str("port", port);
+    }
+    if let Some(ms) = report.flash_ms {
+        ev = ev.with_u64("flash_ms", ms);
+    }
+    if let Some(ms) = report.reenumerate_ms {
+        ev = ev.with_u64("reenumerate_ms", ms);
+    }
+    if let Some(reason) = &report.reason {
+        ev = ev.with_str("reason", reason);
+    }
+    ev
+}
+
+pub fn preflight_to_json(report: PreflightReport) -> JsonEvent {
+    let mut ev = JsonEvent::status("preflight")
+        .with_u64("ready", if report.blocking.is_empty() { 1 } else { 0 })
+        .with_value(
+            "target_ids",
+            serde_json::Value::Array(report.target_ids.into_iter().map(Into::into).collect()),
+        )
+        .with_u64("needs_serial", if report.needs_serial { 1 } else { 0 })
+        .with_u64("bridge_checked", if report.bridge_checked { 1 } else { 0 })
+        .with_value(
+            "blocking",
+            serde_json::Value::Array(
+                report
+                    .blocking
+                    .iter()
+                    .map(|issue| {
+                        serde_json::json!({
+                            "check": issue.check,
+                            "code": issue.code,
+                            "message": issue.message,
+                        })
+                    })
+                    .collect(),
+            ),
+        );
+    if let Some(b) = report.firmware_bytes {
+        ev = ev.with_u64("firmware_bytes", b as u64);
+    }
+    if let Some(crc) = report.firmware_crc32 {
+        ev = ev.with_str("firmware_crc32", &format!("{crc:08x}"));
+    }
+    ev
+}
+
+pub fn exit_codes_to_json() -> JsonEvent {
+    JsonEvent::status("exit_codes").with_value(
+        "codes",
+        serde_json::Value::Array(
+            exit_codes::ALL
+                .iter()
+                .map(|info| {
+                    serde_json::json!({
+                        "code": info.code,
+                        "name": info.name,
+                        "meaning": info.meaning,
+                    })
+                })
+                .collect(),
+        ),
+    )
+}
+
+pub fn selected_targets_to_json(ids: &[String]) -> JsonEvent {
+    JsonEvent::status("selected_targets")
+        .with_u64("count", ids.len() as u64)
+        .with_value(
+            "target_ids",
+            serde_json::Value::Array(ids.iter().map(|id| id.as_str().into()).collect()),
+        )
+}
+
+pub fn reboot_probe_to_json(summary: RebootProbeSummary) -> JsonEvent {
+    reboot_plan_to_json("reboot_probe", summary)
+}
+
+fn reboot_plan_to_json(event: &'static str, summary: RebootProbeSummary) -> JsonEvent {
+    JsonEvent::status(event)
+        .with_u64("targets", summary.actions.len() as u64)
+        .with_u64("needs_serial", if summary.needs_serial { 1 } else { 0 })
+        .with_u64("bridge_enabled", if summary.bridge_enabled { 1 } else { 0 })
+        .with_u64("bridge_control_port", summary.bridge_control_port as u64)
+        .with_value(
+            "actions",
+            serde_json::Value::Array(
+                summary
+                    .actions
+                    .into_iter()
+                    .map(|(target_id, action)| {
+                        serde_json::json!({ "target_id": target_id, "action": action })
+                    })
+                    .collect(),
+            ),
         )
 }
 
+/// `reboot --dry-run`: a `dry_run` event (`operation: "reboot"`) with the probe's fields,
+/// `target_ids`, and `bridge_pause` (whether oc-bridge would be paused).
+pub fn reboot_dry_run_to_json(summary: RebootProbeSummary) -> JsonEvent {
+    let target_ids = summary
+        .actions
+        .iter()
+        .map(|(id, _)| serde_json::Value::from(id.as_str()))
+        .collect();
+    let bridge_pause = summary.needs_serial && summary.bridge_enabled;
+    reboot_plan_to_json("dry_run", summary)
+        .with_str("operation", "reboot")
+        .with_value("target_ids", serde_json::Value::Array(target_ids))
+        .with_u64("bridge_pause", if bridge_pause { 1 } else { 0 })
+}
+
 pub fn operation_summary_to_json(summary: OperationSummary) -> JsonEvent {
     let OperationSummary {
         operation,
@@ -180,6 +501,14 @@ pub fn operation_summary_to_json(summary: OperationSummary) -> JsonEvent {
         bridge_pause,
         bridge_method,
         bridge_reason,
+        firmware,
+        target_firmware_crc32,
+        targets_skipped,
+        retry_detail,
+        retry_detail_omitted,
+        target_errors,
+        firmware_name,
+        phases,
     } = summary;
 
     let total = targets_ok.len() + targets_failed.len();
@@ -209,11 +538,119 @@ pub fn operation_summary_to_json(summary: OperationSummary) -> JsonEvent {
     if let Some(r) = &bridge_reason {
         ev = ev.with_str("bridge_reason", r);
     }
+    if !targets_skipped.is_empty() {
+        ev = ev
+            .with_u64("targets_skipped", targets_skipped.len() as u64)
+            .with_value(
+                "targets_skipped_ids",
+                serde_json::Value::Array(targets_skipped.into_iter().map(Into::into).collect()),
+            );
+    }
+    if !retry_detail.is_empty() {
+        ev = ev.with_value(
+            "retry_detail",
+            serde_json::Value::Array(
+                retry_detail
+                    .iter()
+                    .map(|d| {
+                        serde_json::json!({
+                            "target_id": d.target_id,
+                            "addr": d.addr,
+                            "retries": d.retries,
+                        })
+                    })
+                    .collect(),
+            ),
+        );
+    }
+    if retry_detail_omitted > 0 {
+        ev = ev.with_u64("retry_detail_omitted", retry_detail_omitted as u64);
+    }
+    if !target_errors.is_empty() {
+        ev = ev.with_value(
+            "target_errors",
+            serde_json::Value::Object(
+                target_errors
+                    .into_iter()
+                    .map(|(id, m)| (id, m.into()))
+                    .collect(),
+            ),
+        );
+    }
+    if let Some(name) = &firmware_name {
+        ev = ev.with_str("firmware_name", name);
+    }
+    match firmware.as_slice() {
+        [] => {}
+        [fw] => {
+            ev = ev
+                .with_u64("firmware_bytes", fw.bytes as u64)
+                .with_str("firmware_crc32", &format!("{:08x}", fw.crc32));
+        }
+        images => {
+            ev = ev.with_value(
+                "firmware_images",
+                serde_json::Value::Array(
+                    images
+                        .iter()
+                        .map(|fw| {
+                            serde_json::json!({
+                                "bytes": fw.bytes,
+                                "crc32": format!("{:08x}", fw.crc32),
+                            })
+                        })
+                        .collect(),
+                ),
+            );
+        }
+    }
+    if !target_firmware_crc32.is_empty() {
+        ev = ev.with_value(
+            "target_firmware_crc32",
+            serde_json::Value::Object(
+                target_firmware_crc32
+                    .into_iter()
+                    .map(|(id, crc)| (id, format!("{crc:08x}").into()))
+                    .collect(),
+            ),
+        );
+    }
     if let Some(msg) = &message {
         ev = ev.with_str("message", msg);
     }
 
-    ev
+    match phases_to_json(phases) {
+        Some(phases) => ev.with_value("phases", phases),
+        None => ev,
+    }
+}
+
+/// `None` when no phase ran.
+fn phases_to_json(phases: PhaseTimings) -> Option<serde_json::Value> {
+    let mut obj = serde_json::Map::new();
+    for (k, v) in [
+        ("discovery_ms", phases.discovery_ms),
+        ("hex_load_ms", phases.hex_load_ms),
+        ("bridge_pause_ms", phases.bridge_pause_ms),
+        ("bridge_resume_ms", phases.bridge_resume_ms),
+    ] {
+        if let Some(ms) = v {
+            obj.insert(k.to_string(), ms.into());
+        }
+    }
+    if !phases.target_ms.is_empty() {
+        obj.insert(
+            "target_ms".to_string(),
+            serde_json::Value::Object(
+                phases
+                    .target_ms
+                    .into_iter()
+                    .map(|(id, ms)| (id, ms.into()))
+                    .collect(),
+            ),
+        );
+    }
+    (!obj.is_empty()).then_some(serde_json::Value::Object(obj))
 }
 
 pub fn doctor_to_json(report: DoctorReport) -> JsonEvent {
@@ -241,6 +678,23 @@ pub fn doctor_to_json(report: DoctorReport) -> JsonEvent {
             if report.control_checked { 1 } else { 0 },
         );
 
+    if let Some((path, exists)) = &report.control_socket {
+        ev = ev
+            .with_str("control_socket", &path.display().to_string())
+            .with_u64("control_socket_exists", if *exists { 1 } else { 0 });
+    }
+    if !report.discovery_errors.is_empty() {
+        ev = ev.with_value(
+            "discovery_errors",
+            serde_json::Value::Object(
+                report
+                    .discovery_errors
+                    .iter()
+                    .map(|(subsystem, e)| (subsystem.to_string(), e.as_str().into()))
+                    .collect(),
+            ),
+        );
+    }
     if let Some(st) = &report.control {
         ev = ev.with_value(
             "control",
@@ -260,13 +714,80 @@ pub fn doctor_to_json(report: DoctorReport) -> JsonEvent {
     if let Some(e) = &report.service_error {
         ev = ev.with_str("service_error", e);
     }
+    if let Some(devices) = &report.hid_devices {
+        ev = ev.with_value(
+            "hid_devices",
+            serde_json::Value::Array(devices.iter().map(hid_info_to_value).collect()),
+        );
+    }
+    if let Some(e) = &report.hid_error {
+        ev = ev.with_str("hid_error", e);
+    }
+    if let Some(fixes) = &report.fixes {
+        ev = ev.with_value(
+            "fixes",
+            serde_json::Value::Array(
+                fixes
+                    .iter()
+                    .map(|f| {
+                        serde_json::json!({
+                            "action": f.action,
+                            "status": f.status.as_str(),
+                            "message": f.message,
+                        })
+                    })
+                    .collect(),
+            ),
+        );
+    }
+    if let Some(hint) = &report.permission_hint {
+        ev = ev.with_value(
+            "permission_hint",
+            serde_json::json!({
+                "message": PERMISSION_HINT,
+                "path": hint.path,
+                "error": hint.error,
+                "rules_path": TEENSY_UDEV_RULES_PATH,
+                "rules": TEENSY_UDEV_RULES,
+                "reload": UDEV_RELOAD_COMMAND,
+            }),
+        );
+    }
 
     ev
 }
 
+fn hid_info_to_value(d: &halfkay::HalfKayHidInfo) -> serde_json::Value {
+    let descriptor: String = d
+        .report_descriptor
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    let mut v = serde_json::json!({
+        "path": d.path,
+        "vid": d.vid,
+        "pid": d.pid,
+        "release_number": d.release_number,
+        "usage_page": d.usage_page,
+        "usage": d.usage,
+        "interface_number": d.interface_number,
+        "report_descriptor": descriptor,
+        "input_report_bits": d.report_sizes.input_bits,
+        "output_report_bits": d.report_sizes.output_bits,
+        "feature_report_bits": d.report_sizes.feature_bits,
+    });
+    if let (Some(e), serde_json::Value::Object(obj)) = (&d.report_descriptor_error, &mut v) {
+        obj.insert("report_descriptor_error".to_string(), e.clone().into());
+    }
+    v
+}
+
 pub fn operation_event_to_json(ev: OperationEvent) -> JsonEvent {
     match ev {
         OperationEvent::DiscoverStart => JsonEvent::status("discover_start"),
+        OperationEvent::Warning { code, message } => JsonEvent::status("warning")
+            .with_str("code", code)
+            .with_str("message", &message),
         OperationEvent::TargetDetected { index, target } => JsonEvent::status("target_detected")
             .with_value("target", target_to_value(index, &target)),
         OperationEvent::DiscoverDone { count } => {
@@ -282,7 +803,7 @@ pub fn operation_event_to_json(ev: OperationEvent) -> JsonEvent {
                 midi_studio_loader::bridge_control::BridgePauseMethod::Service => "service",
                 midi_studio_loader::bridge_control::BridgePauseMethod::Process => "process",
             };
-            JsonEvent::status("bridge_paused")
+            let mut ev = JsonEvent::status("bridge_paused")
                 .with_str("method", method)
                 .with_str("id", &info.id)
                 .with_value(
@@ -293,7 +814,14 @@ pub fn operation_event_to_json(ev: OperationEvent) -> JsonEvent {
                             .map(|p| serde_json::Value::from(*p as u64))
                             .collect(),
                     ),
-                )
+                );
+            if let Some(open) = info.serial_open {
+                ev = ev.with_value("serial_open", serde_json::Value::from(open));
+            }
+            if let Some(message) = &info.message {
+                ev = ev.with_str("message", message);
+            }
+            ev
         }
         OperationEvent::BridgePauseSkipped { reason } => {
             let reason = match reason {
@@ -315,6 +843,14 @@ pub fn operation_event_to_json(ev: OperationEvent) -> JsonEvent {
             ev
         }
         OperationEvent::BridgeResumeStart => JsonEvent::status("bridge_resume_start"),
+        OperationEvent::BridgeResumeRetry {
+            attempt,
+            retries,
+            error,
+        } => JsonEvent::status("bridge_resume_retry")
+            .with_u64("attempt", attempt as u64)
+            .with_u64("retries", retries as u64)
+            .with_str("message", &error),
         OperationEvent::BridgeResumed => JsonEvent::status("bridge_resumed"),
         OperationEvent::BridgeResumeFailed { error } => {
             let mut ev =
@@ -324,9 +860,49 @@ pub fn operation_event_to_json(ev: OperationEvent) -> JsonEvent {
             }
             ev
         }
-        OperationEvent::HexLoaded { bytes, blocks } => JsonEvent::status("hex_loaded")
+        OperationEvent::BridgeLeftPaused { hint } => {
+            let mut ev = JsonEvent::status("bridge_left_paused");
+            if let Some(hint) = &hint {
+                ev = ev.with_str("hint", hint);
+            }
+            ev
+        }
+        OperationEvent::HexLoadStart { path } => {
+            JsonEvent::status("hex_load_start").with_str("path", &path)
+        }
+        OperationEvent::HexLoading { line_no } => {
+            JsonEvent::status("hex_loading").with_u64("line_no", line_no as u64)
+        }
+        OperationEvent::HexLoaded {
+            bytes,
+            blocks,
+            crc32,
+            digest,
+        } => JsonEvent::status("hex_loaded")
             .with_u64("bytes", bytes as u64)
-            .with_u64("blocks", blocks as u64),
+            .with_u64("blocks", blocks as u64)
+            .with_str("crc32", &format!("{crc32:08x}"))
+            .with_str("sha256", &digest.sha256_hex()),
+        OperationEvent::BaselineDiff {
+            total_blocks,
+            changed_blocks,
+        } => JsonEvent::status("baseline_diff")
+            .with_u64("total_blocks", total_blocks as u64)
+            .with_u64("changed_blocks", changed_blocks as u64),
+        OperationEvent::FirmwareAssigned {
+            target_id,
+            path,
+            crc32,
+        } => JsonEvent::status("firmware_assigned")
+            .with_str("target_id", &target_id)
+            .with_str("path", &path)
+            .with_str("crc32", &format!("{crc32:08x}")),
+        OperationEvent::TargetSkipped { target_id, reason } => JsonEvent::status("target_skipped")
+            .with_str("target_id", &target_id)
+            .with_str("reason", &reason),
+        OperationEvent::BetweenTargetsDelay { delay_ms } => {
+            JsonEvent::status("between_targets_delay").with_u64("delay_ms", delay_ms)
+        }
         OperationEvent::TargetStart { target_id, kind } => JsonEvent::status("target_start")
             .with_str("target_id", &target_id)
             .with_str(
@@ -349,9 +925,27 @@ pub fn operation_event_to_json(ev: OperationEvent) -> JsonEvent {
             }
             ev
         }
-        OperationEvent::SoftReboot { target_id, port } => JsonEvent::status("soft_reboot")
+        OperationEvent::SoftReboot {
+            target_id,
+            port,
+            strategy,
+            ..
+        } => JsonEvent::status("soft_reboot")
             .with_str("target_id", &target_id)
-            .with_str("port", &port),
+            .with_str("port", &port)
+            .with_str(
+                "strategy",
+                match strategy {
+                    RebootStrategy::MagicBaud | RebootStrategy::Auto => "magic_baud",
+                    RebootStrategy::DtrPulse => "dtr_pulse",
+                },
+            ),
+        OperationEvent::WaitingForHalfKay {
+            target_id,
+            elapsed_ms,
+        } => JsonEvent::status("waiting_for_halfkay")
+            .with_str("target_id", &target_id)
+            .with_u64("elapsed_ms", elapsed_ms),
         OperationEvent::SoftRebootSkipped { target_id, error } => {
             JsonEvent::status("soft_reboot_skipped")
                 .with_str("target_id", &target_id)
@@ -375,21 +969,51 @@ pub fn operation_event_to_json(ev: OperationEvent) -> JsonEvent {
             .with_u64("i", index as u64)
             .with_u64("n", total as u64)
             .with_u64("addr", addr as u64),
+        OperationEvent::Progress {
+            target_id,
+            bytes_written,
+            bytes_total,
+        } => JsonEvent::status("progress")
+            .with_str("target_id", &target_id)
+            .with_u64("bytes_written", bytes_written)
+            .with_u64("bytes_total", bytes_total),
         OperationEvent::Retry {
             target_id,
             addr,
             attempt,
             retries,
+            delay_ms,
             error,
         } => JsonEvent::status("retry")
             .with_str("target_id", &target_id)
             .with_u64("addr", addr as u64)
             .with_u64("attempt", attempt as u64)
             .with_u64("retries", retries as u64)
+            .with_u64("delay_ms", delay_ms)
             .with_str("error", &error),
         OperationEvent::Boot { target_id } => {
             JsonEvent::status("boot").with_str("target_id", &target_id)
         }
+        OperationEvent::BootConfirmed { target_id } => {
+            JsonEvent::status("boot_confirmed").with_str("target_id", &target_id)
+        }
+        OperationEvent::BootFailed {
+            target_id,
+            timeout_ms,
+        } => JsonEvent::status("boot_failed")
+            .with_str("target_id", &target_id)
+            .with_u64("timeout_ms", timeout_ms),
+        OperationEvent::SerialReappeared { target_id, port } => {
+            JsonEvent::status("serial_reappeared")
+                .with_str("target_id", &target_id)
+                .with_str("port", &port)
+        }
+        OperationEvent::VerifySkipped { target_id, reason } => JsonEvent::status("verify_skipped")
+            .with_str("target_id", &target_id)
+            .with_str("reason", reason),
+        OperationEvent::Verified { target_id, port } => JsonEvent::status("verified")
+            .with_str("target_id", &target_id)
+            .with_str("port", &port),
         OperationEvent::Done { target_id } => {
             JsonEvent::status("done").with_str("target_id", &target_id)
         }
@@ -1,9 +1,16 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
 use crate::cli;
+use crate::exit_codes;
 
-use midi_studio_loader::{bridge_control, operation::OperationEvent, targets};
+use midi_studio_loader::{bridge_control, halfkay, hex, operation::OperationEvent, targets};
 
+pub mod csv;
 pub mod human;
 pub mod json;
+mod target_log;
 
 #[cfg(test)]
 mod tests;
@@ -14,32 +21,64 @@ pub struct OutputOptions {
     pub quiet: bool,
     pub json_timestamps: bool,
     pub json_progress: JsonProgressMode,
+    /// Suppress every JSON line except the final verdict.
+    pub json_compact_summary: bool,
+    /// Stream that receives the JSON event lines.
+    pub json_stream: JsonStream,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonStream {
+    Stdout,
+    Stderr,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum JsonProgressMode {
     Blocks,
     Percent,
+    /// `progress` events with byte counts instead of `block` events.
+    Bytes,
     None,
 }
 
 #[derive(Debug, Clone)]
 pub struct DryRunSummary {
     pub bytes: usize,
+    pub digest: hex::FirmwareDigest,
     pub blocks: usize,
     pub blocks_to_write: usize,
+    /// Absolute flash addresses spanned by the firmware data (see `FirmwareImage::min_addr`).
+    pub min_addr: Option<u32>,
+    pub max_addr: Option<u32>,
+    /// Entry point from the HEX start address record, if any.
+    pub start_address: Option<u32>,
     pub target_ids: Vec<String>,
     pub needs_serial: bool,
     pub bridge_enabled: bool,
     pub bridge_control_port: u16,
 }
 
+/// Result of `reboot --probe-only`: what each selected target would go through.
+#[derive(Debug, Clone)]
+pub struct RebootProbeSummary {
+    /// `(target_id, action)` where action is `already_in_bootloader` or `soft_reboot`.
+    pub actions: Vec<(String, &'static str)>,
+    pub needs_serial: bool,
+    pub bridge_enabled: bool,
+    pub bridge_control_port: u16,
+}
+
 #[derive(Debug, Clone)]
 pub struct DoctorReport {
     pub service_id: String,
     pub targets: Vec<targets::Target>,
+    /// Failed enumerations as `(subsystem, message)`; `targets` holds the others' results.
+    pub discovery_errors: Vec<(&'static str, String)>,
 
     pub control_port: u16,
+    /// Control socket in effect instead of the port, and whether it exists.
+    pub control_socket: Option<(PathBuf, bool)>,
     pub control_timeout_ms: u64,
     pub control_checked: bool,
     pub control: Option<bridge_control::BridgeControlStatus>,
@@ -49,6 +88,104 @@ pub struct DoctorReport {
     pub service_error: Option<String>,
 
     pub processes: Vec<bridge_control::OcBridgeProcessInfo>,
+
+    /// HalfKay HID details (`--dump-hid-descriptor` only).
+    pub hid_devices: Option<Vec<halfkay::HalfKayHidInfo>>,
+    pub hid_error: Option<String>,
+
+    /// A visible HalfKay device that cannot be opened for lack of permissions (Linux only).
+    pub permission_hint: Option<PermissionHint>,
+
+    /// Remediation steps (`--fix` only), in the order they ran.
+    pub fixes: Option<Vec<DoctorFix>>,
+}
+
+/// One `doctor --fix` step.
+#[derive(Debug, Clone)]
+pub struct DoctorFix {
+    /// Stable snake_case identifier, e.g. `install_udev_rules`.
+    pub action: &'static str,
+    pub status: FixStatus,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixStatus {
+    /// The change was made.
+    Applied,
+    /// Nothing to do, or the user declined.
+    Skipped,
+    Failed,
+    /// Needs a manual step; `message` says which.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    Manual,
+}
+
+impl FixStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FixStatus::Applied => "applied",
+            FixStatus::Skipped => "skipped",
+            FixStatus::Failed => "failed",
+            FixStatus::Manual => "manual",
+        }
+    }
+}
+
+/// Result of `selftest`: one flash + boot round trip on a single board.
+#[derive(Debug, Clone)]
+pub struct SelftestReport {
+    pub passed: bool,
+    pub target_id: Option<String>,
+    /// USB serial port the board came back on.
+    pub port: Option<String>,
+    /// Why the test failed (`None` when it passed).
+    pub reason: Option<String>,
+    /// From the start until the boot command.
+    pub flash_ms: Option<u64>,
+    /// From the boot command until the serial port came back.
+    pub reenumerate_ms: Option<u64>,
+    pub total_ms: u64,
+}
+
+/// HalfKay device the current user may not open (see `DoctorReport::permission_hint`).
+#[derive(Debug, Clone)]
+pub struct PermissionHint {
+    pub path: String,
+    pub error: String,
+}
+
+pub const PERMISSION_HINT: &str = "insufficient permissions — install 00-teensy.rules";
+/// Where PJRC's udev rules go, and their contents.
+pub const TEENSY_UDEV_RULES_PATH: &str = "/etc/udev/rules.d/00-teensy.rules";
+pub const TEENSY_UDEV_RULES: &str = r#"ATTRS{idVendor}=="16c0", ATTRS{idProduct}=="04[789B]?", ENV{ID_MM_DEVICE_IGNORE}="1", ENV{ID_MM_PORT_IGNORE}="1"
+ATTRS{idVendor}=="16c0", ATTRS{idProduct}=="04[789A]?", ENV{MTP_NO_PROBE}="1"
+SUBSYSTEMS=="usb", ATTRS{idVendor}=="16c0", ATTRS{idProduct}=="04[789ABCD]?", MODE:="0666"
+KERNEL=="ttyACM*", ATTRS{idVendor}=="16c0", ATTRS{idProduct}=="04[789B]?", MODE:="0666"
+"#;
+/// Reload command to run after installing [`TEENSY_UDEV_RULES`].
+pub const UDEV_RELOAD_COMMAND: &str = "sudo udevadm control --reload-rules && sudo udevadm trigger";
+
+/// Result of `preflight`: ready when `blocking` is empty.
+#[derive(Debug, Clone)]
+pub struct PreflightReport {
+    pub firmware_bytes: Option<usize>,
+    pub firmware_crc32: Option<u32>,
+    pub target_ids: Vec<String>,
+    pub needs_serial: bool,
+    /// Whether oc-bridge was probed (only when enabled and the flash would need serial).
+    pub bridge_checked: bool,
+    /// Issues that would stop the flash, in check order.
+    pub blocking: Vec<PreflightIssue>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PreflightIssue {
+    /// `targets`, `firmware` or `bridge`.
+    pub check: &'static str,
+    /// Exit code the flash would likely end with.
+    pub code: i32,
+    pub message: String,
 }
 
 #[derive(Debug, Clone)]
@@ -56,10 +193,71 @@ pub enum Event {
     Operation(OperationEvent),
     OperationSummary(OperationSummary),
     DryRun(DryRunSummary),
-    ListTargets(Vec<targets::Target>),
+    RebootProbe(RebootProbeSummary),
+    /// `reboot --dry-run`: the `reboot --probe-only` plan, reported like `flash --dry-run`.
+    RebootDryRun(RebootProbeSummary),
+    /// Target ids chosen by selection (`--print-selected`).
+    SelectedTargets(Vec<String>),
+    /// Targets paired with their discovery index, in display order.
+    ListTargets(Vec<(usize, targets::Target)>),
+    /// `list --watch`: a target appeared, with its index in the new snapshot.
+    TargetAdded(usize, targets::Target),
+    /// `list --watch`: a target disappeared, with its index in the previous snapshot.
+    TargetRemoved(usize, targets::Target),
     Doctor(DoctorReport),
-    Error { code: i32, message: String },
+    Preflight(PreflightReport),
+    /// `watch`: polling started; the targets already attached are never flashed.
+    WatchStarted {
+        ignored: Vec<String>,
+    },
+    /// `watch` stopped (Ctrl-C): boards flashed and failed since it started.
+    WatchStopped {
+        flashed: usize,
+        failed: usize,
+    },
+    /// `selftest` finished (the verdict).
+    Selftest(SelftestReport),
+    /// `bridge status`: the control endpoint queried and its answer (or the IPC error).
+    BridgeStatus {
+        endpoint: String,
+        status: Result<bridge_control::BridgeControlStatus, String>,
+    },
+    /// The exit code table (`exit_codes::ALL`).
+    ExitCodes,
+    Error {
+        code: i32,
+        message: String,
+    },
+    /// An error whose cause has structured fields (e.g. a HEX parse failure); human output
+    /// shows it like `Error`, JSON merges the `details` object into the `error` event.
+    ErrorWithDetails {
+        code: i32,
+        message: String,
+        details: serde_json::Value,
+    },
     HintAmbiguousTargets,
+    /// No target was found: tell the user how to get one detected.
+    HintNoDevice,
+}
+
+/// Platform-specific steps to get a Teensy detected (see `Event::HintNoDevice`).
+pub fn no_device_steps() -> Vec<&'static str> {
+    let mut steps = vec![
+        "Connect the board with a data-capable USB cable (not charge-only), directly or via a powered hub.",
+        "Press the PROGRAM button on the Teensy to enter the HalfKay bootloader.",
+    ];
+    if cfg!(target_os = "linux") {
+        steps.push(
+            "Install the Teensy udev rules (00-teensy.rules) so 16C0:0478 is accessible without root.",
+        );
+    } else if cfg!(windows) {
+        steps.push(
+            "Check Device Manager: HalfKay shows up as a HID device (16C0:0478) and needs no extra driver.",
+        );
+    } else if cfg!(target_os = "macos") {
+        steps.push("Check System Information > USB for a Teensy (16C0:0478).");
+    }
+    steps
 }
 
 #[derive(Debug, Clone)]
@@ -77,6 +275,62 @@ pub struct OperationSummary {
     pub bridge_pause: String,
     pub bridge_method: Option<String>,
     pub bridge_reason: Option<String>,
+
+    /// Fingerprint of each firmware image loaded, in load order (flash only; one per
+    /// distinct file with `--firmware-map`).
+    pub firmware: Vec<FirmwareFingerprint>,
+    /// CRC-32 of the image assigned to each target, by target id (`--firmware-map` only).
+    pub target_firmware_crc32: BTreeMap<String, u32>,
+
+    /// Targets intentionally left untouched (e.g. no firmware mapped).
+    pub targets_skipped: Vec<String>,
+
+    /// Blocks that needed retries, by address (worst [`MAX_RETRY_DETAIL`] kept).
+    pub retry_detail: Vec<RetryDetail>,
+    /// Retried blocks left out of `retry_detail` by the cap.
+    pub retry_detail_omitted: usize,
+
+    /// Failure message of each failed target, by target id.
+    pub target_errors: BTreeMap<String, String>,
+
+    /// `--firmware-name` label (flash only).
+    pub firmware_name: Option<String>,
+
+    /// Where the time went.
+    pub phases: PhaseTimings,
+}
+
+/// Wall-clock time spent in each phase, in milliseconds; `None` when the phase never ran.
+///
+/// Repeated phases (e.g. one HEX load per mapped firmware) are summed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PhaseTimings {
+    /// `DiscoverStart` -> `DiscoverDone`.
+    pub discovery_ms: Option<u64>,
+    /// `HexLoadStart` -> `HexLoaded`.
+    pub hex_load_ms: Option<u64>,
+    /// `BridgePauseStart` -> paused, skipped or failed.
+    pub bridge_pause_ms: Option<u64>,
+    /// `TargetStart` -> `TargetDone`, by target id.
+    pub target_ms: BTreeMap<String, u64>,
+    /// `BridgeResumeStart` -> resumed, failed or left paused.
+    pub bridge_resume_ms: Option<u64>,
+}
+
+/// Cap on `OperationSummary::retry_detail` entries.
+pub const MAX_RETRY_DETAIL: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FirmwareFingerprint {
+    pub bytes: usize,
+    pub crc32: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryDetail {
+    pub target_id: String,
+    pub addr: usize,
+    pub retries: u64,
 }
 
 pub struct OperationRecorder {
@@ -88,6 +342,23 @@ pub struct OperationRecorder {
     bridge_pause: String,
     bridge_method: Option<String>,
     bridge_reason: Option<String>,
+    firmware: Vec<FirmwareFingerprint>,
+    target_firmware_crc32: BTreeMap<String, u32>,
+    targets_skipped: Vec<String>,
+    retries_by_block: BTreeMap<(String, usize), u64>,
+    target_errors: BTreeMap<String, String>,
+    firmware_name: Option<String>,
+    phases: PhaseTimings,
+    phase_start: BTreeMap<Phase, Instant>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Phase {
+    Discovery,
+    HexLoad,
+    BridgePause,
+    Target(String),
+    BridgeResume,
 }
 
 impl OperationRecorder {
@@ -101,10 +372,29 @@ impl OperationRecorder {
             bridge_pause: "not_attempted".to_string(),
             bridge_method: None,
             bridge_reason: None,
+            firmware: Vec::new(),
+            target_firmware_crc32: BTreeMap::new(),
+            targets_skipped: Vec::new(),
+            retries_by_block: BTreeMap::new(),
+            target_errors: BTreeMap::new(),
+            firmware_name: None,
+            phases: PhaseTimings::default(),
+            phase_start: BTreeMap::new(),
         }
     }
 
+    pub fn with_firmware_name(mut self, name: Option<String>) -> Self {
+        self.firmware_name = name;
+        self
+    }
+
     pub fn observe(&mut self, ev: &OperationEvent) {
+        self.observe_at(ev, Instant::now());
+    }
+
+    /// [`observe`](Self::observe) with an explicit event time (for phase timings).
+    pub fn observe_at(&mut self, ev: &OperationEvent, now: Instant) {
+        self.time_phase(ev, now);
         match ev {
             OperationEvent::BridgePauseStart => {
                 self.bridge_pause = "attempted".to_string();
@@ -137,24 +427,111 @@ impl OperationRecorder {
             OperationEvent::BridgePauseFailed { .. } => {
                 self.bridge_pause = "failed".to_string();
             }
-            OperationEvent::TargetDone { target_id, ok, .. } => {
+            OperationEvent::TargetDone {
+                target_id,
+                ok,
+                message,
+            } => {
                 if *ok {
                     self.targets_ok.push(target_id.clone());
                 } else {
                     self.targets_failed.push(target_id.clone());
+                    if let Some(m) = message {
+                        self.target_errors.insert(target_id.clone(), m.clone());
+                    }
                 }
             }
+            OperationEvent::HexLoaded { bytes, crc32, .. } => {
+                self.firmware.push(FirmwareFingerprint {
+                    bytes: *bytes,
+                    crc32: *crc32,
+                });
+            }
+            OperationEvent::FirmwareAssigned {
+                target_id, crc32, ..
+            } => {
+                self.target_firmware_crc32.insert(target_id.clone(), *crc32);
+            }
+            OperationEvent::TargetSkipped { target_id, .. } => {
+                self.targets_skipped.push(target_id.clone());
+            }
             OperationEvent::Block { .. } => {
                 self.blocks = self.blocks.saturating_add(1);
             }
-            OperationEvent::Retry { .. } => {
+            OperationEvent::Retry {
+                target_id, addr, ..
+            } => {
                 self.retries = self.retries.saturating_add(1);
+                *self
+                    .retries_by_block
+                    .entry((target_id.clone(), *addr))
+                    .or_default() += 1;
             }
             _ => {}
         }
     }
 
+    fn time_phase(&mut self, ev: &OperationEvent, now: Instant) {
+        let (phase, starts) = match ev {
+            OperationEvent::DiscoverStart => (Phase::Discovery, true),
+            OperationEvent::DiscoverDone { .. } => (Phase::Discovery, false),
+            OperationEvent::HexLoadStart { .. } => (Phase::HexLoad, true),
+            OperationEvent::HexLoaded { .. } => (Phase::HexLoad, false),
+            OperationEvent::BridgePauseStart => (Phase::BridgePause, true),
+            OperationEvent::BridgePaused { .. }
+            | OperationEvent::BridgePauseSkipped { .. }
+            | OperationEvent::BridgePauseFailed { .. } => (Phase::BridgePause, false),
+            OperationEvent::TargetStart { target_id, .. } => {
+                (Phase::Target(target_id.clone()), true)
+            }
+            OperationEvent::TargetDone { target_id, .. } => {
+                (Phase::Target(target_id.clone()), false)
+            }
+            OperationEvent::BridgeResumeStart => (Phase::BridgeResume, true),
+            OperationEvent::BridgeResumed
+            | OperationEvent::BridgeResumeFailed { .. }
+            | OperationEvent::BridgeLeftPaused { .. } => (Phase::BridgeResume, false),
+            _ => return,
+        };
+        if starts {
+            self.phase_start.insert(phase, now);
+            return;
+        }
+        let Some(start) = self.phase_start.remove(&phase) else {
+            return;
+        };
+        let ms = now.saturating_duration_since(start).as_millis() as u64;
+        let slot = match phase {
+            Phase::Discovery => &mut self.phases.discovery_ms,
+            Phase::HexLoad => &mut self.phases.hex_load_ms,
+            Phase::BridgePause => &mut self.phases.bridge_pause_ms,
+            Phase::BridgeResume => &mut self.phases.bridge_resume_ms,
+            Phase::Target(id) => {
+                *self.phases.target_ms.entry(id).or_default() += ms;
+                return;
+            }
+        };
+        *slot = Some(slot.unwrap_or(0).saturating_add(ms));
+    }
+
     pub fn finish(self, exit_code: i32, message: Option<String>) -> OperationSummary {
+        let mut retry_detail: Vec<RetryDetail> = self
+            .retries_by_block
+            .into_iter()
+            .map(|((target_id, addr), retries)| RetryDetail {
+                target_id,
+                addr,
+                retries,
+            })
+            .collect();
+        let retry_detail_omitted = retry_detail.len().saturating_sub(MAX_RETRY_DETAIL);
+        if retry_detail_omitted > 0 {
+            // Keep the worst blocks, then restore address order.
+            retry_detail.sort_by_key(|d| std::cmp::Reverse(d.retries));
+            retry_detail.truncate(MAX_RETRY_DETAIL);
+            retry_detail.sort_by(|a, b| (&a.target_id, a.addr).cmp(&(&b.target_id, b.addr)));
+        }
+
         OperationSummary {
             operation: self.operation,
             exit_code,
@@ -166,6 +543,14 @@ impl OperationRecorder {
             bridge_pause: self.bridge_pause,
             bridge_method: self.bridge_method,
             bridge_reason: self.bridge_reason,
+            firmware: self.firmware,
+            target_firmware_crc32: self.target_firmware_crc32,
+            targets_skipped: self.targets_skipped,
+            retry_detail,
+            retry_detail_omitted,
+            target_errors: self.target_errors,
+            firmware_name: self.firmware_name,
+            phases: self.phases,
         }
     }
 }
@@ -175,22 +560,148 @@ pub trait Reporter {
     fn finish(&mut self);
 }
 
+/// Holds every event back and replays them only if the operation failed (`--quiet-success`).
+pub struct QuietSuccess {
+    inner: Box<dyn Reporter>,
+    buffered: Vec<Event>,
+    failed: bool,
+}
+
+impl QuietSuccess {
+    pub fn new(inner: Box<dyn Reporter>) -> Self {
+        Self {
+            inner,
+            buffered: Vec::new(),
+            failed: false,
+        }
+    }
+}
+
+impl Reporter for QuietSuccess {
+    fn emit(&mut self, event: Event) {
+        self.failed |= match &event {
+            Event::Error { .. } | Event::ErrorWithDetails { .. } => true,
+            Event::OperationSummary(s) => s.exit_code != 0,
+            _ => false,
+        };
+        self.buffered.push(event);
+    }
+
+    fn finish(&mut self) {
+        if !self.failed {
+            return;
+        }
+        for event in self.buffered.drain(..) {
+            self.inner.emit(event);
+        }
+        self.inner.finish();
+    }
+}
+
+/// Flushes both standard streams after every event (`--flush-events`).
+///
+/// Keeps lines written to stdout and stderr in emission order when both are piped.
+pub struct FlushEach {
+    inner: Box<dyn Reporter>,
+}
+
+impl FlushEach {
+    pub fn new(inner: Box<dyn Reporter>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Reporter for FlushEach {
+    fn emit(&mut self, event: Event) {
+        self.inner.emit(event);
+        flush_std_streams();
+    }
+
+    fn finish(&mut self) {
+        self.inner.finish();
+        flush_std_streams();
+    }
+}
+
+fn flush_std_streams() {
+    use std::io::Write;
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+}
+
+/// JSON reporter writing to `--json-output <path>` when given (truncated, line-buffered),
+/// else to `opts.json_stream`.
+///
+/// Exits with `EXIT_UNEXPECTED` if the file cannot be created: nothing has run yet.
+fn json_reporter(opts: OutputOptions, path: Option<&Path>) -> json::JsonOutput {
+    let out = json::JsonOutput::new(opts);
+    let Some(path) = path else {
+        return out;
+    };
+    out.with_writer(Box::new(std::io::LineWriter::new(create_output_file(path))))
+}
+
+/// Create (truncate) an output file, exiting with `EXIT_UNEXPECTED` if that fails.
+fn create_output_file(path: &Path) -> std::fs::File {
+    std::fs::File::create(path).unwrap_or_else(|e| {
+        eprintln!("error: cannot create {}: {e}", path.display());
+        std::process::exit(exit_codes::EXIT_UNEXPECTED);
+    })
+}
+
+fn json_stream(arg: cli::JsonStreamArg) -> JsonStream {
+    match arg {
+        cli::JsonStreamArg::Stdout => JsonStream::Stdout,
+        cli::JsonStreamArg::Stderr => JsonStream::Stderr,
+    }
+}
+
 pub fn make_for_flash(args: &cli::FlashArgs) -> Box<dyn Reporter> {
     let json_progress = match args.json_progress {
         cli::JsonProgressArg::Blocks => JsonProgressMode::Blocks,
         cli::JsonProgressArg::Percent => JsonProgressMode::Percent,
+        cli::JsonProgressArg::Bytes => JsonProgressMode::Bytes,
         cli::JsonProgressArg::None => JsonProgressMode::None,
     };
     let opts = OutputOptions {
         verbose: args.verbose,
-        quiet: args.quiet,
+        quiet: args.quiet || args.quiet_success,
         json_timestamps: args.json_timestamps,
         json_progress,
+        json_compact_summary: args.json_compact_summary,
+        json_stream: json_stream(args.json_stream),
     };
-    if args.json {
-        Box::new(json::JsonOutput::new(opts))
+    let out: Box<dyn Reporter> = if args.json {
+        Box::new(
+            json_reporter(opts, args.json_output.as_deref())
+                .with_firmware_name(args.firmware_name.clone()),
+        )
+    } else {
+        Box::new(
+            human::HumanOutput::new(opts)
+                .with_color(!args.no_color)
+                .with_wait(args.wait)
+                .with_firmware_name(args.firmware_name.clone()),
+        )
+    };
+    let out: Box<dyn Reporter> = if args.flush_events {
+        Box::new(FlushEach::new(out))
     } else {
-        Box::new(human::HumanOutput::new(opts).with_wait(args.wait))
+        out
+    };
+    let out: Box<dyn Reporter> = if args.quiet_success {
+        Box::new(QuietSuccess::new(out))
+    } else {
+        out
+    };
+    match &args.output_dir {
+        Some(dir) => Box::new(target_log::TargetLog::new(
+            out,
+            dir.clone(),
+            opts,
+            args.firmware_name.clone(),
+        )),
+        None => out,
     }
 }
 
@@ -200,20 +711,67 @@ pub fn make_for_reboot(args: &cli::RebootArgs) -> Box<dyn Reporter> {
         quiet: false,
         json_timestamps: args.json_timestamps,
         json_progress: JsonProgressMode::Blocks,
+        json_compact_summary: args.json_compact_summary,
+        json_stream: json_stream(args.json_stream),
+    };
+    let out: Box<dyn Reporter> = if args.json {
+        Box::new(json_reporter(opts, args.json_output.as_deref()))
+    } else {
+        Box::new(human::HumanOutput::new(opts).with_color(!args.no_color))
+    };
+    if args.flush_events {
+        Box::new(FlushEach::new(out))
+    } else {
+        out
+    }
+}
+
+pub fn make_for_list(args: &cli::ListArgs) -> Box<dyn Reporter> {
+    let opts = OutputOptions {
+        verbose: false,
+        quiet: false,
+        json_timestamps: false,
+        json_progress: JsonProgressMode::Blocks,
+        json_compact_summary: false,
+        json_stream: JsonStream::Stdout,
+    };
+    match args.output_format() {
+        cli::ListFormatArg::Human => Box::new(human::HumanOutput::new(opts)),
+        cli::ListFormatArg::Json => Box::new(json_reporter(opts, args.output.as_deref())),
+        cli::ListFormatArg::Csv => {
+            let out: Box<dyn std::io::Write> = match args.output.as_deref() {
+                Some(path) => Box::new(std::io::LineWriter::new(create_output_file(path))),
+                None => Box::new(std::io::stdout()),
+            };
+            Box::new(csv::CsvOutput::new(out))
+        }
+    }
+}
+
+pub fn make_for_doctor(args: &cli::DoctorArgs) -> Box<dyn Reporter> {
+    let opts = OutputOptions {
+        verbose: false,
+        quiet: false,
+        json_timestamps: false,
+        json_progress: JsonProgressMode::Blocks,
+        json_compact_summary: false,
+        json_stream: JsonStream::Stdout,
     };
     if args.json {
-        Box::new(json::JsonOutput::new(opts))
+        Box::new(json_reporter(opts, args.json_output.as_deref()))
     } else {
         Box::new(human::HumanOutput::new(opts))
     }
 }
 
-pub fn make_for_list(args: &cli::ListArgs) -> Box<dyn Reporter> {
+pub fn make_for_preflight(args: &cli::PreflightArgs) -> Box<dyn Reporter> {
     let opts = OutputOptions {
         verbose: false,
         quiet: false,
         json_timestamps: false,
         json_progress: JsonProgressMode::Blocks,
+        json_compact_summary: false,
+        json_stream: JsonStream::Stdout,
     };
     if args.json {
         Box::new(json::JsonOutput::new(opts))
@@ -222,12 +780,63 @@ pub fn make_for_list(args: &cli::ListArgs) -> Box<dyn Reporter> {
     }
 }
 
-pub fn make_for_doctor(args: &cli::DoctorArgs) -> Box<dyn Reporter> {
+pub fn make_for_bridge(args: &cli::BridgeArgs) -> Box<dyn Reporter> {
+    // Verbose so resume events, which flash/reboot only show with `-v`, are printed.
+    let opts = OutputOptions {
+        verbose: true,
+        quiet: false,
+        json_timestamps: false,
+        json_progress: JsonProgressMode::Blocks,
+        json_compact_summary: false,
+        json_stream: JsonStream::Stdout,
+    };
+    if args.json {
+        Box::new(json::JsonOutput::new(opts))
+    } else {
+        Box::new(human::HumanOutput::new(opts))
+    }
+}
+
+pub fn make_for_watch(args: &cli::WatchArgs) -> Box<dyn Reporter> {
+    let opts = OutputOptions {
+        verbose: args.verbose,
+        quiet: false,
+        json_timestamps: false,
+        json_progress: JsonProgressMode::Percent,
+        json_compact_summary: false,
+        json_stream: JsonStream::Stdout,
+    };
+    if args.json {
+        Box::new(json::JsonOutput::new(opts))
+    } else {
+        Box::new(human::HumanOutput::new(opts).with_color(!args.no_color))
+    }
+}
+
+pub fn make_for_selftest(args: &cli::SelftestArgs) -> Box<dyn Reporter> {
+    let opts = OutputOptions {
+        verbose: args.verbose,
+        quiet: false,
+        json_timestamps: false,
+        json_progress: JsonProgressMode::Percent,
+        json_compact_summary: false,
+        json_stream: JsonStream::Stdout,
+    };
+    if args.json {
+        Box::new(json::JsonOutput::new(opts))
+    } else {
+        Box::new(human::HumanOutput::new(opts).with_color(!args.no_color))
+    }
+}
+
+pub fn make_for_exit_codes(args: &cli::ExitCodesArgs) -> Box<dyn Reporter> {
     let opts = OutputOptions {
         verbose: false,
         quiet: false,
         json_timestamps: false,
         json_progress: JsonProgressMode::Blocks,
+        json_compact_summary: false,
+        json_stream: JsonStream::Stdout,
     };
     if args.json {
         Box::new(json::JsonOutput::new(opts))
@@ -242,20 +851,28 @@ pub fn target_to_value(index: usize, t: &targets::Target) -> serde_json::Value {
     if let serde_json::Value::Object(obj) = &mut v {
         obj.insert("index".to_string(), serde_json::Value::from(index as u64));
         obj.insert("target_id".to_string(), serde_json::Value::from(t.id()));
+        obj.insert("model".to_string(), serde_json::Value::from(t.model()));
     }
     v
 }
 
 pub fn format_target_line(index: usize, t: &targets::Target) -> String {
     match t {
-        targets::Target::HalfKay(hk) => {
-            format!("[{index}] halfkay {} {:04X}:{:04X}", t.id(), hk.vid, hk.pid)
-        }
+        targets::Target::HalfKay(hk) => format!(
+            "[{index}] halfkay {} {:04X}:{:04X} {} bcdDevice {}",
+            t.id(),
+            hk.vid,
+            hk.pid,
+            t.model().unwrap_or(""),
+            hk.bcd_device
+                .map_or_else(|| "unknown".to_string(), |bcd| format!("0x{bcd:04X}"))
+        ),
         targets::Target::Serial(s) => format!(
-            "[{index}] serial  {} {:04X}:{:04X} {}",
+            "[{index}] serial  {} {:04X}:{:04X} {} {}",
             t.id(),
             s.vid,
             s.pid,
+            t.model().unwrap_or(""),
             s.product.as_deref().unwrap_or("")
         ),
     }
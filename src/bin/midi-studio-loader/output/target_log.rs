@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+use midi_studio_loader::{operation::OperationEvent, targets::Target};
+
+use crate::output::json::JsonOutput;
+use crate::output::{Event, OutputOptions, Reporter};
+
+/// Copies each target's events to `<dir>/<serial-or-id>.ndjson` (`--output-dir`).
+///
+/// Every event is still forwarded to `inner`. Files are created (truncated) on the first
+/// event of their target; the firmware's `hex_loaded` line, when seen, opens each file so
+/// the record stands on its own.
+pub struct TargetLog {
+    inner: Box<dyn Reporter>,
+    dir: PathBuf,
+    json: JsonOutput,
+    serials: HashMap<String, String>,
+    hex_loaded: Option<String>,
+    files: HashMap<String, File>,
+}
+
+impl TargetLog {
+    pub fn new(
+        inner: Box<dyn Reporter>,
+        dir: PathBuf,
+        opts: OutputOptions,
+        firmware_name: Option<String>,
+    ) -> Self {
+        Self {
+            inner,
+            dir,
+            json: JsonOutput::new(opts).with_firmware_name(firmware_name),
+            serials: HashMap::new(),
+            hex_loaded: None,
+            files: HashMap::new(),
+        }
+    }
+
+    fn record(&mut self, ev: &OperationEvent) {
+        if let OperationEvent::TargetDetected {
+            target: Target::Serial(s),
+            ..
+        } = ev
+        {
+            if let Some(sn) = &s.serial_number {
+                self.serials
+                    .insert(format!("serial:{}", s.port_name), sn.clone());
+            }
+        }
+
+        let line = self.json.operation_event_line(ev.clone());
+        let Some(target_id) = ev.target_id() else {
+            if matches!(ev, OperationEvent::HexLoaded { .. }) {
+                self.hex_loaded = Some(line);
+            }
+            return;
+        };
+
+        if !self.files.contains_key(target_id) {
+            let name = self
+                .serials
+                .get(target_id)
+                .map_or_else(|| file_stem(target_id), |sn| file_stem(sn));
+            let path = self.dir.join(format!("{name}.ndjson"));
+            match File::create(&path) {
+                Ok(mut f) => {
+                    if let Some(first) = &self.hex_loaded {
+                        let _ = writeln!(f, "{first}");
+                    }
+                    self.files.insert(target_id.to_string(), f);
+                }
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), err = %e, "cannot create target log");
+                    return;
+                }
+            }
+        }
+        if let Some(f) = self.files.get_mut(target_id) {
+            let _ = writeln!(f, "{line}");
+        }
+    }
+}
+
+impl Reporter for TargetLog {
+    fn emit(&mut self, event: Event) {
+        if let Event::Operation(ev) = &event {
+            self.record(ev);
+        }
+        self.inner.emit(event);
+    }
+
+    fn finish(&mut self) {
+        for f in self.files.values_mut() {
+            let _ = f.flush();
+        }
+        self.inner.finish();
+    }
+}
+
+/// File-name-safe form of a serial number or target id (`serial:/dev/ttyACM0` ->
+/// `serial__dev_ttyACM0`).
+pub fn file_stem(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
@@ -1,15 +1,21 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
 use midi_studio_loader::bridge_control::{
     BridgeControlErrorInfo, BridgePauseInfo, BridgePauseMethod, BridgePauseSkipReason,
     OcBridgeProcessInfo, ServiceStatus,
 };
+use midi_studio_loader::hex::FirmwareDigest;
 use midi_studio_loader::operation::OperationEvent;
+use midi_studio_loader::serial_reboot::RebootStrategy;
 use midi_studio_loader::targets::{self, HalfKayTarget, SerialTarget, TargetKind};
 
 use super::human::HumanOutput;
 use super::json::JsonOutput;
-use crate::output::{JsonProgressMode, OperationSummary, OutputOptions};
+use crate::output::{
+    Event, FirmwareFingerprint, JsonProgressMode, JsonStream, OperationRecorder, OperationSummary,
+    OutputOptions, PhaseTimings, PreflightIssue, PreflightReport, QuietSuccess, Reporter,
+    MAX_RETRY_DETAIL,
+};
 
 fn keys(v: &serde_json::Value) -> BTreeSet<String> {
     v.as_object()
@@ -40,16 +46,56 @@ where
 
 #[test]
 fn json_event_has_schema_and_event() {
+    assert_json_event(
+        OperationEvent::HexLoadStart {
+            path: "fw.hex".to_string(),
+        },
+        "hex_load_start",
+        &["schema", "event", "path"],
+        |v| {
+            assert_eq!(v.get("path").and_then(|v| v.as_str()), Some("fw.hex"));
+        },
+    );
+
+    assert_json_event(
+        OperationEvent::HexLoading { line_no: 4096 },
+        "hex_loading",
+        &["schema", "event", "line_no"],
+        |v| {
+            assert_eq!(v.get("line_no").and_then(|v| v.as_u64()), Some(4096));
+        },
+    );
+
     assert_json_event(
         OperationEvent::HexLoaded {
             bytes: 12,
             blocks: 3,
+            crc32: 0x0000_beef,
+            digest: FirmwareDigest {
+                blocks_crc32: 0,
+                sha256: [0xab; 32],
+            },
         },
         "hex_loaded",
-        &["schema", "event", "bytes", "blocks"],
+        &["schema", "event", "bytes", "blocks", "crc32", "sha256"],
         |v| {
+            assert_eq!(v["sha256"].as_str(), Some("ab".repeat(32).as_str()));
             assert_eq!(v.get("bytes").and_then(|v| v.as_u64()), Some(12));
             assert_eq!(v.get("blocks").and_then(|v| v.as_u64()), Some(3));
+            assert_eq!(v.get("crc32").and_then(|v| v.as_str()), Some("0000beef"));
+        },
+    );
+
+    assert_json_event(
+        OperationEvent::BaselineDiff {
+            total_blocks: 120,
+            changed_blocks: 3,
+        },
+        "baseline_diff",
+        &["schema", "event", "total_blocks", "changed_blocks"],
+        |v| {
+            assert_eq!(v["total_blocks"].as_u64(), Some(120));
+            assert_eq!(v["changed_blocks"].as_u64(), Some(3));
         },
     );
 }
@@ -61,6 +107,8 @@ fn json_timestamps_add_t_ms_when_enabled() {
         quiet: false,
         json_timestamps: true,
         json_progress: JsonProgressMode::Blocks,
+        json_compact_summary: false,
+        json_stream: JsonStream::Stdout,
     });
 
     let s = out.render_event_json(super::json::operation_event_to_json(
@@ -74,6 +122,8 @@ fn json_timestamps_add_t_ms_when_enabled() {
         quiet: false,
         json_timestamps: false,
         json_progress: JsonProgressMode::Blocks,
+        json_compact_summary: false,
+        json_stream: JsonStream::Stdout,
     });
     let s2 = out2.render_event_json(super::json::operation_event_to_json(
         OperationEvent::DiscoverStart,
@@ -82,6 +132,96 @@ fn json_timestamps_add_t_ms_when_enabled() {
     assert!(v2.get("t_ms").is_none());
 }
 
+#[test]
+fn verbose_errors_stay_off_a_json_stderr() {
+    let opts = |json_stream| OutputOptions {
+        verbose: true,
+        quiet: false,
+        json_timestamps: false,
+        json_progress: JsonProgressMode::Blocks,
+        json_compact_summary: false,
+        json_stream,
+    };
+    assert!(JsonOutput::new(opts(JsonStream::Stdout)).echoes_errors());
+    assert!(!JsonOutput::new(opts(JsonStream::Stderr)).echoes_errors());
+    assert!(JsonOutput::new(opts(JsonStream::Stderr))
+        .with_writer(Box::new(std::io::sink()))
+        .echoes_errors());
+}
+
+#[test]
+fn json_output_file_receives_the_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.ndjson");
+    std::fs::write(&path, "stale\n").unwrap();
+
+    let opts = OutputOptions {
+        verbose: false,
+        quiet: false,
+        json_timestamps: false,
+        json_progress: JsonProgressMode::Blocks,
+        json_compact_summary: false,
+        json_stream: JsonStream::Stdout,
+    };
+    let mut out = super::json_reporter(opts, Some(&path));
+    out.emit(Event::Operation(OperationEvent::DiscoverStart));
+    out.emit(Event::Error {
+        code: 10,
+        message: "no target device found".to_string(),
+    });
+    out.finish();
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    let events: Vec<String> = text
+        .lines()
+        .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap()["event"].to_string())
+        .collect();
+    assert_eq!(events, ["\"discover_start\"", "\"error\""]);
+}
+
+#[test]
+fn hex_error_json_contract() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.ndjson");
+    let opts = OutputOptions {
+        verbose: false,
+        quiet: false,
+        json_timestamps: false,
+        json_progress: JsonProgressMode::Blocks,
+        json_compact_summary: true,
+        json_stream: JsonStream::Stdout,
+    };
+    let err = midi_studio_loader::hex::HexError::InvalidChecksum { line_no: 7 };
+    let mut out = super::json_reporter(opts, Some(&path));
+    out.emit(Event::ErrorWithDetails {
+        code: 11,
+        message: format!("invalid firmware file: {err}"),
+        details: err.to_json_value(),
+    });
+    out.finish();
+
+    let v: serde_json::Value =
+        serde_json::from_str(std::fs::read_to_string(&path).unwrap().trim()).unwrap();
+    assert_eq!(
+        keys(&v),
+        [
+            "code",
+            "error_kind",
+            "event",
+            "line_no",
+            "message",
+            "reason",
+            "schema"
+        ]
+        .map(String::from)
+        .into()
+    );
+    assert_eq!(v["event"], "error");
+    assert_eq!(v["error_kind"], "invalid_hex");
+    assert_eq!(v["reason"], "invalid_checksum");
+    assert_eq!(v["line_no"], 7);
+}
+
 #[test]
 fn operation_event_json_contract() {
     assert_json_event(
@@ -142,6 +282,21 @@ fn operation_event_json_contract() {
         },
     );
 
+    assert_json_event(
+        OperationEvent::Warning {
+            code: "bridge_session_active",
+            message: "this will interrupt an active oc-bridge session on COM6".to_string(),
+        },
+        "warning",
+        &["schema", "event", "code", "message"],
+        |v| {
+            assert_eq!(
+                v.get("code").and_then(|v| v.as_str()),
+                Some("bridge_session_active")
+            );
+        },
+    );
+
     assert_json_event(
         OperationEvent::BridgePauseStart,
         "bridge_pause_start",
@@ -155,11 +310,26 @@ fn operation_event_json_contract() {
                 method: BridgePauseMethod::Control,
                 id: "127.0.0.1:7999".to_string(),
                 pids: vec![1234, 5678],
+                serial_open: Some(false),
+                message: Some("serial closed".to_string()),
             },
         },
         "bridge_paused",
-        &["schema", "event", "method", "id", "pids"],
+        &[
+            "schema",
+            "event",
+            "method",
+            "id",
+            "pids",
+            "serial_open",
+            "message",
+        ],
         |v| {
+            assert_eq!(v.get("serial_open").and_then(|v| v.as_bool()), Some(false));
+            assert_eq!(
+                v.get("message").and_then(|v| v.as_str()),
+                Some("serial closed")
+            );
             assert_eq!(v.get("method").and_then(|v| v.as_str()), Some("control"));
             assert_eq!(v.get("id").and_then(|v| v.as_str()), Some("127.0.0.1:7999"));
             assert_eq!(
@@ -214,6 +384,19 @@ fn operation_event_json_contract() {
         &["schema", "event"],
         |_| {},
     );
+    assert_json_event(
+        OperationEvent::BridgeResumeRetry {
+            attempt: 1,
+            retries: 2,
+            error: "timeout".to_string(),
+        },
+        "bridge_resume_retry",
+        &["schema", "event", "attempt", "retries", "message"],
+        |v| {
+            assert_eq!(v["attempt"], 1);
+            assert_eq!(v["retries"], 2);
+        },
+    );
     assert_json_event(
         OperationEvent::BridgeResumed,
         "bridge_resumed",
@@ -233,6 +416,15 @@ fn operation_event_json_contract() {
         |_| {},
     );
 
+    assert_json_event(
+        OperationEvent::BridgeLeftPaused {
+            hint: Some("Try: oc-bridge ctl resume --control-port 7999".to_string()),
+        },
+        "bridge_left_paused",
+        &["schema", "event", "hint"],
+        |_| {},
+    );
+
     assert_json_event(
         OperationEvent::TargetStart {
             target_id: "serial:COM6".to_string(),
@@ -272,14 +464,72 @@ fn operation_event_json_contract() {
         },
     );
 
+    assert_json_event(
+        OperationEvent::FirmwareAssigned {
+            target_id: "serial:COM6".to_string(),
+            path: "bay1.hex".to_string(),
+            crc32: 0xcbf4_3926,
+        },
+        "firmware_assigned",
+        &["schema", "event", "target_id", "path", "crc32"],
+        |v| {
+            assert_eq!(v.get("crc32").and_then(|v| v.as_str()), Some("cbf43926"));
+        },
+    );
+
+    assert_json_event(
+        OperationEvent::TargetSkipped {
+            target_id: "serial:COM7".to_string(),
+            reason: "no firmware mapped for this target".to_string(),
+        },
+        "target_skipped",
+        &["schema", "event", "target_id", "reason"],
+        |_| {},
+    );
+
+    assert_json_event(
+        OperationEvent::BetweenTargetsDelay { delay_ms: 500 },
+        "between_targets_delay",
+        &["schema", "event", "delay_ms"],
+        |v| {
+            assert_eq!(v.get("delay_ms").and_then(|v| v.as_u64()), Some(500));
+        },
+    );
+
+    assert_json_event(
+        OperationEvent::SerialReappeared {
+            target_id: "halfkay:HK".to_string(),
+            port: "COM6".to_string(),
+        },
+        "serial_reappeared",
+        &["schema", "event", "target_id", "port"],
+        |v| {
+            assert_eq!(v.get("port").and_then(|v| v.as_str()), Some("COM6"));
+        },
+    );
+
     assert_json_event(
         OperationEvent::SoftReboot {
             target_id: "serial:COM6".to_string(),
             port: "COM6".to_string(),
+            strategy: RebootStrategy::DtrPulse,
+            baud: 134,
         },
         "soft_reboot",
-        &["schema", "event", "target_id", "port"],
-        |_| {},
+        &["schema", "event", "target_id", "port", "strategy"],
+        |ev| assert_eq!(ev["strategy"], "dtr_pulse"),
+    );
+
+    assert_json_event(
+        OperationEvent::WaitingForHalfKay {
+            target_id: "serial:COM6".to_string(),
+            elapsed_ms: 2000,
+        },
+        "waiting_for_halfkay",
+        &["schema", "event", "target_id", "elapsed_ms"],
+        |v| {
+            assert_eq!(v.get("elapsed_ms").and_then(|v| v.as_u64()), Some(2000));
+        },
     );
 
     assert_json_event(
@@ -329,12 +579,33 @@ fn operation_event_json_contract() {
         },
     );
 
+    assert_json_event(
+        OperationEvent::Progress {
+            target_id: "halfkay:HK1".to_string(),
+            bytes_written: 2048,
+            bytes_total: 8192,
+        },
+        "progress",
+        &[
+            "schema",
+            "event",
+            "target_id",
+            "bytes_written",
+            "bytes_total",
+        ],
+        |v| {
+            assert_eq!(v.get("bytes_written").and_then(|v| v.as_u64()), Some(2048));
+            assert_eq!(v.get("bytes_total").and_then(|v| v.as_u64()), Some(8192));
+        },
+    );
+
     assert_json_event(
         OperationEvent::Retry {
             target_id: "halfkay:HK1".to_string(),
             addr: 0x400,
             attempt: 2,
             retries: 3,
+            delay_ms: 300,
             error: "short write".to_string(),
         },
         "retry",
@@ -345,10 +616,12 @@ fn operation_event_json_contract() {
             "addr",
             "attempt",
             "retries",
+            "delay_ms",
             "error",
         ],
         |v| {
             assert_eq!(v.get("attempt").and_then(|v| v.as_u64()), Some(2));
+            assert_eq!(v["delay_ms"].as_u64(), Some(300));
         },
     );
 
@@ -360,6 +633,48 @@ fn operation_event_json_contract() {
         &["schema", "event", "target_id"],
         |_| {},
     );
+    assert_json_event(
+        OperationEvent::BootConfirmed {
+            target_id: "halfkay:HK1".to_string(),
+        },
+        "boot_confirmed",
+        &["schema", "event", "target_id"],
+        |_| {},
+    );
+    assert_json_event(
+        OperationEvent::BootFailed {
+            target_id: "halfkay:HK1".to_string(),
+            timeout_ms: 500,
+        },
+        "boot_failed",
+        &["schema", "event", "target_id", "timeout_ms"],
+        |v| {
+            assert_eq!(v.get("timeout_ms").and_then(|v| v.as_u64()), Some(500));
+        },
+    );
+    assert_json_event(
+        OperationEvent::VerifySkipped {
+            target_id: "halfkay:HK1".to_string(),
+            reason: "halfkay_target",
+        },
+        "verify_skipped",
+        &["schema", "event", "target_id", "reason"],
+        |v| {
+            assert_eq!(
+                v.get("reason").and_then(|v| v.as_str()),
+                Some("halfkay_target")
+            );
+        },
+    );
+    assert_json_event(
+        OperationEvent::Verified {
+            target_id: "serial:COM6".to_string(),
+            port: "COM6".to_string(),
+        },
+        "verified",
+        &["schema", "event", "target_id", "port"],
+        |_| {},
+    );
     assert_json_event(
         OperationEvent::Done {
             target_id: "halfkay:HK1".to_string(),
@@ -385,6 +700,7 @@ fn ambiguous_help_includes_targets() {
             vid: 0x16C0,
             pid: 0x0478,
             path: "abc".to_string(),
+            bcd_device: None,
         })),
     ];
 
@@ -414,14 +730,17 @@ fn list_target_json_includes_index_and_id() {
 
 #[test]
 fn list_json_contract() {
-    let targets = vec![targets::Target::Serial(SerialTarget {
-        port_name: "COM6".to_string(),
-        vid: 0x16C0,
-        pid: 0x0483,
-        serial_number: None,
-        manufacturer: None,
-        product: None,
-    })];
+    let targets = vec![(
+        3,
+        targets::Target::Serial(SerialTarget {
+            port_name: "COM6".to_string(),
+            vid: 0x16C0,
+            pid: 0x0483,
+            serial_number: None,
+            manufacturer: None,
+            product: None,
+        }),
+    )];
 
     let ev = super::json::list_to_json(&targets);
     let v = serde_json::to_value(&ev).unwrap();
@@ -435,6 +754,7 @@ fn list_json_contract() {
         arr[0].get("target_id").and_then(|v| v.as_str()),
         Some("serial:COM6")
     );
+    assert_eq!(arr[0].get("index").and_then(|v| v.as_u64()), Some(3));
 }
 
 #[test]
@@ -443,18 +763,36 @@ fn list_target_json_kind_is_halfkay() {
         vid: 0x16C0,
         pid: 0x0478,
         path: "HK".to_string(),
+        bcd_device: None,
     });
 
     let v = super::target_to_value(0, &t);
     assert_eq!(v.get("kind").and_then(|v| v.as_str()), Some("halfkay"));
+    assert_eq!(v["model"], "Teensy 4.x");
+
+    let unknown = targets::Target::HalfKay(HalfKayTarget {
+        pid: 0x04FF,
+        ..match t {
+            targets::Target::HalfKay(hk) => hk,
+            targets::Target::Serial(_) => unreachable!(),
+        }
+    });
+    assert!(super::target_to_value(0, &unknown)["model"].is_null());
 }
 
 #[test]
 fn dry_run_json_contract() {
     let ev = super::json::dry_run_to_json(super::DryRunSummary {
         bytes: 123,
+        digest: FirmwareDigest {
+            blocks_crc32: 0x0000_beef,
+            sha256: [0x01; 32],
+        },
         blocks: 10,
         blocks_to_write: 2,
+        min_addr: Some(0x6000_0000),
+        max_addr: Some(0x6000_07ff),
+        start_address: Some(0x6000_1000),
         target_ids: vec!["serial:COM6".to_string()],
         needs_serial: true,
         bridge_enabled: true,
@@ -463,6 +801,11 @@ fn dry_run_json_contract() {
     let v = serde_json::to_value(&ev).unwrap();
     assert_eq!(v.get("event").and_then(|v| v.as_str()), Some("dry_run"));
     assert_eq!(v.get("targets").and_then(|v| v.as_u64()), Some(1));
+    assert_eq!(v["blocks_crc32"].as_str(), Some("0000beef"));
+    assert_eq!(v["min_addr"].as_u64(), Some(0x6000_0000));
+    assert_eq!(v["max_addr"].as_u64(), Some(0x6000_07ff));
+    assert_eq!(v["start_address"].as_u64(), Some(0x6000_1000));
+    assert_eq!(v["sha256"].as_str(), Some("01".repeat(32).as_str()));
     assert_eq!(
         v.get("target_ids")
             .and_then(|v| v.as_array())
@@ -471,6 +814,54 @@ fn dry_run_json_contract() {
     );
 }
 
+#[test]
+fn reboot_dry_run_json_contract() {
+    let ev = super::json::reboot_dry_run_to_json(super::RebootProbeSummary {
+        actions: vec![("serial:COM6".to_string(), "soft_reboot")],
+        needs_serial: true,
+        bridge_enabled: true,
+        bridge_control_port: 7999,
+    });
+    let v = serde_json::to_value(&ev).unwrap();
+    assert_eq!(v["event"], "dry_run");
+    assert_eq!(v["operation"], "reboot");
+    assert_eq!(v["target_ids"], serde_json::json!(["serial:COM6"]));
+    assert_eq!(v["actions"][0]["action"], "soft_reboot");
+    assert_eq!(v["bridge_pause"], 1);
+    assert!(JsonOutput::is_verdict(&Event::RebootDryRun(
+        super::RebootProbeSummary {
+            actions: Vec::new(),
+            needs_serial: false,
+            bridge_enabled: true,
+            bridge_control_port: 7999,
+        }
+    )));
+}
+
+#[test]
+fn reboot_probe_json_contract() {
+    let ev = super::json::reboot_probe_to_json(super::RebootProbeSummary {
+        actions: vec![
+            ("halfkay:HK".to_string(), "already_in_bootloader"),
+            ("serial:COM6".to_string(), "soft_reboot"),
+        ],
+        needs_serial: true,
+        bridge_enabled: true,
+        bridge_control_port: 7999,
+    });
+    let v = serde_json::to_value(&ev).unwrap();
+    assert_eq!(
+        v.get("event").and_then(|v| v.as_str()),
+        Some("reboot_probe")
+    );
+    assert_eq!(v.get("targets").and_then(|v| v.as_u64()), Some(2));
+    let actions = v.get("actions").and_then(|v| v.as_array()).unwrap();
+    assert_eq!(
+        actions[1].get("action").and_then(|v| v.as_str()),
+        Some("soft_reboot")
+    );
+}
+
 #[test]
 fn doctor_json_contract_minimal() {
     let report = super::DoctorReport {
@@ -479,8 +870,11 @@ fn doctor_json_contract_minimal() {
             vid: 0x16C0,
             pid: 0x0478,
             path: "HK".to_string(),
+            bcd_device: None,
         })],
+        discovery_errors: vec![("serial", "serial discovery failed: stuck tty".to_string())],
         control_port: 7999,
+        control_socket: Some((std::path::PathBuf::from("/run/oc-bridge.sock"), false)),
         control_timeout_ms: 2500,
         control_checked: false,
         control: None,
@@ -493,6 +887,17 @@ fn doctor_json_contract_minimal() {
             cmd: None,
             restartable: false,
         }],
+        hid_devices: None,
+        hid_error: None,
+        permission_hint: Some(super::PermissionHint {
+            path: "/dev/hidraw3".to_string(),
+            error: "hid: hidapi error: Permission denied".to_string(),
+        }),
+        fixes: Some(vec![super::DoctorFix {
+            action: "install_udev_rules",
+            status: super::FixStatus::Failed,
+            message: "permission denied".to_string(),
+        }]),
     };
 
     let ev = super::json::doctor_to_json(report);
@@ -503,12 +908,68 @@ fn doctor_json_contract_minimal() {
         Some("OpenControlBridge")
     );
     assert_eq!(v.get("control_checked").and_then(|v| v.as_u64()), Some(0));
+    assert_eq!(v["control_socket_exists"].as_u64(), Some(0));
+    assert_eq!(
+        v["discovery_errors"]["serial"].as_str(),
+        Some("serial discovery failed: stuck tty")
+    );
+    assert!(v["discovery_errors"].get("hid").is_none());
+    assert_eq!(v["permission_hint"]["path"], "/dev/hidraw3");
+    assert_eq!(v["fixes"][0]["action"], "install_udev_rules");
+    assert_eq!(v["fixes"][0]["status"], "failed");
+    assert_eq!(
+        v["permission_hint"]["rules_path"],
+        "/etc/udev/rules.d/00-teensy.rules"
+    );
+    assert!(v["permission_hint"]["rules"]
+        .as_str()
+        .is_some_and(|r| r.contains("MODE:=\"0666\"")));
     assert_eq!(
         v.get("targets").and_then(|v| v.as_array()).map(|a| a.len()),
         Some(1)
     );
 }
 
+#[test]
+fn target_change_json_matches_list_shape() {
+    let t = targets::Target::HalfKay(HalfKayTarget {
+        vid: 0x16C0,
+        pid: 0x0478,
+        path: "HK".to_string(),
+        bcd_device: Some(0x0280),
+    });
+    let list = serde_json::to_value(super::json::list_to_json(&[(2, t.clone())])).unwrap();
+    let ev =
+        serde_json::to_value(super::json::target_change_to_json("target_added", 2, &t)).unwrap();
+    assert_eq!(
+        keys(&ev),
+        ["event", "schema", "target", "ts_ms"]
+            .map(String::from)
+            .into()
+    );
+    assert_eq!(ev["event"].as_str(), Some("target_added"));
+    assert!(ev["ts_ms"].as_u64().is_some_and(|t| t > 0));
+    assert_eq!(ev["target"], list["targets"][0]);
+
+    // HalfKay has no bootloader version to report: the raw bcdDevice (a board code) and the
+    // model derived from it are what identifies the device.
+    let target = &list["targets"][0];
+    assert_eq!(target["bcd_device"].as_u64(), Some(0x0280));
+    assert_eq!(target["model"].as_str(), Some("Teensy 4.1"));
+    assert!(target.get("version").is_none());
+    assert!(target.get("bootloader_version").is_none());
+
+    // Discovery reports the same target object.
+    let detected = serde_json::to_value(super::json::operation_event_to_json(
+        OperationEvent::TargetDetected {
+            index: 2,
+            target: t,
+        },
+    ))
+    .unwrap();
+    assert_eq!(detected["target"], list["targets"][0]);
+}
+
 #[test]
 fn operation_summary_json_contract() {
     let ev = super::json::operation_summary_to_json(OperationSummary {
@@ -522,6 +983,21 @@ fn operation_summary_json_contract() {
         bridge_pause: "paused".to_string(),
         bridge_method: Some("control".to_string()),
         bridge_reason: None,
+        firmware: vec![FirmwareFingerprint {
+            bytes: 4096,
+            crc32: 0xcbf4_3926,
+        }],
+        target_firmware_crc32: BTreeMap::new(),
+        targets_skipped: Vec::new(),
+        retry_detail: Vec::new(),
+        retry_detail_omitted: 0,
+        target_errors: BTreeMap::new(),
+        firmware_name: Some("synth-v2".to_string()),
+        phases: PhaseTimings {
+            discovery_ms: Some(12),
+            target_ms: BTreeMap::from([("serial:COM6".to_string(), 900)]),
+            ..Default::default()
+        },
     });
     let v = serde_json::to_value(&ev).unwrap();
     assert_eq!(
@@ -549,4 +1025,625 @@ fn operation_summary_json_contract() {
             .and_then(|v| v.as_str()),
         Some("serial:COM6")
     );
+    assert_eq!(v["firmware_name"].as_str(), Some("synth-v2"));
+    assert_eq!(v.get("firmware_bytes").and_then(|v| v.as_u64()), Some(4096));
+    assert_eq!(
+        v.get("firmware_crc32").and_then(|v| v.as_str()),
+        Some("cbf43926")
+    );
+    assert_eq!(
+        v["phases"],
+        serde_json::json!({"discovery_ms": 12, "target_ms": {"serial:COM6": 900}})
+    );
+}
+
+#[test]
+fn operation_recorder_times_phases() {
+    use std::time::{Duration, Instant};
+
+    let t0 = Instant::now();
+    let at = |ms| t0 + Duration::from_millis(ms);
+    let target = |id: &str| id.to_string();
+
+    let mut rec = OperationRecorder::new("flash");
+    rec.observe_at(
+        &OperationEvent::HexLoadStart {
+            path: "fw.hex".to_string(),
+        },
+        at(0),
+    );
+    // Throttled progress must not restart the phase.
+    rec.observe_at(&OperationEvent::HexLoading { line_no: 4096 }, at(40));
+    rec.observe_at(
+        &OperationEvent::HexLoaded {
+            bytes: 1024,
+            blocks: 1,
+            crc32: 0,
+            digest: FirmwareDigest {
+                blocks_crc32: 0,
+                sha256: [0; 32],
+            },
+        },
+        at(50),
+    );
+    rec.observe_at(&OperationEvent::DiscoverStart, at(0));
+    rec.observe_at(&OperationEvent::DiscoverDone { count: 1 }, at(30));
+    rec.observe_at(&OperationEvent::BridgePauseStart, at(30));
+    rec.observe_at(
+        &OperationEvent::BridgePauseFailed {
+            error: BridgeControlErrorInfo {
+                message: "timeout".to_string(),
+                hint: None,
+            },
+        },
+        at(250),
+    );
+    rec.observe_at(
+        &OperationEvent::TargetStart {
+            target_id: target("halfkay:A"),
+            kind: TargetKind::HalfKay,
+        },
+        at(300),
+    );
+    rec.observe_at(
+        &OperationEvent::TargetDone {
+            target_id: target("halfkay:A"),
+            ok: true,
+            message: None,
+        },
+        at(1300),
+    );
+    let phases = rec.finish(0, None).phases;
+    assert_eq!(phases.discovery_ms, Some(30));
+    assert_eq!(phases.bridge_pause_ms, Some(220));
+    assert_eq!(phases.hex_load_ms, Some(50));
+    assert_eq!(phases.target_ms["halfkay:A"], 1000);
+
+    let v = serde_json::to_value(super::json::operation_summary_to_json(
+        OperationRecorder::new("reboot").finish(0, None),
+    ))
+    .unwrap();
+    assert!(v.get("phases").is_none());
+}
+
+#[test]
+fn preflight_json_contract() {
+    let v = serde_json::to_value(super::json::preflight_to_json(PreflightReport {
+        firmware_bytes: Some(4096),
+        firmware_crc32: Some(0xcbf4_3926),
+        target_ids: vec!["serial:COM6".to_string()],
+        needs_serial: true,
+        bridge_checked: true,
+        blocking: vec![PreflightIssue {
+            check: "bridge",
+            code: 20,
+            message: "oc-bridge is running".to_string(),
+        }],
+    }))
+    .unwrap();
+    assert_eq!(
+        keys(&v),
+        [
+            "blocking",
+            "bridge_checked",
+            "event",
+            "firmware_bytes",
+            "firmware_crc32",
+            "needs_serial",
+            "ready",
+            "schema",
+            "target_ids",
+        ]
+        .map(String::from)
+        .into()
+    );
+    assert_eq!(v["ready"], 0);
+    assert_eq!(v["blocking"][0]["check"], "bridge");
+    assert_eq!(v["blocking"][0]["code"], 20);
+}
+
+#[test]
+fn bridge_status_json_contract() {
+    let st = midi_studio_loader::bridge_control::BridgeControlStatus {
+        ok: true,
+        paused: true,
+        serial_open: Some(false),
+        message: None,
+        client_count: None,
+        uptime_ms: None,
+        serial_port: None,
+    };
+    let v = serde_json::to_value(super::json::bridge_status_to_json("port 7999", &Ok(st))).unwrap();
+    assert_eq!(
+        keys(&v),
+        ["control", "endpoint", "event", "reachable", "schema"]
+            .map(String::from)
+            .into()
+    );
+    assert_eq!(v["control"]["paused"], true);
+
+    let v = serde_json::to_value(super::json::bridge_status_to_json(
+        "port 7999",
+        &Err("connection refused".to_string()),
+    ))
+    .unwrap();
+    assert_eq!(v["reachable"], 0);
+    assert_eq!(v["error"], "connection refused");
+    assert!(JsonOutput::is_verdict(&Event::BridgeStatus {
+        endpoint: "port 7999".to_string(),
+        status: Err(String::new()),
+    }));
+}
+
+#[test]
+fn list_csv_rows_leave_inapplicable_columns_empty() {
+    use super::csv::{target_row, LIST_HEADER};
+
+    assert_eq!(LIST_HEADER, "index,id,kind,vid,pid,port,serial,product");
+    let hk = targets::Target::HalfKay(HalfKayTarget {
+        vid: 0x16C0,
+        pid: 0x0478,
+        path: "HK1".to_string(),
+        bcd_device: None,
+    });
+    assert_eq!(target_row(0, &hk), "0,halfkay:HK1,halfkay,16C0,0478,,,");
+
+    let serial = targets::Target::Serial(SerialTarget {
+        port_name: "COM6".to_string(),
+        vid: 0x16C0,
+        pid: 0x0483,
+        serial_number: Some("1234567".to_string()),
+        manufacturer: None,
+        product: Some("MIDI Studio, \"rev B\"".to_string()),
+    });
+    assert_eq!(
+        target_row(3, &serial),
+        "3,serial:COM6,serial,16C0,0483,COM6,1234567,\"MIDI Studio, \"\"rev B\"\"\""
+    );
+}
+
+#[test]
+fn selftest_json_contract() {
+    let report = super::SelftestReport {
+        passed: false,
+        target_id: Some("serial:COM6".to_string()),
+        port: None,
+        reason: Some("board did not re-enumerate as USB serial within 10000ms of boot".to_string()),
+        flash_ms: Some(3100),
+        reenumerate_ms: None,
+        total_ms: 13150,
+    };
+    let v = serde_json::to_value(super::json::selftest_to_json(report)).unwrap();
+    assert_eq!(
+        keys(&v),
+        [
+            "event",
+            "flash_ms",
+            "passed",
+            "reason",
+            "schema",
+            "target_id",
+            "total_ms"
+        ]
+        .map(String::from)
+        .into()
+    );
+    assert_eq!(v["event"], "selftest");
+    assert_eq!(v["passed"], 0);
+    assert_eq!(v["flash_ms"], 3100);
+    assert!(JsonOutput::is_verdict(&Event::Selftest(
+        super::SelftestReport {
+            passed: true,
+            target_id: None,
+            port: None,
+            reason: None,
+            flash_ms: None,
+            reenumerate_ms: None,
+            total_ms: 0,
+        }
+    )));
+}
+
+#[test]
+fn watch_json_contract() {
+    let v = serde_json::to_value(super::json::watch_started_to_json(&[
+        "serial:COM3".to_string()
+    ]))
+    .unwrap();
+    assert_eq!(
+        keys(&v),
+        ["event", "ignored", "schema"].map(String::from).into()
+    );
+    assert_eq!(v["ignored"][0], "serial:COM3");
+
+    let v = serde_json::to_value(super::json::watch_stopped_to_json(3, 1)).unwrap();
+    assert_eq!(
+        keys(&v),
+        ["event", "failed", "flashed", "schema"]
+            .map(String::from)
+            .into()
+    );
+    assert_eq!(v["flashed"], 3);
+    assert!(JsonOutput::is_verdict(&Event::WatchStopped {
+        flashed: 0,
+        failed: 0
+    }));
+}
+
+#[test]
+fn selected_targets_json_contract() {
+    let ev = super::json::selected_targets_to_json(&["serial:COM6".to_string()]);
+    let v = serde_json::to_value(&ev).unwrap();
+    assert_eq!(
+        keys(&v),
+        ["count", "event", "schema", "target_ids"]
+            .map(String::from)
+            .into()
+    );
+    assert_eq!(v["target_ids"][0], "serial:COM6");
+    assert!(JsonOutput::is_verdict(&Event::SelectedTargets(Vec::new())));
+}
+
+#[test]
+fn no_device_hint_json_contract() {
+    let v = serde_json::to_value(super::json::no_device_hint_to_json()).unwrap();
+    assert_eq!(
+        keys(&v),
+        ["event", "pid_halfkay", "platform", "schema", "steps", "vid"]
+            .map(String::from)
+            .into()
+    );
+    assert_eq!(v.get("vid").and_then(|v| v.as_u64()), Some(0x16C0));
+    assert_eq!(v.get("pid_halfkay").and_then(|v| v.as_u64()), Some(0x0478));
+    assert!(v["steps"].as_array().is_some_and(|a| !a.is_empty()));
+}
+
+#[test]
+fn exit_codes_json_contract() {
+    let v = serde_json::to_value(super::json::exit_codes_to_json()).unwrap();
+    assert_eq!(
+        keys(&v),
+        ["codes", "event", "schema"].map(String::from).into()
+    );
+    let codes = v["codes"].as_array().unwrap();
+    assert_eq!(codes.len(), crate::exit_codes::ALL.len());
+    assert_eq!(codes[0]["code"].as_i64(), Some(0));
+    assert_eq!(codes[0]["name"].as_str(), Some("ok"));
+
+    let mut seen = BTreeSet::new();
+    for info in crate::exit_codes::ALL {
+        assert!(seen.insert(info.code), "duplicate exit code {}", info.code);
+    }
+    assert!(seen.contains(&crate::exit_codes::EXIT_AMBIGUOUS));
+    assert!(seen.contains(&crate::exit_codes::EXIT_CANCELLED));
+}
+
+#[test]
+fn json_compact_summary_keeps_verdict_events() {
+    assert!(JsonOutput::is_verdict(&Event::Error {
+        code: 10,
+        message: "no target device found".to_string(),
+    }));
+    assert!(!JsonOutput::is_verdict(&Event::Operation(
+        OperationEvent::DiscoverStart
+    )));
+    assert!(!JsonOutput::is_verdict(&Event::HintAmbiguousTargets));
+}
+
+#[test]
+fn quiet_success_replays_only_on_failure() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct Count(Rc<RefCell<usize>>);
+    impl Reporter for Count {
+        fn emit(&mut self, _event: Event) {
+            *self.0.borrow_mut() += 1;
+        }
+        fn finish(&mut self) {}
+    }
+
+    let seen = Rc::new(RefCell::new(0));
+    let mut ok = QuietSuccess::new(Box::new(Count(seen.clone())));
+    ok.emit(Event::Operation(OperationEvent::DiscoverStart));
+    ok.finish();
+    assert_eq!(*seen.borrow(), 0);
+
+    let mut failed = QuietSuccess::new(Box::new(Count(seen.clone())));
+    failed.emit(Event::Operation(OperationEvent::DiscoverStart));
+    failed.emit(Event::Error {
+        code: 10,
+        message: "no target device found".to_string(),
+    });
+    failed.finish();
+    assert_eq!(*seen.borrow(), 2);
+}
+
+#[test]
+fn target_log_writes_one_file_per_target() {
+    struct Sink;
+    impl Reporter for Sink {
+        fn emit(&mut self, _event: Event) {}
+        fn finish(&mut self) {}
+    }
+
+    let dir = std::env::temp_dir().join(format!("msl-target-log-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let opts = OutputOptions {
+        verbose: false,
+        quiet: false,
+        json_timestamps: false,
+        json_progress: JsonProgressMode::Blocks,
+        json_compact_summary: false,
+        json_stream: JsonStream::Stdout,
+    };
+    let mut log = super::target_log::TargetLog::new(Box::new(Sink), dir.clone(), opts, None);
+    log.emit(Event::Operation(OperationEvent::TargetDetected {
+        index: 0,
+        target: targets::Target::Serial(SerialTarget {
+            port_name: "/dev/ttyACM0".to_string(),
+            vid: 0x16C0,
+            pid: 0x0489,
+            serial_number: Some("12345".to_string()),
+            manufacturer: None,
+            product: None,
+        }),
+    }));
+    log.emit(Event::Operation(OperationEvent::HexLoaded {
+        bytes: 1024,
+        blocks: 1,
+        crc32: 0,
+        digest: FirmwareDigest {
+            blocks_crc32: 0,
+            sha256: [0; 32],
+        },
+    }));
+    for id in ["serial:/dev/ttyACM0", "halfkay:A"] {
+        log.emit(Event::Operation(OperationEvent::TargetDone {
+            target_id: id.to_string(),
+            ok: true,
+            message: None,
+        }));
+    }
+    log.finish();
+
+    let serial = std::fs::read_to_string(dir.join("12345.ndjson")).unwrap();
+    let events: Vec<serde_json::Value> = serial
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["event"], "hex_loaded");
+    assert_eq!(events[1]["target_id"], "serial:/dev/ttyACM0");
+    assert!(dir.join("halfkay_A.ndjson").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn operation_summary_retry_detail_is_capped() {
+    let mut rec = OperationRecorder::new("flash");
+    for addr in 0..(MAX_RETRY_DETAIL + 2) {
+        let attempts = if addr == 5 { 3 } else { 1 };
+        for attempt in 1..=attempts {
+            rec.observe(&OperationEvent::Retry {
+                target_id: "halfkay:A".to_string(),
+                addr: addr * 1024,
+                attempt,
+                retries: 3,
+                delay_ms: 150,
+                error: "write failed".to_string(),
+            });
+        }
+    }
+    let summary = rec.finish(0, None);
+    assert_eq!(summary.retries, MAX_RETRY_DETAIL as u64 + 4);
+    assert_eq!(summary.retry_detail.len(), MAX_RETRY_DETAIL);
+    assert_eq!(summary.retry_detail_omitted, 2);
+    assert!(summary
+        .retry_detail
+        .iter()
+        .any(|d| d.addr == 5 * 1024 && d.retries == 3));
+
+    let v = serde_json::to_value(super::json::operation_summary_to_json(summary)).unwrap();
+    let detail = v.get("retry_detail").and_then(|v| v.as_array()).unwrap();
+    assert_eq!(
+        keys(&detail[0]),
+        ["addr", "retries", "target_id"].map(String::from).into()
+    );
+    assert_eq!(
+        v.get("retry_detail_omitted").and_then(|v| v.as_u64()),
+        Some(2)
+    );
+}
+
+#[test]
+fn operation_summary_keeps_every_mapped_firmware() {
+    let mut rec = OperationRecorder::new("flash");
+    for (bytes, crc32) in [(4096, 0x1111_1111), (8192, 0x2222_2222)] {
+        rec.observe(&OperationEvent::HexLoaded {
+            bytes,
+            blocks: bytes / 1024,
+            crc32,
+            digest: FirmwareDigest {
+                blocks_crc32: 0,
+                sha256: [0; 32],
+            },
+        });
+    }
+    for (target_id, crc32) in [("serial:COM6", 0x1111_1111), ("serial:COM7", 0x2222_2222)] {
+        rec.observe(&OperationEvent::FirmwareAssigned {
+            target_id: target_id.to_string(),
+            path: "bay.hex".to_string(),
+            crc32,
+        });
+    }
+
+    let v =
+        serde_json::to_value(super::json::operation_summary_to_json(rec.finish(0, None))).unwrap();
+    assert!(v.get("firmware_bytes").is_none());
+    assert!(v.get("firmware_crc32").is_none());
+    assert_eq!(
+        v["firmware_images"],
+        serde_json::json!([
+            {"bytes": 4096, "crc32": "11111111"},
+            {"bytes": 8192, "crc32": "22222222"},
+        ])
+    );
+    assert_eq!(
+        v["target_firmware_crc32"],
+        serde_json::json!({"serial:COM6": "11111111", "serial:COM7": "22222222"})
+    );
+}
+
+#[test]
+fn summary_footer_lists_failures_with_reasons() {
+    let mut rec = OperationRecorder::new("flash");
+    rec.observe(&OperationEvent::TargetDone {
+        target_id: "halfkay:A".to_string(),
+        ok: true,
+        message: None,
+    });
+    rec.observe(&OperationEvent::TargetDone {
+        target_id: "serial:COM9".to_string(),
+        ok: false,
+        message: Some("write failed @ 0x1000".to_string()),
+    });
+    let summary = rec.finish(12, None);
+    assert_eq!(
+        super::human::summary_footer(&summary).as_deref(),
+        Some("Flashed 1/2 targets; failed: serial:COM9 (write failed @ 0x1000)")
+    );
+
+    let v = serde_json::to_value(super::json::operation_summary_to_json(summary)).unwrap();
+    assert_eq!(
+        v["target_errors"]["serial:COM9"].as_str(),
+        Some("write failed @ 0x1000")
+    );
+
+    let mut single = OperationRecorder::new("reboot");
+    single.observe(&OperationEvent::TargetDone {
+        target_id: "serial:COM6".to_string(),
+        ok: true,
+        message: None,
+    });
+    assert_eq!(super::human::summary_footer(&single.finish(0, None)), None);
+}
+
+#[test]
+fn style_colors_only_when_enabled() {
+    use super::human::{style, Style};
+
+    assert_eq!(style(false, Style::Error, "failed:"), "failed:");
+    assert_eq!(style(true, Style::Ok, "ok:"), "\x1b[32mok:\x1b[0m");
+    assert_eq!(style(true, Style::Warn, "retry"), "\x1b[33mretry\x1b[0m");
+}
+
+#[test]
+fn summary_recap_counts_targets_blocks_and_bridge() {
+    let mut rec = OperationRecorder::new("flash");
+    rec.observe(&OperationEvent::BridgePaused {
+        info: BridgePauseInfo {
+            method: BridgePauseMethod::Control,
+            id: "127.0.0.1:7999".to_string(),
+            pids: Vec::new(),
+            serial_open: None,
+            message: None,
+        },
+    });
+    for i in 0..3 {
+        rec.observe(&OperationEvent::Block {
+            target_id: "halfkay:A".to_string(),
+            index: i,
+            total: 3,
+            addr: i * 1024,
+        });
+    }
+    rec.observe(&OperationEvent::Retry {
+        target_id: "halfkay:A".to_string(),
+        addr: 0,
+        attempt: 1,
+        retries: 3,
+        delay_ms: 10,
+        error: "timeout".to_string(),
+    });
+    rec.observe(&OperationEvent::TargetDone {
+        target_id: "halfkay:A".to_string(),
+        ok: true,
+        message: None,
+    });
+    assert_eq!(
+        super::human::summary_recap(&rec.finish(0, None)),
+        "done: 1 ok, 0 failed, 3 blocks, 1 retries, bridge: control"
+    );
+
+    let mut reboot = OperationRecorder::new("reboot");
+    reboot.observe(&OperationEvent::TargetDone {
+        target_id: "serial:COM6".to_string(),
+        ok: false,
+        message: None,
+    });
+    assert_eq!(
+        super::human::summary_recap(&reboot.finish(12, None)),
+        "done: 0 ok, 1 failed"
+    );
+}
+
+#[test]
+fn interleaved_targets_keep_their_own_progress() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.ndjson");
+    let opts = OutputOptions {
+        verbose: false,
+        quiet: false,
+        json_timestamps: false,
+        json_progress: JsonProgressMode::Percent,
+        json_compact_summary: false,
+        json_stream: JsonStream::Stdout,
+    };
+    let mut json = super::json_reporter(opts, Some(&path));
+    let mut human = HumanOutput::new(OutputOptions {
+        json_progress: JsonProgressMode::None,
+        ..opts
+    });
+    let mut human_lines = Vec::new();
+    let total = 200;
+    let ids = ["halfkay:a", "halfkay:b"];
+    for index in 0..total {
+        for id in ids {
+            let block = OperationEvent::Block {
+                target_id: id.to_string(),
+                index,
+                total,
+                addr: index * 1024,
+            };
+            json.emit(Event::Operation(block.clone()));
+            human.emit(Event::Operation(block));
+        }
+        for id in ids {
+            let written = (index as u64 + 1) * 1024;
+            human_lines.extend(human.progress_text(id, written, total as u64 * 1024, false));
+        }
+    }
+    json.finish();
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    for id in ids {
+        let indexes: Vec<u64> = text
+            .lines()
+            .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap())
+            .filter(|v| v["target_id"] == id)
+            .map(|v| v["i"].as_u64().unwrap())
+            .collect();
+        // One line per percent step: index 0 (0%), then every second block up to 100%.
+        assert_eq!(indexes.len(), 101, "{id}");
+        assert!(indexes.windows(2).all(|w| w[0] < w[1]), "{id}");
+
+        let lines: Vec<&String> = human_lines
+            .iter()
+            .filter(|l| l.starts_with(&format!("{id}: ")))
+            .collect();
+        // 0%, 10%, ..., 100%.
+        assert_eq!(lines.len(), 11, "{id}: {lines:?}");
+        assert!(lines[10].ends_with("programming 100% (200/200)"), "{id}");
+    }
 }
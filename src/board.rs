@@ -0,0 +1,129 @@
+use std::fmt;
+
+use crate::teensy41;
+
+/// Teensy 4.0 flash available to firmware (2 MB minus the 64 KB reserved for EEPROM emulation
+/// and the recovery image).
+const TEENSY40_CODE_SIZE: usize = 2_031_616;
+
+/// Boards the loader can flash.
+///
+/// Both use the same HalfKay protocol, block size and USB product ids; the flash size differs.
+/// HalfKay tells them apart with its USB `bcdDevice` (see [`Board::from_bcd_device`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Board {
+    Teensy40,
+    #[default]
+    Teensy41,
+}
+
+impl Board {
+    pub const ALL: [Board; 2] = [Board::Teensy40, Board::Teensy41];
+
+    /// Stable lowercase identifier (`teensy40`, `teensy41`), as accepted by `--board`.
+    pub fn id(self) -> &'static str {
+        match self {
+            Board::Teensy40 => "teensy40",
+            Board::Teensy41 => "teensy41",
+        }
+    }
+
+    /// Bytes of flash available to firmware, starting at [`teensy41::FLEXSPI_BASE`].
+    pub fn code_size(self) -> usize {
+        match self {
+            Board::Teensy40 => TEENSY40_CODE_SIZE,
+            Board::Teensy41 => teensy41::CODE_SIZE,
+        }
+    }
+
+    /// HalfKay write block size.
+    pub fn block_size(self) -> usize {
+        match self {
+            Board::Teensy40 | Board::Teensy41 => teensy41::BLOCK_SIZE,
+        }
+    }
+
+    /// USB product id of the board's HalfKay bootloader (vendor [`teensy41::VID`]).
+    pub fn pid_halfkay(self) -> u16 {
+        match self {
+            Board::Teensy40 | Board::Teensy41 => teensy41::PID_HALFKAY,
+        }
+    }
+
+    /// First address past the board's flash (`FLEXSPI_BASE + code_size`).
+    pub fn flash_end(self) -> u32 {
+        teensy41::FLEXSPI_BASE + self.code_size() as u32
+    }
+
+    /// USB `bcdDevice` PJRC assigns to the board (reported by HalfKay).
+    pub fn bcd_device(self) -> u16 {
+        match self {
+            Board::Teensy40 => 0x0279,
+            Board::Teensy41 => 0x0280,
+        }
+    }
+
+    /// The board a HalfKay `bcdDevice` identifies, or `None` for another or unknown code.
+    pub fn from_bcd_device(bcd: u16) -> Option<Board> {
+        Board::ALL.into_iter().find(|b| b.bcd_device() == bcd)
+    }
+}
+
+/// PJRC USB product ids this loader recognizes (vendor [`teensy41::VID`]).
+///
+/// PJRC assigns product ids per USB type (bootloader, Serial, Serial + MIDI, ...), not per
+/// board; the board is in `bcdDevice` (see [`model_for_bcd_device`]).
+const TEENSY_PIDS: &[u16] = &[
+    teensy41::PID_HALFKAY,
+    // Teensyduino USB types with a serial interface.
+    0x0483, // Serial
+    0x0489, // Serial + MIDI
+    0x048A, // Serial + MIDI + Audio
+];
+
+/// Whether `pid` is a known Teensy product id.
+pub fn is_teensy_pid(pid: u16) -> bool {
+    TEENSY_PIDS.contains(&pid)
+}
+
+/// Human model name for a Teensy `bcdDevice`: "Teensy 4.0" (0x0279), "Teensy 4.1" (0x0280),
+/// or "Teensy 4.x" when the code is unknown or not reported.
+pub fn model_for_bcd_device(bcd: Option<u16>) -> &'static str {
+    match bcd.and_then(Board::from_bcd_device) {
+        Some(Board::Teensy40) => "Teensy 4.0",
+        Some(Board::Teensy41) => "Teensy 4.1",
+        None => "Teensy 4.x",
+    }
+}
+
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Board::Teensy40 => "Teensy 4.0",
+            Board::Teensy41 => "Teensy 4.1",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bcd_device_identifies_the_board() {
+        assert_eq!(Board::from_bcd_device(0x0279), Some(Board::Teensy40));
+        assert_eq!(Board::from_bcd_device(0x0280), Some(Board::Teensy41));
+        assert_eq!(Board::from_bcd_device(0x0281), None);
+        for board in Board::ALL {
+            assert_eq!(Board::from_bcd_device(board.bcd_device()), Some(board));
+        }
+    }
+
+    #[test]
+    fn model_name_comes_from_bcd_device() {
+        assert_eq!(model_for_bcd_device(Some(0x0279)), "Teensy 4.0");
+        assert_eq!(model_for_bcd_device(Some(0x0280)), "Teensy 4.1");
+        assert_eq!(model_for_bcd_device(Some(0x0281)), "Teensy 4.x");
+        assert_eq!(model_for_bcd_device(None), "Teensy 4.x");
+    }
+}
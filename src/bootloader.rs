@@ -3,7 +3,9 @@ use std::time::{Duration, Instant};
 
 use thiserror::Error;
 
-use crate::halfkay;
+use crate::serial_reboot::{self, RebootStrategy, SerialRebootError};
+use crate::targets::{self, SerialTarget, Target};
+use crate::{board, halfkay, teensy41};
 
 #[derive(Error, Debug)]
 pub enum WaitHalfKayError {
@@ -15,27 +17,391 @@ pub enum WaitHalfKayError {
 
     #[error("HalfKay did not appear after soft reboot")]
     Timeout,
+
+    #[error("wait for HalfKay cancelled")]
+    Cancelled,
+}
+
+#[derive(Error, Debug)]
+pub enum WaitSerialError {
+    #[error("serial port list failed: {0}")]
+    ListFailed(#[from] targets::DiscoverError),
+
+    #[error("no new serial port appeared after boot")]
+    Timeout,
+
+    #[error("multiple new serial ports match the booted board ({count})")]
+    Ambiguous { count: usize },
+
+    #[error("wait for serial port cancelled")]
+    Cancelled,
+}
+
+/// Wait before `RebootStrategy::Auto` falls back from magic baud to a DTR pulse.
+pub const AUTO_DTR_GRACE: Duration = Duration::from_secs(2);
+
+/// How long several new HalfKay paths may coexist before the wait gives up with `Ambiguous`.
+///
+/// A re-enumerating board can briefly show up twice before the stale path goes away.
+pub const AMBIGUITY_SETTLE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone)]
+pub struct SoftRebootOptions {
+    pub strategy: RebootStrategy,
+    /// Baud rate for `RebootStrategy::MagicBaud`.
+    pub baud: u32,
+    /// Delay after each reboot command before polling for HalfKay.
+    pub settle_delay: Duration,
+    /// `RebootStrategy::Auto`: how long magic baud gets before the DTR pulse.
+    pub dtr_grace: Duration,
+    /// Max time to wait for HalfKay, counted from the first reboot command (None = forever).
+    pub timeout: Option<Duration>,
+    pub poll_interval: Duration,
+    /// See [`AMBIGUITY_SETTLE`].
+    pub ambiguity_settle: Duration,
+}
+
+/// Progress of [`soft_reboot_and_wait`], reported to its callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoftRebootStep {
+    /// A reboot command was sent (`MagicBaud` or `DtrPulse`).
+    Sent(RebootStrategy),
+    /// About to enumerate HID, `elapsed` after the first reboot command.
+    Poll(Duration),
+}
+
+#[derive(Error, Debug)]
+pub enum SoftRebootError {
+    /// The first reboot command could not be sent.
+    #[error(transparent)]
+    Reboot(#[from] SerialRebootError),
+
+    #[error(transparent)]
+    Wait(#[from] WaitHalfKayError),
+}
+
+/// Soft-reboot the serial target `port_name` and wait for its HalfKay device.
+///
+/// `on_step` sees each reboot command and each poll; returning `false` from a poll cancels
+/// with `WaitHalfKayError::Cancelled`. With `RebootStrategy::Auto`, a failed DTR pulse (the
+/// port may already be gone) just keeps waiting.
+pub fn soft_reboot_and_wait<F>(
+    port_name: &str,
+    usb_serial: Option<&str>,
+    opts: &SoftRebootOptions,
+    mut on_step: F,
+) -> Result<String, SoftRebootError>
+where
+    F: FnMut(SoftRebootStep) -> bool,
+{
+    let before: HashSet<String> = halfkay::list_paths()
+        .map_err(WaitHalfKayError::ListFailed)?
+        .into_iter()
+        .collect();
+
+    let first = match opts.strategy {
+        RebootStrategy::DtrPulse => RebootStrategy::DtrPulse,
+        RebootStrategy::MagicBaud | RebootStrategy::Auto => RebootStrategy::MagicBaud,
+    };
+    send_reboot(port_name, first, opts.baud)?;
+    let start = Instant::now();
+    on_step(SoftRebootStep::Sent(first));
+    std::thread::sleep(opts.settle_delay);
+
+    if opts.strategy == RebootStrategy::Auto {
+        let grace = opts
+            .timeout
+            .map_or(opts.dtr_grace, |t| t.min(opts.dtr_grace));
+        let fallback = opts.timeout.is_none_or(|t| grace < t);
+        match wait_for_new_halfkay_matching(
+            &before,
+            usb_serial,
+            Some(grace),
+            opts.poll_interval,
+            opts.ambiguity_settle,
+            |_| on_step(SoftRebootStep::Poll(start.elapsed())),
+        ) {
+            Err(WaitHalfKayError::Timeout) if fallback => {}
+            r => return r.map_err(Into::into),
+        }
+        if send_reboot(port_name, RebootStrategy::DtrPulse, opts.baud).is_ok() {
+            on_step(SoftRebootStep::Sent(RebootStrategy::DtrPulse));
+            std::thread::sleep(opts.settle_delay);
+        }
+    }
+
+    let remaining = opts.timeout.map(|t| t.saturating_sub(start.elapsed()));
+    wait_for_new_halfkay_matching(
+        &before,
+        usb_serial,
+        remaining,
+        opts.poll_interval,
+        opts.ambiguity_settle,
+        |_| on_step(SoftRebootStep::Poll(start.elapsed())),
+    )
+    .map_err(Into::into)
+}
+
+fn send_reboot(
+    port_name: &str,
+    strategy: RebootStrategy,
+    baud: u32,
+) -> Result<(), SerialRebootError> {
+    match strategy {
+        RebootStrategy::DtrPulse => serial_reboot::reboot_via_dtr(port_name),
+        RebootStrategy::MagicBaud | RebootStrategy::Auto => {
+            serial_reboot::soft_reboot_port_with_baud(port_name, baud)
+        }
+    }
 }
 
+/// Wait for exactly one HalfKay path that is not in `before`.
+///
+/// Several new paths are given [`AMBIGUITY_SETTLE`] to collapse to one before this fails
+/// with `Ambiguous`.
 pub fn wait_for_new_halfkay(
     before: &HashSet<String>,
     timeout: Duration,
     poll_interval: Duration,
 ) -> Result<String, WaitHalfKayError> {
+    wait_for_new_halfkay_with(before, Some(timeout), poll_interval, |_| true)
+}
+
+/// Like [`wait_for_new_halfkay`], but calls `on_poll(elapsed)` before each enumeration.
+///
+/// `timeout = None` waits forever. Returning `false` from `on_poll` aborts with `Cancelled`.
+pub fn wait_for_new_halfkay_with<F>(
+    before: &HashSet<String>,
+    timeout: Option<Duration>,
+    poll_interval: Duration,
+    on_poll: F,
+) -> Result<String, WaitHalfKayError>
+where
+    F: FnMut(Duration) -> bool,
+{
+    wait_for_new_halfkay_matching(
+        before,
+        None,
+        timeout,
+        poll_interval,
+        AMBIGUITY_SETTLE,
+        on_poll,
+    )
+}
+
+/// Like [`wait_for_new_halfkay_with`], for a board rebooted from serial.
+///
+/// With `usb_serial`, a new HalfKay device reporting another board's serial number is never
+/// picked, and among several new devices the one whose serial matches wins. Otherwise keeps
+/// polling for up to `settle` and only fails with `Ambiguous` if more than one candidate is
+/// still there (`settle = 0` fails right away).
+pub fn wait_for_new_halfkay_matching<F>(
+    before: &HashSet<String>,
+    usb_serial: Option<&str>,
+    timeout: Option<Duration>,
+    poll_interval: Duration,
+    settle: Duration,
+    on_poll: F,
+) -> Result<String, WaitHalfKayError>
+where
+    F: FnMut(Duration) -> bool,
+{
+    poll_new_halfkay(
+        before,
+        usb_serial,
+        || match usb_serial {
+            Some(_) => halfkay::list_serial_numbers(),
+            None => Ok(halfkay::list_paths()?
+                .into_iter()
+                .map(|p| (p, None))
+                .collect()),
+        },
+        timeout,
+        poll_interval,
+        settle,
+        on_poll,
+    )
+}
+
+/// The polling loop of [`wait_for_new_halfkay_matching`], with HID enumeration injected.
+fn poll_new_halfkay<L, F>(
+    before: &HashSet<String>,
+    usb_serial: Option<&str>,
+    mut list: L,
+    timeout: Option<Duration>,
+    poll_interval: Duration,
+    settle: Duration,
+    mut on_poll: F,
+) -> Result<String, WaitHalfKayError>
+where
+    L: FnMut() -> Result<Vec<(String, Option<String>)>, halfkay::HalfKayError>,
+    F: FnMut(Duration) -> bool,
+{
     let start = Instant::now();
+    // When the current run of ambiguous polls started, and the latest count.
+    let mut ambiguous: Option<(Instant, usize)> = None;
     loop {
-        let now = halfkay::list_paths()?;
-        if let Some(p) = diff_new_halfkay(before, &now)? {
-            return Ok(p);
+        if !on_poll(start.elapsed()) {
+            return Err(WaitHalfKayError::Cancelled);
+        }
+
+        let now = list()?;
+        match pick_new_halfkay(before, usb_serial, &now) {
+            Ok(Some(p)) => return Ok(p),
+            Ok(None) => ambiguous = None,
+            Err(WaitHalfKayError::Ambiguous { count }) => {
+                let since = ambiguous.map_or_else(Instant::now, |(since, _)| since);
+                if since.elapsed() >= settle {
+                    return Err(WaitHalfKayError::Ambiguous { count });
+                }
+                ambiguous = Some((since, count));
+            }
+            Err(e) => return Err(e),
         }
 
+        if timeout.is_some_and(|t| start.elapsed() >= t) {
+            return Err(match ambiguous {
+                Some((_, count)) => WaitHalfKayError::Ambiguous { count },
+                None => WaitHalfKayError::Timeout,
+            });
+        }
+        std::thread::sleep(poll_interval);
+    }
+}
+
+/// The new HalfKay path among `now` (path, HalfKay serial number) that is the rebooted board.
+///
+/// With `usb_serial`, devices reporting a non-matching serial are left out; a device that
+/// reports no serial is still a candidate, unless exactly one other one matches.
+fn pick_new_halfkay(
+    before: &HashSet<String>,
+    usb_serial: Option<&str>,
+    now: &[(String, Option<String>)],
+) -> Result<Option<String>, WaitHalfKayError> {
+    let matches = |hk: &str| usb_serial.is_none_or(|usb| halfkay::serial_matches(usb, hk));
+    let mut candidates: Vec<&(String, Option<String>)> = now
+        .iter()
+        .filter(|(path, serial)| !before.contains(path) && serial.as_deref().is_none_or(matches))
+        .collect();
+    candidates.sort();
+    if let [(path, _)] = candidates[..] {
+        return Ok(Some(path.clone()));
+    }
+    if usb_serial.is_some() {
+        if let [(path, _)] = candidates
+            .iter()
+            .filter(|(_, serial)| serial.is_some())
+            .copied()
+            .collect::<Vec<_>>()[..]
+        {
+            return Ok(Some(path.clone()));
+        }
+    }
+    if candidates.len() > 1 {
+        return Err(WaitHalfKayError::Ambiguous {
+            count: candidates.len(),
+        });
+    }
+    Ok(None)
+}
+
+/// Names of the serial ports present right now.
+pub fn serial_port_names() -> Result<HashSet<String>, targets::DiscoverError> {
+    Ok(targets::discover_serial_targets()?
+        .into_iter()
+        .filter_map(|t| match t {
+            Target::Serial(s) => Some(s.port_name),
+            Target::HalfKay(_) => None,
+        })
+        .collect())
+}
+
+/// Wait for the booted board to come back as a Teensy serial port that is not in `before`.
+///
+/// `halfkay_serial` is the board's HalfKay serial number: when known, only a port whose USB
+/// serial number [`halfkay::serial_matches`] it counts, so another board or an unrelated
+/// device appearing meanwhile is ignored. Several matching ports fail with `Ambiguous`.
+/// Returning `false` from `on_poll` aborts with `Cancelled`.
+pub fn wait_for_new_serial<F>(
+    before: &HashSet<String>,
+    halfkay_serial: Option<&str>,
+    timeout: Duration,
+    poll_interval: Duration,
+    on_poll: F,
+) -> Result<String, WaitSerialError>
+where
+    F: FnMut() -> bool,
+{
+    let list = || {
+        Ok(targets::discover_serial_targets()?
+            .into_iter()
+            .filter_map(|t| match t {
+                Target::Serial(s) => Some(s),
+                Target::HalfKay(_) => None,
+            })
+            .collect())
+    };
+    poll_new_serial(
+        before,
+        halfkay_serial,
+        list,
+        timeout,
+        poll_interval,
+        on_poll,
+    )
+}
+
+/// The polling loop of [`wait_for_new_serial`], with serial discovery injected.
+fn poll_new_serial<L, F>(
+    before: &HashSet<String>,
+    halfkay_serial: Option<&str>,
+    mut list: L,
+    timeout: Duration,
+    poll_interval: Duration,
+    mut on_poll: F,
+) -> Result<String, WaitSerialError>
+where
+    L: FnMut() -> Result<Vec<SerialTarget>, targets::DiscoverError>,
+    F: FnMut() -> bool,
+{
+    let start = Instant::now();
+    loop {
+        let mut ports: Vec<String> = list()?
+            .into_iter()
+            .filter(|t| is_returning_board(t, before, halfkay_serial))
+            .map(|t| t.port_name)
+            .collect();
+        match ports.len() {
+            0 => {}
+            1 => return Ok(ports.remove(0)),
+            count => return Err(WaitSerialError::Ambiguous { count }),
+        }
+        if !on_poll() {
+            return Err(WaitSerialError::Cancelled);
+        }
         if start.elapsed() >= timeout {
-            return Err(WaitHalfKayError::Timeout);
+            return Err(WaitSerialError::Timeout);
         }
         std::thread::sleep(poll_interval);
     }
 }
 
+/// Whether `t` is a new Teensy serial port that may be the board with `halfkay_serial`.
+fn is_returning_board(
+    t: &SerialTarget,
+    before: &HashSet<String>,
+    halfkay_serial: Option<&str>,
+) -> bool {
+    t.vid == teensy41::VID
+        && board::is_teensy_pid(t.pid)
+        && !before.contains(&t.port_name)
+        && halfkay_serial.is_none_or(|hk| {
+            t.serial_number
+                .as_deref()
+                .is_some_and(|usb| halfkay::serial_matches(usb, hk))
+        })
+}
+
 pub fn diff_new_halfkay(
     before: &HashSet<String>,
     now: &[String],
@@ -60,6 +426,102 @@ pub fn diff_new_halfkay(
 mod tests {
     use super::*;
 
+    fn ports(names: &[&str]) -> HashSet<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// A Teensy serial port with USB serial number `sn`.
+    fn teensy(port: &str, sn: &str) -> SerialTarget {
+        SerialTarget {
+            serial_number: Some(sn.to_string()),
+            ..targets::serial_target(port)
+        }
+    }
+
+    #[test]
+    fn new_serial_port_must_be_the_booted_board() {
+        let before = ports(&["COM3"]);
+        let mut polls = vec![
+            vec![
+                teensy("COM3", "12345670"),
+                // Another board coming back, and a non-Teensy PJRC device.
+                teensy("COM4", "7654320"),
+                SerialTarget {
+                    pid: 0x0487,
+                    ..teensy("COM5", "12345670")
+                },
+                teensy("COM9", "12345670"),
+            ],
+            vec![teensy("COM3", "12345670")],
+        ];
+        let port = poll_new_serial(
+            &before,
+            Some("12D687"),
+            || Ok(polls.pop().unwrap()),
+            Duration::from_secs(1),
+            Duration::ZERO,
+            || true,
+        )
+        .unwrap();
+        assert_eq!(port, "COM9");
+    }
+
+    #[test]
+    fn atomic_wait_is_not_confirmed_by_another_board() {
+        // Both boards come back in the same poll; the lower name is not the flashed one.
+        let before = ports(&["COM3"]);
+        let now = || Ok(vec![teensy("COM4", "7654320"), teensy("COM5", "12345670")]);
+        let port = poll_new_serial(
+            &before,
+            Some("12D687"),
+            now,
+            Duration::from_secs(1),
+            Duration::ZERO,
+            || true,
+        )
+        .unwrap();
+        assert_eq!(port, "COM5");
+    }
+
+    #[test]
+    fn new_serial_ports_the_board_cannot_be_told_from_are_ambiguous() {
+        let before = ports(&["COM3"]);
+        let now = || Ok(vec![teensy("COM6", "1230"), teensy("COM9", "4560")]);
+        let r = poll_new_serial(
+            &before,
+            None,
+            now,
+            Duration::from_secs(1),
+            Duration::ZERO,
+            || true,
+        );
+        assert!(matches!(r, Err(WaitSerialError::Ambiguous { count: 2 })));
+    }
+
+    #[test]
+    fn new_serial_wait_times_out_or_cancels() {
+        let before = ports(&["COM3"]);
+        let r = poll_new_serial(
+            &before,
+            None,
+            || Ok(vec![targets::serial_target("COM3")]),
+            Duration::ZERO,
+            Duration::ZERO,
+            || true,
+        );
+        assert!(matches!(r, Err(WaitSerialError::Timeout)));
+
+        let r = poll_new_serial(
+            &before,
+            None,
+            || Ok(vec![targets::serial_target("COM3")]),
+            Duration::from_secs(1),
+            Duration::ZERO,
+            || false,
+        );
+        assert!(matches!(r, Err(WaitSerialError::Cancelled)));
+    }
+
     #[test]
     fn test_diff_new_halfkay() {
         let mut before = HashSet::new();
@@ -75,4 +537,94 @@ mod tests {
         let err = diff_new_halfkay(&before, &now2).unwrap_err();
         assert!(matches!(err, WaitHalfKayError::Ambiguous { count: 2 }));
     }
+
+    type HalfKayList = Vec<(String, Option<String>)>;
+
+    fn scripted(polls: &[&[&str]]) -> impl FnMut() -> Result<HalfKayList, halfkay::HalfKayError> {
+        let mut polls: std::collections::VecDeque<HalfKayList> = polls
+            .iter()
+            .map(|p| p.iter().map(|s| (s.to_string(), None)).collect())
+            .collect();
+        move || {
+            Ok(if polls.len() > 1 {
+                polls.pop_front().unwrap()
+            } else {
+                polls[0].clone()
+            })
+        }
+    }
+
+    #[test]
+    fn ambiguous_halfkay_gets_a_settle_window() {
+        let before: HashSet<String> = ["A".to_string()].into();
+        let ms = Duration::from_millis;
+        let wait = |polls: &[&[&str]], settle| {
+            poll_new_halfkay(
+                &before,
+                None,
+                scripted(polls),
+                Some(ms(500)),
+                ms(1),
+                settle,
+                |_| true,
+            )
+        };
+
+        // Transient duplicate collapses to one path within the window.
+        let r = wait(&[&["A", "B", "C"], &["A", "B", "C"], &["A", "C"]], ms(200));
+        assert_eq!(r.unwrap(), "C");
+
+        // Still two at the end of the window.
+        let r = wait(&[&["A", "B", "C"]], ms(20));
+        assert!(matches!(r, Err(WaitHalfKayError::Ambiguous { count: 2 })));
+
+        // No window: fails on the first ambiguous poll, as before.
+        let r = wait(&[&["A", "B", "C"], &["A", "C"]], Duration::ZERO);
+        assert!(matches!(r, Err(WaitHalfKayError::Ambiguous { count: 2 })));
+
+        // A single new path is returned straight away.
+        assert_eq!(wait(&[&["A", "B"]], ms(200)).unwrap(), "B");
+    }
+
+    #[test]
+    fn new_halfkay_must_report_the_rebooted_board_serial() {
+        let before: HashSet<String> = ["A".to_string()].into();
+        let ms = Duration::from_millis;
+        let wait = |now: HalfKayList| {
+            poll_new_halfkay(
+                &before,
+                Some("12345670"),
+                move || Ok(now.clone()),
+                Some(ms(20)),
+                ms(1),
+                ms(5),
+                |_| true,
+            )
+        };
+        let hk = |path: &str, serial: Option<&str>| (path.to_string(), serial.map(String::from));
+
+        // A single new device of another board is not taken for the rebooted one.
+        let r = wait(vec![hk("A", None), hk("B", Some("ABCDEF"))]);
+        assert!(matches!(r, Err(WaitHalfKayError::Timeout)), "{r:?}");
+
+        assert_eq!(
+            wait(vec![hk("A", None), hk("B", Some("12D687"))]).unwrap(),
+            "B"
+        );
+        // No serial reported: cannot be ruled out.
+        assert_eq!(wait(vec![hk("A", None), hk("B", None)]).unwrap(), "B");
+        // Among several, the matching serial wins.
+        assert_eq!(
+            wait(vec![hk("B", None), hk("C", Some("12D687"))]).unwrap(),
+            "C"
+        );
+    }
+
+    #[test]
+    fn wait_for_new_halfkay_with_honors_cancel() {
+        let before = HashSet::new();
+        let err = wait_for_new_halfkay_with(&before, None, Duration::from_millis(1), |_| false)
+            .unwrap_err();
+        assert!(matches!(err, WaitHalfKayError::Cancelled));
+    }
 }
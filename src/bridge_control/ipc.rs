@@ -1,3 +1,5 @@
+use std::fmt;
+use std::path::PathBuf;
 use std::time::Duration;
 
 use serde::Serialize;
@@ -7,63 +9,139 @@ use serde::Deserialize;
 
 use super::BridgeControlError;
 
+/// Connect timeout for the localhost control port.
+///
+/// Kept short and separate from the configured timeout (which must cover the pause ack's
+/// serial-close round trip) so a bridge that is not running fails fast.
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Where oc-bridge listens for control requests (see
+/// [`BridgeControlOptions::control_endpoint`](super::BridgeControlOptions::control_endpoint)).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlEndpoint {
+    /// TCP on 127.0.0.1.
+    Port(u16),
+    /// Unix domain socket (Linux/macOS only).
+    Socket(PathBuf),
+}
+
+impl ControlEndpoint {
+    /// Short identifier reported in pause info (`127.0.0.1:<port>` or the socket path).
+    pub fn id(&self) -> String {
+        match self {
+            ControlEndpoint::Port(port) => format!("127.0.0.1:{port}"),
+            ControlEndpoint::Socket(path) => path.display().to_string(),
+        }
+    }
+
+    /// `oc-bridge ctl` arguments selecting this endpoint, for hints.
+    pub(super) fn ctl_args(&self) -> String {
+        match self {
+            ControlEndpoint::Port(port) => format!("--control-port {port}"),
+            ControlEndpoint::Socket(path) => format!("--control-socket {}", path.display()),
+        }
+    }
+}
+
+impl fmt::Display for ControlEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlEndpoint::Port(port) => write!(f, "port {port}"),
+            ControlEndpoint::Socket(path) => write!(f, "socket {}", path.display()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct BridgeControlStatus {
     pub ok: bool,
     pub paused: bool,
     pub serial_open: Option<bool>,
     pub message: Option<String>,
+    /// Connected bridge clients (not reported by older bridges).
+    pub client_count: Option<u64>,
+    /// Bridge uptime (not reported by older bridges).
+    pub uptime_ms: Option<u64>,
+    /// Serial port the bridge is configured for (not reported by older bridges).
+    pub serial_port: Option<String>,
 }
 
 pub fn control_status(
     port: u16,
     timeout: Duration,
 ) -> Result<BridgeControlStatus, BridgeControlError> {
-    let resp = control_send(port, "status", timeout)?;
+    control_status_at(&ControlEndpoint::Port(port), timeout)
+}
+
+/// [`control_status`] over any endpoint (TCP port or Unix socket).
+pub fn control_status_at(
+    endpoint: &ControlEndpoint,
+    timeout: Duration,
+) -> Result<BridgeControlStatus, BridgeControlError> {
+    let resp = control_send(endpoint, "status", timeout)?;
     Ok(BridgeControlStatus {
         ok: resp.ok,
         paused: resp.paused,
         serial_open: resp.serial_open,
         message: resp.message,
+        client_count: resp.client_count,
+        uptime_ms: resp.uptime_ms,
+        serial_port: resp.serial_port,
     })
 }
 
-pub(super) fn control_pause(port: u16, timeout: Duration) -> Result<(), BridgeControlError> {
-    let resp = control_send(port, "pause", timeout)?;
+/// Sends `pause` and returns the bridge's ack once it confirms the serial port is released.
+pub(super) fn control_pause(
+    endpoint: &ControlEndpoint,
+    timeout: Duration,
+) -> Result<BridgeControlStatus, BridgeControlError> {
+    let resp = control_send(endpoint, "pause", timeout)?;
     if !resp.ok {
         return Err(BridgeControlError::CommandFailed {
-            cmd: format!("oc-bridge control pause (port {port})"),
+            cmd: format!("oc-bridge control pause ({endpoint})"),
             message: resp.message.unwrap_or_else(|| "unknown error".to_string()),
         });
     }
     if !resp.paused {
         return Err(BridgeControlError::CommandFailed {
-            cmd: format!("oc-bridge control pause (port {port})"),
+            cmd: format!("oc-bridge control pause ({endpoint})"),
             message: "bridge did not enter paused state".to_string(),
         });
     }
     if let Some(open) = resp.serial_open {
         if open {
             return Err(BridgeControlError::CommandFailed {
-                cmd: format!("oc-bridge control pause (port {port})"),
+                cmd: format!("oc-bridge control pause ({endpoint})"),
                 message: "bridge reports serial_open=true after pause".to_string(),
             });
         }
     }
-    Ok(())
+    Ok(BridgeControlStatus {
+        ok: resp.ok,
+        paused: resp.paused,
+        serial_open: resp.serial_open,
+        message: resp.message,
+        client_count: resp.client_count,
+        uptime_ms: resp.uptime_ms,
+        serial_port: resp.serial_port,
+    })
 }
 
-pub(super) fn control_resume(port: u16, timeout: Duration) -> Result<(), BridgeControlError> {
-    let resp = control_send(port, "resume", timeout)?;
+/// Ask oc-bridge to resume serial use over IPC; fails if it still reports `paused`.
+pub fn control_resume(
+    endpoint: &ControlEndpoint,
+    timeout: Duration,
+) -> Result<(), BridgeControlError> {
+    let resp = control_send(endpoint, "resume", timeout)?;
     if !resp.ok {
         return Err(BridgeControlError::CommandFailed {
-            cmd: format!("oc-bridge control resume (port {port})"),
+            cmd: format!("oc-bridge control resume ({endpoint})"),
             message: resp.message.unwrap_or_else(|| "unknown error".to_string()),
         });
     }
     if resp.paused {
         return Err(BridgeControlError::CommandFailed {
-            cmd: format!("oc-bridge control resume (port {port})"),
+            cmd: format!("oc-bridge control resume ({endpoint})"),
             message: "bridge still paused after resume".to_string(),
         });
     }
@@ -76,6 +154,9 @@ struct ControlResp {
     paused: bool,
     serial_open: Option<bool>,
     message: Option<String>,
+    client_count: Option<u64>,
+    uptime_ms: Option<u64>,
+    serial_port: Option<String>,
 }
 
 #[cfg(feature = "cli")]
@@ -89,32 +170,65 @@ struct ControlRespJson {
     serial_open: Option<bool>,
     #[serde(default)]
     message: Option<String>,
+    #[serde(default)]
+    client_count: Option<u64>,
+    #[serde(default)]
+    uptime_ms: Option<u64>,
+    #[serde(default)]
+    serial_port: Option<String>,
 }
 
 fn control_send(
-    port: u16,
+    endpoint: &ControlEndpoint,
     cmd: &str,
     timeout: Duration,
 ) -> Result<ControlResp, BridgeControlError> {
-    use std::io::{Read, Write};
     use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream};
 
-    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port);
-    let mut stream = TcpStream::connect_timeout(&addr, timeout).map_err(|e| {
-        BridgeControlError::CommandFailed {
-            cmd: format!("oc-bridge control connect (port {port})"),
-            message: e.to_string(),
+    let connect_failed = |e: std::io::Error| BridgeControlError::CommandFailed {
+        cmd: format!("oc-bridge control connect ({endpoint})"),
+        message: e.to_string(),
+    };
+    match endpoint {
+        ControlEndpoint::Port(port) => {
+            let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), *port);
+            let stream = TcpStream::connect_timeout(&addr, timeout.min(CONNECT_TIMEOUT))
+                .map_err(connect_failed)?;
+            let _ = stream.set_read_timeout(Some(timeout));
+            let _ = stream.set_write_timeout(Some(timeout));
+            exchange(stream, endpoint, cmd)
         }
-    })?;
-    let _ = stream.set_read_timeout(Some(timeout));
-    let _ = stream.set_write_timeout(Some(timeout));
+        #[cfg(unix)]
+        ControlEndpoint::Socket(path) => {
+            // Local sockets connect immediately or fail (no listener), so no connect timeout.
+            let stream = std::os::unix::net::UnixStream::connect(path).map_err(connect_failed)?;
+            let _ = stream.set_read_timeout(Some(timeout));
+            let _ = stream.set_write_timeout(Some(timeout));
+            exchange(stream, endpoint, cmd)
+        }
+        #[cfg(not(unix))]
+        ControlEndpoint::Socket(_) => Err(BridgeControlError::CommandFailed {
+            cmd: format!("oc-bridge control connect ({endpoint})"),
+            message: "unix domain sockets are not supported on this platform".to_string(),
+        }),
+    }
+}
 
+/// Send one request and read the response until the bridge closes the connection.
+fn exchange<S>(
+    mut stream: S,
+    endpoint: &ControlEndpoint,
+    cmd: &str,
+) -> Result<ControlResp, BridgeControlError>
+where
+    S: std::io::Read + std::io::Write,
+{
     // Forward-compatible request format. oc-bridge should ignore unknown fields.
     let req = format!("{{\"schema\":1,\"cmd\":\"{cmd}\"}}\n");
     stream
         .write_all(req.as_bytes())
         .map_err(|e| BridgeControlError::CommandFailed {
-            cmd: format!("oc-bridge control write (port {port})"),
+            cmd: format!("oc-bridge control write ({endpoint})"),
             message: e.to_string(),
         })?;
     stream.flush().ok();
@@ -123,7 +237,7 @@ fn control_send(
     stream
         .read_to_string(&mut out)
         .map_err(|e| BridgeControlError::CommandFailed {
-            cmd: format!("oc-bridge control read (port {port})"),
+            cmd: format!("oc-bridge control read ({endpoint})"),
             message: e.to_string(),
         })?;
 
@@ -149,6 +263,9 @@ fn parse_control_response(s: &str) -> Result<ControlResp, BridgeControlError> {
                 paused: v.paused,
                 serial_open: v.serial_open,
                 message: v.message,
+                client_count: v.client_count,
+                uptime_ms: v.uptime_ms,
+                serial_port: v.serial_port,
             });
         }
     }
@@ -173,6 +290,9 @@ fn parse_control_response(s: &str) -> Result<ControlResp, BridgeControlError> {
         paused,
         serial_open,
         message,
+        client_count: extract_json_u64_field(&compact, "client_count"),
+        uptime_ms: extract_json_u64_field(&compact, "uptime_ms"),
+        serial_port: extract_json_string_field(&compact, "serial_port"),
     })
 }
 
@@ -183,3 +303,77 @@ fn extract_json_string_field(s: &str, key: &str) -> Option<String> {
     let end = rest.find('"')?;
     Some(rest[..end].to_string())
 }
+
+fn extract_json_u64_field(s: &str, key: &str) -> Option<u64> {
+    let needle = format!("\"{key}\":");
+    let idx = s.find(&needle)?;
+    let rest = &s[(idx + needle.len())..];
+    let end = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    rest[..end].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_detail_is_optional() {
+        let resp = parse_control_response(
+            "{\"schema\":1,\"ok\":true,\"paused\":false,\"client_count\":3,\"uptime_ms\":120000,\"serial_port\":\"COM6\"}\n",
+        )
+        .unwrap();
+        assert_eq!(resp.client_count, Some(3));
+        assert_eq!(resp.uptime_ms, Some(120_000));
+        assert_eq!(resp.serial_port.as_deref(), Some("COM6"));
+
+        let resp = parse_control_response("{\"ok\":true,\"paused\":true}").unwrap();
+        assert_eq!(resp.client_count, None);
+        assert_eq!(resp.uptime_ms, None);
+        assert_eq!(resp.serial_port, None);
+
+        assert_eq!(
+            extract_json_u64_field("{\"uptime_ms\":42}", "uptime_ms"),
+            Some(42)
+        );
+        assert_eq!(
+            extract_json_u64_field("{\"uptime_ms\":null}", "uptime_ms"),
+            None
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn control_status_over_unix_socket() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::net::UnixListener;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("oc-bridge.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut req = String::new();
+            BufReader::new(&stream).read_line(&mut req).unwrap();
+            (&stream)
+                .write_all(b"{\"schema\":1,\"ok\":true,\"paused\":false,\"serial_open\":true}\n")
+                .unwrap();
+            req
+        });
+
+        let endpoint = ControlEndpoint::Socket(path.clone());
+        let st = control_status_at(&endpoint, Duration::from_secs(2)).unwrap();
+        assert!(st.ok && !st.paused);
+        assert_eq!(st.serial_open, Some(true));
+        assert_eq!(
+            server.join().unwrap(),
+            "{\"schema\":1,\"cmd\":\"status\"}\n"
+        );
+        assert_eq!(endpoint.id(), path.display().to_string());
+
+        drop(dir);
+        let err = control_status_at(&endpoint, Duration::from_secs(2)).unwrap_err();
+        assert!(err.to_string().contains("connect (socket "), "{err}");
+    }
+}
@@ -1,4 +1,5 @@
-use std::time::Duration;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 use serde::Serialize;
 use thiserror::Error;
@@ -9,7 +10,9 @@ mod ipc;
 mod process;
 mod service;
 
-pub use ipc::{control_status, BridgeControlStatus};
+pub use ipc::{
+    control_resume, control_status, control_status_at, BridgeControlStatus, ControlEndpoint,
+};
 pub use process::{list_oc_bridge_processes, OcBridgeProcessInfo};
 pub use service::{default_service_id_for_platform, service_status, ServiceStatus};
 
@@ -23,6 +26,19 @@ pub enum BridgeControlMethod {
     None,
 }
 
+/// When to resume a bridge that was paused for an operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BridgeResumePolicy {
+    /// Resume after every operation.
+    #[default]
+    Always,
+    /// Resume only when every target succeeded; keep it paused after a failure.
+    OnSuccess,
+    /// Leave the bridge paused; the caller resumes it explicitly.
+    Never,
+}
+
 #[derive(Debug, Clone)]
 pub struct BridgeControlOptions {
     /// Enable automatic bridge pause/resume.
@@ -55,8 +71,40 @@ pub struct BridgeControlOptions {
     /// When available, we prefer this over stopping the OS service.
     pub control_port: u16,
 
+    /// Unix domain socket for oc-bridge IPC (e.g. `$XDG_RUNTIME_DIR/oc-bridge.sock`).
+    ///
+    /// When set, replaces `control_port` on Linux/macOS; ignored on Windows.
+    pub control_socket: Option<PathBuf>,
+
     /// Max time to wait for oc-bridge IPC.
     pub control_timeout: Duration,
+
+    /// Whether to resume the bridge when the operation completes normally.
+    ///
+    /// Dropping the guard (panic/abort) always attempts a resume regardless of policy.
+    pub resume_policy: BridgeResumePolicy,
+
+    /// Probe oc-bridge before pausing and report when it has a serial port open.
+    pub warn_active_session: bool,
+
+    /// Extra resume attempts when restarting the OS service or relaunching processes.
+    ///
+    /// IPC resumes are not retried (they fail fast and the hint is actionable).
+    pub resume_retries: u32,
+
+    /// Delay between resume attempts.
+    pub resume_retry_interval: Duration,
+
+    /// Extra IPC `pause` attempts before giving up on the control method.
+    ///
+    /// All attempts share the `control_timeout` budget.
+    pub control_retries: u32,
+
+    /// Arguments to relaunch oc-bridge with when the process fallback could not read its
+    /// command line (default: `--daemon --no-relaunch`).
+    ///
+    /// Captured command lines are always reused as-is.
+    pub relaunch_args: Option<Vec<String>>,
 }
 
 impl Default for BridgeControlOptions {
@@ -68,13 +116,31 @@ impl Default for BridgeControlOptions {
             service_id: None,
             timeout: Duration::from_secs(5),
             control_port: 7999,
+            control_socket: None,
             // oc-bridge pause waits for the serial port to actually close (ack), so
             // this needs to cover that round-trip.
             control_timeout: Duration::from_millis(2500),
+            resume_policy: BridgeResumePolicy::Always,
+            warn_active_session: true,
+            resume_retries: 2,
+            resume_retry_interval: Duration::from_secs(1),
+            control_retries: 2,
+            relaunch_args: None,
         }
     }
 }
 
+impl BridgeControlOptions {
+    /// The IPC endpoint in effect: `control_socket` when set (Unix only), else `control_port`.
+    pub fn control_endpoint(&self) -> ControlEndpoint {
+        #[cfg(unix)]
+        if let Some(path) = &self.control_socket {
+            return ControlEndpoint::Socket(path.clone());
+        }
+        ControlEndpoint::Port(self.control_port)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum BridgePauseMethod {
@@ -88,6 +154,10 @@ pub struct BridgePauseInfo {
     pub method: BridgePauseMethod,
     pub id: String,
     pub pids: Vec<u32>,
+    /// `serial_open` from the bridge's pause ack (control method only).
+    pub serial_open: Option<bool>,
+    /// `message` from the bridge's pause ack (control method only).
+    pub message: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -125,41 +195,97 @@ pub enum BridgeControlError {
 
 #[derive(Debug, Clone)]
 enum ResumePlan {
-    Control { port: u16, timeout: Duration },
-    Service { id: String },
-    Processes { cmds: Vec<process::RelaunchCmd> },
+    Control {
+        endpoint: ControlEndpoint,
+        timeout: Duration,
+    },
+    Service {
+        id: String,
+    },
+    Processes {
+        cmds: Vec<process::RelaunchCmd>,
+    },
 }
 
 #[derive(Debug)]
 pub struct BridgeGuard {
     resume: Option<ResumePlan>,
     timeout: Duration,
+    retries: u32,
+    retry_interval: Duration,
 }
 
 impl BridgeGuard {
     pub fn resume_hint(&self) -> Option<String> {
         match self.resume.as_ref() {
-            Some(ResumePlan::Control { port, .. }) => {
-                Some(format!("Try: oc-bridge ctl resume --control-port {port}"))
+            Some(ResumePlan::Control { endpoint, .. }) => {
+                Some(format!("Try: oc-bridge ctl resume {}", endpoint.ctl_args()))
             }
             Some(ResumePlan::Service { id }) => Some(service::hint_start_service(id)),
             _ => None,
         }
     }
 
+    /// Disarm the guard and leave the bridge paused.
+    ///
+    /// Returns the hint a user needs to resume it manually.
+    pub fn keep_paused(mut self) -> Option<String> {
+        let hint = self.resume_hint();
+        self.resume = None;
+        hint
+    }
+
     pub fn resume(&mut self) -> Result<(), BridgeControlError> {
+        self.resume_with_retries(|_, _, _| {})
+    }
+
+    /// Like [`BridgeGuard::resume`], but reports each failed attempt that will be retried.
+    ///
+    /// Service and process plans are retried up to `resume_retries` times;
+    /// `on_retry(attempt, retries, err)` is called before sleeping.
+    pub fn resume_with_retries<F>(&mut self, on_retry: F) -> Result<(), BridgeControlError>
+    where
+        F: FnMut(u32, u32, &BridgeControlError),
+    {
+        self.resume_with(resume, on_retry)
+    }
+
+    /// The retry loop of [`BridgeGuard::resume_with_retries`], with the resume call injected.
+    fn resume_with<R, F>(
+        &mut self,
+        mut resume: R,
+        mut on_retry: F,
+    ) -> Result<(), BridgeControlError>
+    where
+        R: FnMut(ResumePlan, Duration) -> Result<(), BridgeControlError>,
+        F: FnMut(u32, u32, &BridgeControlError),
+    {
         let Some(plan) = self.resume.clone() else {
             return Ok(());
         };
-        match resume(plan.clone(), self.timeout) {
-            Ok(()) => {
-                self.resume = None;
-                Ok(())
-            }
-            Err(e) => {
-                // Keep the plan for Drop() best-effort retries.
-                self.resume = Some(plan);
-                Err(e)
+        let retries = match plan {
+            ResumePlan::Control { .. } => 0,
+            ResumePlan::Service { .. } | ResumePlan::Processes { .. } => self.retries,
+        };
+
+        let mut attempt = 0;
+        loop {
+            match resume(plan.clone(), self.timeout) {
+                Ok(()) => {
+                    self.resume = None;
+                    return Ok(());
+                }
+                Err(e) if attempt < retries => {
+                    attempt += 1;
+                    warn!(err = %e, attempt, retries, "bridge resume failed; retrying");
+                    on_retry(attempt, retries, &e);
+                    std::thread::sleep(self.retry_interval);
+                }
+                Err(e) => {
+                    // Keep the plan for Drop() best-effort retries.
+                    self.resume = Some(plan);
+                    return Err(e);
+                }
             }
         }
     }
@@ -176,6 +302,8 @@ pub(crate) fn test_noop_guard() -> BridgeGuard {
     BridgeGuard {
         resume: None,
         timeout: Duration::from_millis(1),
+        retries: 0,
+        retry_interval: Duration::ZERO,
     }
 }
 
@@ -184,6 +312,28 @@ pub struct BridgePause {
     pub outcome: BridgePauseOutcome,
 }
 
+/// oc-bridge's status when it reports a live (unpaused) serial session over IPC.
+///
+/// Not queried when bridge control is disabled or does not use IPC. Best-effort: any IPC
+/// failure is treated as "no active session".
+pub fn active_session(opts: &BridgeControlOptions) -> Option<BridgeControlStatus> {
+    if !opts.enabled
+        || !matches!(
+            opts.method,
+            BridgeControlMethod::Auto | BridgeControlMethod::Control
+        )
+    {
+        return None;
+    }
+    match ipc::control_status_at(&opts.control_endpoint(), opts.control_timeout) {
+        Ok(st) => (st.ok && !st.paused && st.serial_open == Some(true)).then_some(st),
+        Err(e) => {
+            debug!(err = %e, "bridge session probe failed");
+            None
+        }
+    }
+}
+
 pub fn pause_oc_bridge(opts: &BridgeControlOptions) -> BridgePause {
     if !opts.enabled || opts.method == BridgeControlMethod::None {
         return BridgePause {
@@ -211,29 +361,85 @@ pub fn pause_oc_bridge(opts: &BridgeControlOptions) -> BridgePause {
     }
 }
 
-fn pause_control_only(opts: &BridgeControlOptions) -> BridgePause {
-    match ipc::control_pause(opts.control_port, opts.control_timeout) {
-        Ok(()) => BridgePause {
-            guard: Some(BridgeGuard {
-                resume: Some(ResumePlan::Control {
-                    port: opts.control_port,
-                    timeout: opts.control_timeout,
-                }),
-                timeout: opts.timeout,
-            }),
-            outcome: BridgePauseOutcome::Paused(BridgePauseInfo {
-                method: BridgePauseMethod::Control,
-                id: format!("127.0.0.1:{}", opts.control_port),
-                pids: Vec::new(),
+/// Delay between IPC `pause` attempts.
+const CONTROL_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Floor for a single IPC attempt, so a nearly spent budget still gets a real try.
+const MIN_CONTROL_ATTEMPT: Duration = Duration::from_millis(50);
+
+/// IPC `pause`, retried up to `control_retries` times within one `control_timeout` budget.
+///
+/// Each attempt only gets the time left in the budget; no retry starts once it is spent.
+fn control_pause_with_retries(
+    opts: &BridgeControlOptions,
+) -> Result<BridgeControlStatus, BridgeControlError> {
+    let endpoint = opts.control_endpoint();
+    control_pause_with(opts, |timeout| ipc::control_pause(&endpoint, timeout))
+}
+
+/// The retry loop of [`control_pause_with_retries`], with the IPC call injected.
+fn control_pause_with<P>(
+    opts: &BridgeControlOptions,
+    mut pause: P,
+) -> Result<BridgeControlStatus, BridgeControlError>
+where
+    P: FnMut(Duration) -> Result<BridgeControlStatus, BridgeControlError>,
+{
+    let deadline = Instant::now() + opts.control_timeout;
+    let mut attempt = 0;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        debug!(
+            attempt,
+            retries = opts.control_retries,
+            remaining_ms = remaining.as_millis() as u64,
+            "control pause attempt"
+        );
+        let err = match pause(remaining.max(MIN_CONTROL_ATTEMPT)) {
+            Ok(ack) => return Ok(ack),
+            Err(e) => e,
+        };
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if attempt >= opts.control_retries || remaining <= CONTROL_RETRY_INTERVAL {
+            return Err(err);
+        }
+        attempt += 1;
+        debug!(err = %err, attempt, retries = opts.control_retries, "control pause failed; retrying");
+        std::thread::sleep(CONTROL_RETRY_INTERVAL);
+    }
+}
+
+fn control_paused(opts: &BridgeControlOptions, ack: BridgeControlStatus) -> BridgePause {
+    BridgePause {
+        guard: Some(BridgeGuard {
+            resume: Some(ResumePlan::Control {
+                endpoint: opts.control_endpoint(),
+                timeout: opts.control_timeout,
             }),
-        },
+            timeout: opts.timeout,
+            retries: opts.resume_retries,
+            retry_interval: opts.resume_retry_interval,
+        }),
+        outcome: BridgePauseOutcome::Paused(BridgePauseInfo {
+            method: BridgePauseMethod::Control,
+            id: opts.control_endpoint().id(),
+            pids: Vec::new(),
+            serial_open: ack.serial_open,
+            message: ack.message,
+        }),
+    }
+}
+
+fn pause_control_only(opts: &BridgeControlOptions) -> BridgePause {
+    match control_pause_with_retries(opts) {
+        Ok(ack) => control_paused(opts, ack),
         Err(e) => BridgePause {
             guard: None,
             outcome: BridgePauseOutcome::Failed(BridgeControlErrorInfo {
                 message: format!("oc-bridge control pause failed: {e}"),
                 hint: Some(format!(
-                    "Try: oc-bridge ctl pause --control-port {}",
-                    opts.control_port
+                    "Try: oc-bridge ctl pause {}",
+                    opts.control_endpoint().ctl_args()
                 )),
             }),
         },
@@ -250,11 +456,15 @@ fn pause_service_only(opts: &BridgeControlOptions, service_id: &str) -> BridgePa
                         id: service_id.to_string(),
                     }),
                     timeout: opts.timeout,
+                    retries: opts.resume_retries,
+                    retry_interval: opts.resume_retry_interval,
                 }),
                 outcome: BridgePauseOutcome::Paused(BridgePauseInfo {
                     method: BridgePauseMethod::Service,
                     id: service_id.to_string(),
                     pids: Vec::new(),
+                    serial_open: None,
+                    message: None,
                 }),
             },
             Err(e) => BridgePause {
@@ -295,7 +505,7 @@ fn pause_process_only(opts: &BridgeControlOptions) -> BridgePause {
         };
     }
 
-    match process::pause_process_fallback(opts.timeout) {
+    match process::pause_process_fallback(opts.timeout, opts.relaunch_args.as_deref()) {
         process::ProcessPauseOutcome::Paused {
             info,
             relaunch_cmds,
@@ -305,6 +515,8 @@ fn pause_process_only(opts: &BridgeControlOptions) -> BridgePause {
                     cmds: relaunch_cmds,
                 }),
                 timeout: opts.timeout,
+                retries: opts.resume_retries,
+                retry_interval: opts.resume_retry_interval,
             }),
             outcome: BridgePauseOutcome::Paused(info),
         },
@@ -336,21 +548,8 @@ fn pause_auto(opts: &BridgeControlOptions, service_id: &str) -> BridgePause {
         "pause auto"
     );
     // Prefer IPC pause/resume.
-    if let Ok(()) = ipc::control_pause(opts.control_port, opts.control_timeout) {
-        return BridgePause {
-            guard: Some(BridgeGuard {
-                resume: Some(ResumePlan::Control {
-                    port: opts.control_port,
-                    timeout: opts.control_timeout,
-                }),
-                timeout: opts.timeout,
-            }),
-            outcome: BridgePauseOutcome::Paused(BridgePauseInfo {
-                method: BridgePauseMethod::Control,
-                id: format!("127.0.0.1:{}", opts.control_port),
-                pids: Vec::new(),
-            }),
-        };
+    if let Ok(ack) = control_pause_with_retries(opts) {
+        return control_paused(opts, ack);
     }
 
     // Service-first.
@@ -363,11 +562,15 @@ fn pause_auto(opts: &BridgeControlOptions, service_id: &str) -> BridgePause {
                             id: service_id.to_string(),
                         }),
                         timeout: opts.timeout,
+                        retries: opts.resume_retries,
+                        retry_interval: opts.resume_retry_interval,
                     }),
                     outcome: BridgePauseOutcome::Paused(BridgePauseInfo {
                         method: BridgePauseMethod::Service,
                         id: service_id.to_string(),
                         pids: Vec::new(),
+                        serial_open: None,
+                        message: None,
                     }),
                 };
             }
@@ -409,7 +612,7 @@ fn pause_auto(opts: &BridgeControlOptions, service_id: &str) -> BridgePause {
     }
 
     // Process fallback (only if restartable).
-    match process::pause_process_fallback(opts.timeout) {
+    match process::pause_process_fallback(opts.timeout, opts.relaunch_args.as_deref()) {
         process::ProcessPauseOutcome::Paused {
             info,
             relaunch_cmds,
@@ -419,6 +622,8 @@ fn pause_auto(opts: &BridgeControlOptions, service_id: &str) -> BridgePause {
                     cmds: relaunch_cmds,
                 }),
                 timeout: opts.timeout,
+                retries: opts.resume_retries,
+                retry_interval: opts.resume_retry_interval,
             }),
             outcome: BridgePauseOutcome::Paused(info),
         },
@@ -435,8 +640,140 @@ fn pause_auto(opts: &BridgeControlOptions, service_id: &str) -> BridgePause {
 
 fn resume(plan: ResumePlan, timeout: Duration) -> Result<(), BridgeControlError> {
     match plan {
-        ResumePlan::Control { port, timeout } => ipc::control_resume(port, timeout),
+        ResumePlan::Control { endpoint, timeout } => ipc::control_resume(&endpoint, timeout),
         ResumePlan::Service { id } => service::start_service(&id, timeout),
         ResumePlan::Processes { cmds } => process::resume_processes(&cmds),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn control_pause_retries_stay_within_control_timeout() {
+        // Grab a free port, then close it so every attempt is refused.
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let opts = BridgeControlOptions {
+            control_port: port,
+            control_timeout: Duration::from_millis(350),
+            control_retries: 1000,
+            ..Default::default()
+        };
+
+        let start = Instant::now();
+        assert!(control_pause_with_retries(&opts).is_err());
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    fn paused_ack() -> BridgeControlStatus {
+        BridgeControlStatus {
+            ok: true,
+            paused: true,
+            serial_open: Some(false),
+            message: None,
+            client_count: None,
+            uptime_ms: None,
+            serial_port: None,
+        }
+    }
+
+    #[test]
+    fn control_pause_succeeds_on_a_later_attempt() {
+        let opts = BridgeControlOptions {
+            control_timeout: Duration::from_secs(5),
+            control_retries: 2,
+            ..Default::default()
+        };
+        let mut attempts = 0;
+        let r = control_pause_with(&opts, |_| {
+            attempts += 1;
+            if attempts == 1 {
+                Err(BridgeControlError::Timeout)
+            } else {
+                Ok(paused_ack())
+            }
+        });
+        assert!(r.is_ok());
+        assert_eq!(attempts, 2);
+    }
+
+    #[test]
+    fn control_pause_stops_when_the_budget_is_spent() {
+        // 125ms left after the first attempt: one retry, started with ~25ms of budget.
+        let opts = BridgeControlOptions {
+            control_timeout: Duration::from_millis(325),
+            control_retries: 1000,
+            ..Default::default()
+        };
+        let mut timeouts = Vec::new();
+        let r = control_pause_with(&opts, |timeout| {
+            if timeouts.is_empty() {
+                std::thread::sleep(Duration::from_millis(200));
+            }
+            timeouts.push(timeout);
+            Err(BridgeControlError::Timeout)
+        });
+        assert!(matches!(r, Err(BridgeControlError::Timeout)));
+        assert_eq!(timeouts.len(), 2, "{timeouts:?}");
+        assert!(timeouts[0] <= opts.control_timeout);
+        assert_eq!(timeouts[1], MIN_CONTROL_ATTEMPT);
+    }
+
+    fn service_guard(retries: u32) -> BridgeGuard {
+        BridgeGuard {
+            resume: Some(ResumePlan::Service {
+                id: "open-control-bridge".to_string(),
+            }),
+            timeout: Duration::from_millis(1),
+            retries,
+            retry_interval: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn resume_succeeds_on_attempt_2() {
+        let mut guard = service_guard(2);
+        let mut calls = 0;
+        let mut retried = Vec::new();
+        let r = guard.resume_with(
+            |_, _| {
+                calls += 1;
+                if calls == 1 {
+                    Err(BridgeControlError::Timeout)
+                } else {
+                    Ok(())
+                }
+            },
+            |attempt, retries, _| retried.push((attempt, retries)),
+        );
+        assert!(r.is_ok());
+        assert_eq!(calls, 2);
+        assert_eq!(retried, [(1, 2)]);
+        assert!(guard.resume.is_none());
+    }
+
+    #[test]
+    fn resume_keeps_the_plan_once_retries_are_exhausted() {
+        let mut guard = service_guard(2);
+        let mut calls = 0;
+        let mut retried = Vec::new();
+        let r = guard.resume_with(
+            |_, _| {
+                calls += 1;
+                Err(BridgeControlError::Timeout)
+            },
+            |attempt, retries, _| retried.push((attempt, retries)),
+        );
+        assert!(matches!(r, Err(BridgeControlError::Timeout)));
+        assert_eq!(calls, 3);
+        assert_eq!(retried, [(1, 2), (2, 2)]);
+        // Still armed: the hint is available and Drop tries once more.
+        assert!(guard.resume_hint().is_some());
+        guard.resume = None;
+    }
+}
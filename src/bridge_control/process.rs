@@ -54,7 +54,10 @@ pub fn list_oc_bridge_processes() -> Vec<OcBridgeProcessInfo> {
 }
 
 #[cfg(not(feature = "process-fallback"))]
-pub(super) fn pause_process_fallback(_timeout: Duration) -> ProcessPauseOutcome {
+pub(super) fn pause_process_fallback(
+    _timeout: Duration,
+    _relaunch_args: Option<&[String]>,
+) -> ProcessPauseOutcome {
     // Build without sysinfo process support: we cannot safely stop/relaunch processes.
     ProcessPauseOutcome::Skipped(BridgePauseSkipReason::ProcessNotRestartable)
 }
@@ -83,7 +86,10 @@ pub fn list_oc_bridge_processes() -> Vec<OcBridgeProcessInfo> {
 }
 
 #[cfg(feature = "process-fallback")]
-pub(super) fn pause_process_fallback(timeout: Duration) -> ProcessPauseOutcome {
+pub(super) fn pause_process_fallback(
+    timeout: Duration,
+    relaunch_args: Option<&[String]>,
+) -> ProcessPauseOutcome {
     use sysinfo::{ProcessRefreshKind, RefreshKind, System, UpdateKind};
 
     // Process fallback (only if restartable).
@@ -111,12 +117,7 @@ pub(super) fn pause_process_fallback(timeout: Duration) -> ProcessPauseOutcome {
             return ProcessPauseOutcome::Skipped(BridgePauseSkipReason::ProcessNotRestartable);
         };
 
-        let args = p
-            .cmd
-            .clone()
-            .unwrap_or_else(|| vec!["--daemon".to_string(), "--no-relaunch".to_string()]);
-
-        relaunch_cmds.push(RelaunchCmd { exe, args });
+        relaunch_cmds.push(relaunch_cmd(exe, p.cmd.clone(), relaunch_args));
     }
 
     // Terminate all oc-bridge processes.
@@ -131,6 +132,8 @@ pub(super) fn pause_process_fallback(timeout: Duration) -> ProcessPauseOutcome {
         method: BridgePauseMethod::Process,
         id: "oc-bridge".to_string(),
         pids,
+        serial_open: None,
+        message: None,
     };
 
     ProcessPauseOutcome::Paused {
@@ -139,6 +142,20 @@ pub(super) fn pause_process_fallback(timeout: Duration) -> ProcessPauseOutcome {
     }
 }
 
+/// Relaunch `exe` with its captured argv, else `relaunch_args`, else a best guess.
+#[cfg(feature = "process-fallback")]
+fn relaunch_cmd(
+    exe: PathBuf,
+    cmd: Option<Vec<String>>,
+    relaunch_args: Option<&[String]>,
+) -> RelaunchCmd {
+    let args = cmd.unwrap_or_else(|| match relaunch_args {
+        Some(args) => args.to_vec(),
+        None => vec!["--daemon".to_string(), "--no-relaunch".to_string()],
+    });
+    RelaunchCmd { exe, args }
+}
+
 #[cfg(feature = "process-fallback")]
 #[derive(Debug, Clone)]
 struct OcBridgeProcess {
@@ -229,3 +246,25 @@ fn get_process_by_pid(system: &sysinfo::System, pid_u32: u32) -> Option<&sysinfo
         }
     })
 }
+
+#[cfg(all(test, feature = "process-fallback"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relaunch_args_override_only_the_guess() {
+        let exe = PathBuf::from("/usr/bin/oc-bridge");
+        let custom = vec!["--config".to_string(), "/etc/oc-bridge.toml".to_string()];
+
+        let cmd = relaunch_cmd(exe.clone(), None, Some(&custom));
+        assert_eq!(cmd.exe, exe);
+        assert_eq!(cmd.args, custom);
+
+        let captured = vec!["--port".to_string(), "7999".to_string()];
+        let cmd = relaunch_cmd(exe.clone(), Some(captured.clone()), Some(&custom));
+        assert_eq!(cmd.args, captured);
+
+        let cmd = relaunch_cmd(exe, None, None);
+        assert_eq!(cmd.args, ["--daemon", "--no-relaunch"]);
+    }
+}
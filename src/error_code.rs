@@ -0,0 +1,29 @@
+//! Stable numeric error codes for FFI consumers.
+//!
+//! These values are part of the public contract: they never change meaning and are never
+//! reused. They are independent of the CLI process exit codes. Codes are shared between
+//! `FlashError` and `RebootError` where the failure means the same thing.
+
+pub const NO_TARGETS: u32 = 1;
+pub const TARGET_NOT_FOUND: u32 = 2;
+pub const AMBIGUOUS_TARGET: u32 = 3;
+pub const DISCOVERY_FAILED: u32 = 4;
+pub const INVALID_HEX: u32 = 5;
+pub const BRIDGE_PAUSE_FAILED: u32 = 6;
+pub const SOFT_REBOOT_FAILED: u32 = 7;
+pub const OPEN_HALFKAY_FAILED: u32 = 8;
+pub const WRITE_FAILED: u32 = 9;
+pub const REOPEN_FAILED: u32 = 10;
+pub const MULTI_TARGET_FAILED: u32 = 11;
+pub const HALFKAY_TIMEOUT: u32 = 12;
+pub const CANCELLED: u32 = 13;
+pub const BOOT_NOT_CONFIRMED: u32 = 14;
+pub const NO_FIRMWARE_MAPPED: u32 = 15;
+pub const SERIAL_MISMATCH: u32 = 16;
+pub const BOOT_FAILED: u32 = 17;
+pub const SERIAL_REBOOT_FORBIDDEN: u32 = 18;
+pub const VERIFY_FAILED: u32 = 19;
+pub const READBACK_FAILED: u32 = 20;
+pub const OPERATION_TIMEOUT: u32 = 21;
+pub const BOARD_MISMATCH: u32 = 22;
+pub const UNEXPECTED: u32 = 255;
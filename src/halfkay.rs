@@ -5,6 +5,8 @@ use hidapi::HidApi;
 use hidapi::HidDevice;
 #[cfg(not(windows))]
 use std::ffi::CString;
+#[cfg(not(windows))]
+use std::sync::mpsc;
 use thiserror::Error;
 
 use crate::{hex::FirmwareImage, teensy41};
@@ -18,11 +20,32 @@ const FAST_BLOCK_TIMEOUT: Duration = Duration::from_millis(500);
 #[cfg(not(windows))]
 const RETRY_SLEEP: Duration = Duration::from_millis(10);
 
-fn block_total_timeout(write_index: usize) -> Duration {
+/// Interval between post-boot probes in [`boot_teensy41_confirmed`].
+const BOOT_PROBE_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Per-block write deadlines; the default is the PJRC policy above (45s, then 500ms).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteTimeouts {
+    /// Deadline for the first few blocks, which wait for the flash erase.
+    pub erase: Duration,
+    /// Deadline for every later block.
+    pub write: Duration,
+}
+
+impl Default for WriteTimeouts {
+    fn default() -> Self {
+        Self {
+            erase: SLOW_BLOCK_TIMEOUT,
+            write: FAST_BLOCK_TIMEOUT,
+        }
+    }
+}
+
+fn block_total_timeout(write_index: usize, timeouts: &WriteTimeouts) -> Duration {
     if write_index <= SLOW_BLOCK_MAX_INDEX {
-        SLOW_BLOCK_TIMEOUT
+        timeouts.erase
     } else {
-        FAST_BLOCK_TIMEOUT
+        timeouts.write
     }
 }
 
@@ -47,11 +70,22 @@ mod win32;
 #[cfg(windows)]
 use win32::Win32HalfKayDevice;
 
+/// Options controlling how a HalfKay HID handle is opened.
+#[derive(Debug, Clone, Default)]
+pub struct OpenOptions {
+    /// Open without sharing so no other process can use the device mid-flash.
+    ///
+    /// Windows only; ignored elsewhere.
+    pub exclusive: bool,
+}
+
 #[derive(serde::Serialize)]
 pub struct HalfKayDeviceSummary {
     pub vid: u16,
     pub pid: u16,
     pub path: String,
+    /// USB `bcdDevice`: on HalfKay, PJRC's board code (0x0279 = Teensy 4.0, 0x0280 = 4.1).
+    pub release_number: u16,
 }
 
 pub struct HalfKayDevice {
@@ -61,12 +95,69 @@ pub struct HalfKayDevice {
 
 enum Backend {
     #[cfg(not(windows))]
-    HidApi(HidDevice),
+    HidApi(HidWriter),
+    /// The handle is stuck in a call that timed out (see [`write_hid_bounded`]); reopen.
+    #[cfg(not(windows))]
+    Detached,
     #[cfg(windows)]
     Win32(Win32HalfKayDevice),
 }
 
+/// A hidapi handle owned by one writer thread for the lifetime of the [`HalfKayDevice`].
+///
+/// `hidapi` writes block with no timeout, so every call goes through the thread and the caller
+/// waits with `recv_timeout`. Dropping the writer closes the channel and ends the thread once
+/// its current call returns.
+#[cfg(not(windows))]
+struct HidWriter {
+    requests: mpsc::Sender<HidRequest>,
+    replies: mpsc::Receiver<hidapi::HidResult<usize>>,
+}
+
+#[cfg(not(windows))]
+enum HidRequest {
+    Write(Vec<u8>),
+    /// Zero-timeout read, see [`still_attached`].
+    Probe,
+}
+
+#[cfg(not(windows))]
+impl HidWriter {
+    fn spawn(h: HidDevice) -> Self {
+        let (requests, rx) = mpsc::channel::<HidRequest>();
+        let (tx, replies) = mpsc::channel();
+        std::thread::spawn(move || {
+            for req in rx {
+                let r = match req {
+                    HidRequest::Write(report) => h.write(&report),
+                    HidRequest::Probe => h.read_timeout(&mut [0u8; 1], 0),
+                };
+                if tx.send(r).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { requests, replies }
+    }
+
+    /// Run `req` on the writer thread, waiting at most `timeout` for its result.
+    fn call(
+        &self,
+        req: HidRequest,
+        timeout: Duration,
+    ) -> Result<hidapi::HidResult<usize>, HalfKayError> {
+        self.requests
+            .send(req)
+            .map_err(|_| HalfKayError::NoDevice)?;
+        self.replies.recv_timeout(timeout).map_err(|e| match e {
+            mpsc::RecvTimeoutError::Timeout => HalfKayError::Timeout,
+            mpsc::RecvTimeoutError::Disconnected => HalfKayError::NoDevice,
+        })
+    }
+}
+
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum HalfKayError {
     #[error("hid: {0}")]
     Hid(#[from] hidapi::HidError),
@@ -87,6 +178,28 @@ pub enum HalfKayError {
 
     #[error("no HalfKay device found")]
     NoDevice,
+
+    #[error("HalfKay device is in use by another process")]
+    Busy,
+
+    #[error("HID write did not complete in time")]
+    Timeout,
+}
+
+impl HalfKayError {
+    /// The OS refused access to the device node (on Linux: missing udev rules).
+    pub fn is_permission_denied(&self) -> bool {
+        match self {
+            HalfKayError::Hid(hidapi::HidError::IoError { error }) => {
+                error.kind() == std::io::ErrorKind::PermissionDenied
+            }
+            // hidraw reports open failures as text: "...: Permission denied".
+            HalfKayError::Hid(hidapi::HidError::HidApiError { message }) => {
+                message.contains("Permission denied")
+            }
+            _ => false,
+        }
+    }
 }
 
 pub fn list_devices() -> Result<Vec<HalfKayDeviceSummary>, HalfKayError> {
@@ -98,6 +211,7 @@ pub fn list_devices() -> Result<Vec<HalfKayDeviceSummary>, HalfKayError> {
                 vid: d.vendor_id(),
                 pid: d.product_id(),
                 path: d.path().to_string_lossy().to_string(),
+                release_number: d.release_number(),
             });
         }
     }
@@ -116,21 +230,167 @@ pub fn list_paths() -> Result<Vec<String>, HalfKayError> {
     Ok(out)
 }
 
+/// USB serial number reported by the HalfKay device at `path`, if any.
+///
+/// HalfKay reports the board serial in hex, unlike the decimal string of the running firmware.
+pub fn serial_number_for_path(path: &str) -> Result<Option<String>, HalfKayError> {
+    Ok(list_serial_numbers()?
+        .into_iter()
+        .find(|(p, _)| p == path)
+        .and_then(|(_, serial)| serial))
+}
+
+/// Every HalfKay path with its USB serial number (see [`serial_number_for_path`]).
+pub fn list_serial_numbers() -> Result<Vec<(String, Option<String>)>, HalfKayError> {
+    let api = HidApi::new()?;
+    let mut out: Vec<(String, Option<String>)> = api
+        .device_list()
+        .filter(|d| d.vendor_id() == teensy41::VID && d.product_id() == teensy41::PID_HALFKAY)
+        .map(|d| {
+            let serial = d
+                .serial_number()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty());
+            (d.path().to_string_lossy().to_string(), serial)
+        })
+        .collect();
+    out.sort();
+    Ok(out)
+}
+
+/// Compare a running Teensy's USB serial (decimal) with its HalfKay serial (hex).
+///
+/// Some bootloader versions report the serial divided by 10.
+pub fn serial_matches(usb: &str, halfkay: &str) -> bool {
+    match (usb.parse::<u64>(), u64::from_str_radix(halfkay, 16)) {
+        (Ok(usb), Ok(hk)) => usb == hk || Some(usb) == hk.checked_mul(10),
+        _ => usb.eq_ignore_ascii_case(halfkay),
+    }
+}
+
+/// Raw HID details of a HalfKay device, for diagnosing boards that enumerate but reject writes.
+#[derive(Debug, Clone)]
+pub struct HalfKayHidInfo {
+    pub vid: u16,
+    pub pid: u16,
+    pub path: String,
+    pub release_number: u16,
+    pub usage_page: u16,
+    pub usage: u16,
+    pub interface_number: i32,
+
+    /// Raw report descriptor (empty when it could not be read).
+    pub report_descriptor: Vec<u8>,
+    pub report_descriptor_error: Option<String>,
+    pub report_sizes: HidReportSizes,
+}
+
+/// Total report sizes declared by a HID report descriptor, in bits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HidReportSizes {
+    pub input_bits: u32,
+    pub output_bits: u32,
+    pub feature_bits: u32,
+}
+
+pub fn describe_devices() -> Result<Vec<HalfKayHidInfo>, HalfKayError> {
+    let api = HidApi::new()?;
+    let mut out: Vec<HalfKayHidInfo> = Vec::new();
+    for d in api.device_list() {
+        if d.vendor_id() != teensy41::VID || d.product_id() != teensy41::PID_HALFKAY {
+            continue;
+        }
+
+        let mut buf = [0u8; 4096];
+        let (report_descriptor, report_descriptor_error) = match api
+            .open_path(d.path())
+            .and_then(|dev| dev.get_report_descriptor(&mut buf))
+        {
+            Ok(n) => (buf[..n].to_vec(), None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+
+        out.push(HalfKayHidInfo {
+            vid: d.vendor_id(),
+            pid: d.product_id(),
+            path: d.path().to_string_lossy().to_string(),
+            release_number: d.release_number(),
+            usage_page: d.usage_page(),
+            usage: d.usage(),
+            interface_number: d.interface_number(),
+            report_sizes: parse_report_sizes(&report_descriptor),
+            report_descriptor,
+            report_descriptor_error,
+        });
+    }
+    out.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(out)
+}
+
+/// Sum the Input/Output/Feature report sizes declared by a HID report descriptor.
+///
+/// Only tracks Report Size/Count globals (no Push/Pop), which covers HalfKay descriptors.
+pub fn parse_report_sizes(desc: &[u8]) -> HidReportSizes {
+    let mut sizes = HidReportSizes::default();
+    let mut report_size: u32 = 0;
+    let mut report_count: u32 = 0;
+
+    let mut i = 0;
+    while i < desc.len() {
+        let prefix = desc[i];
+        if prefix == 0xFE {
+            // Long item: [0xFE, size, tag, data...]
+            let size = desc.get(i + 1).copied().unwrap_or(0) as usize;
+            i += 3 + size;
+            continue;
+        }
+
+        let size = match prefix & 0x03 {
+            3 => 4,
+            n => n as usize,
+        };
+        let data = desc.get(i + 1..i + 1 + size).unwrap_or(&[]);
+        let value = data
+            .iter()
+            .rev()
+            .fold(0u32, |acc, b| (acc << 8) | *b as u32);
+
+        // Descriptors come from the device: saturate rather than overflow on bogus sizes.
+        let bits = report_size.saturating_mul(report_count);
+        match prefix & 0xFC {
+            0x74 => report_size = value,
+            0x94 => report_count = value,
+            0x80 => sizes.input_bits = sizes.input_bits.saturating_add(bits),
+            0x90 => sizes.output_bits = sizes.output_bits.saturating_add(bits),
+            0xB0 => sizes.feature_bits = sizes.feature_bits.saturating_add(bits),
+            _ => {}
+        }
+
+        i += 1 + size;
+    }
+    sizes
+}
+
 pub fn open_by_path(path: &str) -> Result<HalfKayDevice, HalfKayError> {
+    open_by_path_with(path, &OpenOptions::default())
+}
+
+pub fn open_by_path_with(path: &str, opts: &OpenOptions) -> Result<HalfKayDevice, HalfKayError> {
     #[cfg(not(windows))]
     {
+        let _ = opts;
         let api = HidApi::new()?;
         let cpath = CString::new(path).map_err(|_| HalfKayError::InvalidPath)?;
         let dev = api.open_path(&cpath)?;
         Ok(HalfKayDevice {
-            backend: Backend::HidApi(dev),
+            backend: Backend::HidApi(HidWriter::spawn(dev)),
             path: path.to_string(),
         })
     }
 
     #[cfg(windows)]
     {
-        let dev = Win32HalfKayDevice::open_hid_path(path)?;
+        let dev = Win32HalfKayDevice::open_hid_path(path, opts)?;
         Ok(HalfKayDevice {
             backend: Backend::Win32(dev),
             path: path.to_string(),
@@ -157,14 +417,14 @@ pub fn open_halfkay_device(
             {
                 let dev = api.open_path(dev.path())?;
                 return Ok(HalfKayDevice {
-                    backend: Backend::HidApi(dev),
+                    backend: Backend::HidApi(HidWriter::spawn(dev)),
                     path,
                 });
             }
 
             #[cfg(windows)]
             {
-                let dev = Win32HalfKayDevice::open_hid_path(&path)?;
+                let dev = Win32HalfKayDevice::open_hid_path(&path, &OpenOptions::default())?;
                 return Ok(HalfKayDevice {
                     backend: Backend::Win32(dev),
                     path,
@@ -189,6 +449,18 @@ pub fn write_block_teensy41(
     fw: &FirmwareImage,
     block_addr: usize,
     write_index: usize,
+) -> Result<(), HalfKayError> {
+    write_block_teensy41_with(dev, fw, block_addr, write_index, &WriteTimeouts::default())
+}
+
+/// Like [`write_block_teensy41`], retrying (or, on Windows, waiting) up to the `timeouts`
+/// deadline of block `write_index`.
+pub fn write_block_teensy41_with(
+    dev: &mut HalfKayDevice,
+    fw: &FirmwareImage,
+    block_addr: usize,
+    write_index: usize,
+    timeouts: &WriteTimeouts,
 ) -> Result<(), HalfKayError> {
     let end = block_addr + teensy41::BLOCK_SIZE;
     let mut report = [0u8; teensy41::PACKET_SIZE + 1];
@@ -196,19 +468,14 @@ pub fn write_block_teensy41(
 
     match &dev.backend {
         #[cfg(not(windows))]
-        Backend::HidApi(_) => {
-            let timeout = block_total_timeout(write_index);
+        Backend::HidApi(_) | Backend::Detached => {
+            let timeout = block_total_timeout(write_index, timeouts);
             let start = Instant::now();
             let mut last_reopen = Instant::now();
 
             loop {
-                // Keep borrows short so we can best-effort reopen between attempts.
-                let r = match &dev.backend {
-                    Backend::HidApi(h) => h.write(&report).map_err(HalfKayError::Hid),
-                    #[allow(unreachable_patterns)]
-                    _ => return Err(HalfKayError::NoDevice),
-                };
-                match r {
+                let remaining = timeout.saturating_sub(start.elapsed());
+                match write_hid_bounded(dev, &report, remaining.max(RETRY_SLEEP)) {
                     Ok(n) => {
                         if n != report.len() {
                             return Err(HalfKayError::ShortWrite {
@@ -226,7 +493,9 @@ pub fn write_block_teensy41(
                         // If the HID pipe broke, reopening often recovers immediately.
                         // Throttle reopen attempts to avoid making success cases slower.
                         let is_broken_pipe = err.to_string().contains("Broken pipe");
-                        if is_broken_pipe && last_reopen.elapsed() >= Duration::from_millis(100) {
+                        let needs_reopen =
+                            is_broken_pipe || matches!(dev.backend, Backend::Detached);
+                        if needs_reopen && last_reopen.elapsed() >= Duration::from_millis(100) {
                             reopen_best_effort(dev);
                             last_reopen = Instant::now();
                         }
@@ -239,7 +508,7 @@ pub fn write_block_teensy41(
 
         #[cfg(windows)]
         Backend::Win32(h) => {
-            let total_timeout_ms: u32 = block_total_timeout(write_index)
+            let total_timeout_ms: u32 = block_total_timeout(write_index, timeouts)
                 .as_millis()
                 .try_into()
                 .unwrap_or(u32::MAX);
@@ -248,6 +517,83 @@ pub fn write_block_teensy41(
     }
 }
 
+/// Write `report` through the device's [`HidWriter`], so a device wedged mid-erase cannot
+/// hang the caller. If the write has not returned after `timeout`, the handle is left to the
+/// writer thread, `dev` becomes `Detached` and this fails with `Timeout`.
+#[cfg(not(windows))]
+fn write_hid_bounded(
+    dev: &mut HalfKayDevice,
+    report: &[u8],
+    timeout: Duration,
+) -> Result<usize, HalfKayError> {
+    let Backend::HidApi(w) = &dev.backend else {
+        return Err(HalfKayError::NoDevice);
+    };
+    match w.call(HidRequest::Write(report.to_vec()), timeout) {
+        Ok(r) => r.map_err(HalfKayError::Hid),
+        Err(e) => {
+            dev.backend = Backend::Detached;
+            Err(e)
+        }
+    }
+}
+
+/// What [`boot_teensy41_confirmed`] inferred from the device after the boot command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootOutcome {
+    /// The HalfKay handle went away: the board left the bootloader.
+    Confirmed,
+    /// HalfKay still answered after the timeout: the board did not boot.
+    NotConfirmed,
+}
+
+/// Like [`boot_teensy41`], then probes the handle until it invalidates or `timeout` elapses.
+///
+/// A successful boot detaches HalfKay, so a failing probe confirms it; a handle that still
+/// works after `timeout` means the boot command was ignored.
+pub fn boot_teensy41_confirmed(
+    dev: &mut HalfKayDevice,
+    timeout: Duration,
+) -> Result<BootOutcome, HalfKayError> {
+    boot_teensy41(dev)?;
+    let start = Instant::now();
+    loop {
+        if !still_attached(dev) {
+            return Ok(BootOutcome::Confirmed);
+        }
+        if start.elapsed() >= timeout {
+            return Ok(BootOutcome::NotConfirmed);
+        }
+        std::thread::sleep(BOOT_PROBE_INTERVAL);
+    }
+}
+
+/// Non-destructive liveness probe of an open HalfKay handle.
+fn still_attached(dev: &mut HalfKayDevice) -> bool {
+    #[cfg(not(windows))]
+    {
+        // HalfKay never sends input reports: a zero-timeout read returns Ok(0) while the
+        // device is attached and an error once it has been removed.
+        match &dev.backend {
+            Backend::HidApi(w) => match w.call(HidRequest::Probe, FAST_BLOCK_TIMEOUT) {
+                Ok(r) => r.is_ok(),
+                // A late reply would answer the next call: drop the writer, as for writes.
+                Err(_) => {
+                    dev.backend = Backend::Detached;
+                    true
+                }
+            },
+            Backend::Detached => list_paths().is_ok_and(|paths| paths.contains(&dev.path)),
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        // The overlapped Win32 handle has no cheap read; check enumeration instead.
+        list_paths().map_or(true, |paths| paths.contains(&dev.path))
+    }
+}
+
 pub fn boot_teensy41(dev: &mut HalfKayDevice) -> Result<(), HalfKayError> {
     let mut report = [0u8; teensy41::PACKET_SIZE + 1];
     fill_boot_report_teensy41(&mut report);
@@ -259,13 +605,8 @@ pub fn boot_teensy41(dev: &mut HalfKayDevice) -> Result<(), HalfKayError> {
         let mut last_reopen = Instant::now();
 
         loop {
-            let r = match &dev.backend {
-                Backend::HidApi(h) => h.write(&report).map_err(HalfKayError::Hid),
-                #[allow(unreachable_patterns)]
-                _ => return Ok(()),
-            };
-
-            match r {
+            let remaining = timeout.saturating_sub(start.elapsed());
+            match write_hid_bounded(dev, &report, remaining.max(RETRY_SLEEP)) {
                 Ok(_) => return Ok(()),
                 Err(err) => {
                     if start.elapsed() >= timeout {
@@ -338,6 +679,37 @@ pub fn build_boot_report_teensy41() -> Vec<u8> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn permission_denied_is_recognized() {
+        let hid = |message: &str| {
+            HalfKayError::Hid(hidapi::HidError::HidApiError {
+                message: message.to_string(),
+            })
+        };
+        assert!(
+            hid("Failed to open a device with path '/dev/hidraw3': Permission denied")
+                .is_permission_denied()
+        );
+        assert!(
+            !hid("Failed to open a device with path '/dev/hidraw3': No such device")
+                .is_permission_denied()
+        );
+        assert!(HalfKayError::Hid(hidapi::HidError::IoError {
+            error: std::io::ErrorKind::PermissionDenied.into(),
+        })
+        .is_permission_denied());
+        assert!(!HalfKayError::Busy.is_permission_denied());
+    }
+
+    #[test]
+    fn serial_matches_hex_and_scaled_forms() {
+        // 0x9DF5A = 647002
+        assert!(serial_matches("647002", "0009DF5A"));
+        assert!(serial_matches("6470020", "9DF5A"));
+        assert!(!serial_matches("647003", "0009DF5A"));
+        assert!(serial_matches("ABC-1", "abc-1"));
+    }
+
     #[test]
     fn test_block_report_format() {
         let block_addr = 0x0012_3400usize;
@@ -381,15 +753,62 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_report_sizes_reads_halfkay_output_report() {
+        let desc = [
+            0x06, 0x9C, 0xFF, // Usage Page (vendor 0xFF9C)
+            0x09, 0x21, // Usage
+            0xA1, 0x01, // Collection (Application)
+            0x75, 0x08, // Report Size (8)
+            0x15, 0x00, // Logical Minimum (0)
+            0x26, 0xFF, 0x00, // Logical Maximum (255)
+            0x96, 0x40, 0x04, // Report Count (1088)
+            0x09, 0x22, // Usage
+            0x91, 0x02, // Output (Data, Var, Abs)
+            0xC0, // End Collection
+        ];
+        let sizes = parse_report_sizes(&desc);
+        assert_eq!(sizes.output_bits as usize, teensy41::PACKET_SIZE * 8);
+        assert_eq!(sizes.input_bits, 0);
+        assert_eq!(sizes.feature_bits, 0);
+    }
+
+    #[test]
+    fn parse_report_sizes_saturates_on_huge_reports() {
+        let desc = [
+            0x77, 0xFF, 0xFF, 0xFF, 0xFF, // Report Size (u32::MAX)
+            0x97, 0xFF, 0xFF, 0xFF, 0xFF, // Report Count (u32::MAX)
+            0x91, 0x02, // Output
+            0x91, 0x02, // Output
+            0x75, 0x08, // Report Size (8)
+            0x95, 0x02, // Report Count (2)
+            0x81, 0x02, // Input
+        ];
+        let sizes = parse_report_sizes(&desc);
+        assert_eq!(sizes.output_bits, u32::MAX);
+        assert_eq!(sizes.input_bits, 16);
+    }
+
     #[test]
     fn test_block_total_timeout_matches_pjrc_policy() {
+        let pjrc = WriteTimeouts::default();
         for i in 0..=SLOW_BLOCK_MAX_INDEX {
-            assert_eq!(block_total_timeout(i), SLOW_BLOCK_TIMEOUT);
+            assert_eq!(block_total_timeout(i, &pjrc), SLOW_BLOCK_TIMEOUT);
         }
         assert_eq!(
-            block_total_timeout(SLOW_BLOCK_MAX_INDEX + 1),
+            block_total_timeout(SLOW_BLOCK_MAX_INDEX + 1, &pjrc),
             FAST_BLOCK_TIMEOUT
         );
-        assert_eq!(block_total_timeout(9999), FAST_BLOCK_TIMEOUT);
+        assert_eq!(block_total_timeout(9999, &pjrc), FAST_BLOCK_TIMEOUT);
+
+        let custom = WriteTimeouts {
+            erase: Duration::from_secs(90),
+            write: Duration::from_millis(200),
+        };
+        assert_eq!(block_total_timeout(0, &custom), Duration::from_secs(90));
+        assert_eq!(
+            block_total_timeout(SLOW_BLOCK_MAX_INDEX + 1, &custom),
+            Duration::from_millis(200)
+        );
     }
 }
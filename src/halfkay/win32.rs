@@ -4,8 +4,8 @@ use std::os::windows::ffi::OsStrExt;
 use std::time::{Duration, Instant};
 
 use windows_sys::Win32::Foundation::{
-    CloseHandle, GetLastError, GENERIC_READ, GENERIC_WRITE, HANDLE, INVALID_HANDLE_VALUE,
-    WAIT_FAILED, WAIT_OBJECT_0, WAIT_TIMEOUT,
+    CloseHandle, GetLastError, ERROR_SHARING_VIOLATION, GENERIC_READ, GENERIC_WRITE, HANDLE,
+    INVALID_HANDLE_VALUE, WAIT_FAILED, WAIT_OBJECT_0, WAIT_TIMEOUT,
 };
 use windows_sys::Win32::Storage::FileSystem::{
     CreateFileW, WriteFile, FILE_FLAG_OVERLAPPED, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
@@ -16,7 +16,7 @@ use windows_sys::Win32::System::Diagnostics::Debug::{
 use windows_sys::Win32::System::Threading::{CreateEventW, ResetEvent, WaitForSingleObject};
 use windows_sys::Win32::System::IO::{CancelIoEx, GetOverlappedResult, OVERLAPPED};
 
-use super::HalfKayError;
+use super::{HalfKayError, OpenOptions};
 
 pub struct Win32HalfKayDevice {
     handle: HANDLE,
@@ -24,7 +24,7 @@ pub struct Win32HalfKayDevice {
 }
 
 impl Win32HalfKayDevice {
-    pub fn open_hid_path(path: &str) -> Result<Self, HalfKayError> {
+    pub fn open_hid_path(path: &str, opts: &OpenOptions) -> Result<Self, HalfKayError> {
         let wide: Vec<u16> = OsStr::new(path)
             .encode_wide()
             .chain(iter::once(0))
@@ -36,11 +36,17 @@ impl Win32HalfKayDevice {
             return Err(last_error("CreateEventW"));
         }
 
+        let share_mode = if opts.exclusive {
+            0
+        } else {
+            FILE_SHARE_READ | FILE_SHARE_WRITE
+        };
+
         let handle = unsafe {
             CreateFileW(
                 wide.as_ptr(),
                 GENERIC_READ | GENERIC_WRITE,
-                FILE_SHARE_READ | FILE_SHARE_WRITE,
+                share_mode,
                 std::ptr::null(),
                 OPEN_EXISTING,
                 FILE_FLAG_OVERLAPPED,
@@ -49,8 +55,12 @@ impl Win32HalfKayDevice {
         };
 
         if handle == INVALID_HANDLE_VALUE {
+            let code = unsafe { GetLastError() };
             unsafe { CloseHandle(event) };
-            return Err(last_error("CreateFileW"));
+            if opts.exclusive && code == ERROR_SHARING_VIOLATION {
+                return Err(HalfKayError::Busy);
+            }
+            return Err(win32_error("CreateFileW", code));
         }
 
         Ok(Self { handle, event })
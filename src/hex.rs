@@ -1,190 +1,869 @@
 use std::fs::File;
 use std::io::{self, BufRead, BufReader};
 use std::path::Path;
+use std::time::{Duration, Instant};
 
 use thiserror::Error;
 
+use crate::board::Board;
 use crate::teensy41;
 
+/// Options controlling how firmware files are parsed.
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// Require an EOF record (`01`) before the end of input.
+    ///
+    /// Catches truncated files that would otherwise flash partial firmware.
+    pub strict_eof: bool,
+
+    /// Reject images whose populated size exceeds this many bytes.
+    pub max_firmware_bytes: Option<usize>,
+
+    /// Reject records that rewrite an already-written byte with a different value
+    /// (`OverlappingData`). Identical rewrites are allowed.
+    pub strict_overlaps: bool,
+
+    /// Erased-flash value used to pad the image and to detect blank blocks (default 0xFF,
+    /// what Teensy flash erases to). Block 0 is written even when it is blank.
+    pub fill: Option<u8>,
+
+    /// Load address of raw `.bin` files (default: [`teensy41::FLEXSPI_BASE`]).
+    pub bin_base: Option<u32>,
+
+    /// Board the image is built for; data beyond its flash is rejected (default Teensy 4.1).
+    pub board: Board,
+}
+
+/// Minimum spacing between progress callbacks while parsing a HEX file.
+pub const LOAD_PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
 pub struct FirmwareImage {
     pub data: Vec<u8>,
     pub byte_count: usize,
     pub num_blocks: usize,
     pub blocks_to_write: Vec<usize>,
+    /// Lowest flash offset written by the file (`None` when it has no data).
+    ///
+    /// Reflects the data only: block 0, always written, does not count.
+    pub min_addr: Option<usize>,
+    /// Highest flash offset written by the file (`None` when it has no data).
+    pub max_addr: Option<usize>,
+    /// Entry point declared by an Intel HEX start linear address record (type 05).
+    ///
+    /// Always inside the FlexSPI code region when present.
+    pub start_address: Option<u32>,
+    /// Erased-flash value the image was padded with (see [`LoadOptions::fill`]).
+    pub fill: u8,
+    /// Board the image was loaded for; `data` is its [`Board::code_size`] long.
+    pub board: Board,
+}
+
+/// Firmware file formats accepted by [`FirmwareImage::load_teensy41_auto`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FirmwareFormat {
+    /// Intel HEX (`:` records).
+    IntelHex,
+    /// Motorola S-record (`.s19`/`.srec`, `S` records).
+    Srec,
+    /// Raw binary (`.bin`), placed at [`LoadOptions::bin_base`].
+    Bin,
+}
+
+impl FirmwareFormat {
+    /// Guess the format: `.bin` files are raw binaries; otherwise the first non-empty line
+    /// decides, `S` meaning S-record and anything else Intel HEX (reported by that parser if
+    /// it is not).
+    pub fn sniff(path: &Path) -> Result<Self, FirmwareError> {
+        if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("bin"))
+        {
+            return Ok(FirmwareFormat::Bin);
+        }
+        let f = File::open(path).map_err(FirmwareError::Io)?;
+        for line in BufReader::new(f).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            let line = line.trim();
+            if !line.is_empty() {
+                return Ok(if line.starts_with('S') {
+                    FirmwareFormat::Srec
+                } else {
+                    FirmwareFormat::IntelHex
+                });
+            }
+        }
+        Ok(FirmwareFormat::IntelHex)
+    }
 }
 
 impl FirmwareImage {
-    pub fn load_teensy41(path: &Path) -> Result<Self, HexError> {
-        let mut data = vec![0xFFu8; teensy41::CODE_SIZE];
-        let mut byte_count: usize = 0;
+    /// Load Intel HEX, S-record or raw binary for `board` (see [`FirmwareImage::load_teensy41_auto`]).
+    pub fn load(path: &Path, board: Board) -> Result<Self, FirmwareError> {
+        let opts = LoadOptions {
+            board,
+            ..Default::default()
+        };
+        Self::load_teensy41_auto_with_progress(path, &opts, |_| {})
+    }
+
+    pub fn load_teensy41(path: &Path) -> Result<Self, FirmwareError> {
+        Self::load_teensy41_with_options(path, &LoadOptions::default())
+    }
+
+    /// Load Intel HEX with the opt-in checks of `opts`: e.g. `LoadOptions { strict_overlaps:
+    /// true, ..Default::default() }` rejects records that overwrite already-written bytes with
+    /// different values (`OverlappingData`).
+    pub fn load_teensy41_with_options(
+        path: &Path,
+        opts: &LoadOptions,
+    ) -> Result<Self, FirmwareError> {
+        Self::load_teensy41_with_progress(path, opts, |_| {})
+    }
+
+    /// Like [`FirmwareImage::load_teensy41_with_options`], reporting the current line number
+    /// at most every [`LOAD_PROGRESS_INTERVAL`] (small files never report).
+    pub fn load_teensy41_with_progress<F>(
+        path: &Path,
+        opts: &LoadOptions,
+        on_progress: F,
+    ) -> Result<Self, FirmwareError>
+    where
+        F: FnMut(usize),
+    {
+        Self::load_teensy41_format(path, FirmwareFormat::IntelHex, opts, on_progress)
+    }
+
+    /// Parse Intel HEX from memory or any other reader (default [`LoadOptions`]).
+    ///
+    /// `load_teensy41` is this over the opened file: I/O errors are `Io`, invalid UTF-8 is
+    /// `NotText`.
+    pub fn load_teensy41_from_reader<R: BufRead>(reader: R) -> Result<Self, FirmwareError> {
+        Self::parse_lines(
+            reader,
+            FirmwareFormat::IntelHex,
+            &LoadOptions::default(),
+            |_| {},
+        )
+    }
+
+    /// Load a Motorola S-record file (S1/S2/S3 data records).
+    ///
+    /// Produces the same image as the equivalent Intel HEX.
+    pub fn load_teensy41_srec(path: &Path) -> Result<Self, FirmwareError> {
+        Self::load_teensy41_format(path, FirmwareFormat::Srec, &LoadOptions::default(), |_| {})
+    }
+
+    /// Load a raw binary whose first byte belongs at `base` (FlexSPI addresses are rebased
+    /// like Intel HEX ones).
+    ///
+    /// Every block the binary touches is written, even when blank.
+    pub fn load_teensy41_bin(path: &Path, base: u32) -> Result<Self, FirmwareError> {
+        let opts = LoadOptions {
+            bin_base: Some(base),
+            ..Default::default()
+        };
+        Self::load_teensy41_format(path, FirmwareFormat::Bin, &opts, |_| {})
+    }
+
+    /// Load Intel HEX, S-record or raw binary, picked by [`FirmwareFormat::sniff`].
+    pub fn load_teensy41_auto(path: &Path) -> Result<Self, FirmwareError> {
+        Self::load_teensy41_auto_with_progress(path, &LoadOptions::default(), |_| {})
+    }
 
-        let f = File::open(path).map_err(HexError::Io)?;
-        let r = BufReader::new(f);
+    /// [`FirmwareImage::load_teensy41_auto`] with options and progress, like
+    /// [`FirmwareImage::load_teensy41_with_progress`].
+    pub fn load_teensy41_auto_with_progress<F>(
+        path: &Path,
+        opts: &LoadOptions,
+        on_progress: F,
+    ) -> Result<Self, FirmwareError>
+    where
+        F: FnMut(usize),
+    {
+        let format = FirmwareFormat::sniff(path)?;
+        Self::load_teensy41_format(path, format, opts, on_progress)
+    }
+
+    fn load_teensy41_format<F>(
+        path: &Path,
+        format: FirmwareFormat,
+        opts: &LoadOptions,
+        on_progress: F,
+    ) -> Result<Self, FirmwareError>
+    where
+        F: FnMut(usize),
+    {
+        if format == FirmwareFormat::Bin {
+            let base = opts.bin_base.unwrap_or(teensy41::FLEXSPI_BASE);
+            return load_bin(path, base, opts)?.finish(opts, true);
+        }
 
-        let mut ext_addr: u32 = 0;
+        let f = File::open(path).map_err(FirmwareError::Io)?;
+        Self::parse_lines(BufReader::new(f), format, opts, on_progress)
+    }
+
+    fn parse_lines<R, F>(
+        r: R,
+        format: FirmwareFormat,
+        opts: &LoadOptions,
+        mut on_progress: F,
+    ) -> Result<Self, FirmwareError>
+    where
+        R: BufRead,
+        F: FnMut(usize),
+    {
+        let mut last_progress = Instant::now();
+        let mut image = ImageBuilder::new(opts);
+        let mut hex = HexState::default();
+        let mut saw_eof = false;
 
         for (line_no, line) in r.lines().enumerate() {
             let line_no = line_no + 1;
+            if last_progress.elapsed() >= LOAD_PROGRESS_INTERVAL {
+                on_progress(line_no);
+                last_progress = Instant::now();
+            }
             let line = match line {
                 Ok(s) => s,
                 Err(e) if e.kind() == io::ErrorKind::InvalidData => {
-                    return Err(HexError::NotText { line_no });
+                    return Err(FirmwareError::NotText { line_no });
                 }
-                Err(e) => return Err(HexError::Io(e)),
+                Err(e) => return Err(FirmwareError::Io(e)),
             };
+            // 1-based column of the first non-whitespace character.
+            let first_col = line.len() - line.trim_start().len() + 1;
             let line = line.trim();
             if line.is_empty() {
                 continue;
             }
-            if !line.starts_with(':') {
-                return Err(HexError::InvalidLine {
-                    line_no,
-                    msg: "missing ':' prefix".to_string(),
-                });
+            let more = match format {
+                FirmwareFormat::IntelHex => {
+                    parse_ihex_line(line, line_no, first_col, &mut hex, &mut image)?
+                }
+                FirmwareFormat::Srec => parse_srec_line(line, line_no, first_col, &mut image)?,
+                FirmwareFormat::Bin => unreachable!("raw binaries are not line-based"),
+            };
+            if !more {
+                saw_eof = true;
+                break;
             }
+        }
+
+        image.finish(opts, saw_eof)
+    }
 
-            let bytes = decode_hex_bytes(&line[1..])
-                .map_err(|e| HexError::InvalidLine { line_no, msg: e })?;
+    /// CRC-32 (IEEE) of the full padded flash image.
+    ///
+    /// Identifies the firmware independently of HEX formatting (line length, record order).
+    pub fn crc32(&self) -> u32 {
+        crc32_ieee(&self.data)
+    }
 
-            if bytes.len() < 5 {
-                return Err(HexError::InvalidLine {
-                    line_no,
-                    msg: "record too short".to_string(),
-                });
-            }
+    /// Audit fingerprint: CRC-32 of the blocks to write and SHA-256 of the full image.
+    ///
+    /// Computed from the reconstructed image, so record order and line layout do not matter.
+    pub fn content_digest(&self) -> FirmwareDigest {
+        let block_size = self.board.block_size();
+        let blocks_crc32 = !self.blocks_to_write.iter().fold(!0u32, |c, start| {
+            crc32_update(c, &self.data[*start..*start + block_size])
+        });
+        FirmwareDigest {
+            blocks_crc32,
+            sha256: sha256(&self.data),
+        }
+    }
 
-            let len = bytes[0] as usize;
-            let addr = u16::from_be_bytes([bytes[1], bytes[2]]) as u32;
-            let rec_type = bytes[3];
+    /// The image bytes up to the last byte that differs from [`FirmwareImage::fill`] (0xFF by
+    /// default).
+    ///
+    /// Unwritten flash reads as the fill, so trailing fill bytes (written or not) are dropped.
+    pub fn populated(&self) -> &[u8] {
+        let end = self
+            .data
+            .iter()
+            .rposition(|b| *b != self.fill)
+            .map_or(0, |i| i + 1);
+        &self.data[..end]
+    }
 
-            if bytes.len() != 5 + len {
-                return Err(HexError::InvalidLine {
-                    line_no,
-                    msg: format!(
-                        "bad length (expected {} bytes after ':', got {})",
-                        2 * (5 + len),
-                        2 * bytes.len()
-                    ),
-                });
-            }
+    /// Schedule every block for writing, including blank ones (full recovery reflash).
+    pub fn write_every_block(&mut self) {
+        let block_size = self.board.block_size();
+        self.blocks_to_write = (0..self.num_blocks).map(|i| i * block_size).collect();
+    }
+
+    /// Start addresses of the blocks whose bytes differ from `other`, in ascending order.
+    ///
+    /// Compares the full padded images, so a block blanked since `other` counts as changed.
+    pub fn diff_blocks(&self, other: &FirmwareImage) -> Vec<usize> {
+        let block_size = self.board.block_size();
+        (0..self.data.len().min(other.data.len()))
+            .step_by(block_size)
+            .filter(|start| {
+                let end = start + block_size;
+                self.data[*start..end] != other.data[*start..end]
+            })
+            .collect()
+    }
+}
+
+/// See [`FirmwareImage::content_digest`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FirmwareDigest {
+    /// CRC-32 (IEEE) of the blocks in `blocks_to_write`, concatenated in order.
+    pub blocks_crc32: u32,
+    /// SHA-256 of the full padded flash image ([`Board::code_size`] bytes).
+    pub sha256: [u8; 32],
+}
+
+impl FirmwareDigest {
+    /// Lowercase hex of [`FirmwareDigest::sha256`].
+    pub fn sha256_hex(&self) -> String {
+        self.sha256.iter().map(|b| format!("{b:02x}")).collect()
+    }
+}
+
+/// Images compare equal when their flash contents are identical.
+///
+/// Only [`FirmwareImage::populated`] is compared: trailing fill-byte regions are ignored, as are
+/// `byte_count` and `blocks_to_write` (which depend on HEX layout and `LoadOptions`).
+impl PartialEq for FirmwareImage {
+    fn eq(&self, other: &Self) -> bool {
+        self.populated() == other.populated()
+    }
+}
+
+impl Eq for FirmwareImage {}
+
+impl std::hash::Hash for FirmwareImage {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.populated().hash(state);
+    }
+}
+
+/// Errors from loading a firmware file (Intel HEX or S-record).
+#[derive(Error, Debug)]
+pub enum FirmwareError {
+    #[error("io: {0}")]
+    Io(io::Error),
+
+    #[error(
+        "input is not a text Intel HEX or S-record file (invalid UTF-8 at line {line_no}); did you pass a .elf?"
+    )]
+    NotText { line_no: usize },
+
+    #[error("invalid hex line {line_no}{}: {msg}", fmt_col(.col))]
+    InvalidLine {
+        line_no: usize,
+        /// 1-based column of the offending character, when known.
+        col: Option<usize>,
+        msg: String,
+    },
+
+    #[error("invalid S-record line {line_no}{}: {msg}", fmt_col(.col))]
+    InvalidSrecLine {
+        line_no: usize,
+        /// 1-based column of the offending character, when known.
+        col: Option<usize>,
+        msg: String,
+    },
+
+    #[error("invalid checksum at line {line_no}")]
+    InvalidChecksum { line_no: usize },
+
+    #[error("address overflow at line {line_no}")]
+    AddressOverflow { line_no: usize },
+
+    #[error("address out of flash range at line {line_no}: 0x{addr:08X}")]
+    AddressOutOfRange { line_no: usize, addr: u32 },
+
+    #[error(
+        "this firmware appears built for a different Teensy model (highest address 0x{max_addr:08X}; {board} flash is 0x{:08X}..0x{:08X})",
+        teensy41::FLEXSPI_BASE,
+        board.flash_end()
+    )]
+    WrongModel { max_addr: u32, board: Board },
 
-            let payload = &bytes[4..4 + len];
-            let checksum = bytes[4 + len];
-            if checksum != checksum_ihex(&bytes[0..4 + len]) {
-                return Err(HexError::InvalidChecksum { line_no });
+    #[error("firmware image is {bytes} bytes, above the {max}-byte limit")]
+    TooLarge { bytes: usize, max: usize },
+
+    #[error("missing EOF record (file truncated?)")]
+    MissingEof,
+
+    #[error(
+        "line {line_no} overwrites data already written at 0x{addr:08X} with a different value"
+    )]
+    OverlappingData { line_no: usize, addr: u32 },
+
+    #[error("binary firmware file is empty")]
+    EmptyBinary,
+
+    #[error(
+        "binary of {len} bytes at 0x{base:08X} does not fit {board} flash (0x{:08X}..0x{:08X})",
+        teensy41::FLEXSPI_BASE,
+        board.flash_end()
+    )]
+    BinOutOfRange { base: u32, len: usize, board: Board },
+}
+
+/// The Intel HEX name of [`FirmwareError`], kept for existing callers.
+pub type HexError = FirmwareError;
+
+#[cfg(feature = "cli")]
+impl FirmwareError {
+    /// Structured fields for machine-readable error reports.
+    ///
+    /// Always `error_kind: "invalid_hex"` and a snake_case `reason` (the variant), plus the
+    /// variant's location fields when it has them (`line_no`, `col`, `addr` as `0x` hex,
+    /// `detail`).
+    pub fn to_json_value(&self) -> serde_json::Value {
+        let mut v = serde_json::json!({ "error_kind": "invalid_hex" });
+        let (reason, line_no, col, addr, detail) = match self {
+            FirmwareError::Io(_) => ("io", None, None, None, None),
+            FirmwareError::NotText { line_no } => ("not_text", Some(*line_no), None, None, None),
+            FirmwareError::InvalidLine { line_no, col, msg } => {
+                ("invalid_line", Some(*line_no), *col, None, Some(msg))
+            }
+            FirmwareError::InvalidSrecLine { line_no, col, msg } => {
+                ("invalid_srec_line", Some(*line_no), *col, None, Some(msg))
             }
+            FirmwareError::InvalidChecksum { line_no } => {
+                ("invalid_checksum", Some(*line_no), None, None, None)
+            }
+            FirmwareError::AddressOverflow { line_no } => {
+                ("address_overflow", Some(*line_no), None, None, None)
+            }
+            FirmwareError::AddressOutOfRange { line_no, addr } => (
+                "address_out_of_range",
+                Some(*line_no),
+                None,
+                Some(*addr),
+                None,
+            ),
+            FirmwareError::WrongModel { max_addr, .. } => {
+                ("wrong_model", None, None, Some(*max_addr), None)
+            }
+            FirmwareError::TooLarge { .. } => ("too_large", None, None, None, None),
+            FirmwareError::MissingEof => ("missing_eof", None, None, None, None),
+            FirmwareError::OverlappingData { line_no, addr } => {
+                ("overlapping_data", Some(*line_no), None, Some(*addr), None)
+            }
+            FirmwareError::EmptyBinary => ("empty_binary", None, None, None, None),
+            FirmwareError::BinOutOfRange { base, .. } => {
+                ("bin_out_of_range", None, None, Some(*base), None)
+            }
+        };
+        v["reason"] = reason.into();
+        if let Some(line_no) = line_no {
+            v["line_no"] = line_no.into();
+        }
+        if let Some(col) = col {
+            v["col"] = col.into();
+        }
+        if let Some(addr) = addr {
+            v["addr"] = format!("0x{addr:08X}").into();
+        }
+        if let Some(detail) = detail {
+            v["detail"] = detail.as_str().into();
+        }
+        v
+    }
+}
 
-            match rec_type {
-                0x00 => {
-                    // data
-                    byte_count = byte_count.saturating_add(len);
-                    for (i, b) in payload.iter().copied().enumerate() {
-                        let abs = ext_addr
-                            .checked_add(addr)
-                            .and_then(|v| v.checked_add(i as u32))
-                            .ok_or(HexError::AddressOverflow { line_no })?;
-                        let abs = map_teensy41_addr(abs)
-                            .ok_or(HexError::AddressOutOfRange { line_no, addr: abs })?;
-                        data[abs] = b;
-                    }
-                }
-                0x01 => {
-                    // EOF
-                    break;
-                }
-                0x02 if len == 2 => {
-                    // extended segment address (<< 4)
-                    let seg = u16::from_be_bytes([payload[0], payload[1]]) as u32;
-                    ext_addr = seg << 4;
-                }
-                0x04 if len == 2 => {
-                    // extended linear address (<< 16)
-                    let hi = u16::from_be_bytes([payload[0], payload[1]]) as u32;
-                    ext_addr = hi << 16;
-                    // Teensy 4.x HEX uses FlexSPI base (0x60000000).
-                    if ext_addr >= teensy41::FLEXSPI_BASE
-                        && ext_addr < teensy41::FLEXSPI_BASE + teensy41::CODE_SIZE as u32
-                    {
-                        ext_addr -= teensy41::FLEXSPI_BASE;
+fn fmt_col(col: &Option<usize>) -> String {
+    match col {
+        Some(c) => format!(", col {c}"),
+        None => String::new(),
+    }
+}
+
+/// Flash image being filled in by a record parser.
+struct ImageBuilder {
+    data: Vec<u8>,
+    byte_count: usize,
+    // Out-of-range bytes are tallied over the whole file so firmware built for another
+    // model is diagnosed as such instead of by its first offending line.
+    first_out_of_range: Option<(usize, u32)>,
+    out_of_range_bytes: usize,
+    max_addr: u32,
+    /// Flash offsets always written, blank or not (raw binaries).
+    dirty: std::ops::Range<usize>,
+    /// In-range flash offsets actually written.
+    used: Option<(usize, usize)>,
+    fill: u8,
+    board: Board,
+    /// Which bytes have been written (`strict_overlaps` only).
+    written: Option<Vec<bool>>,
+    start_address: Option<u32>,
+}
+
+impl ImageBuilder {
+    fn new(opts: &LoadOptions) -> Self {
+        let fill = opts.fill.unwrap_or(0xFF);
+        let code_size = opts.board.code_size();
+        Self {
+            fill,
+            board: opts.board,
+            written: opts.strict_overlaps.then(|| vec![false; code_size]),
+            data: vec![fill; code_size],
+            byte_count: 0,
+            first_out_of_range: None,
+            out_of_range_bytes: 0,
+            max_addr: 0,
+            dirty: 0..0,
+            used: None,
+            start_address: None,
+        }
+    }
+
+    /// Store one data byte at flash offset `abs`; `raw` is its address as written in the file.
+    fn put(&mut self, line_no: usize, abs: u32, raw: u32, b: u8) -> Result<(), FirmwareError> {
+        self.byte_count = self.byte_count.saturating_add(1);
+        self.max_addr = self.max_addr.max(raw);
+        match map_flash_addr(abs, self.data.len()) {
+            Some(abs) => {
+                if let Some(written) = &mut self.written {
+                    if std::mem::replace(&mut written[abs], true) && self.data[abs] != b {
+                        return Err(FirmwareError::OverlappingData { line_no, addr: raw });
                     }
                 }
-                _ => {
-                    // ignore other non-data records
-                }
+                self.data[abs] = b;
+                self.used = Some(match self.used {
+                    Some((lo, hi)) => (lo.min(abs), hi.max(abs)),
+                    None => (abs, abs),
+                });
+            }
+            None => {
+                self.out_of_range_bytes += 1;
+                self.first_out_of_range.get_or_insert((line_no, abs));
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self, opts: &LoadOptions, saw_eof: bool) -> Result<FirmwareImage, FirmwareError> {
+        if let Some((line_no, addr)) = self.first_out_of_range {
+            if self.out_of_range_bytes == self.byte_count || self.max_addr >= self.board.flash_end()
+            {
+                return Err(FirmwareError::WrongModel {
+                    max_addr: self.max_addr,
+                    board: self.board,
+                });
             }
+            return Err(FirmwareError::AddressOutOfRange { line_no, addr });
+        }
+
+        if opts.strict_eof && !saw_eof {
+            return Err(FirmwareError::MissingEof);
         }
 
-        let num_blocks = teensy41::CODE_SIZE / teensy41::BLOCK_SIZE;
+        let data = self.data;
+        let block_size = self.board.block_size();
+        let num_blocks = data.len() / block_size;
 
         let mut blocks_to_write: Vec<usize> = Vec::new();
         for block_idx in 0..num_blocks {
-            let start = block_idx * teensy41::BLOCK_SIZE;
-            if block_idx == 0 {
+            let start = block_idx * block_size;
+            let touched = start < self.dirty.end && self.dirty.start < start + block_size;
+            if block_idx == 0 || touched {
                 blocks_to_write.push(start);
                 continue;
             }
-            if !is_block_blank(&data, start) {
+            if !is_block_blank(&data[start..start + block_size], self.fill) {
                 blocks_to_write.push(start);
             }
         }
 
-        Ok(Self {
+        let image = FirmwareImage {
             data,
-            byte_count,
+            byte_count: self.byte_count,
             num_blocks,
             blocks_to_write,
-        })
+            min_addr: self.used.map(|(lo, _)| lo),
+            max_addr: self.used.map(|(_, hi)| hi),
+            start_address: self.start_address,
+            fill: self.fill,
+            board: self.board,
+        };
+        if let Some(max) = opts.max_firmware_bytes {
+            let bytes = image.populated().len();
+            if bytes > max {
+                return Err(FirmwareError::TooLarge { bytes, max });
+            }
+        }
+        Ok(image)
     }
 }
 
-#[derive(Error, Debug)]
-pub enum HexError {
-    #[error("io: {0}")]
-    Io(io::Error),
+/// Read a raw binary into an image, its first byte at `base`.
+fn load_bin(path: &Path, base: u32, opts: &LoadOptions) -> Result<ImageBuilder, FirmwareError> {
+    let bin = std::fs::read(path).map_err(FirmwareError::Io)?;
+    if bin.is_empty() {
+        return Err(FirmwareError::EmptyBinary);
+    }
+    let start = if in_flexspi(base) {
+        base - teensy41::FLEXSPI_BASE
+    } else {
+        base
+    } as usize;
+    let end = start
+        .checked_add(bin.len())
+        .filter(|end| *end <= opts.board.code_size())
+        .ok_or(FirmwareError::BinOutOfRange {
+            base,
+            len: bin.len(),
+            board: opts.board,
+        })?;
 
-    #[error(
-        "input is not a text Intel HEX file (invalid UTF-8 at line {line_no}); did you pass a .elf?"
-    )]
-    NotText { line_no: usize },
+    let mut image = ImageBuilder::new(opts);
+    image.data[start..end].copy_from_slice(&bin);
+    image.byte_count = bin.len();
+    image.max_addr = base.saturating_add(bin.len() as u32 - 1);
+    image.dirty = start..end;
+    image.used = Some((start, end - 1));
+    Ok(image)
+}
 
-    #[error("invalid hex line {line_no}: {msg}")]
-    InvalidLine { line_no: usize, msg: String },
+/// Intel HEX addressing state carried across records.
+#[derive(Default)]
+struct HexState {
+    ext_addr: u32,
+    flexspi: bool,
+}
 
-    #[error("invalid checksum at line {line_no}")]
-    InvalidChecksum { line_no: usize },
+/// Parse one Intel HEX record. Returns `Ok(false)` at the EOF record.
+fn parse_ihex_line(
+    line: &str,
+    line_no: usize,
+    first_col: usize,
+    st: &mut HexState,
+    image: &mut ImageBuilder,
+) -> Result<bool, FirmwareError> {
+    if !line.starts_with(':') {
+        return Err(FirmwareError::InvalidLine {
+            line_no,
+            col: Some(first_col),
+            msg: "missing ':' prefix".to_string(),
+        });
+    }
 
-    #[error("address overflow at line {line_no}")]
-    AddressOverflow { line_no: usize },
+    let bytes =
+        decode_hex_bytes(&line[1..]).map_err(|(offset, msg)| FirmwareError::InvalidLine {
+            line_no,
+            col: Some(first_col + 1 + offset),
+            msg,
+        })?;
 
-    #[error("address out of Teensy 4.1 range at line {line_no}: 0x{addr:08X}")]
-    AddressOutOfRange { line_no: usize, addr: u32 },
+    if bytes.len() < 5 {
+        return Err(FirmwareError::InvalidLine {
+            line_no,
+            col: None,
+            msg: "record too short".to_string(),
+        });
+    }
+
+    let len = bytes[0] as usize;
+    let addr = u16::from_be_bytes([bytes[1], bytes[2]]) as u32;
+    let rec_type = bytes[3];
+
+    if bytes.len() != 5 + len {
+        return Err(FirmwareError::InvalidLine {
+            line_no,
+            col: None,
+            msg: format!(
+                "bad length (expected {} bytes after ':', got {})",
+                2 * (5 + len),
+                2 * bytes.len()
+            ),
+        });
+    }
+
+    let payload = &bytes[4..4 + len];
+    let checksum = bytes[4 + len];
+    if checksum != checksum_ihex(&bytes[0..4 + len]) {
+        return Err(FirmwareError::InvalidChecksum { line_no });
+    }
+
+    match rec_type {
+        0x00 => {
+            // data
+            for (i, b) in payload.iter().copied().enumerate() {
+                let abs = st
+                    .ext_addr
+                    .checked_add(addr)
+                    .and_then(|v| v.checked_add(i as u32))
+                    .ok_or(FirmwareError::AddressOverflow { line_no })?;
+                // Address as written in the file, before FlexSPI rebasing.
+                let raw = if st.flexspi {
+                    abs.saturating_add(teensy41::FLEXSPI_BASE)
+                } else {
+                    abs
+                };
+                image.put(line_no, abs, raw, b)?;
+            }
+        }
+        0x01 => {
+            // EOF
+            return Ok(false);
+        }
+        0x02 if len == 2 => {
+            // extended segment address (<< 4)
+            let seg = u16::from_be_bytes([payload[0], payload[1]]) as u32;
+            st.ext_addr = seg << 4;
+            st.flexspi = false;
+        }
+        0x05 if len == 4 => {
+            // start linear address (entry point)
+            let entry = u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]);
+            if !in_flexspi(entry) || entry >= image.board.flash_end() {
+                return Err(FirmwareError::AddressOutOfRange {
+                    line_no,
+                    addr: entry,
+                });
+            }
+            image.start_address = Some(entry);
+        }
+        0x04 if len == 2 => {
+            // extended linear address (<< 16)
+            let hi = u16::from_be_bytes([payload[0], payload[1]]) as u32;
+            st.ext_addr = hi << 16;
+            // Teensy 4.x HEX uses FlexSPI base (0x60000000).
+            st.flexspi = in_flexspi(st.ext_addr);
+            if st.flexspi {
+                st.ext_addr -= teensy41::FLEXSPI_BASE;
+            }
+        }
+        _ => {
+            // ignore other non-data records (e.g. 03, start segment address)
+        }
+    }
+    Ok(true)
 }
 
-fn is_block_blank(data: &[u8], start: usize) -> bool {
-    let end = start + teensy41::BLOCK_SIZE;
-    data[start..end].iter().all(|b| *b == 0xFF)
+/// Parse one S-record. Returns `Ok(false)` at a termination record (S7/S8/S9).
+fn parse_srec_line(
+    line: &str,
+    line_no: usize,
+    first_col: usize,
+    image: &mut ImageBuilder,
+) -> Result<bool, FirmwareError> {
+    let invalid =
+        |col: Option<usize>, msg: String| FirmwareError::InvalidSrecLine { line_no, col, msg };
+
+    let rec_type = match line.as_bytes() {
+        [b'S', t, ..] if t.is_ascii_digit() => *t - b'0',
+        [b'S', ..] => return Err(invalid(Some(first_col + 1), "invalid record type".into())),
+        _ => return Err(invalid(Some(first_col), "missing 'S' prefix".into())),
+    };
+
+    let bytes = decode_hex_bytes(&line[2..])
+        .map_err(|(offset, msg)| invalid(Some(first_col + 2 + offset), msg))?;
+
+    let addr_len = match rec_type {
+        0 | 1 | 5 | 9 => 2,
+        2 | 6 | 8 => 3,
+        3 | 7 => 4,
+        _ => return Err(invalid(Some(first_col + 1), "invalid record type".into())),
+    };
+
+    // count, address, checksum.
+    if bytes.len() < 2 + addr_len {
+        return Err(invalid(None, "record too short".to_string()));
+    }
+    let count = bytes[0] as usize;
+    if bytes.len() != 1 + count {
+        return Err(invalid(
+            None,
+            format!(
+                "bad length (expected {} digits after the type, got {})",
+                2 * (1 + count),
+                2 * bytes.len()
+            ),
+        ));
+    }
+
+    let checksum = bytes[count];
+    if checksum != checksum_srec(&bytes[..count]) {
+        return Err(FirmwareError::InvalidChecksum { line_no });
+    }
+
+    let addr = bytes[1..1 + addr_len]
+        .iter()
+        .fold(0u32, |a, b| (a << 8) | *b as u32);
+    let payload = &bytes[1 + addr_len..count];
+
+    match rec_type {
+        1..=3 => {
+            // Teensy 4.x firmware is linked at the FlexSPI base (0x60000000).
+            let base = if in_flexspi(addr) {
+                teensy41::FLEXSPI_BASE
+            } else {
+                0
+            };
+            for (i, b) in payload.iter().copied().enumerate() {
+                let raw = addr
+                    .checked_add(i as u32)
+                    .ok_or(FirmwareError::AddressOverflow { line_no })?;
+                image.put(line_no, raw - base, raw, b)?;
+            }
+        }
+        7..=9 => return Ok(false),
+        _ => {
+            // header (S0) and record counts (S5/S6)
+        }
+    }
+    Ok(true)
+}
+
+/// Whether `addr` lies in the FlexSPI window of the largest board (rebasing only: each
+/// board's own flash limit is enforced when bytes are stored).
+fn in_flexspi(addr: u32) -> bool {
+    addr >= teensy41::FLEXSPI_BASE && addr < teensy41::FLEXSPI_BASE + teensy41::CODE_SIZE as u32
 }
 
-fn map_teensy41_addr(addr: u32) -> Option<usize> {
-    // After FlexSPI mapping, valid firmware addresses are within [0, CODE_SIZE).
+fn is_block_blank(block: &[u8], fill: u8) -> bool {
+    block.iter().all(|b| *b == fill)
+}
+
+fn map_flash_addr(addr: u32, code_size: usize) -> Option<usize> {
+    // After FlexSPI mapping, valid firmware addresses are within [0, code_size).
     let a = addr as usize;
-    if a < teensy41::CODE_SIZE {
+    if a < code_size {
         Some(a)
     } else {
         None
     }
 }
 
-fn decode_hex_bytes(s: &str) -> Result<Vec<u8>, String> {
+/// Decode hex digit pairs. On error, returns the byte offset of the offending digit.
+fn decode_hex_bytes(s: &str) -> Result<Vec<u8>, (usize, String)> {
+    let bytes = s.as_bytes();
+    let invalid_digit = |i: usize| {
+        let pair_start = i - i % 2;
+        let pair_end = (pair_start + 2).min(bytes.len());
+        (
+            i,
+            format!(
+                "invalid hex digit in '{}'",
+                String::from_utf8_lossy(&bytes[pair_start..pair_end])
+            ),
+        )
+    };
+
+    if let Some(i) = bytes.iter().position(|c| from_hex_digit(*c).is_none()) {
+        return Err(invalid_digit(i));
+    }
     if !s.len().is_multiple_of(2) {
-        return Err("odd number of hex digits".to_string());
+        return Err((s.len() - 1, "odd number of hex digits".to_string()));
     }
+
     let mut out = Vec::with_capacity(s.len() / 2);
-    let bytes = s.as_bytes();
     let mut i = 0;
     while i < bytes.len() {
-        let hi = from_hex_digit(bytes[i]).ok_or_else(|| "invalid hex digit".to_string())?;
-        let lo = from_hex_digit(bytes[i + 1]).ok_or_else(|| "invalid hex digit".to_string())?;
+        let hi = from_hex_digit(bytes[i]).ok_or_else(|| invalid_digit(i))?;
+        let lo = from_hex_digit(bytes[i + 1]).ok_or_else(|| invalid_digit(i + 1))?;
         out.push((hi << 4) | lo);
         i += 2;
     }
@@ -205,6 +884,114 @@ fn checksum_ihex(bytes: &[u8]) -> u8 {
     (!sum).wrapping_add(1)
 }
 
+fn checksum_srec(bytes: &[u8]) -> u8 {
+    !bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b))
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xEDB8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+fn crc32_ieee(bytes: &[u8]) -> u32 {
+    !crc32_update(!0u32, bytes)
+}
+
+fn crc32_update(crc: u32, bytes: &[u8]) -> u32 {
+    bytes.iter().fold(crc, |c, b| {
+        CRC32_TABLE[((c ^ *b as u32) & 0xFF) as usize] ^ (c >> 8)
+    })
+}
+
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-256 (FIPS 180-4).
+fn sha256(bytes: &[u8]) -> [u8; 32] {
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+
+    let bit_len = (bytes.len() as u64).wrapping_mul(8);
+    let mut tail = bytes[bytes.len() - bytes.len() % 64..].to_vec();
+    tail.push(0x80);
+    while tail.len() % 64 != 56 {
+        tail.push(0);
+    }
+    tail.extend_from_slice(&bit_len.to_be_bytes());
+
+    let full = &bytes[..bytes.len() - bytes.len() % 64];
+    for chunk in full.chunks_exact(64).chain(tail.chunks_exact(64)) {
+        let mut w = [0u32; 64];
+        for (i, word) in chunk.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(SHA256_K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (hv, v) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *hv = hv.wrapping_add(v);
+        }
+    }
+
+    let mut out = [0u8; 32];
+    for (o, v) in out.chunks_exact_mut(4).zip(h) {
+        o.copy_from_slice(&v.to_be_bytes());
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -227,6 +1014,350 @@ mod tests {
         s
     }
 
+    fn srec_record(rec_type: u8, addr: u32, payload: &[u8]) -> String {
+        let addr_len = match rec_type {
+            2 | 8 => 3,
+            3 | 7 => 4,
+            _ => 2,
+        };
+        let mut bytes: Vec<u8> = vec![(addr_len + payload.len() + 1) as u8];
+        bytes.extend_from_slice(&addr.to_be_bytes()[4 - addr_len..]);
+        bytes.extend_from_slice(payload);
+        bytes.push(checksum_srec(&bytes));
+
+        let mut s = format!("S{rec_type}");
+        for b in bytes {
+            s.push_str(&format!("{b:02X}"));
+        }
+        s
+    }
+
+    fn write_temp(content: &str) -> NamedTempFile {
+        let mut f = NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut f, content.as_bytes()).unwrap();
+        f
+    }
+
+    #[test]
+    fn srec_matches_equivalent_intel_hex() {
+        let payload = [0xDE, 0xAD, 0xBE, 0xEF];
+        let ihex = write_temp(&format!(
+            "{}\n{}\n{}\n",
+            ihex_record(0x0000, 0x04, &[0x60, 0x01]),
+            ihex_record(0x0010, 0x00, &payload),
+            ihex_record(0x0000, 0x01, &[]),
+        ));
+        let srec = write_temp(&format!(
+            "{}\n{}\n{}\n",
+            srec_record(0, 0, b"fw"),
+            srec_record(3, 0x6001_0010, &payload),
+            srec_record(7, 0x6000_0000, &[]),
+        ));
+
+        let a = FirmwareImage::load_teensy41(ihex.path()).unwrap();
+        let b = FirmwareImage::load_teensy41_srec(srec.path()).unwrap();
+        assert!(a == b);
+        assert_eq!(a.byte_count, b.byte_count);
+        assert_eq!(a.blocks_to_write, b.blocks_to_write);
+
+        assert_eq!(
+            FirmwareFormat::sniff(srec.path()).unwrap(),
+            FirmwareFormat::Srec
+        );
+        assert_eq!(
+            FirmwareFormat::sniff(ihex.path()).unwrap(),
+            FirmwareFormat::IntelHex
+        );
+        assert!(FirmwareImage::load_teensy41_auto(srec.path()).unwrap() == a);
+    }
+
+    #[test]
+    fn srec_rejects_bad_checksum_short_record_and_other_models() {
+        let good = srec_record(1, 0x0010, &[0x01, 0x02]);
+        let bad = format!("{}00", &good[..good.len() - 2]);
+        let f = write_temp(&format!("{bad}\n"));
+        assert!(matches!(
+            FirmwareImage::load_teensy41_srec(f.path()),
+            Err(FirmwareError::InvalidChecksum { line_no: 1 })
+        ));
+
+        let f = write_temp("S1020000\n");
+        match FirmwareImage::load_teensy41_srec(f.path()) {
+            Err(e @ FirmwareError::InvalidSrecLine { .. }) => {
+                assert!(e.to_string().contains("record too short"), "{e}")
+            }
+            Err(e) => panic!("expected InvalidSrecLine, got {e:?}"),
+            Ok(_) => panic!("expected InvalidSrecLine"),
+        }
+
+        let f = write_temp(&format!("{}\n", srec_record(3, 0x607C_0000, &[0x01])));
+        assert!(matches!(
+            FirmwareImage::load_teensy41_srec(f.path()),
+            Err(FirmwareError::WrongModel {
+                max_addr: 0x607C_0000,
+                board: Board::Teensy41,
+            })
+        ));
+    }
+
+    #[test]
+    fn bin_places_bytes_at_unaligned_base_and_marks_touched_blocks() {
+        let mut f = tempfile::Builder::new().suffix(".bin").tempfile().unwrap();
+        let mut bin = vec![0xFFu8; teensy41::BLOCK_SIZE];
+        bin[0] = 0xAA;
+        std::io::Write::write_all(&mut f, &bin).unwrap();
+
+        let base = teensy41::FLEXSPI_BASE + teensy41::BLOCK_SIZE as u32 * 2 + 0x10;
+        let fw = FirmwareImage::load_teensy41_bin(f.path(), base).unwrap();
+        let start = teensy41::BLOCK_SIZE * 2 + 0x10;
+        assert_eq!(fw.min_addr, Some(start));
+        assert_eq!(fw.max_addr, Some(start + bin.len() - 1));
+        assert_eq!(fw.data[start], 0xAA);
+        assert_eq!(fw.byte_count, bin.len());
+        // Straddles blocks 2 and 3; the all-0xFF tail in block 3 is still written.
+        let b = teensy41::BLOCK_SIZE;
+        assert_eq!(fw.blocks_to_write, vec![0, 2 * b, 3 * b]);
+
+        let opts = LoadOptions {
+            bin_base: Some(base),
+            ..Default::default()
+        };
+        let auto = FirmwareImage::load_teensy41_auto_with_progress(f.path(), &opts, |_| {});
+        assert!(auto.unwrap() == fw);
+    }
+
+    #[test]
+    fn bin_rejects_empty_and_oversized_input() {
+        let empty = NamedTempFile::new().unwrap();
+        assert!(matches!(
+            FirmwareImage::load_teensy41_bin(empty.path(), teensy41::FLEXSPI_BASE),
+            Err(FirmwareError::EmptyBinary)
+        ));
+
+        let f = write_temp("ab");
+        let last = teensy41::FLEXSPI_BASE + teensy41::CODE_SIZE as u32 - 1;
+        assert!(matches!(
+            FirmwareImage::load_teensy41_bin(f.path(), last),
+            Err(FirmwareError::BinOutOfRange { len: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn from_reader_matches_file_load_and_reports_not_text() {
+        let content = format!(
+            "{}\n{}\n",
+            ihex_record(0x0010, 0x00, &[0xDE, 0xAD, 0xBE, 0xEF]),
+            ihex_record(0x0000, 0x01, &[]),
+        );
+        let f = write_temp(&content);
+        let from_file = FirmwareImage::load_teensy41(f.path()).unwrap();
+        let from_mem =
+            FirmwareImage::load_teensy41_from_reader(io::Cursor::new(content.into_bytes()))
+                .unwrap();
+        assert!(from_file == from_mem);
+        assert_eq!(from_file.blocks_to_write, from_mem.blocks_to_write);
+
+        let elf = io::Cursor::new(b"\x7fELF\xff\xfe\n".to_vec());
+        assert!(matches!(
+            FirmwareImage::load_teensy41_from_reader(elf),
+            Err(FirmwareError::NotText { line_no: 1 })
+        ));
+    }
+
+    #[test]
+    fn strict_rejects_conflicting_overlap_but_allows_identical_rewrite() {
+        let ext = ihex_record(0x0000, 0x04, &[0x60, 0x00]);
+        let first = ihex_record(0x0010, 0x00, &[0xDE, 0xAD, 0xBE, 0xEF]);
+        let same = ihex_record(0x0012, 0x00, &[0xBE, 0xEF]);
+        let clash = ihex_record(0x0013, 0x00, &[0x00]);
+        let eof = ihex_record(0x0000, 0x01, &[]);
+
+        let strict = LoadOptions {
+            strict_overlaps: true,
+            ..Default::default()
+        };
+        let ok = write_temp(&format!("{ext}\n{first}\n{same}\n{eof}\n"));
+        assert!(FirmwareImage::load_teensy41_with_options(ok.path(), &strict).is_ok());
+
+        let bad = write_temp(&format!("{ext}\n{first}\n{clash}\n{eof}\n"));
+        match FirmwareImage::load_teensy41_with_options(bad.path(), &strict) {
+            Err(FirmwareError::OverlappingData { line_no, addr }) => {
+                assert_eq!((line_no, addr), (3, 0x6000_0013));
+            }
+            Err(e) => panic!("expected OverlappingData, got {e:?}"),
+            Ok(_) => panic!("expected OverlappingData"),
+        }
+        // Default: last write wins, as before.
+        let fw = FirmwareImage::load_teensy41(bad.path()).unwrap();
+        assert_eq!(fw.data[0x13], 0x00);
+    }
+
+    #[test]
+    fn start_linear_address_is_captured_and_validated() {
+        let data = ihex_record(0x0010, 0x00, &[0xDE, 0xAD]);
+        let eof = ihex_record(0x0000, 0x01, &[]);
+
+        let none = write_temp(&format!("{data}\n{eof}\n"));
+        let fw = FirmwareImage::load_teensy41(none.path()).unwrap();
+        assert_eq!(fw.start_address, None);
+
+        let entry = ihex_record(0x0000, 0x05, &0x6000_1000u32.to_be_bytes());
+        let f = write_temp(&format!("{data}\n{entry}\n{eof}\n"));
+        let fw = FirmwareImage::load_teensy41(f.path()).unwrap();
+        assert_eq!(fw.start_address, Some(0x6000_1000));
+
+        let ram = ihex_record(0x0000, 0x05, &0x2000_0000u32.to_be_bytes());
+        let f = write_temp(&format!("{data}\n{ram}\n{eof}\n"));
+        assert!(matches!(
+            FirmwareImage::load_teensy41(f.path()),
+            Err(FirmwareError::AddressOutOfRange {
+                line_no: 2,
+                addr: 0x2000_0000
+            })
+        ));
+    }
+
+    #[test]
+    fn fill_byte_pads_image_and_defines_blank_blocks() {
+        let zeros = ihex_record(0x0800, 0x00, &[0x00; 4]);
+        let ones = ihex_record(0x0C00, 0x00, &[0xFF; 4]);
+        let eof = ihex_record(0x0000, 0x01, &[]);
+        let f = write_temp(&format!("{zeros}\n{ones}\n{eof}\n"));
+        let b = teensy41::BLOCK_SIZE;
+
+        let ff = FirmwareImage::load_teensy41(f.path()).unwrap();
+        assert_eq!(ff.blocks_to_write, vec![0, 2 * b]);
+
+        let opts = LoadOptions {
+            fill: Some(0x00),
+            ..Default::default()
+        };
+        let zero = FirmwareImage::load_teensy41_with_options(f.path(), &opts).unwrap();
+        assert_eq!(zero.data[0x10], 0x00);
+        assert_eq!(zero.blocks_to_write, vec![0, 3 * b]);
+        assert_eq!(zero.populated().len(), 0x0C04);
+    }
+
+    #[test]
+    fn diff_blocks_reports_changed_blocks_only() {
+        let eof = ihex_record(0x0000, 0x01, &[]);
+        let old = write_temp(&format!(
+            "{}\n{}\n{eof}\n",
+            ihex_record(0x0000, 0x00, &[1, 2, 3, 4]),
+            ihex_record(0x0800, 0x00, &[5, 6, 7, 8])
+        ));
+        let new = write_temp(&format!(
+            "{}\n{}\n{}\n{eof}\n",
+            ihex_record(0x0000, 0x00, &[1, 2, 3, 4]),
+            ihex_record(0x0800, 0x00, &[5, 6, 7, 9]),
+            ihex_record(0x1000, 0x00, &[0xAA])
+        ));
+        let old = FirmwareImage::load_teensy41(old.path()).unwrap();
+        let new = FirmwareImage::load_teensy41(new.path()).unwrap();
+        let b = teensy41::BLOCK_SIZE;
+
+        assert_eq!(new.diff_blocks(&old), vec![2 * b, 4 * b]);
+        assert_eq!(old.diff_blocks(&new), vec![2 * b, 4 * b]);
+        assert!(new.diff_blocks(&new).is_empty());
+    }
+
+    #[test]
+    fn board_limits_image_to_its_flash() {
+        let ext = ihex_record(0x0000, 0x04, &[0x60, 0x1F]);
+        let high = ihex_record(0x0000, 0x00, &[0x01]);
+        let eof = ihex_record(0x0000, 0x01, &[]);
+        let f = write_temp(&format!("{ext}\n{high}\n{eof}\n"));
+
+        let t41 = FirmwareImage::load(f.path(), Board::Teensy41).unwrap();
+        assert_eq!(t41.data.len(), teensy41::CODE_SIZE);
+        assert_eq!(t41.blocks_to_write, vec![0, 0x1F_0000]);
+
+        let err = FirmwareImage::load(f.path(), Board::Teensy40)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err,
+            FirmwareError::WrongModel {
+                max_addr: 0x601F_0000,
+                board: Board::Teensy40,
+            }
+        ));
+        assert!(err
+            .to_string()
+            .contains("Teensy 4.0 flash is 0x60000000..0x601F0000"));
+
+        let low = write_temp(&format!("{ext}\n{eof}\n"));
+        let t40 = FirmwareImage::load(low.path(), Board::Teensy40).unwrap();
+        assert_eq!(t40.num_blocks, Board::Teensy40.code_size() / 1024);
+    }
+
+    #[test]
+    fn crc32_matches_reference_vector() {
+        assert_eq!(crc32_ieee(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn sha256_matches_reference_vectors() {
+        let hex = |d: [u8; 32]| {
+            FirmwareDigest {
+                blocks_crc32: 0,
+                sha256: d,
+            }
+            .sha256_hex()
+        };
+        assert_eq!(
+            hex(sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hex(sha256(&[b'a'; 1_000])),
+            "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+        );
+    }
+
+    #[test]
+    fn content_digest_ignores_record_order() {
+        let eof = ihex_record(0x0000, 0x01, &[]);
+        let a = ihex_record(0x0400, 0x00, &[0x01, 0x02]);
+        let b = ihex_record(0x0010, 0x00, &[0xDE, 0xAD]);
+        let one = write_temp(&format!("{a}\n{b}\n{eof}\n"));
+        let two = write_temp(&format!("{b}\n{a}\n{eof}\n"));
+
+        let one = FirmwareImage::load_teensy41(one.path()).unwrap();
+        let two = FirmwareImage::load_teensy41(two.path()).unwrap();
+        assert_eq!(one.content_digest(), two.content_digest());
+        assert_eq!(
+            one.content_digest().blocks_crc32,
+            crc32_ieee(&one.data[..teensy41::BLOCK_SIZE * 2])
+        );
+    }
+
+    #[test]
+    fn equality_ignores_hex_layout_and_trailing_blank() {
+        let eof = ihex_record(0x0000, 0x01, &[]);
+        let one = ihex_record(0x0010, 0x00, &[0xDE, 0xAD, 0xBE, 0xEF]);
+        let split = format!(
+            "{}\n{}\n{}",
+            ihex_record(0x0012, 0x00, &[0xBE, 0xEF]),
+            ihex_record(0x0010, 0x00, &[0xDE, 0xAD]),
+            ihex_record(0x0020, 0x00, &[0xFF, 0xFF]),
+        );
+
+        let mut a = NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut a, format!("{one}\n{eof}\n").as_bytes()).unwrap();
+        let mut b = NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut b, format!("{split}\n{eof}\n").as_bytes()).unwrap();
+
+        let a = FirmwareImage::load_teensy41(a.path()).unwrap();
+        let b = FirmwareImage::load_teensy41(b.path()).unwrap();
+        assert_eq!(a.populated().len(), 0x14);
+        assert_eq!((a.min_addr, a.max_addr), (Some(0x10), Some(0x13)));
+        // Trailing 0xFF data still counts as used.
+        assert_eq!((b.min_addr, b.max_addr), (Some(0x10), Some(0x21)));
+        assert!(a == b);
+        assert_eq!(a.content_digest(), b.content_digest());
+    }
+
     #[test]
     fn test_load_teensy41_maps_flexspi_base() {
         // Set extended linear address = 0x6000 -> 0x60000000 (FlexSPI base)
@@ -258,12 +1389,35 @@ mod tests {
         std::io::Write::write_all(&mut f, content.as_bytes()).unwrap();
 
         let err = match FirmwareImage::load_teensy41(f.path()) {
-            Ok(_) => panic!("expected AddressOutOfRange"),
+            Ok(_) => panic!("expected WrongModel"),
             Err(e) => e,
         };
         match err {
-            HexError::AddressOutOfRange { .. } => {}
-            _ => panic!("expected AddressOutOfRange, got {err:?}"),
+            HexError::WrongModel { max_addr, .. } => assert_eq!(max_addr, 0x607C_0000),
+            _ => panic!("expected WrongModel, got {err:?}"),
+        }
+    }
+
+    #[test]
+    fn test_load_teensy41_reports_stray_out_of_range_line() {
+        // Valid FlexSPI data, then one record in OCRAM (0x20200000).
+        let ext = ihex_record(0x0000, 0x04, &[0x60, 0x00]);
+        let data = ihex_record(0x0010, 0x00, &[0xDE, 0xAD, 0xBE, 0xEF]);
+        let ram = ihex_record(0x0000, 0x04, &[0x20, 0x20]);
+        let stray = ihex_record(0x0000, 0x00, &[0x01]);
+        let eof = ihex_record(0x0000, 0x01, &[]);
+
+        let content = format!("{ext}\n{data}\n{ram}\n{stray}\n{eof}\n");
+        let mut f = NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut f, content.as_bytes()).unwrap();
+
+        match FirmwareImage::load_teensy41(f.path()) {
+            Err(HexError::AddressOutOfRange { line_no, addr }) => {
+                assert_eq!(line_no, 4);
+                assert_eq!(addr, 0x2020_0000);
+            }
+            Err(e) => panic!("expected AddressOutOfRange, got {e:?}"),
+            Ok(_) => panic!("expected AddressOutOfRange"),
         }
     }
 
@@ -285,4 +1439,93 @@ mod tests {
             _ => panic!("expected InvalidChecksum, got {err:?}"),
         }
     }
+
+    #[test]
+    fn test_load_teensy41_reports_invalid_digit_column() {
+        let data = ihex_record(0x0010, 0x00, &[0xDE, 0xAD, 0xBE, 0xEF]);
+        // Corrupt the first payload nibble (':' + 8 header digits, then payload).
+        let bad = format!("{}G{}", &data[..9], &data[10..]);
+        let eof = ihex_record(0x0000, 0x01, &[]);
+        let content = format!("  {bad}\n{eof}\n");
+        let mut f = NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut f, content.as_bytes()).unwrap();
+
+        let err = match FirmwareImage::load_teensy41(f.path()) {
+            Ok(_) => panic!("expected InvalidLine"),
+            Err(e) => e,
+        };
+        match &err {
+            HexError::InvalidLine { line_no, col, msg } => {
+                assert_eq!(*line_no, 1);
+                assert_eq!(*col, Some(12));
+                assert!(msg.contains("'GE'"), "{msg}");
+            }
+            _ => panic!("expected InvalidLine, got {err:?}"),
+        }
+        assert!(err.to_string().starts_with("invalid hex line 1, col 12:"));
+
+        #[cfg(feature = "cli")]
+        {
+            let v = err.to_json_value();
+            assert_eq!(v["error_kind"], "invalid_hex");
+            assert_eq!(v["reason"], "invalid_line");
+            assert_eq!(
+                (v["line_no"].as_u64(), v["col"].as_u64()),
+                (Some(1), Some(12))
+            );
+            assert!(v.get("addr").is_none());
+        }
+    }
+
+    #[test]
+    fn test_load_teensy41_strict_eof_rejects_truncated_file() {
+        let data = ihex_record(0x0010, 0x00, &[0xDE, 0xAD, 0xBE, 0xEF]);
+        let mut f = NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut f, format!("{data}\n").as_bytes()).unwrap();
+
+        assert!(FirmwareImage::load_teensy41(f.path()).is_ok());
+
+        let opts = LoadOptions {
+            strict_eof: true,
+            ..Default::default()
+        };
+        let err = match FirmwareImage::load_teensy41_with_options(f.path(), &opts) {
+            Ok(_) => panic!("expected MissingEof"),
+            Err(e) => e,
+        };
+        assert!(matches!(err, HexError::MissingEof), "{err:?}");
+    }
+
+    #[test]
+    fn test_write_every_block_includes_blank_blocks() {
+        let data = ihex_record(0x0010, 0x00, &[0xDE, 0xAD, 0xBE, 0xEF]);
+        let eof = ihex_record(0x0000, 0x01, &[]);
+        let mut f = NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut f, format!("{data}\n{eof}\n").as_bytes()).unwrap();
+
+        let mut fw = FirmwareImage::load_teensy41(f.path()).unwrap();
+        assert_eq!(fw.blocks_to_write.len(), 1);
+
+        fw.write_every_block();
+        assert_eq!(fw.blocks_to_write.len(), fw.num_blocks);
+        assert_eq!(fw.blocks_to_write[1], fw.board.block_size());
+    }
+
+    #[test]
+    fn test_load_teensy41_max_firmware_bytes() {
+        let data = ihex_record(0x0010, 0x00, &[0xDE, 0xAD, 0xBE, 0xEF]);
+        let eof = ihex_record(0x0000, 0x01, &[]);
+        let mut f = NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut f, format!("{data}\n{eof}\n").as_bytes()).unwrap();
+
+        let opts = LoadOptions {
+            max_firmware_bytes: Some(0x10),
+            ..Default::default()
+        };
+        match FirmwareImage::load_teensy41_with_options(f.path(), &opts) {
+            Err(HexError::TooLarge { bytes, max }) => assert_eq!((bytes, max), (0x14, 0x10)),
+            Err(e) => panic!("expected TooLarge, got {e:?}"),
+            Ok(_) => panic!("expected TooLarge"),
+        }
+    }
 }
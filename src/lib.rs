@@ -1,10 +1,13 @@
 pub mod api;
+pub mod board;
 pub mod bootloader;
 pub mod bridge_control;
+pub mod error_code;
 pub mod halfkay;
 pub mod hex;
 pub mod operation;
 mod operation_runner;
+pub mod readback;
 pub mod reboot_api;
 pub mod selector;
 pub mod serial_reboot;
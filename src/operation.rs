@@ -1,5 +1,7 @@
 use crate::{
     bridge_control,
+    hex::FirmwareDigest,
+    serial_reboot::RebootStrategy,
     targets::{Target, TargetKind},
 };
 
@@ -12,6 +14,12 @@ pub enum OperationEvent {
         index: usize,
         target: Target,
     },
+    /// Non-fatal condition worth surfacing; `code` is a stable snake_case identifier.
+    Warning {
+        code: &'static str,
+        message: String,
+    },
+
     /// Target discovery finished for this poll.
     DiscoverDone {
         count: usize,
@@ -32,14 +40,66 @@ pub enum OperationEvent {
         error: bridge_control::BridgeControlErrorInfo,
     },
     BridgeResumeStart,
+    /// A service/process resume attempt failed and will be retried.
+    BridgeResumeRetry {
+        attempt: u32,
+        retries: u32,
+        error: String,
+    },
     BridgeResumed,
     BridgeResumeFailed {
         error: bridge_control::BridgeControlErrorInfo,
     },
+    /// The bridge was intentionally left paused (see `BridgeResumePolicy`).
+    BridgeLeftPaused {
+        hint: Option<String>,
+    },
+
+    /// Still polling for HalfKay after a soft reboot (reported about once per second).
+    WaitingForHalfKay {
+        target_id: String,
+        elapsed_ms: u64,
+    },
 
+    /// About to parse a firmware file (once per file).
+    HexLoadStart {
+        path: String,
+    },
+    /// Still parsing the HEX file (rate-limited; absent for small files).
+    HexLoading {
+        line_no: usize,
+    },
     HexLoaded {
         bytes: usize,
         blocks: usize,
+        /// `FirmwareImage::crc32()` of the loaded image.
+        crc32: u32,
+        /// `FirmwareImage::content_digest()` of the loaded image.
+        digest: FirmwareDigest,
+    },
+
+    /// Blocks of the image that differ from a baseline image, out of the `total_blocks`
+    /// written (every block is still written).
+    BaselineDiff {
+        total_blocks: usize,
+        changed_blocks: usize,
+    },
+
+    /// Firmware chosen for a target from a `FirmwareMap`.
+    FirmwareAssigned {
+        target_id: String,
+        path: String,
+        crc32: u32,
+    },
+    /// Target left untouched (e.g. no firmware mapped for it).
+    TargetSkipped {
+        target_id: String,
+        reason: String,
+    },
+
+    /// Pausing before the next target (`between_targets_delay`).
+    BetweenTargetsDelay {
+        delay_ms: u64,
     },
 
     /// Operation begins on a target.
@@ -54,9 +114,13 @@ pub enum OperationEvent {
         message: Option<String>,
     },
 
+    /// A reboot command was sent; `Auto` reports each attempt (`MagicBaud`, then `DtrPulse`).
     SoftReboot {
         target_id: String,
         port: String,
+        strategy: RebootStrategy,
+        /// Baud rate set for `MagicBaud` (`--reboot-baud`).
+        baud: u32,
     },
     SoftRebootSkipped {
         target_id: String,
@@ -77,17 +141,96 @@ pub enum OperationEvent {
         total: usize,
         addr: usize,
     },
+    /// Bytes programmed so far on this target, after each block write succeeds.
+    Progress {
+        target_id: String,
+        bytes_written: u64,
+        bytes_total: u64,
+    },
     Retry {
         target_id: String,
         addr: usize,
         attempt: u32,
         retries: u32,
+        /// Wait before the retry (`FlashOptions::retry_policy`).
+        delay_ms: u64,
         error: String,
     },
     Boot {
         target_id: String,
     },
+    /// The HalfKay handle invalidated after the boot command (`confirm_boot`).
+    BootConfirmed {
+        target_id: String,
+    },
+    /// HalfKay was still attached `timeout_ms` after the boot command (`confirm_boot`).
+    BootFailed {
+        target_id: String,
+        timeout_ms: u64,
+    },
+    /// The device came back as USB serial after boot (`atomic` mode).
+    SerialReappeared {
+        target_id: String,
+        port: String,
+    },
+    /// `verify` was requested but flash could not be read back (`reason`: `halfkay_target`,
+    /// `no_reboot` or `readback_unsupported`).
+    VerifySkipped {
+        target_id: String,
+        reason: &'static str,
+    },
+    /// Every written block read back equal to the image (`verify`).
+    Verified {
+        target_id: String,
+        port: String,
+    },
     Done {
         target_id: String,
     },
 }
+
+impl OperationEvent {
+    /// The target this event is scoped to, if any.
+    pub fn target_id(&self) -> Option<&str> {
+        match self {
+            OperationEvent::TargetSelected { target_id }
+            | OperationEvent::WaitingForHalfKay { target_id, .. }
+            | OperationEvent::FirmwareAssigned { target_id, .. }
+            | OperationEvent::TargetSkipped { target_id, .. }
+            | OperationEvent::TargetStart { target_id, .. }
+            | OperationEvent::TargetDone { target_id, .. }
+            | OperationEvent::SoftReboot { target_id, .. }
+            | OperationEvent::SoftRebootSkipped { target_id, .. }
+            | OperationEvent::HalfKayAppeared { target_id, .. }
+            | OperationEvent::HalfKayOpen { target_id, .. }
+            | OperationEvent::Block { target_id, .. }
+            | OperationEvent::Progress { target_id, .. }
+            | OperationEvent::Retry { target_id, .. }
+            | OperationEvent::Boot { target_id }
+            | OperationEvent::BootConfirmed { target_id }
+            | OperationEvent::BootFailed { target_id, .. }
+            | OperationEvent::SerialReappeared { target_id, .. }
+            | OperationEvent::VerifySkipped { target_id, .. }
+            | OperationEvent::Verified { target_id, .. }
+            | OperationEvent::Done { target_id } => Some(target_id),
+            OperationEvent::DiscoverStart
+            | OperationEvent::TargetDetected { .. }
+            | OperationEvent::Warning { .. }
+            | OperationEvent::DiscoverDone { .. }
+            | OperationEvent::BridgePauseStart
+            | OperationEvent::BridgePaused { .. }
+            | OperationEvent::BridgePauseSkipped { .. }
+            | OperationEvent::BridgePauseFailed { .. }
+            | OperationEvent::BridgeResumeStart
+            | OperationEvent::BridgeResumeRetry { .. }
+            | OperationEvent::BridgeResumed
+            | OperationEvent::BridgeResumeFailed { .. }
+            | OperationEvent::BridgeLeftPaused { .. }
+            | OperationEvent::HexLoadStart { .. }
+            | OperationEvent::HexLoading { .. }
+            | OperationEvent::HexLoaded { .. }
+            | OperationEvent::BaselineDiff { .. }
+            | OperationEvent::BetweenTargetsDelay { .. } => None,
+        }
+    }
+}
@@ -1,3 +1,7 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::time::Duration;
+
 use crate::bridge_control;
 use crate::operation::OperationEvent;
 use crate::targets::{Target, TargetKind};
@@ -26,6 +30,7 @@ pub(crate) fn run_targets_with_bridge<
 >(
     selected: Vec<Target>,
     bridge: &bridge_control::BridgeControlOptions,
+    between_targets_delay: Duration,
     pause_bridge: PauseBridge,
     mut run_target: RunTarget,
     errors: RunTargetsErrors<IsAmbiguous, MakeAmbiguous, MakeMultiFailed, MakeBridgePauseFailed>,
@@ -42,101 +47,283 @@ where
     MakeBridgePauseFailed: Fn(bridge_control::BridgeControlErrorInfo) -> E,
 {
     let total = selected.len();
-    let multi = total > 1;
-
-    let needs_serial = selected.iter().any(|t| t.kind() == TargetKind::Serial);
-
-    let mut failed = 0usize;
-    let mut fatal_err: Option<E> = None;
-    let mut ambiguous_message: Option<String> = None;
-
-    let mut bridge_guard: Option<bridge_control::BridgeGuard> = None;
-    if needs_serial {
-        on_event(OperationEvent::BridgePauseStart);
-        let paused = pause_bridge(bridge);
-        match &paused.outcome {
-            bridge_control::BridgePauseOutcome::Paused(info) => {
-                on_event(OperationEvent::BridgePaused { info: info.clone() });
-            }
-            bridge_control::BridgePauseOutcome::Skipped(reason) => {
-                on_event(OperationEvent::BridgePauseSkipped {
-                    reason: reason.clone(),
-                });
-            }
-            bridge_control::BridgePauseOutcome::Failed(error) => {
-                on_event(OperationEvent::BridgePauseFailed {
-                    error: error.clone(),
-                });
-                // Safety-first: if we needed serial but couldn't pause the bridge,
-                // abort before attempting any device operations.
-                return Err((errors.make_bridge_pause_failed)(error.clone()));
-            }
+    let bridge_guard = pause_for_serial_targets(&selected, bridge, pause_bridge, on_event)
+        .map_err(&errors.make_bridge_pause_failed)?;
+
+    let mut results: Vec<Result<(), E>> = Vec::with_capacity(total);
+    for (i, target) in selected.iter().enumerate() {
+        if i > 0 && !between_targets_delay.is_zero() {
+            on_event(OperationEvent::BetweenTargetsDelay {
+                delay_ms: between_targets_delay.as_millis() as u64,
+            });
+            std::thread::sleep(between_targets_delay);
         }
-        bridge_guard = paused.guard;
+        results.push(run_one_target(target, on_event, &mut run_target));
     }
 
-    if fatal_err.is_none() {
-        for target in selected {
-            let target_id = target.id();
-            on_event(OperationEvent::TargetStart {
-                target_id: target_id.clone(),
-                kind: target.kind(),
-            });
+    let result = combine_results(results, total, &errors);
+    resume_bridge_with_events(bridge_guard, bridge, result.is_ok(), on_event);
+    result
+}
 
-            match run_target(&target, &target_id, on_event) {
-                Ok(()) => {
-                    on_event(OperationEvent::TargetDone {
-                        target_id,
-                        ok: true,
-                        message: None,
-                    });
-                }
-                Err(e) => {
-                    failed += 1;
-                    if (errors.is_ambiguous)(&e) && ambiguous_message.is_none() {
-                        ambiguous_message = Some(e.to_string());
-                    }
-
-                    on_event(OperationEvent::TargetDone {
-                        target_id,
-                        ok: false,
-                        message: Some(e.to_string()),
-                    });
-
-                    if !multi {
-                        fatal_err = Some(e);
-                        break;
-                    }
+/// Scheduling knobs of [`run_targets_with_bridge_parallel`].
+pub(crate) struct ParallelPacing {
+    /// Max HalfKay targets in flight.
+    pub workers: usize,
+    /// Pause between consecutive serial targets.
+    pub between_targets_delay: Duration,
+}
+
+/// Like [`run_targets_with_bridge`], but runs HalfKay targets on up to `workers` threads.
+///
+/// HalfKay targets share no serial state, so they are flashed concurrently first; serial
+/// targets then run one at a time on the calling thread (each soft reboot waits for "a new
+/// HalfKay device", which concurrent work would confuse). oc-bridge is paused once around
+/// the whole run. Worker events are forwarded to `on_event` on the calling thread, each
+/// target's events in order. Every failure is counted (as with several targets sequentially);
+/// `between_targets_delay` only applies between serial targets.
+pub(crate) fn run_targets_with_bridge_parallel<
+    F,
+    E,
+    RunTarget,
+    IsAmbiguous,
+    MakeAmbiguous,
+    MakeMultiFailed,
+    MakeBridgePauseFailed,
+    PauseBridge,
+>(
+    selected: Vec<Target>,
+    bridge: &bridge_control::BridgeControlOptions,
+    pacing: ParallelPacing,
+    pause_bridge: PauseBridge,
+    run_target: RunTarget,
+    errors: RunTargetsErrors<IsAmbiguous, MakeAmbiguous, MakeMultiFailed, MakeBridgePauseFailed>,
+    on_event: &mut F,
+) -> Result<(), E>
+where
+    F: FnMut(OperationEvent),
+    E: std::fmt::Display + Send,
+    PauseBridge: FnOnce(&bridge_control::BridgeControlOptions) -> bridge_control::BridgePause,
+    RunTarget: Fn(&Target, &str, &mut dyn FnMut(OperationEvent)) -> Result<(), E> + Sync,
+    IsAmbiguous: Fn(&E) -> bool,
+    MakeAmbiguous: Fn(String) -> E,
+    MakeMultiFailed: Fn(usize, usize) -> E,
+    MakeBridgePauseFailed: Fn(bridge_control::BridgeControlErrorInfo) -> E,
+{
+    let total = selected.len();
+    let bridge_guard = pause_for_serial_targets(&selected, bridge, pause_bridge, on_event)
+        .map_err(&errors.make_bridge_pause_failed)?;
+
+    let (halfkay, serial): (Vec<Target>, Vec<Target>) = selected
+        .into_iter()
+        .partition(|t| t.kind() == TargetKind::HalfKay);
+
+    let mut results: Vec<Result<(), E>> = Vec::with_capacity(total);
+    let next = AtomicUsize::new(0);
+    let done = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        let (tx, rx) = mpsc::channel::<OperationEvent>();
+        for _ in 0..pacing.workers.clamp(1, halfkay.len().max(1)) {
+            let tx = tx.clone();
+            let (next, done, halfkay, run_target) = (&next, &done, &halfkay, &run_target);
+            scope.spawn(move || {
+                while let Some(target) = halfkay.get(next.fetch_add(1, Ordering::SeqCst)) {
+                    let mut send = |ev| {
+                        let _ = tx.send(ev);
+                    };
+                    let res = run_one_target(target, &mut send, |t, id, ev| run_target(t, id, ev));
+                    done.lock().unwrap_or_else(|e| e.into_inner()).push(res);
                 }
-            }
+            });
+        }
+        drop(tx);
+        for ev in rx {
+            on_event(ev);
+        }
+    });
+    results.extend(done.into_inner().unwrap_or_else(|e| e.into_inner()));
+
+    for (i, target) in serial.iter().enumerate() {
+        if i > 0 && !pacing.between_targets_delay.is_zero() {
+            on_event(OperationEvent::BetweenTargetsDelay {
+                delay_ms: pacing.between_targets_delay.as_millis() as u64,
+            });
+            std::thread::sleep(pacing.between_targets_delay);
         }
+        results.push(run_one_target(target, on_event, |t, id, ev| {
+            run_target(t, id, ev)
+        }));
     }
 
-    let result = if let Some(e) = fatal_err {
-        Err(e)
-    } else if let Some(message) = ambiguous_message {
-        Err((errors.make_ambiguous)(message))
-    } else if failed > 0 {
-        Err((errors.make_multi_failed)(failed, total))
-    } else {
+    let result = combine_results(results, total, &errors);
+    resume_bridge_with_events(bridge_guard, bridge, result.is_ok(), on_event);
+    result
+}
+
+/// Runs one target between its `TargetStart` and `TargetDone` events.
+fn run_one_target<E, G, RunTarget>(
+    target: &Target,
+    on_event: &mut G,
+    run_target: RunTarget,
+) -> Result<(), E>
+where
+    E: std::fmt::Display,
+    G: FnMut(OperationEvent),
+    RunTarget: FnOnce(&Target, &str, &mut G) -> Result<(), E>,
+{
+    let target_id = target.id();
+    on_event(OperationEvent::TargetStart {
+        target_id: target_id.clone(),
+        kind: target.kind(),
+    });
+    let res = run_target(target, &target_id, on_event);
+    on_event(OperationEvent::TargetDone {
+        target_id,
+        ok: res.is_ok(),
+        message: res.as_ref().err().map(|e| e.to_string()),
+    });
+    res
+}
+
+/// Folds the per-target results of a run into its result.
+///
+/// A single target fails with its own error. With several, every failure is counted: the
+/// first ambiguous-target error wins (so the caller still gets its hint), otherwise the run
+/// fails with `make_multi_failed(failed, total)`.
+fn combine_results<E, IsAmbiguous, MakeAmbiguous, MakeMultiFailed, MakeBridgePauseFailed>(
+    results: Vec<Result<(), E>>,
+    total: usize,
+    errors: &RunTargetsErrors<IsAmbiguous, MakeAmbiguous, MakeMultiFailed, MakeBridgePauseFailed>,
+) -> Result<(), E>
+where
+    E: std::fmt::Display,
+    IsAmbiguous: Fn(&E) -> bool,
+    MakeAmbiguous: Fn(String) -> E,
+    MakeMultiFailed: Fn(usize, usize) -> E,
+{
+    let mut failures: Vec<E> = results.into_iter().filter_map(Result::err).collect();
+    if total <= 1 {
+        return failures.pop().map_or(Ok(()), Err);
+    }
+    if let Some(e) = failures.iter().find(|e| (errors.is_ambiguous)(e)) {
+        return Err((errors.make_ambiguous)(e.to_string()));
+    }
+    if failures.is_empty() {
         Ok(())
-    };
+    } else {
+        Err((errors.make_multi_failed)(failures.len(), total))
+    }
+}
 
-    if let Some(mut g) = bridge_guard {
-        on_event(OperationEvent::BridgeResumeStart);
-        let hint = g.resume_hint();
-        match g.resume() {
-            Ok(()) => on_event(OperationEvent::BridgeResumed),
-            Err(e) => on_event(OperationEvent::BridgeResumeFailed {
-                error: bridge_control::BridgeControlErrorInfo {
-                    message: format!("bridge resume failed: {e}"),
-                    hint,
-                },
-            }),
+/// Pauses oc-bridge when any of `selected` is a serial target (see [`pause_bridge_with_events`]).
+fn pause_for_serial_targets<F, PauseBridge>(
+    selected: &[Target],
+    bridge: &bridge_control::BridgeControlOptions,
+    pause_bridge: PauseBridge,
+    on_event: &mut F,
+) -> Result<Option<bridge_control::BridgeGuard>, bridge_control::BridgeControlErrorInfo>
+where
+    F: FnMut(OperationEvent),
+    PauseBridge: FnOnce(&bridge_control::BridgeControlOptions) -> bridge_control::BridgePause,
+{
+    if !selected.iter().any(|t| matches!(t, Target::Serial(_))) {
+        return Ok(None);
+    }
+    pause_bridge_with_events(bridge, pause_bridge, on_event)
+}
+
+/// Pauses oc-bridge, reporting the active-session warning and the pause outcome as events.
+///
+/// The warning names the serial port oc-bridge reports holding. A failed pause is returned so callers abort before touching any device.
+pub(crate) fn pause_bridge_with_events<F, PauseBridge>(
+    bridge: &bridge_control::BridgeControlOptions,
+    pause_bridge: PauseBridge,
+    on_event: &mut F,
+) -> Result<Option<bridge_control::BridgeGuard>, bridge_control::BridgeControlErrorInfo>
+where
+    F: FnMut(OperationEvent),
+    PauseBridge: FnOnce(&bridge_control::BridgeControlOptions) -> bridge_control::BridgePause,
+{
+    if let Some(status) = bridge
+        .warn_active_session
+        .then(|| bridge_control::active_session(bridge))
+        .flatten()
+    {
+        on_event(OperationEvent::Warning {
+            code: "bridge_session_active",
+            message: session_warning(&status),
+        });
+    }
+
+    on_event(OperationEvent::BridgePauseStart);
+    let paused = pause_bridge(bridge);
+    match paused.outcome {
+        bridge_control::BridgePauseOutcome::Paused(info) => {
+            on_event(OperationEvent::BridgePaused { info });
+        }
+        bridge_control::BridgePauseOutcome::Skipped(reason) => {
+            on_event(OperationEvent::BridgePauseSkipped { reason });
+        }
+        bridge_control::BridgePauseOutcome::Failed(error) => {
+            on_event(OperationEvent::BridgePauseFailed {
+                error: error.clone(),
+            });
+            // Safety-first: if we needed serial but couldn't pause the bridge,
+            // abort before attempting any device operations.
+            return Err(error);
         }
     }
+    Ok(paused.guard)
+}
 
-    result
+fn session_warning(status: &bridge_control::BridgeControlStatus) -> String {
+    match &status.serial_port {
+        Some(port) => format!("this will interrupt an active oc-bridge session on {port}"),
+        None => "this will interrupt an active oc-bridge session".to_string(),
+    }
+}
+
+/// Applies the resume policy to `guard` (if any), reporting the outcome as events.
+pub(crate) fn resume_bridge_with_events<F>(
+    guard: Option<bridge_control::BridgeGuard>,
+    bridge: &bridge_control::BridgeControlOptions,
+    ok: bool,
+    on_event: &mut F,
+) where
+    F: FnMut(OperationEvent),
+{
+    let Some(mut g) = guard else {
+        return;
+    };
+    let resume = match bridge.resume_policy {
+        bridge_control::BridgeResumePolicy::Always => true,
+        bridge_control::BridgeResumePolicy::OnSuccess => ok,
+        bridge_control::BridgeResumePolicy::Never => false,
+    };
+    if !resume {
+        on_event(OperationEvent::BridgeLeftPaused {
+            hint: g.keep_paused(),
+        });
+        return;
+    }
+
+    on_event(OperationEvent::BridgeResumeStart);
+    let hint = g.resume_hint();
+    match g.resume_with_retries(|attempt, retries, e| {
+        on_event(OperationEvent::BridgeResumeRetry {
+            attempt,
+            retries,
+            error: e.to_string(),
+        })
+    }) {
+        Ok(()) => on_event(OperationEvent::BridgeResumed),
+        Err(e) => on_event(OperationEvent::BridgeResumeFailed {
+            error: bridge_control::BridgeControlErrorInfo {
+                message: format!("bridge resume failed: {e}"),
+                hint,
+            },
+        }),
+    }
 }
 
 #[cfg(test)]
@@ -144,7 +331,6 @@ mod tests {
     use super::*;
     use crate::targets::{HalfKayTarget, SerialTarget, Target, PJRC_VID};
     use std::sync::{Arc, Mutex};
-    use std::time::Duration;
 
     #[derive(Debug)]
     struct DummyError(String);
@@ -155,6 +341,21 @@ mod tests {
         }
     }
 
+    /// Bridge control that fails fast; override fields with `..bridge_opts()`.
+    fn bridge_opts() -> bridge_control::BridgeControlOptions {
+        bridge_control::BridgeControlOptions {
+            method: bridge_control::BridgeControlMethod::Control,
+            control_timeout: Duration::from_millis(1),
+            timeout: Duration::from_millis(1),
+            allow_process_fallback: false,
+            warn_active_session: false,
+            resume_retries: 0,
+            resume_retry_interval: Duration::ZERO,
+            control_retries: 0,
+            ..Default::default()
+        }
+    }
+
     fn serial_target(port: &str) -> Target {
         Target::Serial(SerialTarget {
             port_name: port.to_string(),
@@ -171,21 +372,14 @@ mod tests {
             vid: PJRC_VID,
             pid: crate::teensy41::PID_HALFKAY,
             path: path.to_string(),
+            bcd_device: None,
         })
     }
 
     #[test]
     fn pause_failed_aborts_before_touching_device() {
         let selected = vec![serial_target("COM6")];
-        let opts = bridge_control::BridgeControlOptions {
-            enabled: true,
-            method: bridge_control::BridgeControlMethod::Control,
-            control_port: 7999,
-            control_timeout: Duration::from_millis(1),
-            timeout: Duration::from_millis(1),
-            service_id: None,
-            allow_process_fallback: false,
-        };
+        let opts = bridge_opts();
 
         let ran = Arc::new(Mutex::new(false));
         let ran2 = ran.clone();
@@ -195,6 +389,7 @@ mod tests {
         let res = run_targets_with_bridge(
             selected,
             &opts,
+            Duration::ZERO,
             |_opts| bridge_control::BridgePause {
                 guard: None,
                 outcome: bridge_control::BridgePauseOutcome::Failed(
@@ -240,15 +435,7 @@ mod tests {
     #[test]
     fn resume_events_emitted_even_when_target_fails() {
         let selected = vec![serial_target("COM6")];
-        let opts = bridge_control::BridgeControlOptions {
-            enabled: true,
-            method: bridge_control::BridgeControlMethod::Control,
-            control_port: 7999,
-            control_timeout: Duration::from_millis(1),
-            timeout: Duration::from_millis(1),
-            service_id: None,
-            allow_process_fallback: false,
-        };
+        let opts = bridge_opts();
 
         let events: Arc<Mutex<Vec<OperationEvent>>> = Arc::new(Mutex::new(Vec::new()));
         let events2 = events.clone();
@@ -256,6 +443,7 @@ mod tests {
         let res = run_targets_with_bridge(
             selected,
             &opts,
+            Duration::ZERO,
             |_opts| bridge_control::BridgePause {
                 guard: Some(bridge_control::test_noop_guard()),
                 outcome: bridge_control::BridgePauseOutcome::Paused(
@@ -263,6 +451,8 @@ mod tests {
                         method: bridge_control::BridgePauseMethod::Control,
                         id: "127.0.0.1:7999".to_string(),
                         pids: Vec::new(),
+                        serial_open: Some(false),
+                        message: None,
                     },
                 ),
             },
@@ -295,13 +485,8 @@ mod tests {
     fn halfkay_targets_do_not_pause_bridge() {
         let selected = vec![halfkay_target("\\\\?\\HID#VID_16C0&PID_0478#TEST")];
         let opts = bridge_control::BridgeControlOptions {
-            enabled: true,
             method: bridge_control::BridgeControlMethod::Auto,
-            control_port: 7999,
-            control_timeout: Duration::from_millis(1),
-            timeout: Duration::from_millis(1),
-            service_id: None,
-            allow_process_fallback: false,
+            ..bridge_opts()
         };
 
         let events: Arc<Mutex<Vec<OperationEvent>>> = Arc::new(Mutex::new(Vec::new()));
@@ -310,6 +495,7 @@ mod tests {
         let res = run_targets_with_bridge(
             selected,
             &opts,
+            Duration::ZERO,
             |_opts| panic!("pause bridge should not be called for halfkay targets"),
             |_target, _target_id, _on_event| Ok(()),
             RunTargetsErrors {
@@ -341,4 +527,186 @@ mod tests {
             .iter()
             .any(|e| matches!(e, OperationEvent::BridgeResumed)));
     }
+
+    #[test]
+    fn between_targets_delay_is_reported_between_targets_only() {
+        let selected = vec![halfkay_target("HK1"), halfkay_target("HK2")];
+        let opts = bridge_opts();
+
+        let events: Arc<Mutex<Vec<OperationEvent>>> = Arc::new(Mutex::new(Vec::new()));
+        let events2 = events.clone();
+
+        let res = run_targets_with_bridge(
+            selected,
+            &opts,
+            Duration::from_millis(1),
+            |_opts| panic!("pause bridge should not be called for halfkay targets"),
+            |_target, _target_id, _on_event| Ok(()),
+            RunTargetsErrors {
+                is_ambiguous: |_e: &DummyError| false,
+                make_ambiguous: DummyError,
+                make_multi_failed: |_failed, _total| DummyError("multi".to_string()),
+                make_bridge_pause_failed: |err: bridge_control::BridgeControlErrorInfo| {
+                    DummyError(err.message)
+                },
+            },
+            &mut |ev| events2.lock().unwrap().push(ev),
+        );
+
+        assert!(res.is_ok());
+        let evs = events.lock().unwrap();
+        let delays = evs
+            .iter()
+            .filter(|e| matches!(e, OperationEvent::BetweenTargetsDelay { delay_ms: 1 }))
+            .count();
+        assert_eq!(delays, 1);
+    }
+
+    #[test]
+    fn resume_policy_never_leaves_bridge_paused() {
+        let selected = vec![serial_target("COM6")];
+        let opts = bridge_control::BridgeControlOptions {
+            resume_policy: bridge_control::BridgeResumePolicy::Never,
+            ..bridge_opts()
+        };
+
+        let events: Arc<Mutex<Vec<OperationEvent>>> = Arc::new(Mutex::new(Vec::new()));
+        let events2 = events.clone();
+
+        let res = run_targets_with_bridge(
+            selected,
+            &opts,
+            Duration::ZERO,
+            |_opts| bridge_control::BridgePause {
+                guard: Some(bridge_control::test_noop_guard()),
+                outcome: bridge_control::BridgePauseOutcome::Paused(
+                    bridge_control::BridgePauseInfo {
+                        method: bridge_control::BridgePauseMethod::Control,
+                        id: "127.0.0.1:7999".to_string(),
+                        pids: Vec::new(),
+                        serial_open: Some(false),
+                        message: None,
+                    },
+                ),
+            },
+            |_target, _target_id, _on_event| Ok(()),
+            RunTargetsErrors {
+                is_ambiguous: |_e: &DummyError| false,
+                make_ambiguous: DummyError,
+                make_multi_failed: |_failed, _total| DummyError("multi".to_string()),
+                make_bridge_pause_failed: |err: bridge_control::BridgeControlErrorInfo| {
+                    DummyError(err.message)
+                },
+            },
+            &mut |ev| events2.lock().unwrap().push(ev),
+        );
+
+        assert!(res.is_ok());
+        let evs = events.lock().unwrap();
+        assert!(evs
+            .iter()
+            .any(|e| matches!(e, OperationEvent::BridgeLeftPaused { .. })));
+        assert!(!evs
+            .iter()
+            .any(|e| matches!(e, OperationEvent::BridgeResumeStart)));
+    }
+
+    #[test]
+    fn parallel_runs_halfkay_targets_concurrently_and_counts_failures() {
+        use std::sync::atomic::AtomicUsize;
+
+        let selected = vec![
+            halfkay_target("HK1"),
+            halfkay_target("HK2"),
+            halfkay_target("HK3"),
+            halfkay_target("HK4"),
+        ];
+        let running = AtomicUsize::new(0);
+        let max_running = AtomicUsize::new(0);
+        let mut events = Vec::new();
+
+        let res = run_targets_with_bridge_parallel(
+            selected,
+            &bridge_control::BridgeControlOptions::default(),
+            ParallelPacing {
+                workers: 2,
+                between_targets_delay: Duration::ZERO,
+            },
+            |_opts| panic!("pause bridge should not be called for halfkay targets"),
+            |_target, target_id, on_event| {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(now, Ordering::SeqCst);
+                on_event(OperationEvent::Boot {
+                    target_id: target_id.to_string(),
+                });
+                std::thread::sleep(Duration::from_millis(50));
+                running.fetch_sub(1, Ordering::SeqCst);
+                if target_id.ends_with("HK3") {
+                    Err(DummyError("boom".to_string()))
+                } else {
+                    Ok(())
+                }
+            },
+            RunTargetsErrors {
+                is_ambiguous: |_e: &DummyError| false,
+                make_ambiguous: DummyError,
+                make_multi_failed: |failed, total| DummyError(format!("{failed}/{total}")),
+                make_bridge_pause_failed: |err: bridge_control::BridgeControlErrorInfo| {
+                    DummyError(err.message)
+                },
+            },
+            &mut |ev| events.push(ev),
+        );
+
+        assert_eq!(res.unwrap_err().0, "1/4");
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+        let done: Vec<bool> = events
+            .iter()
+            .filter_map(|e| match e {
+                OperationEvent::TargetDone { ok, .. } => Some(*ok),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(done.len(), 4);
+        assert_eq!(done.iter().filter(|ok| !**ok).count(), 1);
+        // Each target's own events stay in order.
+        for id in ["halfkay:HK1", "halfkay:HK3"] {
+            let kinds: Vec<&str> = events
+                .iter()
+                .filter(|e| e.target_id() == Some(id))
+                .map(|e| match e {
+                    OperationEvent::TargetStart { .. } => "start",
+                    OperationEvent::Boot { .. } => "boot",
+                    OperationEvent::TargetDone { .. } => "done",
+                    _ => "other",
+                })
+                .collect();
+            assert_eq!(kinds, ["start", "boot", "done"]);
+        }
+    }
+
+    #[test]
+    fn session_warning_names_the_bridge_port() {
+        let status = bridge_control::BridgeControlStatus {
+            ok: true,
+            paused: false,
+            serial_open: Some(true),
+            message: None,
+            client_count: None,
+            uptime_ms: None,
+            serial_port: Some("COM6".to_string()),
+        };
+        assert_eq!(
+            session_warning(&status),
+            "this will interrupt an active oc-bridge session on COM6"
+        );
+        let older = bridge_control::BridgeControlStatus {
+            serial_port: None,
+            ..status
+        };
+        assert_eq!(
+            session_warning(&older),
+            "this will interrupt an active oc-bridge session"
+        );
+    }
 }
@@ -0,0 +1,267 @@
+//! Post-flash verification by reading flash back over USB serial.
+//!
+//! HalfKay cannot read flash, so verification needs the freshly booted firmware's help. Firmware
+//! opts in by implementing this line protocol on its USB serial port:
+//!
+//! 1. Handshake: the loader sends [`HANDSHAKE_REQUEST`] (`msl-readback?\n`). Firmware that
+//!    implements the protocol answers with exactly [`HANDSHAKE_REPLY`] (`msl-readback 1\n`, the
+//!    protocol version) as its first line.
+//! 2. `read <addr> <len>\n` (`addr` in hex, `len` in decimal) -> exactly `len` raw bytes of flash
+//!    starting at absolute address `addr`.
+//!
+//! `read` is only ever sent after a matching handshake reply. Any other first line, or no reply
+//! at all (stock firmware ignores the request), means the capability is not advertised and the
+//! target is reported unsupported. Only a `read` left unanswered is a [`ReadbackError::Timeout`].
+
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::hex::FirmwareImage;
+use crate::teensy41;
+
+/// Sent first to ask whether the firmware implements readback.
+pub const HANDSHAKE_REQUEST: &str = "msl-readback?\n";
+/// The only reply that enables `read` (protocol version 1).
+pub const HANDSHAKE_REPLY: &str = "msl-readback 1\n";
+
+/// Longest handshake reply accepted; anything longer is not this protocol.
+const MAX_HANDSHAKE_LINE: usize = 256;
+
+#[derive(Error, Debug)]
+pub enum ReadbackError {
+    #[error("serial port '{port}': {source}")]
+    Open {
+        port: String,
+        #[source]
+        source: serialport::Error,
+    },
+
+    #[error("firmware did not answer the readback {stage} in time")]
+    Timeout { stage: &'static str },
+
+    #[error("readback I/O: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Result of comparing flash against the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadbackOutcome {
+    /// The firmware did not answer the handshake with [`HANDSHAKE_REPLY`]; nothing was compared.
+    Unsupported,
+    /// Every written block matches the image.
+    Match,
+    /// First differing byte, as a flash offset (like `FlashError::WriteFailed`).
+    Mismatch { addr: usize },
+}
+
+/// Open `port_name`, run the handshake and compare every written block of `fw`.
+///
+/// An unrecognized or missing handshake reply is [`ReadbackOutcome::Unsupported`]; a `read`
+/// unanswered within `timeout` is [`ReadbackError::Timeout`].
+pub fn verify_port(
+    port_name: &str,
+    fw: &FirmwareImage,
+    timeout: Duration,
+) -> Result<ReadbackOutcome, ReadbackError> {
+    let mut port = serialport::new(port_name, 115_200)
+        .timeout(timeout)
+        .open()
+        .map_err(|e| ReadbackError::Open {
+            port: port_name.to_string(),
+            source: e,
+        })?;
+    verify(&mut port, fw)
+}
+
+/// [`verify_port`] over any byte stream (the serial port in practice).
+pub fn verify<S>(port: &mut S, fw: &FirmwareImage) -> Result<ReadbackOutcome, ReadbackError>
+where
+    S: Read + Write + ?Sized,
+{
+    if !handshake(port)? {
+        return Ok(ReadbackOutcome::Unsupported);
+    }
+    let block_size = fw.board.block_size();
+    let mut buf = vec![0u8; block_size];
+    for &block_addr in &fw.blocks_to_write {
+        let expected = &fw.data[block_addr..block_addr + block_size];
+        read_flash(port, teensy41::FLEXSPI_BASE + block_addr as u32, &mut buf)?;
+        if let Some(i) = buf.iter().zip(expected).position(|(a, b)| a != b) {
+            return Ok(ReadbackOutcome::Mismatch {
+                addr: block_addr + i,
+            });
+        }
+    }
+    Ok(ReadbackOutcome::Match)
+}
+
+/// Send [`HANDSHAKE_REQUEST`] and report whether the first reply line is [`HANDSHAKE_REPLY`].
+///
+/// Silence until the port timeout is a "no": the firmware does not advertise readback.
+fn handshake<S>(port: &mut S) -> Result<bool, ReadbackError>
+where
+    S: Read + Write + ?Sized,
+{
+    port.write_all(HANDSHAKE_REQUEST.as_bytes())?;
+    port.flush()?;
+
+    let expected = HANDSHAKE_REPLY.trim_end().as_bytes();
+    let mut line = Vec::new();
+    let mut byte = [0u8; 1];
+    loop {
+        match port.read(&mut byte) {
+            Ok(0) => break,
+            Ok(_) if byte[0] == b'\n' => break,
+            Ok(_) if line.len() >= MAX_HANDSHAKE_LINE => return Ok(false),
+            Ok(_) => line.push(byte[0]),
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => return Ok(false),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(line.strip_suffix(b"\r").unwrap_or(&line) == expected)
+}
+
+fn read_flash<S>(port: &mut S, addr: u32, buf: &mut [u8]) -> Result<(), ReadbackError>
+where
+    S: Read + Write + ?Sized,
+{
+    port.write_all(format!("read {addr:08x} {}\n", buf.len()).as_bytes())?;
+    port.flush()?;
+    port.read_exact(buf).map_err(|e| timeout_or_io(e, "read"))
+}
+
+fn timeout_or_io(e: io::Error, stage: &'static str) -> ReadbackError {
+    match e.kind() {
+        io::ErrorKind::TimedOut => ReadbackError::Timeout { stage },
+        _ => ReadbackError::Io(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+
+    /// Scripted firmware: answers the handshake with `hello` and serves `read` from `flash`
+    /// (flash offsets); with an empty `flash`, `read` goes unanswered.
+    struct FakeFirmware {
+        hello: &'static str,
+        flash: Vec<u8>,
+        input: Vec<u8>,
+        output: io::Cursor<Vec<u8>>,
+    }
+
+    impl Write for FakeFirmware {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.input.extend_from_slice(buf);
+            while let Some(end) = self.input.iter().position(|&b| b == b'\n') {
+                let cmd = String::from_utf8(self.input.drain(..=end).collect()).unwrap();
+                let mut reply = self.output.get_ref()[self.output.position() as usize..].to_vec();
+                let parts: Vec<&str> = cmd.split_whitespace().collect();
+                match parts.as_slice() {
+                    ["msl-readback?"] => reply.extend_from_slice(self.hello.as_bytes()),
+                    ["read", ..] if self.flash.is_empty() => {}
+                    ["read", addr, len] => {
+                        let addr = u32::from_str_radix(addr, 16).unwrap() - teensy41::FLEXSPI_BASE;
+                        let len: usize = len.parse().unwrap();
+                        reply.extend_from_slice(&self.flash[addr as usize..addr as usize + len]);
+                    }
+                    _ => panic!("unexpected command {cmd:?}"),
+                }
+                self.output = io::Cursor::new(reply);
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Read for FakeFirmware {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.output.read(buf)? {
+                0 => Err(io::ErrorKind::TimedOut.into()),
+                n => Ok(n),
+            }
+        }
+    }
+
+    fn image() -> FirmwareImage {
+        let board = Board::Teensy41;
+        let mut data = vec![0xFF; board.code_size()];
+        data[..4].copy_from_slice(&[1, 2, 3, 4]);
+        data[board.block_size() + 7] = 0x42;
+        FirmwareImage {
+            num_blocks: board.code_size() / board.block_size(),
+            blocks_to_write: vec![0, board.block_size()],
+            byte_count: 5,
+            min_addr: Some(0),
+            max_addr: Some(board.block_size() + 7),
+            start_address: None,
+            fill: 0xFF,
+            board,
+            data,
+        }
+    }
+
+    fn firmware(hello: &'static str, flash: Vec<u8>) -> FakeFirmware {
+        FakeFirmware {
+            hello,
+            flash,
+            input: Vec::new(),
+            output: io::Cursor::new(Vec::new()),
+        }
+    }
+
+    #[test]
+    fn verify_compares_written_blocks() {
+        let fw = image();
+
+        let mut dev = firmware(HANDSHAKE_REPLY, fw.data.clone());
+        assert_eq!(verify(&mut dev, &fw).unwrap(), ReadbackOutcome::Match);
+
+        let mut flash = fw.data.clone();
+        flash[fw.board.block_size() + 7] = 0x00;
+        let mut dev = firmware("msl-readback 1\r\n", flash);
+        assert_eq!(
+            verify(&mut dev, &fw).unwrap(),
+            ReadbackOutcome::Mismatch {
+                addr: fw.board.block_size() + 7
+            }
+        );
+    }
+
+    #[test]
+    fn verify_without_handshake_sends_no_read() {
+        let fw = image();
+
+        // A `read` would go unanswered and fail the verify.
+        for hello in ["readback\n", "msl-readback 2\n", "msl-readback 1 extra\n"] {
+            let mut dev = firmware(hello, Vec::new());
+            assert_eq!(verify(&mut dev, &fw).unwrap(), ReadbackOutcome::Unsupported);
+        }
+    }
+
+    #[test]
+    fn unanswered_handshake_is_unsupported() {
+        let fw = image();
+        // Stock firmware ignores the request, or the reply is cut short.
+        for hello in ["", "msl-read"] {
+            let mut dev = firmware(hello, Vec::new());
+            assert_eq!(verify(&mut dev, &fw).unwrap(), ReadbackOutcome::Unsupported);
+        }
+    }
+
+    #[test]
+    fn unanswered_read_is_a_timeout() {
+        let fw = image();
+        let mut dev = firmware(HANDSHAKE_REPLY, Vec::new());
+        assert!(matches!(
+            verify(&mut dev, &fw),
+            Err(ReadbackError::Timeout { stage: "read" })
+        ));
+    }
+}
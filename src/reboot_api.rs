@@ -1,4 +1,6 @@
 use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
 use thiserror::Error;
@@ -6,7 +8,10 @@ use tracing::{debug, warn};
 
 use crate::api::FlashSelection;
 use crate::operation::OperationEvent;
-use crate::{bootloader, bridge_control, halfkay, serial_reboot, targets, targets::Target};
+use crate::{
+    bootloader, bridge_control, error_code, halfkay, serial_reboot, targets, targets::Target,
+    teensy41,
+};
 
 #[derive(Debug, Clone)]
 pub struct RebootOptions {
@@ -29,9 +34,31 @@ pub struct RebootOptions {
     /// Delay after triggering a serial reboot before polling for HalfKay.
     pub soft_reboot_delay: Duration,
 
+    /// Baud rate that triggers the soft reboot (see
+    /// [`serial_reboot::soft_reboot_port_with_baud`]).
+    pub reboot_baud: u32,
+
+    /// How serial targets are rebooted into HalfKay.
+    pub reboot_strategy: serial_reboot::RebootStrategy,
+
+    /// Pause between consecutive targets (e.g. to let a USB hub's power settle).
+    pub between_targets_delay: Duration,
+
     pub bridge: bridge_control::BridgeControlOptions,
+
+    /// [`boot_teensy41_with_selection`]: after the boot command, wait up to this long for the
+    /// board's USB serial port to come back (`SerialReappeared`). None = don't wait.
+    pub wait_for_serial_after_boot: Option<Duration>,
+
+    /// Cancellation flag; set it to `true` to abort the wait for HalfKay.
+    ///
+    /// Cancelling returns `RebootError::Cancelled` and still resumes oc-bridge.
+    pub cancel: Option<Arc<AtomicBool>>,
 }
 
+/// How often `WaitingForHalfKay` is reported while polling.
+const WAITING_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
 impl Default for RebootOptions {
     fn default() -> Self {
         Self {
@@ -39,19 +66,36 @@ impl Default for RebootOptions {
             wait_timeout: Some(Duration::from_secs(60)),
             poll_interval: Duration::from_millis(50),
             soft_reboot_delay: Duration::from_millis(250),
+            reboot_baud: serial_reboot::TEENSY_REBOOT_BAUD,
+            reboot_strategy: serial_reboot::RebootStrategy::default(),
+            between_targets_delay: Duration::ZERO,
             bridge: bridge_control::BridgeControlOptions::default(),
+            wait_for_serial_after_boot: None,
+            cancel: None,
         }
     }
 }
 
+impl RebootOptions {
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|c| c.load(Ordering::SeqCst))
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum RebootErrorKind {
     NoDevice,
     AmbiguousTarget,
+    /// Booted, but the firmware's USB serial port did not come back in time.
+    BootNotConfirmed,
+    Cancelled,
     Unexpected,
 }
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum RebootError {
     #[error("no target device found")]
     NoTargets,
@@ -78,9 +122,22 @@ pub enum RebootError {
     #[error("HalfKay did not appear after soft reboot")]
     HalfKayTimeout,
 
+    #[error("unable to open HalfKay device at {path}: {source}")]
+    OpenHalfKay {
+        path: String,
+        #[source]
+        source: halfkay::HalfKayError,
+    },
+
+    #[error("device did not come back as USB serial within {timeout_ms}ms after boot")]
+    BootNotConfirmed { timeout_ms: u64 },
+
     #[error("unable to pause oc-bridge: {message}")]
     BridgePauseFailed { message: String },
 
+    #[error("reboot cancelled")]
+    Cancelled,
+
     #[error("reboot failed for {failed}/{total} targets")]
     MultiTargetFailed { failed: usize, total: usize },
 
@@ -97,22 +154,51 @@ impl RebootError {
             RebootError::DiscoveryFailed { .. } => RebootErrorKind::Unexpected,
             RebootError::SoftRebootFailed { .. } => RebootErrorKind::NoDevice,
             RebootError::HalfKayTimeout => RebootErrorKind::NoDevice,
+            RebootError::OpenHalfKay { .. } => RebootErrorKind::NoDevice,
+            RebootError::BootNotConfirmed { .. } => RebootErrorKind::BootNotConfirmed,
             RebootError::BridgePauseFailed { .. } => RebootErrorKind::Unexpected,
+            RebootError::Cancelled => RebootErrorKind::Cancelled,
             RebootError::MultiTargetFailed { .. } => RebootErrorKind::NoDevice,
             RebootError::Unexpected { .. } => RebootErrorKind::Unexpected,
         }
     }
+
+    /// Stable numeric code for this error (see [`crate::error_code`]).
+    pub fn code(&self) -> u32 {
+        match self {
+            RebootError::NoTargets => error_code::NO_TARGETS,
+            RebootError::TargetNotFound { .. } => error_code::TARGET_NOT_FOUND,
+            RebootError::AmbiguousTarget { .. } => error_code::AMBIGUOUS_TARGET,
+            RebootError::DiscoveryFailed { .. } => error_code::DISCOVERY_FAILED,
+            RebootError::SoftRebootFailed { .. } => error_code::SOFT_REBOOT_FAILED,
+            RebootError::HalfKayTimeout => error_code::HALFKAY_TIMEOUT,
+            RebootError::OpenHalfKay { .. } => error_code::OPEN_HALFKAY_FAILED,
+            RebootError::BootNotConfirmed { .. } => error_code::BOOT_NOT_CONFIRMED,
+            RebootError::BridgePauseFailed { .. } => error_code::BRIDGE_PAUSE_FAILED,
+            RebootError::Cancelled => error_code::CANCELLED,
+            RebootError::MultiTargetFailed { .. } => error_code::MULTI_TARGET_FAILED,
+            RebootError::Unexpected { .. } => error_code::UNEXPECTED,
+        }
+    }
 }
 
-pub fn reboot_teensy41_with_selection<F>(
+/// What a reboot would do, without touching any device or oc-bridge.
+pub struct RebootPlan {
+    pub selected_targets: Vec<Target>,
+    /// Whether any target needs a serial soft reboot (and thus an oc-bridge pause).
+    pub needs_serial: bool,
+}
+
+/// Run discovery and target selection only.
+pub fn plan_reboot<F>(
     opts: &RebootOptions,
     selection: FlashSelection,
     mut on_event: F,
-) -> Result<(), RebootError>
+) -> Result<RebootPlan, RebootError>
 where
     F: FnMut(OperationEvent),
 {
-    debug!("reboot teensy41 with selection");
+    debug!("plan reboot");
     on_event(OperationEvent::DiscoverStart);
     let targets =
         targets::discover_targets().map_err(|e| RebootError::DiscoveryFailed { source: e })?;
@@ -148,12 +234,142 @@ where
             message: other.to_string(),
         },
     })?;
+    let needs_serial = selected
+        .iter()
+        .any(|t| t.kind() == targets::TargetKind::Serial);
+
+    Ok(RebootPlan {
+        selected_targets: selected,
+        needs_serial,
+    })
+}
+
+pub fn reboot_teensy41_with_selection<F>(
+    opts: &RebootOptions,
+    selection: FlashSelection,
+    mut on_event: F,
+) -> Result<(), RebootError>
+where
+    F: FnMut(OperationEvent),
+{
+    debug!("reboot teensy41 with selection");
+    let plan = plan_reboot(opts, selection, &mut on_event)?;
+
+    run_with_bridge(
+        plan.selected_targets,
+        opts,
+        |target, target_id, on_event| {
+            reboot_one_target(target, target_id, opts, on_event).map(|_| ())
+        },
+        &mut on_event,
+    )
+}
+
+/// Send the boot command to the selected HalfKay targets, restarting the firmware already in
+/// flash (e.g. after a flash interrupted between the last block and boot).
+///
+/// Selected serial targets are already running: they only get an `already_running` warning.
+/// No serial port is opened, so oc-bridge is not paused.
+pub fn boot_teensy41_with_selection<F>(
+    opts: &RebootOptions,
+    selection: FlashSelection,
+    mut on_event: F,
+) -> Result<(), RebootError>
+where
+    F: FnMut(OperationEvent),
+{
+    debug!("boot teensy41 with selection");
+    let plan = plan_reboot(opts, selection, &mut on_event)?;
+
+    let mut in_bootloader = Vec::new();
+    for target in plan.selected_targets {
+        match target {
+            Target::HalfKay(_) => in_bootloader.push(target),
+            Target::Serial(_) => on_event(OperationEvent::Warning {
+                code: "already_running",
+                message: format!("{} is already running its firmware", target.id()),
+            }),
+        }
+    }
+
+    run_with_bridge(
+        in_bootloader,
+        opts,
+        |target, target_id, on_event| boot_one_target(target, target_id, opts, on_event),
+        &mut on_event,
+    )
+}
+
+/// Get the selected board into HalfKay and return it.
+///
+/// Idempotent: a board already in HalfKay is returned as-is without touching oc-bridge; a
+/// serial board is soft-rebooted with oc-bridge paused around it. Exactly one target must be
+/// selected.
+pub fn ensure_bootloader<F>(
+    opts: &RebootOptions,
+    selection: FlashSelection,
+    mut on_event: F,
+) -> Result<targets::HalfKayTarget, RebootError>
+where
+    F: FnMut(OperationEvent),
+{
+    debug!("ensure bootloader");
+    let plan = plan_reboot(opts, selection, &mut on_event)?;
+    if plan.selected_targets.len() != 1 {
+        return Err(RebootError::AmbiguousTarget {
+            message: format!(
+                "expected exactly one target, {} selected",
+                plan.selected_targets.len()
+            ),
+        });
+    }
+    if let Target::HalfKay(t) = &plan.selected_targets[0] {
+        return Ok(t.clone());
+    }
+
+    let mut path = None;
+    run_with_bridge(
+        plan.selected_targets,
+        opts,
+        |target, target_id, on_event| {
+            path = Some(reboot_one_target(target, target_id, opts, on_event)?);
+            Ok(())
+        },
+        &mut on_event,
+    )?;
+
+    let path = path.ok_or_else(|| RebootError::Unexpected {
+        message: "HalfKay path missing after reboot".to_string(),
+    })?;
+    let bcd_device = halfkay::list_devices()
+        .ok()
+        .and_then(|devices| devices.into_iter().find(|d| d.path == path))
+        .map(|d| d.release_number)
+        .filter(|&bcd| bcd != 0);
+    Ok(targets::HalfKayTarget {
+        vid: teensy41::VID,
+        pid: teensy41::PID_HALFKAY,
+        path,
+        bcd_device,
+    })
+}
 
+fn run_with_bridge<F, R>(
+    selected: Vec<Target>,
+    opts: &RebootOptions,
+    run_target: R,
+    on_event: &mut F,
+) -> Result<(), RebootError>
+where
+    F: FnMut(OperationEvent),
+    R: FnMut(&Target, &str, &mut F) -> Result<(), RebootError>,
+{
     crate::operation_runner::run_targets_with_bridge(
         selected,
         &opts.bridge,
+        opts.between_targets_delay,
         bridge_control::pause_oc_bridge,
-        |target, target_id, on_event| reboot_one_target(target, target_id, opts, on_event),
+        run_target,
         crate::operation_runner::RunTargetsErrors {
             is_ambiguous: |e: &RebootError| matches!(e.kind(), RebootErrorKind::AmbiguousTarget),
             make_ambiguous: |message| RebootError::AmbiguousTarget { message },
@@ -166,7 +382,7 @@ where
                 RebootError::BridgePauseFailed { message: msg }
             },
         },
-        &mut on_event,
+        on_event,
     )
 }
 
@@ -175,79 +391,163 @@ fn reboot_one_target<F>(
     target_id: &str,
     opts: &RebootOptions,
     on_event: &mut F,
-) -> Result<(), RebootError>
+) -> Result<String, RebootError>
 where
     F: FnMut(OperationEvent),
 {
     debug!(target_id = target_id, kind = ?target.kind(), "reboot target");
+    if opts.is_cancelled() {
+        return Err(RebootError::Cancelled);
+    }
+
     match target {
         Target::HalfKay(t) => {
             on_event(OperationEvent::HalfKayOpen {
                 target_id: target_id.to_string(),
                 path: t.path.clone(),
             });
-            Ok(())
+            Ok(t.path.clone())
         }
 
         Target::Serial(t) => {
-            let before = halfkay::list_paths().map_err(|e| RebootError::DiscoveryFailed {
-                source: targets::DiscoverError::Hid(e),
-            })?;
-            let before: HashSet<String> = before.into_iter().collect();
-
-            match serial_reboot::soft_reboot_port(&t.port_name) {
-                Ok(()) => {
-                    on_event(OperationEvent::SoftReboot {
-                        target_id: target_id.to_string(),
-                        port: t.port_name.clone(),
-                    });
-                    std::thread::sleep(opts.soft_reboot_delay);
-                }
-                Err(e) => {
+            let reboot = bootloader::SoftRebootOptions {
+                strategy: opts.reboot_strategy,
+                baud: opts.reboot_baud,
+                settle_delay: opts.soft_reboot_delay,
+                dtr_grace: bootloader::AUTO_DTR_GRACE,
+                timeout: opts.wait_timeout,
+                poll_interval: opts.poll_interval,
+                ambiguity_settle: bootloader::AMBIGUITY_SETTLE,
+            };
+            let mut next_report = WAITING_REPORT_INTERVAL;
+            let path = bootloader::soft_reboot_and_wait(
+                &t.port_name,
+                t.serial_number.as_deref(),
+                &reboot,
+                |step| match step {
+                    bootloader::SoftRebootStep::Sent(strategy) => {
+                        on_event(OperationEvent::SoftReboot {
+                            target_id: target_id.to_string(),
+                            port: t.port_name.clone(),
+                            strategy,
+                            baud: opts.reboot_baud,
+                        });
+                        true
+                    }
+                    bootloader::SoftRebootStep::Poll(elapsed) => {
+                        if opts.is_cancelled() {
+                            return false;
+                        }
+                        if elapsed >= next_report {
+                            on_event(OperationEvent::WaitingForHalfKay {
+                                target_id: target_id.to_string(),
+                                elapsed_ms: elapsed.as_millis() as u64,
+                            });
+                            next_report += WAITING_REPORT_INTERVAL;
+                        }
+                        true
+                    }
+                },
+            )
+            .map_err(|e| match e {
+                bootloader::SoftRebootError::Reboot(e) => {
                     warn!(target_id = target_id, port = %t.port_name, err = %e, "soft reboot failed");
                     on_event(OperationEvent::SoftRebootSkipped {
                         target_id: target_id.to_string(),
                         error: e.to_string(),
                     });
-                    return Err(RebootError::SoftRebootFailed {
+                    RebootError::SoftRebootFailed {
                         port: t.port_name.clone(),
                         source: e,
-                    });
+                    }
                 }
-            }
-
-            let path = wait_for_new_halfkay(&before, opts.wait_timeout, opts.poll_interval)?;
+                bootloader::SoftRebootError::Wait(e) => map_wait_error(e),
+            })?;
 
             on_event(OperationEvent::HalfKayAppeared {
                 target_id: target_id.to_string(),
                 path: path.clone(),
             });
-            Ok(())
+            Ok(path)
         }
     }
 }
 
-fn wait_for_new_halfkay(
+fn boot_one_target<F>(
+    target: &Target,
+    target_id: &str,
+    opts: &RebootOptions,
+    on_event: &mut F,
+) -> Result<(), RebootError>
+where
+    F: FnMut(OperationEvent),
+{
+    debug!(target_id = target_id, "boot target");
+    if opts.is_cancelled() {
+        return Err(RebootError::Cancelled);
+    }
+    let Target::HalfKay(t) = target else {
+        return Ok(());
+    };
+
+    // The board is in HalfKay, so its serial port is absent from this snapshot.
+    let serial_before = match opts.wait_for_serial_after_boot {
+        Some(_) => Some(
+            bootloader::serial_port_names()
+                .map_err(|source| RebootError::DiscoveryFailed { source })?,
+        ),
+        None => None,
+    };
+    // Tells the board's port apart from others appearing meanwhile (best-effort).
+    let halfkay_serial = match opts.wait_for_serial_after_boot {
+        Some(_) => halfkay::serial_number_for_path(&t.path).ok().flatten(),
+        None => None,
+    };
+
+    let mut dev = halfkay::open_by_path(&t.path).map_err(|source| RebootError::OpenHalfKay {
+        path: t.path.clone(),
+        source,
+    })?;
+    on_event(OperationEvent::HalfKayOpen {
+        target_id: target_id.to_string(),
+        path: dev.path.clone(),
+    });
+    on_event(OperationEvent::Boot {
+        target_id: target_id.to_string(),
+    });
+    // Best-effort, as when flashing: the boot may invalidate the handle mid-write.
+    let _ = halfkay::boot_teensy41(&mut dev);
+    drop(dev);
+
+    if let (Some(before), Some(timeout)) = (serial_before, opts.wait_for_serial_after_boot) {
+        let port = wait_for_new_serial(&before, halfkay_serial.as_deref(), timeout, opts)?;
+        on_event(OperationEvent::SerialReappeared {
+            target_id: target_id.to_string(),
+            port,
+        });
+    }
+    Ok(())
+}
+
+fn wait_for_new_serial(
     before: &HashSet<String>,
-    timeout: Option<Duration>,
-    poll_interval: Duration,
+    halfkay_serial: Option<&str>,
+    timeout: Duration,
+    opts: &RebootOptions,
 ) -> Result<String, RebootError> {
-    match timeout {
-        Some(t) => {
-            bootloader::wait_for_new_halfkay(before, t, poll_interval).map_err(map_wait_error)
-        }
-        None => loop {
-            let now = halfkay::list_paths().map_err(|e| RebootError::DiscoveryFailed {
-                source: targets::DiscoverError::Hid(e),
-            })?;
-            match bootloader::diff_new_halfkay(before, &now) {
-                Ok(Some(p)) => return Ok(p),
-                Ok(None) => {}
-                Err(e) => return Err(map_wait_error(e)),
-            }
-            std::thread::sleep(poll_interval);
+    bootloader::wait_for_new_serial(before, halfkay_serial, timeout, opts.poll_interval, || {
+        !opts.is_cancelled()
+    })
+    .map_err(|e| match e {
+        bootloader::WaitSerialError::ListFailed(source) => RebootError::DiscoveryFailed { source },
+        bootloader::WaitSerialError::Timeout => RebootError::BootNotConfirmed {
+            timeout_ms: timeout.as_millis() as u64,
         },
-    }
+        e @ bootloader::WaitSerialError::Ambiguous { .. } => RebootError::AmbiguousTarget {
+            message: e.to_string(),
+        },
+        bootloader::WaitSerialError::Cancelled => RebootError::Cancelled,
+    })
 }
 
 fn map_wait_error(e: bootloader::WaitHalfKayError) -> RebootError {
@@ -256,6 +556,7 @@ fn map_wait_error(e: bootloader::WaitHalfKayError) -> RebootError {
             message: format!("multiple new HalfKay devices appeared ({count})"),
         },
         bootloader::WaitHalfKayError::Timeout => RebootError::HalfKayTimeout,
+        bootloader::WaitHalfKayError::Cancelled => RebootError::Cancelled,
         bootloader::WaitHalfKayError::ListFailed(e) => RebootError::DiscoveryFailed {
             source: targets::DiscoverError::Hid(e),
         },
@@ -6,6 +6,18 @@ use crate::targets::Target;
 pub enum TargetSelector {
     Index(usize),
     Id(String),
+    /// `halfkay:` followed by a pattern containing `*` (`?` matches one character), matched
+    /// against HalfKay HID paths (ASCII case-insensitive).
+    HalfKayPathGlob(String),
+    /// `serial:` followed by a pattern containing `*` or `?`, matched against serial port
+    /// names (ASCII case-insensitive).
+    SerialPortGlob(String),
+    /// `sn:` followed by a USB serial number (trimmed), matched exactly (case-sensitive)
+    /// against serial targets. HalfKay targets carry no serial number and never match.
+    SerialNumber(String),
+    /// `product:` followed by a USB product string, matched against serial targets
+    /// (case-insensitive, whole string).
+    Product(String),
 }
 
 #[derive(Error, Debug)]
@@ -37,6 +49,39 @@ pub fn parse_selector(s: &str) -> Result<TargetSelector, SelectorError> {
         return Ok(TargetSelector::Index(idx));
     }
 
+    if let Some(pattern) = s.strip_prefix("halfkay:") {
+        // Only `*` opts in: Windows HID paths contain a literal `?` (`\\?\HID#...`).
+        if pattern.contains('*') {
+            return Ok(TargetSelector::HalfKayPathGlob(pattern.to_string()));
+        }
+    }
+
+    if let Some(pattern) = s.strip_prefix("serial:") {
+        if pattern.contains(['*', '?']) {
+            return Ok(TargetSelector::SerialPortGlob(pattern.to_string()));
+        }
+    }
+
+    if let Some(sn) = s.strip_prefix("sn:") {
+        let sn = sn.trim();
+        if sn.is_empty() {
+            return Err(SelectorError::InvalidSelector(
+                "empty serial number".to_string(),
+            ));
+        }
+        return Ok(TargetSelector::SerialNumber(sn.to_string()));
+    }
+
+    if let Some(product) = s.strip_prefix("product:") {
+        let product = product.trim();
+        if product.is_empty() {
+            return Err(SelectorError::InvalidSelector(
+                "empty product name".to_string(),
+            ));
+        }
+        return Ok(TargetSelector::Product(product.to_string()));
+    }
+
     if s.starts_with("serial:") || s.starts_with("halfkay:") {
         return Ok(TargetSelector::Id(s.to_string()));
     }
@@ -64,14 +109,47 @@ pub fn resolve(selector: &TargetSelector, targets: &[Target]) -> Result<Vec<usiz
             }
             Ok(vec![*i])
         }
-        TargetSelector::Id(id) => Ok(targets
+        TargetSelector::Id(_)
+        | TargetSelector::HalfKayPathGlob(_)
+        | TargetSelector::SerialPortGlob(_)
+        | TargetSelector::SerialNumber(_)
+        | TargetSelector::Product(_) => Ok(targets
             .iter()
             .enumerate()
-            .filter_map(|(i, t)| if t.id() == *id { Some(i) } else { None })
+            .filter_map(|(i, t)| if matches(selector, t) { Some(i) } else { None })
             .collect()),
     }
 }
 
+/// Whether `target` on its own satisfies `selector`.
+///
+/// `Index` selectors are positional and never match a lone target; use [`resolve`] for them.
+pub fn matches(selector: &TargetSelector, target: &Target) -> bool {
+    match selector {
+        TargetSelector::Index(_) => false,
+        TargetSelector::Id(id) => target.id() == *id,
+        TargetSelector::HalfKayPathGlob(pattern) => match target {
+            Target::HalfKay(hk) => glob_match(pattern, &hk.path),
+            Target::Serial(_) => false,
+        },
+        TargetSelector::SerialPortGlob(pattern) => match target {
+            Target::Serial(t) => glob_match(pattern, &t.port_name),
+            Target::HalfKay(_) => false,
+        },
+        TargetSelector::SerialNumber(sn) => match target {
+            Target::Serial(t) => t.serial_number.as_deref() == Some(sn.as_str()),
+            Target::HalfKay(_) => false,
+        },
+        TargetSelector::Product(product) => match target {
+            Target::Serial(t) => t
+                .product
+                .as_deref()
+                .is_some_and(|p| p.to_lowercase() == product.to_lowercase()),
+            Target::HalfKay(_) => false,
+        },
+    }
+}
+
 pub fn resolve_one(selector: &TargetSelector, targets: &[Target]) -> Result<usize, SelectorError> {
     let matches = resolve(selector, targets)?;
     if matches.is_empty() {
@@ -87,17 +165,53 @@ pub fn resolve_one(selector: &TargetSelector, targets: &[Target]) -> Result<usiz
     Ok(matches[0])
 }
 
+impl std::fmt::Display for TargetSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&selector_string(self))
+    }
+}
+
 fn selector_string(s: &TargetSelector) -> String {
     match s {
         TargetSelector::Index(i) => format!("index:{i}"),
         TargetSelector::Id(id) => id.clone(),
+        TargetSelector::HalfKayPathGlob(pattern) => format!("halfkay:{pattern}"),
+        TargetSelector::SerialPortGlob(pattern) => format!("serial:{pattern}"),
+        TargetSelector::SerialNumber(sn) => format!("sn:{sn}"),
+        TargetSelector::Product(product) => format!("product:{product}"),
+    }
+}
+
+/// `*` matches any run of characters, `?` exactly one; everything else literally.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+
+    // Iterative matcher with single-star backtracking.
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi].eq_ignore_ascii_case(&t[ti])) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
     }
+    p[pi..].iter().all(|c| *c == '*')
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::targets::{HalfKayTarget, SerialTarget, Target};
+    use crate::targets::{serial_target, HalfKayTarget, SerialTarget, Target};
 
     #[test]
     fn test_parse_selector() {
@@ -119,6 +233,7 @@ mod tests {
                 vid: 0x16C0,
                 pid: 0x0478,
                 path: "HK1".to_string(),
+                bcd_device: None,
             }),
             Target::Serial(SerialTarget {
                 port_name: "COM6".to_string(),
@@ -134,4 +249,150 @@ mod tests {
         let idx = resolve_one(&sel, &targets).unwrap();
         assert_eq!(idx, 1);
     }
+
+    #[test]
+    fn test_halfkay_path_glob() {
+        let hk = |path: &str| {
+            Target::HalfKay(HalfKayTarget {
+                vid: 0x16C0,
+                pid: 0x0478,
+                path: path.to_string(),
+                bcd_device: None,
+            })
+        };
+        let targets = vec![
+            hk(r"\\?\HID#VID_16C0&PID_0478#7&1A2B&0&0000#{4d1e55b2}"),
+            hk(r"\\?\HID#VID_16C0&PID_0478#7&1a2b&0&0001#{4d1e55b2}"),
+            hk(r"\\?\HID#VID_16C0&PID_0478#7&9F00&0&0000#{4d1e55b2}"),
+        ];
+
+        let sel = parse_selector("halfkay:*#7&1a2b&*").unwrap();
+        assert_eq!(
+            sel,
+            TargetSelector::HalfKayPathGlob("*#7&1a2b&*".to_string())
+        );
+        assert_eq!(resolve(&sel, &targets).unwrap(), vec![0, 1]);
+        assert!(matches!(
+            resolve_one(&sel, &targets),
+            Err(SelectorError::MultipleMatches { .. })
+        ));
+
+        let one = parse_selector("halfkay:*9F00&0&000?#*").unwrap();
+        assert_eq!(resolve_one(&one, &targets).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_serial_port_glob() {
+        let serial = |port: &str| Target::Serial(serial_target(port));
+        let targets = vec![serial("COM3"), serial("COM12"), serial("/dev/ttyACM0")];
+
+        let com = parse_selector("serial:COM*").unwrap();
+        assert_eq!(com, TargetSelector::SerialPortGlob("COM*".to_string()));
+        assert_eq!(resolve(&com, &targets).unwrap(), vec![0, 1]);
+        assert!(matches!(
+            resolve_one(&com, &targets),
+            Err(SelectorError::MultipleMatches { selector }) if selector == "serial:COM*"
+        ));
+
+        let acm = parse_selector("serial:/dev/ttyACM?").unwrap();
+        assert_eq!(resolve_one(&acm, &targets).unwrap(), 2);
+
+        // No wildcard: literal id match, as before.
+        let exact = parse_selector("serial:COM1").unwrap();
+        assert_eq!(exact, TargetSelector::Id("serial:COM1".to_string()));
+        assert!(matches!(
+            resolve_one(&exact, &targets),
+            Err(SelectorError::NoMatch { .. })
+        ));
+
+        let none = parse_selector("serial:/dev/ttyUSB*").unwrap();
+        assert!(resolve(&none, &targets).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_serial_number_selector_is_exact() {
+        let serial = |port: &str, sn: Option<&str>| {
+            Target::Serial(SerialTarget {
+                serial_number: sn.map(str::to_string),
+                ..serial_target(port)
+            })
+        };
+        let targets = vec![
+            serial("COM5", Some("12345")),
+            serial("COM6", Some("123456")),
+            serial("COM7", None),
+        ];
+
+        let sel = parse_selector("sn:123456").unwrap();
+        assert_eq!(sel, TargetSelector::SerialNumber("123456".to_string()));
+        assert_eq!(resolve_one(&sel, &targets).unwrap(), 1);
+        assert_eq!(sel.to_string(), "sn:123456");
+        assert_eq!(parse_selector("sn: 123456").unwrap(), sel);
+
+        let upper = parse_selector("sn:ABC").unwrap();
+        let lower = vec![serial("COM5", Some("abc"))];
+        assert!(matches!(
+            resolve_one(&upper, &lower),
+            Err(SelectorError::NoMatch { selector }) if selector == "sn:ABC"
+        ));
+        assert!(parse_selector("sn:").is_err());
+        assert!(parse_selector("sn: ").is_err());
+    }
+
+    #[test]
+    fn test_product_selector() {
+        let serial = |port: &str, product: Option<&str>| {
+            Target::Serial(SerialTarget {
+                product: product.map(str::to_string),
+                ..serial_target(port)
+            })
+        };
+        let targets = vec![
+            serial("COM5", Some("MIDI Studio Mk1")),
+            serial("COM6", Some("MIDI Studio Mk2")),
+            serial("COM7", None),
+        ];
+
+        let sel = parse_selector("product:midi studio MK2").unwrap();
+        assert_eq!(sel, TargetSelector::Product("midi studio MK2".to_string()));
+        assert_eq!(resolve_one(&sel, &targets).unwrap(), 1);
+
+        let none = parse_selector("product:MIDI Studio").unwrap();
+        assert!(matches!(
+            resolve_one(&none, &targets),
+            Err(SelectorError::NoMatch { selector }) if selector == "product:MIDI Studio"
+        ));
+
+        let twins = vec![
+            serial("COM5", Some("MIDI Studio Mk2")),
+            serial("COM6", Some("MIDI Studio Mk2")),
+        ];
+        assert!(matches!(
+            resolve_one(&sel, &twins),
+            Err(SelectorError::MultipleMatches { .. })
+        ));
+        assert_eq!(resolve(&sel, &twins).unwrap(), vec![0, 1]);
+        assert!(parse_selector("product:").is_err());
+    }
+
+    #[test]
+    fn test_matches_single_target() {
+        let serial = Target::Serial(serial_target("COM6"));
+        let hk = Target::HalfKay(HalfKayTarget {
+            vid: 0x16C0,
+            pid: 0x0478,
+            path: "/dev/hidraw3".to_string(),
+            bcd_device: None,
+        });
+
+        assert!(matches(&parse_selector("COM6").unwrap(), &serial));
+        assert!(!matches(&parse_selector("COM7").unwrap(), &serial));
+        assert!(matches(
+            &parse_selector("halfkay:/dev/hidraw*").unwrap(),
+            &hk
+        ));
+        assert!(!matches(&parse_selector("halfkay:*").unwrap(), &serial));
+        assert!(!matches(&TargetSelector::Index(0), &hk));
+        assert!(hk.matches_selector(&parse_selector("halfkay:/dev/hidraw3").unwrap()));
+    }
 }
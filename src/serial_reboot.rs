@@ -2,6 +2,21 @@ use std::time::Duration;
 
 use thiserror::Error;
 
+/// Line-coding baud rate that makes Teensyduino firmware reboot into HalfKay.
+pub const TEENSY_REBOOT_BAUD: u32 = 134;
+
+/// How a serial target is told to reboot into its bootloader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RebootStrategy {
+    /// Set the "magic" baud rate ([`soft_reboot_port_with_baud`]).
+    #[default]
+    MagicBaud,
+    /// Pulse DTR ([`reboot_via_dtr`]), for adapters that ignore the baud rate.
+    DtrPulse,
+    /// Magic baud first, then a DTR pulse if the bootloader does not show up in time.
+    Auto,
+}
+
 #[derive(Error, Debug)]
 pub enum SerialRebootError {
     #[error("no Teensy USB serial ports found")]
@@ -46,9 +61,15 @@ pub fn soft_reboot_teensy41(preferred_port: Option<&str>) -> Result<String, Seri
 }
 
 pub fn soft_reboot_port(port_name: &str) -> Result<(), SerialRebootError> {
-    // The Teensyduino "134 baud" mechanism: setting line coding to 134 triggers reboot.
+    soft_reboot_port_with_baud(port_name, TEENSY_REBOOT_BAUD)
+}
+
+/// Soft-reboot through a "magic baud" line coding: 134 for Teensyduino, 1200 for boards using
+/// the 1200bps touch convention.
+pub fn soft_reboot_port_with_baud(port_name: &str, baud: u32) -> Result<(), SerialRebootError> {
+    // Setting the line coding to the magic baud triggers the reboot.
     // We only need to open the port and apply settings.
-    let builder = serialport::new(port_name, 134)
+    let builder = serialport::new(port_name, baud)
         .timeout(Duration::from_millis(500))
         .data_bits(serialport::DataBits::Eight)
         .parity(serialport::Parity::None)
@@ -61,7 +82,27 @@ pub fn soft_reboot_port(port_name: &str) -> Result<(), SerialRebootError> {
     })?;
 
     // Some drivers only send line coding on explicit set.
-    let _ = port.set_baud_rate(134);
+    let _ = port.set_baud_rate(baud);
+    std::thread::sleep(Duration::from_millis(120));
+    drop(port);
+    Ok(())
+}
+
+/// Soft-reboot with a DTR pulse (the 1200bps touch with DTR dropped).
+pub fn reboot_via_dtr(port_name: &str) -> Result<(), SerialRebootError> {
+    let port_err = |e| SerialRebootError::SerialPort {
+        port: port_name.to_string(),
+        source: e,
+    };
+    let mut port = serialport::new(port_name, 1200)
+        .timeout(Duration::from_millis(500))
+        .open()
+        .map_err(port_err)?;
+
+    // The board resets when DTR falls.
+    port.write_data_terminal_ready(true).map_err(port_err)?;
+    std::thread::sleep(Duration::from_millis(50));
+    port.write_data_terminal_ready(false).map_err(port_err)?;
     std::thread::sleep(Duration::from_millis(120));
     drop(port);
     Ok(())
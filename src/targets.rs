@@ -1,11 +1,12 @@
 use serde::Serialize;
 use thiserror::Error;
 
-use crate::{halfkay, teensy41};
+use crate::selector::{self, TargetSelector};
+use crate::{board, halfkay, teensy41};
 
 pub const PJRC_VID: u16 = teensy41::VID;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, PartialOrd, Ord)]
 #[serde(rename_all = "snake_case")]
 pub enum TargetKind {
     #[serde(rename = "halfkay")]
@@ -35,6 +36,25 @@ impl Target {
             Target::Serial(t) => format!("serial:{}", t.port_name),
         }
     }
+
+    /// Teensy model, `None` for a non-Teensy USB id (see [`board::model_for_bcd_device`]).
+    ///
+    /// Serial ports do not expose `bcdDevice`, so serial targets read "Teensy 4.x".
+    pub fn model(&self) -> Option<&'static str> {
+        let (vid, pid, bcd) = match self {
+            Target::HalfKay(t) => (t.vid, t.pid, t.bcd_device),
+            Target::Serial(t) => (t.vid, t.pid, None),
+        };
+        if vid != PJRC_VID || !board::is_teensy_pid(pid) {
+            return None;
+        }
+        Some(board::model_for_bcd_device(bcd))
+    }
+
+    /// See [`selector::matches`].
+    pub fn matches_selector(&self, selector: &TargetSelector) -> bool {
+        selector::matches(selector, self)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
@@ -42,6 +62,16 @@ pub struct HalfKayTarget {
     pub vid: u16,
     pub pid: u16,
     pub path: String,
+    /// Raw USB `bcdDevice`, when reported (non-zero). HalfKay uses it as a board code, not
+    /// as a bootloader version.
+    pub bcd_device: Option<u16>,
+}
+
+impl HalfKayTarget {
+    /// The board identified by [`HalfKayTarget::bcd_device`], when it is a known code.
+    pub fn board(&self) -> Option<board::Board> {
+        self.bcd_device.and_then(board::Board::from_bcd_device)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
@@ -63,16 +93,36 @@ pub enum DiscoverError {
     Serial(#[from] serialport::Error),
 }
 
-pub fn discover_targets() -> Result<Vec<Target>, DiscoverError> {
-    let mut out: Vec<Target> = Vec::new();
+impl DiscoverError {
+    /// Which enumeration failed: `"hid"` (HalfKay) or `"serial"` (USB serial ports).
+    pub fn subsystem(&self) -> &'static str {
+        match self {
+            DiscoverError::Hid(_) => "hid",
+            DiscoverError::Serial(_) => "serial",
+        }
+    }
+}
 
-    for d in halfkay::list_devices()? {
-        out.push(Target::HalfKay(HalfKayTarget {
+/// Enumerate HalfKay bootloader devices only, sorted by path.
+///
+/// Cheap enough to poll: does not touch serial ports.
+pub fn discover_halfkay_targets() -> Result<Vec<Target>, DiscoverError> {
+    let mut out: Vec<HalfKayTarget> = halfkay::list_devices()?
+        .into_iter()
+        .map(|d| HalfKayTarget {
             vid: d.vid,
             pid: d.pid,
             path: d.path,
-        }));
-    }
+            bcd_device: (d.release_number != 0).then_some(d.release_number),
+        })
+        .collect();
+    out.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(out.into_iter().map(Target::HalfKay).collect())
+}
+
+/// Enumerate PJRC USB serial devices only, sorted by port name.
+pub fn discover_serial_targets() -> Result<Vec<Target>, DiscoverError> {
+    let mut out: Vec<SerialTarget> = Vec::new();
 
     for p in serialport::available_ports()? {
         let serialport::SerialPortInfo {
@@ -88,36 +138,160 @@ pub fn discover_targets() -> Result<Vec<Target>, DiscoverError> {
             continue;
         }
 
-        out.push(Target::Serial(SerialTarget {
+        out.push(SerialTarget {
             port_name,
             vid: usb.vid,
             pid: usb.pid,
             serial_number: usb.serial_number,
             manufacturer: usb.manufacturer,
             product: usb.product,
-        }));
+        });
     }
 
-    out.sort_by(|a, b| {
-        let ak = a.kind();
-        let bk = b.kind();
-        if ak != bk {
-            return ak.cmp(&bk);
-        }
-        match (a, b) {
-            (Target::HalfKay(aa), Target::HalfKay(bb)) => aa.path.cmp(&bb.path),
-            (Target::Serial(aa), Target::Serial(bb)) => aa.port_name.cmp(&bb.port_name),
-            _ => std::cmp::Ordering::Equal,
-        }
-    });
+    out.sort_by(|a, b| a.port_name.cmp(&b.port_name));
+    Ok(out.into_iter().map(Target::Serial).collect())
+}
 
+/// Enumerate HalfKay and PJRC USB serial devices, deduplicated ([`dedupe_targets`]) and in
+/// [`sort_targets`] order.
+///
+/// The order does not depend on how the OS enumerates devices, so `index:<n>` selectors are
+/// stable for a given set of attached devices; they index one discovery pass and can shift
+/// when devices come or go.
+pub fn discover_targets() -> Result<Vec<Target>, DiscoverError> {
+    let mut out: Vec<Target> = discover_halfkay_targets()?;
+    out.extend(discover_serial_targets()?);
+    dedupe_targets(&mut out);
+    sort_targets(&mut out);
     Ok(out)
 }
 
+/// Drop targets already listed under the same kind, port/path and USB serial number,
+/// keeping the first.
+///
+/// Windows sometimes enumerates one serial device through two paths; left in, the copy makes
+/// auto selection fail as ambiguous.
+pub fn dedupe_targets(targets: &mut Vec<Target>) {
+    let mut seen = std::collections::HashSet::new();
+    targets.retain(|t| {
+        let key = match t {
+            Target::HalfKay(hk) => (TargetKind::HalfKay, hk.path.clone(), None),
+            Target::Serial(s) => (
+                TargetKind::Serial,
+                s.port_name.clone(),
+                s.serial_number.clone(),
+            ),
+        };
+        seen.insert(key)
+    });
+}
+
+/// Discovery order: HalfKay targets before serial ones, then by [`Target::id`].
+pub fn sort_targets(targets: &mut [Target]) {
+    targets.sort_by_cached_key(|t| (t.kind(), t.id()));
+}
+
+/// A Teensy USB serial target on `port` with no descriptor strings; override fields with
+/// struct update syntax.
+#[cfg(test)]
+pub(crate) fn serial_target(port: &str) -> SerialTarget {
+    SerialTarget {
+        port_name: port.to_string(),
+        vid: PJRC_VID,
+        pid: 0x0489,
+        serial_number: None,
+        manufacturer: None,
+        product: None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn dedupe_targets_keeps_first_of_each_device() {
+        let serial = |port: &str, sn: Option<&str>, product: &str| {
+            Target::Serial(SerialTarget {
+                serial_number: sn.map(str::to_string),
+                product: Some(product.to_string()),
+                ..serial_target(port)
+            })
+        };
+        let hk = Target::HalfKay(HalfKayTarget {
+            vid: PJRC_VID,
+            pid: 0x0478,
+            path: "HK1".to_string(),
+            bcd_device: None,
+        });
+
+        let mut targets = vec![
+            serial("COM6", Some("1234"), "first"),
+            hk.clone(),
+            serial("COM6", Some("1234"), "second path"),
+            hk.clone(),
+            // Same port, different board (serial number): kept.
+            serial("COM6", Some("5678"), "other"),
+            serial("COM7", Some("1234"), "other port"),
+        ];
+        dedupe_targets(&mut targets);
+
+        let kept: Vec<(String, Option<String>)> = targets
+            .iter()
+            .map(|t| match t {
+                Target::Serial(s) => (t.id(), s.product.clone()),
+                Target::HalfKay(_) => (t.id(), None),
+            })
+            .collect();
+        let expected = [
+            ("serial:COM6", Some("first")),
+            ("halfkay:HK1", None),
+            ("serial:COM6", Some("other")),
+            ("serial:COM7", Some("other port")),
+        ]
+        .map(|(id, p)| (id.to_string(), p.map(str::to_string)));
+        assert_eq!(kept, expected);
+    }
+
+    #[test]
+    fn sort_targets_is_independent_of_enumeration_order() {
+        let hk = |path: &str| {
+            Target::HalfKay(HalfKayTarget {
+                vid: PJRC_VID,
+                pid: 0x0478,
+                path: path.to_string(),
+                bcd_device: None,
+            })
+        };
+        let serial = |port: &str| Target::Serial(serial_target(port));
+        let expected = [
+            "halfkay:/dev/hidraw1",
+            "halfkay:/dev/hidraw3",
+            "serial:/dev/ttyACM0",
+            "serial:/dev/ttyACM2",
+        ];
+
+        let shuffles = [
+            vec![
+                serial("/dev/ttyACM2"),
+                hk("/dev/hidraw3"),
+                serial("/dev/ttyACM0"),
+                hk("/dev/hidraw1"),
+            ],
+            vec![
+                hk("/dev/hidraw3"),
+                serial("/dev/ttyACM0"),
+                hk("/dev/hidraw1"),
+                serial("/dev/ttyACM2"),
+            ],
+        ];
+        for mut targets in shuffles {
+            sort_targets(&mut targets);
+            let ids: Vec<String> = targets.iter().map(Target::id).collect();
+            assert_eq!(ids, expected);
+        }
+    }
+
     #[test]
     fn test_target_id_format() {
         let t1 = Target::Serial(SerialTarget {
@@ -134,7 +308,45 @@ mod tests {
             vid: PJRC_VID,
             pid: teensy41::PID_HALFKAY,
             path: "\\\\?\\HID#VID_16C0&PID_0478#...".to_string(),
+            bcd_device: None,
         });
         assert!(t2.id().starts_with("halfkay:"));
     }
+
+    #[test]
+    fn model_comes_from_known_pjrc_ids() {
+        let serial = |vid: u16, pid: u16| {
+            Target::Serial(SerialTarget {
+                vid,
+                pid,
+                ..serial_target("COM6")
+            })
+        };
+        assert_eq!(serial(PJRC_VID, 0x0489).model(), Some("Teensy 4.x"));
+        assert_eq!(serial(PJRC_VID, 0x04FF).model(), None);
+        assert_eq!(serial(0x1234, 0x0489).model(), None);
+
+        let hk = |bcd_device| {
+            Target::HalfKay(HalfKayTarget {
+                vid: PJRC_VID,
+                pid: teensy41::PID_HALFKAY,
+                path: "HK".to_string(),
+                bcd_device,
+            })
+        };
+        assert_eq!(hk(Some(0x0279)).model(), Some("Teensy 4.0"));
+        assert_eq!(hk(Some(0x0280)).model(), Some("Teensy 4.1"));
+        assert_eq!(hk(Some(0x0281)).model(), Some("Teensy 4.x"));
+        assert_eq!(hk(None).model(), Some("Teensy 4.x"));
+    }
+
+    #[test]
+    fn discover_error_names_subsystem() {
+        let e = DiscoverError::from(serialport::Error::new(
+            serialport::ErrorKind::Unknown,
+            "stuck tty",
+        ));
+        assert_eq!(e.subsystem(), "serial");
+        assert_eq!(e.to_string(), "serial discovery failed: stuck tty");
+    }
 }